@@ -1,5 +1,5 @@
 use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
-use rust_relations_explorer::graph::KnowledgeGraph;
+use rust_relations_explorer::graph::builder::GraphBuilder;
 use rust_relations_explorer::utils::cache::CacheMode;
 use std::path::Path;
 
@@ -13,22 +13,17 @@ fn bench_build_graph(c: &mut Criterion) {
             CacheMode::Use => "use_cache",
             CacheMode::Ignore => "ignore_cache",
         };
-        group.bench_function(
-            BenchmarkId::new("build_from_directory_with_cache_opts", label),
-            |b| {
-                b.iter(|| {
-                    let root = Path::new(".");
-                    let graph = KnowledgeGraph::build_from_directory_with_cache_opts(
-                        black_box(root),
-                        mode,
-                        false,
-                    )
+        group.bench_function(BenchmarkId::new("graph_builder", label), |b| {
+            b.iter(|| {
+                let root = Path::new(".");
+                let graph = GraphBuilder::new(black_box(root))
+                    .cache_mode(mode)
+                    .build()
                     .expect("build graph");
-                    // prevent optimizer from discarding
-                    black_box(graph.files.len())
-                })
-            },
-        );
+                // prevent optimizer from discarding
+                black_box(graph.files.len())
+            })
+        });
     }
 
     group.finish();
@@ -1,20 +1,22 @@
-use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use rust_relations_explorer::graph::builder::GraphBuilder;
+use rust_relations_explorer::graph::index::GraphIndex;
 use rust_relations_explorer::graph::KnowledgeGraph;
 use rust_relations_explorer::query::{
-    CentralityMetric, ConnectedFilesQuery, HubsQuery, Query, ShortestPathQuery,
+    CentralityMetric, ConnectedFilesQuery, CycleDetectionQuery, FunctionUsageQuery, HubsQuery,
+    ModuleCentralityQuery, Query, ShortestPathQuery,
 };
-use rust_relations_explorer::utils::cache::CacheMode;
 use std::path::Path;
 
 fn build_graph_once() -> KnowledgeGraph {
     let root = Path::new(".");
-    KnowledgeGraph::build_from_directory_with_cache_opts(root, CacheMode::Use, false)
-        .expect("build graph")
+    GraphBuilder::new(root).build().expect("build graph")
 }
 
 fn bench_queries(c: &mut Criterion) {
-    // Setup outside of iter
+    // Setup outside of iter; the first query builds the shared index
     let graph = build_graph_once();
+    black_box(graph.index());
 
     let mut group = c.benchmark_group("queries");
 
@@ -44,7 +46,7 @@ fn bench_queries(c: &mut Criterion) {
     // Hubs (top 10 by total degree)
     group.bench_function(BenchmarkId::new("hubs", "top10_total"), |b| {
         b.iter(|| {
-            let q = HubsQuery { metric: CentralityMetric::Total, top: 10 };
+            let q = HubsQuery { metric: CentralityMetric::Total, top: 10, weighted: false };
             let res = q.run(black_box(&graph));
             black_box(res.len())
         })
@@ -61,6 +63,50 @@ fn bench_queries(c: &mut Criterion) {
         });
     }
 
+    // Module centrality (top 10 by total degree)
+    group.bench_function(BenchmarkId::new("module_centrality", "top10_total"), |b| {
+        b.iter(|| {
+            let q =
+                ModuleCentralityQuery { metric: CentralityMetric::Total, top: 10, weighted: false };
+            let res = q.run(black_box(&graph));
+            black_box(res.len())
+        })
+    });
+
+    // Cycles over the call graph
+    group.bench_function("cycles", |b| {
+        b.iter(|| {
+            let res = CycleDetectionQuery::new().run(black_box(&graph));
+            black_box(res.len())
+        })
+    });
+
+    // Callers of a common function name
+    group.bench_function(BenchmarkId::new("function_usage", "new_callers"), |b| {
+        b.iter(|| {
+            let res = FunctionUsageQuery::callers("new").run(black_box(&graph));
+            black_box(res.len())
+        })
+    });
+
+    group.finish();
+
+    // Cost the index adds to the first query after a build or load
+    let mut group = c.benchmark_group("index");
+    group.bench_function("build", |b| b.iter(|| black_box(GraphIndex::build(black_box(&graph)))));
+    if let Some(sample) = files.first().cloned() {
+        group.bench_function("connected_files_cold", |b| {
+            b.iter_batched(
+                || {
+                    let mut g = graph.clone();
+                    g.invalidate_index();
+                    g
+                },
+                |g| black_box(ConnectedFilesQuery { file: sample.clone() }.run(&g).len()),
+                BatchSize::LargeInput,
+            )
+        });
+    }
     group.finish();
 }
 
@@ -1,12 +1,9 @@
-use rust_relations_explorer::graph::KnowledgeGraph;
-use rust_relations_explorer::utils::cache::CacheMode;
+use rust_relations_explorer::graph::builder::GraphBuilder;
 
 fn main() {
     let root = std::path::Path::new(".");
     let no_ignore = false; // set true to include ignored files
-    let graph =
-        KnowledgeGraph::build_from_directory_with_cache_opts(root, CacheMode::Use, no_ignore)
-            .expect("build graph");
+    let graph = GraphBuilder::new(root).no_ignore(no_ignore).build().expect("build graph");
     println!(
         "Built graph: files={}, relationships={}",
         graph.files.len(),
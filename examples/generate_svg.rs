@@ -1,13 +1,11 @@
-use rust_relations_explorer::graph::KnowledgeGraph;
-use rust_relations_explorer::utils::cache::CacheMode;
+use rust_relations_explorer::graph::builder::GraphBuilder;
 use rust_relations_explorer::visualization::{
     DotGenerator, DotOptions, DotTheme, EdgeStyle, RankDir, SvgGenerator, SvgOptions,
 };
 
 fn main() {
     let root = std::path::Path::new(".");
-    let graph = KnowledgeGraph::build_from_directory_with_cache_opts(root, CacheMode::Use, false)
-        .expect("build graph");
+    let graph = GraphBuilder::new(root).build().expect("build graph");
 
     // Generate DOT with options
     let dot_opts = DotOptions {
@@ -17,6 +15,7 @@ fn main() {
         rankdir: RankDir::LR,
         splines: EdgeStyle::Curved,
         rounded: true,
+        weighted: false,
     };
     let dot = DotGenerator::new().generate_dot_with_options(&graph, dot_opts).expect("dot");
     std::fs::write("graph.dot", dot).expect("write dot");
@@ -1,10 +1,9 @@
-use rust_relations_explorer::graph::{ItemId, KnowledgeGraph};
-use rust_relations_explorer::utils::cache::CacheMode;
+use rust_relations_explorer::graph::builder::GraphBuilder;
+use rust_relations_explorer::graph::ItemId;
 
 fn main() {
     let root = std::path::Path::new(".");
-    let graph = KnowledgeGraph::build_from_directory_with_cache_opts(root, CacheMode::Use, false)
-        .expect("build graph");
+    let graph = GraphBuilder::new(root).build().expect("build graph");
 
     // Pick a file like src/lib.rs if it exists; otherwise, print the first file.
     let target = std::path::Path::new("src/lib.rs");
@@ -1,6 +1,12 @@
 use crate::cli::{Cli, Commands, ItemKindArg, OutputFormat, QueryCommands};
-use crate::graph::KnowledgeGraph;
+use crate::graph::builder::{BuildOptions, BuildProgress, GraphBuilder};
+use crate::graph::format::GraphFormat;
+use crate::graph::{BuildFingerprint, KnowledgeGraph};
 use crate::query::Query;
+use crate::utils::cache::CacheMode;
+use crate::utils::file_walker::{self, DiscoveryOptions};
+use crate::utils::git::Revision;
+use crate::utils::watch::{SourceWatcher, WatchBackend, WatchOptions};
 use crate::visualization::{
     DotGenerator, DotOptions, DotTheme, EdgeStyle, RankDir, SvgGenerator, SvgOptions,
 };
@@ -8,6 +14,376 @@ use clap::CommandFactory;
 use clap_complete::generate;
 use std::fs;
 use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+// Encoding of a saved graph: explicit `--graph-format`, else the file extension
+fn graph_file_format(
+    path: &std::path::Path,
+    arg: Option<crate::cli::GraphFormatArg>,
+) -> GraphFormat {
+    match arg {
+        Some(crate::cli::GraphFormatArg::Json) => GraphFormat::Json,
+        Some(crate::cli::GraphFormatArg::Binary) => GraphFormat::Binary,
+        None => GraphFormat::from_path(path),
+    }
+}
+
+fn load_graph(
+    path: &str,
+    arg: Option<crate::cli::GraphFormatArg>,
+    root: Option<&std::path::Path>,
+) -> Result<KnowledgeGraph, crate::errors::KnowledgeGraphError> {
+    let p = std::path::Path::new(path);
+    let mut graph = KnowledgeGraph::load(p, graph_file_format(p, arg))?;
+    // The file rules are the saved graph's own; parser and features must match this build
+    let options = &graph.metadata.options;
+    let current = BuildFingerprint::current(options.no_ignore);
+    if let Err(e) = crate::graph::schema::check_fingerprint(options, &current) {
+        eprintln!("Warning: {path}: {e}");
+    }
+    if let Some(root) = root {
+        graph.rebase(root);
+    }
+    Ok(graph)
+}
+
+// Builder for `options` that reports discovery and analysis on stderr with `-v`, and each
+// parsed file with `-vv`
+fn graph_builder(options: BuildOptions, verbose: u8, quiet: bool) -> GraphBuilder<'static> {
+    let builder = GraphBuilder::from_options(options);
+    if quiet || verbose == 0 {
+        return builder;
+    }
+    builder.progress(move |event| match event {
+        BuildProgress::Discovered { root, files } => {
+            eprintln!("Discovered {files} files under {}", root.display());
+        }
+        BuildProgress::Parsed { path, done, total } if verbose > 1 => {
+            eprintln!("[{done}/{total}] {}", path.display());
+        }
+        BuildProgress::Analyzing { files } => {
+            eprintln!("Analyzing relationships across {files} files");
+        }
+        _ => {}
+    })
+}
+
+// ` (xN)` suffix for relationships produced by several sites
+fn times(count: usize) -> String {
+    if count > 1 {
+        format!(" (x{count})")
+    } else {
+        String::new()
+    }
+}
+
+// Degree cell for centrality tables: counts print as integers, weights with two decimals
+fn degree(v: f64) -> String {
+    let s = format!("{v:.2}");
+    s.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+// One `path:line  snippet` row per evidence record
+fn print_evidence(indent: &str, evidence: &[crate::graph::Evidence]) {
+    for ev in evidence {
+        println!("{indent}{}:{}  {}", ev.file.display(), ev.line, ev.snippet);
+    }
+}
+
+fn print_diff(diff: &crate::graph::diff::GraphDiff) {
+    if diff.is_empty() {
+        println!("No structural changes");
+        return;
+    }
+    let item =
+        |it: &crate::graph::diff::DiffItem| format!("{}  {}:{}", it.id, it.file.display(), it.line);
+    let section = |title: &str, rows: Vec<String>| {
+        if !rows.is_empty() {
+            println!("{title} ({}):", rows.len());
+            for r in rows {
+                println!("  {r}");
+            }
+        }
+    };
+    section("Added items", diff.added_items.iter().map(|it| format!("+ {}", item(it))).collect());
+    section(
+        "Removed items",
+        diff.removed_items.iter().map(|it| format!("- {}", item(it))).collect(),
+    );
+    section(
+        "Moved items",
+        diff.moved_items
+            .iter()
+            .map(|m| format!("~ {} -> {}", item(&m.from), item(&m.to)))
+            .collect(),
+    );
+    section(
+        "Visibility changes",
+        diff.visibility_changes
+            .iter()
+            .map(|v| format!("{}: {} -> {}", v.id, v.before, v.after))
+            .collect(),
+    );
+    let rel = |sign: char, r: &crate::graph::diff::DiffRelationship| {
+        format!("{sign} [{}] {} -> {}", r.kind, r.from, r.to)
+    };
+    section("Added relationships", diff.added_relationships.iter().map(|r| rel('+', r)).collect());
+    section(
+        "Removed relationships",
+        diff.removed_relationships.iter().map(|r| rel('-', r)).collect(),
+    );
+    section(
+        "New cycles",
+        diff.new_cycles
+            .iter()
+            .map(|c| {
+                let mut names: Vec<String> = c.iter().map(|p| p.display().to_string()).collect();
+                if let Some(first) = names.first().cloned() {
+                    names.push(first);
+                }
+                names.join(" -> ")
+            })
+            .collect(),
+    );
+    section(
+        "Centrality changes",
+        diff.centrality
+            .iter()
+            .map(|c| format!("{}  {} -> {} ({:+})", c.file.display(), c.before, c.after, c.delta))
+            .collect(),
+    );
+}
+
+// Files written by `build`, rewritten on every `--watch` round
+struct BuildOutputs {
+    json: Option<String>,
+    dot: Option<String>,
+    svg: Option<String>,
+    save: Option<String>,
+    graph_format: Option<crate::cli::GraphFormatArg>,
+    dot_opts: DotOptions,
+    svg_opts: SvgOptions,
+    filter: Option<crate::graph::filter::GraphFilter>,
+}
+
+impl BuildOutputs {
+    // Reports each output that cannot be written and returns whether all of them were
+    fn write(&self, graph: &KnowledgeGraph) -> bool {
+        let mut written = true;
+        // Same root-relative JSON as `--save`
+        if let Some(json_path) = &self.json {
+            if let Err(e) = graph.save_json(Path::new(json_path)) {
+                eprintln!("Failed to write JSON output {json_path}: {e}");
+                written = false;
+            }
+        }
+
+        // Diagrams show the filtered subgraph; saved graphs stay complete
+        let shown = self.filter.as_ref().map(|f| graph.subgraph(f));
+        let drawn = shown.as_ref().unwrap_or(graph);
+
+        if let Some(dot_path) = &self.dot {
+            match DotGenerator::new().generate_dot_with_options(drawn, self.dot_opts) {
+                Ok(content) => {
+                    if let Err(e) = fs::write(dot_path, content) {
+                        eprintln!("Failed to write DOT output {dot_path}: {e}");
+                        written = false;
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Visualization error: {e}");
+                    written = false;
+                }
+            }
+        }
+
+        if let Some(svg_path) = &self.svg {
+            match SvgGenerator::new().generate_svg_with_options(drawn, self.svg_opts) {
+                Ok(content) => {
+                    if let Err(e) = fs::write(svg_path, content) {
+                        eprintln!("Failed to write SVG output {svg_path}: {e}");
+                        written = false;
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Visualization error: {e}");
+                    written = false;
+                }
+            }
+        }
+
+        if let Some(save_path) = &self.save {
+            let p = Path::new(save_path);
+            if let Err(e) = graph.save(p, graph_file_format(p, self.graph_format)) {
+                eprintln!("Failed to save graph {save_path}: {e}");
+                written = false;
+            }
+        }
+        written
+    }
+}
+
+// Report of `build --stats`
+fn print_build_stats(graph: &KnowledgeGraph) {
+    let Some(stats) = &graph.metadata.stats else {
+        return;
+    };
+    let per_kind = |counts: &std::collections::BTreeMap<String, usize>| {
+        let total: usize = counts.values().sum();
+        let parts: Vec<String> = counts.iter().map(|(k, n)| format!("{k} {n}")).collect();
+        format!("{total} ({})", parts.join(", "))
+    };
+    println!(
+        "Files: {} discovered, {} reused from cache, {} parsed",
+        stats.files_discovered, stats.files_reused, stats.files_parsed
+    );
+    println!("Items: {}", per_kind(&stats.items_by_kind));
+    println!("Relationships: {}", per_kind(&stats.relationships_by_type));
+    println!("Unresolved crate imports: {}", stats.unresolved_imports);
+    println!("Timings:");
+    for (phase, ms) in stats.timings.phases() {
+        println!("  {phase:<20} {ms:>9.1} ms");
+    }
+    println!("  {:<20} {:>9.1} ms", "total", stats.timings.total_ms);
+    if let Some(bytes) = stats.peak_memory_bytes {
+        #[allow(clippy::cast_precision_loss)]
+        let mib = bytes as f64 / (1024.0 * 1024.0);
+        println!("Peak memory: {mib:.1} MiB");
+    }
+}
+
+// Dry run of `build --list-files`, of the working tree or of `rev`; returns the exit code.
+fn print_file_list(roots: &[PathBuf], opts: &DiscoveryOptions, rev: Option<&str>) -> i32 {
+    let (mut scanned, mut skipped) = (0, 0);
+    for root in roots {
+        let found = match rev {
+            Some(rev) => Revision::open(root, rev, opts).map(|r| r.discovery()),
+            None => file_walker::explain(root, opts),
+        };
+        let found = match found {
+            Ok(d) => d,
+            Err(e) => {
+                eprintln!("{e}");
+                return 2;
+            }
+        };
+        if roots.len() > 1 {
+            println!("# {}", root.display());
+        }
+        let rel = |p: &Path| p.strip_prefix(root).unwrap_or(p).display().to_string();
+        for f in &found.files {
+            println!("scan  {}", rel(f));
+        }
+        for (p, why) in &found.skipped {
+            let slash = if p.is_dir() { "/" } else { "" };
+            println!("skip  {}{slash}  ({why})", rel(p));
+        }
+        scanned += found.files.len();
+        skipped += found.skipped.len();
+    }
+    println!("{scanned} files to scan, {skipped} skipped");
+    0
+}
+
+// `src/a.rs, src/b.rs` relative to the watched roots, shortened past three files
+fn changed_files(changed: &[PathBuf], roots: &[PathBuf]) -> String {
+    let names: Vec<String> = changed
+        .iter()
+        .map(|f| {
+            let rel = roots.iter().find_map(|r| f.strip_prefix(r).ok()).unwrap_or(f);
+            rel.display().to_string()
+        })
+        .collect();
+    match names.len() {
+        0..=3 => names.join(", "),
+        n => format!("{} and {} more", names[..3].join(", "), n - 3),
+    }
+}
+
+// `cache stats|clear|verify` for one store; returns the exit code.
+fn run_cache(
+    action: crate::cli::CacheCommands,
+    store: &crate::utils::cache::CacheStore,
+    format: OutputFormat,
+) -> i32 {
+    use crate::cli::CacheCommands;
+    let json = matches!(format, OutputFormat::Json);
+    let print_json = |value: serde_json::Result<String>| match value {
+        Ok(s) => {
+            println!("{s}");
+            true
+        }
+        Err(e) => {
+            eprintln!("JSON encode error: {e}");
+            false
+        }
+    };
+    match action {
+        CacheCommands::Stats => {
+            let stats = store.stats();
+            if json {
+                return if print_json(serde_json::to_string_pretty(&stats)) { 0 } else { 1 };
+            }
+            println!("Cache: {} ({})", stats.file.display(), stats.state);
+            if stats.state != "missing" {
+                println!("  Size: {} bytes", stats.bytes);
+            }
+            if stats.state == "current" || stats.state == "outdated" {
+                println!("  Format: {}, written by version {}", stats.format, stats.tool_version);
+            }
+            if stats.state == "current" {
+                println!(
+                    "  Files: {} ({} analyzed), items: {}, cached edges: {}",
+                    stats.entries, stats.analyzed, stats.items, stats.edges
+                );
+                if stats.revisions > 0 {
+                    println!("  Files from git revisions: {}", stats.revisions);
+                }
+            }
+            0
+        }
+        CacheCommands::Clear => match store.clear() {
+            Ok(removed) => {
+                if json {
+                    let value = serde_json::json!({ "file": store.file(), "removed": removed });
+                    return if print_json(serde_json::to_string_pretty(&value)) { 0 } else { 1 };
+                }
+                if removed {
+                    println!("Removed {}", store.file().display());
+                } else {
+                    println!("No cache at {}", store.file().display());
+                }
+                0
+            }
+            Err(e) => {
+                eprintln!("Failed to clear cache {}: {e}", store.file().display());
+                1
+            }
+        },
+        CacheCommands::Verify => {
+            let report = store.verify();
+            let code = i32::from(!report.is_ok());
+            if json {
+                return if print_json(serde_json::to_string_pretty(&report)) { code } else { 1 };
+            }
+            println!("Cache: {} ({})", report.file.display(), report.state);
+            for p in &report.problems {
+                println!("  Problem: {p}");
+            }
+            for f in &report.stale {
+                println!("  Changed since cached: {}", f.display());
+            }
+            for f in &report.missing {
+                println!("  No longer exists: {}", f.display());
+            }
+            if report.is_ok() {
+                println!("OK");
+            }
+            code
+        }
+    }
+}
 
 /// Run the CLI logic in-process.
 ///
@@ -27,12 +403,77 @@ pub fn run_cli(cli: Cli) -> i32 {
             generate(shell, &mut cmd, bin_name, &mut out);
             0
         }
+        Commands::Diff {
+            old,
+            new,
+            graph_format,
+            config: _,
+            format,
+            max_new_cycles,
+            max_removed_items,
+            max_added_relationships,
+            max_removed_relationships,
+            max_centrality_delta,
+        } => {
+            let load = |p: &str| {
+                let path = std::path::Path::new(p);
+                KnowledgeGraph::load(path, graph_file_format(path, graph_format))
+            };
+            let (old_graph, new_graph) = match (load(&old), load(&new)) {
+                (Ok(o), Ok(n)) => (o, n),
+                (Err(e), _) | (_, Err(e)) => {
+                    eprintln!("Load graph failed: {e}");
+                    return 1;
+                }
+            };
+            if let Err(e) = crate::graph::schema::check_fingerprint(
+                &old_graph.metadata.options,
+                &new_graph.metadata.options,
+            ) {
+                eprintln!("Warning: comparing {old} with {new}: {e}");
+            }
+            let diff = KnowledgeGraph::diff(&old_graph, &new_graph);
+            if matches!(format, OutputFormat::Json) {
+                match serde_json::to_string_pretty(&diff) {
+                    Ok(s) => println!("{s}"),
+                    Err(e) => {
+                        eprintln!("JSON encode error: {e}");
+                        return 1;
+                    }
+                }
+            } else {
+                print_diff(&diff);
+            }
+            let limits = crate::graph::diff::DiffThresholds {
+                max_new_cycles,
+                max_removed_items,
+                max_added_relationships,
+                max_removed_relationships,
+                max_centrality_delta,
+            };
+            let exceeded = diff.exceeded(&limits);
+            for msg in &exceeded {
+                eprintln!("Threshold exceeded: {msg}");
+            }
+            if exceeded.is_empty() {
+                0
+            } else {
+                3
+            }
+        }
+        Commands::Cache { action, path, location, format, .. } => {
+            let root = path.unwrap_or_else(|| PathBuf::from("."));
+            let store = crate::utils::cache::CacheStore::new(&root, &location.location());
+            run_cache(action, &store, format)
+        }
         Commands::Build {
             path,
             config,
             no_ignore,
-            no_cache,
-            rebuild,
+            discovery,
+            list_files,
+            rev,
+            build,
             json,
             dot,
             svg,
@@ -42,41 +483,34 @@ pub fn run_cli(cli: Cli) -> i32 {
             dot_rankdir,
             dot_splines,
             dot_rounded,
+            dot_weighted,
             svg_interactive,
             save,
+            graph_format,
+            filter,
+            stats,
+            watch,
+            poll,
+            poll_interval,
+            debounce,
         } => {
-            // Determine cache mode
-            let mode = if rebuild {
-                crate::utils::cache::CacheMode::Rebuild
-            } else if no_cache {
-                crate::utils::cache::CacheMode::Ignore
-            } else {
-                crate::utils::cache::CacheMode::Use
-            };
-
-            let build_path = path.as_ref().unwrap().as_path();
-            if matches!(mode, crate::utils::cache::CacheMode::Rebuild) {
-                crate::utils::cache::clear_cache(build_path);
+            let discovery = discovery.options(no_ignore);
+            if list_files {
+                return print_file_list(&path, &discovery, rev.as_deref());
             }
-
-            let graph = match KnowledgeGraph::build_from_directory_with_cache_opts(
-                build_path, mode, no_ignore,
-            ) {
-                Ok(g) => g,
+            if rev.is_some() && path.len() > 1 {
+                eprintln!("--rev builds a single --path");
+                return 2;
+            }
+            let filter = match filter.to_filter() {
+                Ok(f) => f,
                 Err(e) => {
-                    eprintln!("Build failed: {e}");
-                    return 1;
+                    eprintln!("Invalid filter: {e}");
+                    return 2;
                 }
             };
-
-            // Optionally write JSON output
-            if let Some(json_path) = json {
-                let serialized =
-                    serde_json::to_string_pretty(&graph).expect("serialize graph to JSON");
-                if let Err(e) = fs::write(&json_path, serialized) {
-                    eprintln!("Failed to write JSON output {json_path}: {e}");
-                }
-            }
+            let mut options = build.options(path.clone(), discovery.clone());
+            options.revision.clone_from(&rev);
 
             // DOT options from flags and optional config overrides
             let mut clusters = matches!(dot_clusters, crate::cli::OnOffArg::On);
@@ -95,6 +529,7 @@ pub fn run_cli(cli: Cli) -> i32 {
                 crate::cli::DotSplinesArg::Curved => EdgeStyle::Curved,
             };
             let mut rounded = matches!(dot_rounded, crate::cli::OnOffArg::On);
+            let mut weighted = matches!(dot_weighted, crate::cli::OnOffArg::On);
             if let Some(cfg_path) = config.as_ref() {
                 if let Some(cfg) =
                     crate::utils::config::load_config_at(std::path::Path::new(cfg_path))
@@ -122,1165 +557,1342 @@ pub fn run_cli(cli: Cli) -> i32 {
                         if let Some(v) = dot.rounded {
                             rounded = v;
                         }
+                        if let Some(v) = dot.weighted {
+                            weighted = v;
+                        }
                     }
                 }
             }
-            let dot_opts = DotOptions { clusters, legend, theme, rankdir, splines, rounded };
-
-            if let Some(dot_path) = dot {
-                match DotGenerator::new().generate_dot_with_options(&graph, dot_opts) {
-                    Ok(content) => {
-                        if let Err(e) = fs::write(&dot_path, content) {
-                            eprintln!("Failed to write DOT output {dot_path}: {e}");
+            let dot_opts =
+                DotOptions { clusters, legend, theme, rankdir, splines, rounded, weighted };
+            let mut interactive = matches!(svg_interactive, crate::cli::OnOffArg::On);
+            if let Some(cfg_path) = config.as_ref() {
+                if let Some(cfg) =
+                    crate::utils::config::load_config_at(std::path::Path::new(cfg_path))
+                {
+                    if let Some(svg) = cfg.svg {
+                        if let Some(v) = svg.interactive {
+                            interactive = v;
                         }
                     }
-                    Err(e) => eprintln!("Visualization error: {e}"),
                 }
             }
+            let outputs = BuildOutputs {
+                json,
+                dot,
+                svg,
+                save,
+                graph_format,
+                dot_opts,
+                svg_opts: SvgOptions { dot: dot_opts, interactive },
+                filter,
+            };
 
-            if let Some(svg_path) = svg {
-                let mut interactive = matches!(svg_interactive, crate::cli::OnOffArg::On);
-                if let Some(cfg_path) = config.as_ref() {
-                    if let Some(cfg) =
-                        crate::utils::config::load_config_at(std::path::Path::new(cfg_path))
-                    {
-                        if let Some(svg) = cfg.svg {
-                            if let Some(v) = svg.interactive {
-                                interactive = v;
-                            }
-                        }
+            let built = graph_builder(options.clone(), cli.verbose, cli.quiet).build();
+            let mut written = true;
+            let mut graph = match built {
+                Ok(g) => {
+                    written = outputs.write(&g);
+                    Some(g)
+                }
+                Err(e) => {
+                    eprintln!("Build failed: {e}");
+                    if !watch {
+                        return 1;
                     }
+                    None
                 }
-                let svg_opts = SvgOptions { dot: dot_opts, interactive };
-                match SvgGenerator::new().generate_svg_with_options(&graph, svg_opts) {
-                    Ok(content) => {
-                        if let Err(e) = fs::write(&svg_path, content) {
-                            eprintln!("Failed to write SVG output {svg_path}: {e}");
-                        }
+            };
+            if !cli.quiet {
+                let shown: Vec<String> = path.iter().map(|p| p.display().to_string()).collect();
+                match &rev {
+                    Some(rev) => {
+                        println!("Build completed for path: {} at {rev}", shown.join(", "));
                     }
-                    Err(e) => eprintln!("Visualization error: {e}"),
+                    None => println!("Build completed for path: {}", shown.join(", ")),
                 }
             }
-
-            if let Some(save_path) = save {
-                if let Err(e) = KnowledgeGraph::save_json(&graph, std::path::Path::new(&save_path))
-                {
-                    eprintln!("Failed to save graph JSON {save_path}: {e}");
-                }
+            if let Some(g) = graph.as_ref().filter(|_| stats) {
+                print_build_stats(g);
+            }
+            if !watch {
+                return i32::from(!written);
             }
 
+            let backend = if poll {
+                WatchBackend::Poll(Duration::from_millis(poll_interval.max(1)))
+            } else {
+                WatchBackend::Native
+            };
+            let watch_options = WatchOptions {
+                backend,
+                debounce: Duration::from_millis(debounce),
+                discovery: discovery.clone(),
+            };
+            let mut watcher = SourceWatcher::new(&path, watch_options);
+            // Later rounds always reuse the cache, whatever the first build did
+            options.cache_mode = CacheMode::Use;
+            let rebuilder = graph_builder(options, cli.verbose, cli.quiet);
             if !cli.quiet {
-                println!("Build completed for path: {}", build_path.display());
+                let how = if watcher.is_polling() { "polling" } else { "notifications" };
+                println!("Watching for changes ({how}); press Ctrl-C to stop");
+            }
+            loop {
+                let changed = watcher.wait();
+                let started = Instant::now();
+                let next = match rebuilder.build() {
+                    Ok(g) => g,
+                    Err(e) => {
+                        eprintln!("Build failed: {e}");
+                        continue;
+                    }
+                };
+                // A failed write is reported and retried on the next change
+                outputs.write(&next);
+                if stats {
+                    print_build_stats(&next);
+                }
+                if !cli.quiet {
+                    let summary = match &graph {
+                        Some(prev) => KnowledgeGraph::diff(prev, &next).summary(),
+                        None => format!("{} files", next.files.len()),
+                    };
+                    println!(
+                        "Rebuilt in {} ms after {}: {summary}",
+                        started.elapsed().as_millis(),
+                        changed_files(&changed, &path)
+                    );
+                }
+                graph = Some(next);
             }
-            0
         }
-        Commands::Query { query } => match query {
-            QueryCommands::ConnectedFiles {
-                path,
-                config,
-                no_ignore,
-                file,
-                graph: graph_path,
-                format,
-                offset,
-                limit,
-                file_pos: _,
-            } => {
-                let graph = if let Some(p) = graph_path {
-                    match KnowledgeGraph::load_json(std::path::Path::new(&p)) {
-                        Ok(g) => g,
-                        Err(e) => {
-                            eprintln!("Load graph failed: {e}");
-                            return 1;
+        Commands::Query { query, min_confidence, graph_format, root, filter, build, discovery } => {
+            let filter = match filter.to_filter() {
+                Ok(f) => f,
+                Err(e) => {
+                    eprintln!("Invalid filter: {e}");
+                    return 2;
+                }
+            };
+            match query {
+                QueryCommands::ConnectedFiles {
+                    path,
+                    config,
+                    no_ignore,
+                    file,
+                    explain,
+                    graph: graph_path,
+                    format,
+                    offset,
+                    limit,
+                    file_pos: _,
+                } => {
+                    let mut graph = if let Some(p) = graph_path {
+                        match load_graph(&p, graph_format, root.as_deref()) {
+                            Ok(g) => g,
+                            Err(e) => {
+                                eprintln!("Load graph failed: {e}");
+                                return 1;
+                            }
                         }
-                    }
-                } else {
-                    let res = match KnowledgeGraph::build_from_directory_opts(
-                        path.as_ref().unwrap().as_path(),
-                        no_ignore,
-                    ) {
-                        Ok(g) => g,
-                        Err(e) => {
-                            eprintln!("Build failed: {e}");
-                            return 1;
+                    } else {
+                        let opts = build
+                            .options(vec![path.clone().unwrap()], discovery.options(no_ignore));
+                        match graph_builder(opts, cli.verbose, cli.quiet).build() {
+                            Ok(g) => g,
+                            Err(e) => {
+                                eprintln!("Build failed: {e}");
+                                return 1;
+                            }
                         }
                     };
-                    res
-                };
-                let file = match file.as_ref() {
-                    Some(f) => f,
-                    None => {
-                        eprintln!("Missing file argument. Provide <file> or --file <path>.");
-                        return 2;
+                    graph.retain_min_confidence(min_confidence);
+                    if let Some(f) = &filter {
+                        graph = graph.subgraph(f);
                     }
-                };
-                let q = crate::query::ConnectedFilesQuery::new(file);
-                let results = q.run(&graph);
-                let fmt = if let Some(cfg_path) = config.as_ref() {
-                    if let Some(cfg) =
-                        crate::utils::config::load_config_at(std::path::Path::new(cfg_path))
-                    {
-                        match cfg.query.and_then(|q| q.default_format).as_deref() {
-                            Some("json") => OutputFormat::Json,
-                            Some("text") => OutputFormat::Text,
-                            _ => format,
+                    let file = match file.as_ref() {
+                        Some(f) => f,
+                        None => {
+                            eprintln!("Missing file argument. Provide <file> or --file <path>.");
+                            return 2;
+                        }
+                    };
+                    let q = crate::query::ConnectedFilesQuery::new(file);
+                    let fmt = if let Some(cfg_path) = config.as_ref() {
+                        if let Some(cfg) =
+                            crate::utils::config::load_config_at(std::path::Path::new(cfg_path))
+                        {
+                            match cfg.query.and_then(|q| q.default_format).as_deref() {
+                                Some("json") => OutputFormat::Json,
+                                Some("text") => OutputFormat::Text,
+                                _ => format,
+                            }
+                        } else {
+                            format
                         }
                     } else {
                         format
+                    };
+                    if explain {
+                        let results = q.explain(&graph);
+                        let start = offset.min(results.len());
+                        let end = match limit {
+                            Some(l) => (start + l).min(results.len()),
+                            None => results.len(),
+                        };
+                        let page = &results[start..end];
+                        if matches!(fmt, OutputFormat::Json) {
+                            match serde_json::to_string_pretty(page) {
+                                Ok(s) => println!("{s}"),
+                                Err(e) => {
+                                    eprintln!("JSON encode error: {e}");
+                                    return 1;
+                                }
+                            }
+                        } else {
+                            for (i, entry) in page.iter().enumerate() {
+                                println!("{}. {}", start + i + 1, entry.path.display());
+                                for r in &entry.relations {
+                                    println!(
+                                        "   [{}] {} -> {}{}",
+                                        r.relation,
+                                        r.from,
+                                        r.to,
+                                        times(r.count)
+                                    );
+                                    print_evidence("      ", &r.evidence);
+                                }
+                            }
+                        }
+                        return 0;
                     }
-                } else {
-                    format
-                };
-                let start = offset.min(results.len());
-                let end = match limit {
-                    Some(l) => (start + l).min(results.len()),
-                    None => results.len(),
-                };
-                let page = &results[start..end];
-                if matches!(fmt, OutputFormat::Json) {
-                    let out: Vec<String> = page.iter().map(|p| p.display().to_string()).collect();
-                    match serde_json::to_string_pretty(&out) {
-                        Ok(s) => println!("{s}"),
-                        Err(e) => {
-                            eprintln!("JSON encode error: {e}");
-                            return 1;
+                    let results = q.run(&graph);
+                    let start = offset.min(results.len());
+                    let end = match limit {
+                        Some(l) => (start + l).min(results.len()),
+                        None => results.len(),
+                    };
+                    let page = &results[start..end];
+                    if matches!(fmt, OutputFormat::Json) {
+                        let out: Vec<String> =
+                            page.iter().map(|p| p.display().to_string()).collect();
+                        match serde_json::to_string_pretty(&out) {
+                            Ok(s) => println!("{s}"),
+                            Err(e) => {
+                                eprintln!("JSON encode error: {e}");
+                                return 1;
+                            }
                         }
+                    } else {
+                        let rows: Vec<Vec<String>> = page
+                            .iter()
+                            .enumerate()
+                            .map(|(i, p)| {
+                                vec![format!("{}", start + i + 1), p.display().to_string()]
+                            })
+                            .collect();
+                        let table = crate::utils::table::render(&["#", "Path"], &rows);
+                        println!("{table}");
                     }
-                } else {
-                    let rows: Vec<Vec<String>> = page
-                        .iter()
-                        .enumerate()
-                        .map(|(i, p)| vec![format!("{}", start + i + 1), p.display().to_string()])
-                        .collect();
-                    let table = crate::utils::table::render(&["#", "Path"], &rows);
-                    println!("{table}");
+                    0
                 }
-                0
-            }
-            QueryCommands::FunctionUsage {
-                path,
-                config,
-                no_ignore,
-                function,
-                direction,
-                graph: graph_path,
-                format,
-                offset,
-                limit,
-            } => {
-                let graph = if let Some(p) = graph_path {
-                    match KnowledgeGraph::load_json(std::path::Path::new(&p)) {
-                        Ok(g) => g,
-                        Err(e) => {
-                            eprintln!("Load graph failed: {e}");
-                            return 1;
+                QueryCommands::FunctionUsage {
+                    path,
+                    config,
+                    no_ignore,
+                    function,
+                    direction,
+                    through_traits,
+                    graph: graph_path,
+                    format,
+                    offset,
+                    limit,
+                } => {
+                    let mut graph = if let Some(p) = graph_path {
+                        match load_graph(&p, graph_format, root.as_deref()) {
+                            Ok(g) => g,
+                            Err(e) => {
+                                eprintln!("Load graph failed: {e}");
+                                return 1;
+                            }
                         }
-                    }
-                } else {
-                    if no_ignore {
-                        std::env::set_var("KNOWLEDGE_RS_NO_IGNORE", "1");
-                    }
-                    let res = match KnowledgeGraph::build_from_directory(
-                        path.as_ref().unwrap().as_path(),
-                    ) {
-                        Ok(g) => g,
-                        Err(e) => {
-                            eprintln!("Build failed: {e}");
-                            if no_ignore {
-                                std::env::remove_var("KNOWLEDGE_RS_NO_IGNORE");
+                    } else {
+                        let opts = build
+                            .options(vec![path.clone().unwrap()], discovery.options(no_ignore));
+                        match graph_builder(opts, cli.verbose, cli.quiet).build() {
+                            Ok(g) => g,
+                            Err(e) => {
+                                eprintln!("Build failed: {e}");
+                                return 1;
                             }
-                            return 1;
                         }
                     };
-                    if no_ignore {
-                        std::env::remove_var("KNOWLEDGE_RS_NO_IGNORE");
+                    graph.retain_min_confidence(min_confidence);
+                    if let Some(f) = &filter {
+                        graph = graph.subgraph(f);
                     }
-                    res
-                };
-                let dir = match direction {
-                    crate::cli::Direction::Callees => crate::query::UsageDirection::Callees,
-                    crate::cli::Direction::Callers => crate::query::UsageDirection::Callers,
-                };
-                let q = crate::query::FunctionUsageQuery { function, direction: dir };
-                let results = q.run(&graph);
-                let fmt = if let Some(cfg_path) = config.as_ref() {
-                    if let Some(cfg) =
-                        crate::utils::config::load_config_at(std::path::Path::new(cfg_path))
-                    {
-                        match cfg.query.and_then(|q| q.default_format).as_deref() {
-                            Some("json") => OutputFormat::Json,
-                            Some("text") => OutputFormat::Text,
-                            _ => format,
+                    let dir = match direction {
+                        crate::cli::Direction::Callees => crate::query::UsageDirection::Callees,
+                        crate::cli::Direction::Callers => crate::query::UsageDirection::Callers,
+                    };
+                    let q = crate::query::FunctionUsageQuery {
+                        function,
+                        direction: dir,
+                        through_traits,
+                    };
+                    let results = q.run(&graph);
+                    let fmt = if let Some(cfg_path) = config.as_ref() {
+                        if let Some(cfg) =
+                            crate::utils::config::load_config_at(std::path::Path::new(cfg_path))
+                        {
+                            match cfg.query.and_then(|q| q.default_format).as_deref() {
+                                Some("json") => OutputFormat::Json,
+                                Some("text") => OutputFormat::Text,
+                                _ => format,
+                            }
+                        } else {
+                            format
                         }
                     } else {
                         format
-                    }
-                } else {
-                    format
-                };
-                let start = offset.min(results.len());
-                let end = match limit {
-                    Some(l) => (start + l).min(results.len()),
-                    None => results.len(),
-                };
-                let page = &results[start..end];
-                if matches!(fmt, OutputFormat::Json) {
-                    let out: Vec<String> = page.iter().map(|p| p.display().to_string()).collect();
-                    match serde_json::to_string_pretty(&out) {
-                        Ok(s) => println!("{s}"),
-                        Err(e) => {
-                            eprintln!("JSON encode error: {e}");
-                            return 1;
+                    };
+                    let start = offset.min(results.len());
+                    let end = match limit {
+                        Some(l) => (start + l).min(results.len()),
+                        None => results.len(),
+                    };
+                    let page = &results[start..end];
+                    if matches!(fmt, OutputFormat::Json) {
+                        match serde_json::to_string_pretty(page) {
+                            Ok(s) => println!("{s}"),
+                            Err(e) => {
+                                eprintln!("JSON encode error: {e}");
+                                return 1;
+                            }
+                        }
+                    } else {
+                        // `file:line,line  id`, one caller or callee per line
+                        for e in page {
+                            let lines: Vec<String> =
+                                e.lines.iter().map(ToString::to_string).collect();
+                            if lines.is_empty() {
+                                println!("{}  {}", e.file.display(), e.id.0);
+                            } else {
+                                println!("{}:{}  {}", e.file.display(), lines.join(","), e.id.0);
+                            }
                         }
                     }
-                } else {
-                    for p in page {
-                        println!("{}", p.display());
-                    }
+                    0
                 }
-                0
-            }
-            QueryCommands::Cycles {
-                path,
-                config,
-                no_ignore,
-                graph: graph_path,
-                format,
-                offset,
-                limit,
-            } => {
-                let graph = if let Some(p) = graph_path {
-                    match KnowledgeGraph::load_json(std::path::Path::new(&p)) {
-                        Ok(g) => g,
-                        Err(e) => {
-                            eprintln!("Load graph failed: {e}");
-                            return 1;
+                QueryCommands::Cycles {
+                    path,
+                    config,
+                    no_ignore,
+                    graph: graph_path,
+                    format,
+                    offset,
+                    limit,
+                } => {
+                    let mut graph = if let Some(p) = graph_path {
+                        match load_graph(&p, graph_format, root.as_deref()) {
+                            Ok(g) => g,
+                            Err(e) => {
+                                eprintln!("Load graph failed: {e}");
+                                return 1;
+                            }
                         }
-                    }
-                } else {
-                    if no_ignore {
-                        std::env::set_var("KNOWLEDGE_RS_NO_IGNORE", "1");
-                    }
-                    let res = match KnowledgeGraph::build_from_directory(
-                        path.as_ref().unwrap().as_path(),
-                    ) {
-                        Ok(g) => g,
-                        Err(e) => {
-                            eprintln!("Build failed: {e}");
-                            if no_ignore {
-                                std::env::remove_var("KNOWLEDGE_RS_NO_IGNORE");
+                    } else {
+                        let opts = build
+                            .options(vec![path.clone().unwrap()], discovery.options(no_ignore));
+                        match graph_builder(opts, cli.verbose, cli.quiet).build() {
+                            Ok(g) => g,
+                            Err(e) => {
+                                eprintln!("Build failed: {e}");
+                                return 1;
                             }
-                            return 1;
                         }
                     };
-                    if no_ignore {
-                        std::env::remove_var("KNOWLEDGE_RS_NO_IGNORE");
+                    graph.retain_min_confidence(min_confidence);
+                    if let Some(f) = &filter {
+                        graph = graph.subgraph(f);
                     }
-                    res
-                };
-                let q = crate::query::CycleDetectionQuery::new();
-                let cycles = q.run(&graph);
-                let fmt = if let Some(cfg_path) = config.as_ref() {
-                    if let Some(cfg) =
-                        crate::utils::config::load_config_at(std::path::Path::new(cfg_path))
-                    {
-                        match cfg.query.and_then(|q| q.default_format).as_deref() {
-                            Some("json") => OutputFormat::Json,
-                            Some("text") => OutputFormat::Text,
-                            _ => format,
+                    let q = crate::query::CycleDetectionQuery::new();
+                    let cycles = q.run(&graph);
+                    let fmt = if let Some(cfg_path) = config.as_ref() {
+                        if let Some(cfg) =
+                            crate::utils::config::load_config_at(std::path::Path::new(cfg_path))
+                        {
+                            match cfg.query.and_then(|q| q.default_format).as_deref() {
+                                Some("json") => OutputFormat::Json,
+                                Some("text") => OutputFormat::Text,
+                                _ => format,
+                            }
+                        } else {
+                            format
                         }
                     } else {
                         format
-                    }
-                } else {
-                    format
-                };
-                let start = offset.min(cycles.len());
-                let end = match limit {
-                    Some(l) => (start + l).min(cycles.len()),
-                    None => cycles.len(),
-                };
-                let page = &cycles[start..end];
-                if matches!(fmt, OutputFormat::Json) {
-                    let out: Vec<Vec<String>> = page
-                        .iter()
-                        .map(|cyc| cyc.iter().map(|p| p.display().to_string()).collect())
-                        .collect();
-                    match serde_json::to_string_pretty(&out) {
-                        Ok(s) => println!("{s}"),
-                        Err(e) => {
-                            eprintln!("JSON encode error: {e}");
-                            return 1;
+                    };
+                    let start = offset.min(cycles.len());
+                    let end = match limit {
+                        Some(l) => (start + l).min(cycles.len()),
+                        None => cycles.len(),
+                    };
+                    let page = &cycles[start..end];
+                    if matches!(fmt, OutputFormat::Json) {
+                        let out: Vec<Vec<String>> = page
+                            .iter()
+                            .map(|cyc| cyc.iter().map(|p| p.display().to_string()).collect())
+                            .collect();
+                        match serde_json::to_string_pretty(&out) {
+                            Ok(s) => println!("{s}"),
+                            Err(e) => {
+                                eprintln!("JSON encode error: {e}");
+                                return 1;
+                            }
+                        }
+                    } else {
+                        for cyc in page {
+                            let parts: Vec<String> =
+                                cyc.iter().map(|p| p.display().to_string()).collect();
+                            println!("{}", parts.join(" -> "));
                         }
                     }
-                } else {
-                    for cyc in page {
-                        let parts: Vec<String> =
-                            cyc.iter().map(|p| p.display().to_string()).collect();
-                        println!("{}", parts.join(" -> "));
-                    }
+                    0
                 }
-                0
-            }
-            QueryCommands::Path {
-                path,
-                config,
-                no_ignore,
-                from,
-                to,
-                graph: graph_path,
-                format,
-                offset,
-                limit,
-            } => {
-                let graph = if let Some(p) = graph_path {
-                    match KnowledgeGraph::load_json(std::path::Path::new(&p)) {
-                        Ok(g) => g,
-                        Err(e) => {
-                            eprintln!("Load graph failed: {e}");
-                            return 1;
+                QueryCommands::Path {
+                    path,
+                    config,
+                    no_ignore,
+                    from,
+                    to,
+                    graph: graph_path,
+                    format,
+                    offset,
+                    limit,
+                } => {
+                    let mut graph = if let Some(p) = graph_path {
+                        match load_graph(&p, graph_format, root.as_deref()) {
+                            Ok(g) => g,
+                            Err(e) => {
+                                eprintln!("Load graph failed: {e}");
+                                return 1;
+                            }
                         }
-                    }
-                } else {
-                    if no_ignore {
-                        std::env::set_var("KNOWLEDGE_RS_NO_IGNORE", "1");
-                    }
-                    let res = match KnowledgeGraph::build_from_directory(
-                        path.as_ref().unwrap().as_path(),
-                    ) {
-                        Ok(g) => g,
-                        Err(e) => {
-                            eprintln!("Build failed: {e}");
-                            if no_ignore {
-                                std::env::remove_var("KNOWLEDGE_RS_NO_IGNORE");
+                    } else {
+                        let opts = build
+                            .options(vec![path.clone().unwrap()], discovery.options(no_ignore));
+                        match graph_builder(opts, cli.verbose, cli.quiet).build() {
+                            Ok(g) => g,
+                            Err(e) => {
+                                eprintln!("Build failed: {e}");
+                                return 1;
                             }
-                            return 1;
                         }
                     };
-                    if no_ignore {
-                        std::env::remove_var("KNOWLEDGE_RS_NO_IGNORE");
+                    graph.retain_min_confidence(min_confidence);
+                    if let Some(f) = &filter {
+                        graph = graph.subgraph(f);
                     }
-                    res
-                };
-                let q = crate::query::ShortestPathQuery::new(&from, &to);
-                let results = q.run(&graph);
-                let fmt = if let Some(cfg_path) = config.as_ref() {
-                    if let Some(cfg) =
-                        crate::utils::config::load_config_at(std::path::Path::new(cfg_path))
-                    {
-                        match cfg.query.and_then(|q| q.default_format).as_deref() {
-                            Some("json") => OutputFormat::Json,
-                            Some("text") => OutputFormat::Text,
-                            _ => format,
+                    let q = crate::query::ShortestPathQuery::new(&from, &to);
+                    let results = q.run(&graph);
+                    let fmt = if let Some(cfg_path) = config.as_ref() {
+                        if let Some(cfg) =
+                            crate::utils::config::load_config_at(std::path::Path::new(cfg_path))
+                        {
+                            match cfg.query.and_then(|q| q.default_format).as_deref() {
+                                Some("json") => OutputFormat::Json,
+                                Some("text") => OutputFormat::Text,
+                                _ => format,
+                            }
+                        } else {
+                            format
                         }
                     } else {
                         format
-                    }
-                } else {
-                    format
-                };
-                if matches!(fmt, OutputFormat::Json) {
-                    let start = offset.min(results.len());
-                    let end = match limit {
-                        Some(l) => (start + l).min(results.len()),
-                        None => results.len(),
                     };
-                    let page = &results[start..end];
-                    let out: Vec<String> = page.iter().map(|p| p.display().to_string()).collect();
-                    match serde_json::to_string_pretty(&out) {
-                        Ok(s) => println!("{s}"),
-                        Err(e) => {
-                            eprintln!("JSON encode error: {e}");
-                            return 1;
+                    if matches!(fmt, OutputFormat::Json) {
+                        let start = offset.min(results.len());
+                        let end = match limit {
+                            Some(l) => (start + l).min(results.len()),
+                            None => results.len(),
+                        };
+                        let page = &results[start..end];
+                        let out: Vec<String> =
+                            page.iter().map(|p| p.display().to_string()).collect();
+                        match serde_json::to_string_pretty(&out) {
+                            Ok(s) => println!("{s}"),
+                            Err(e) => {
+                                eprintln!("JSON encode error: {e}");
+                                return 1;
+                            }
                         }
+                    } else if results.is_empty() {
+                        println!("<no path>");
+                    } else {
+                        let start = offset.min(results.len());
+                        let end = match limit {
+                            Some(l) => (start + l).min(results.len()),
+                            None => results.len(),
+                        };
+                        let page = &results[start..end];
+                        let rows: Vec<Vec<String>> = page
+                            .iter()
+                            .enumerate()
+                            .map(|(i, p)| {
+                                vec![format!("{}", start + i + 1), p.display().to_string()]
+                            })
+                            .collect();
+                        let table = crate::utils::table::render(&["Step", "Path"], &rows);
+                        println!("{table}");
                     }
-                } else if results.is_empty() {
-                    println!("<no path>");
-                } else {
-                    let start = offset.min(results.len());
-                    let end = match limit {
-                        Some(l) => (start + l).min(results.len()),
-                        None => results.len(),
-                    };
-                    let page = &results[start..end];
-                    let rows: Vec<Vec<String>> = page
-                        .iter()
-                        .enumerate()
-                        .map(|(i, p)| vec![format!("{}", start + i + 1), p.display().to_string()])
-                        .collect();
-                    let table = crate::utils::table::render(&["Step", "Path"], &rows);
-                    println!("{table}");
+                    0
                 }
-                0
-            }
-            QueryCommands::Hubs {
-                path,
-                config,
-                no_ignore,
-                graph: graph_path,
-                metric,
-                top,
-                format,
-                offset,
-                limit,
-            } => {
-                use crate::query::{CentralityMetric, HubsQuery};
-                let graph = if let Some(p) = graph_path {
-                    match KnowledgeGraph::load_json(std::path::Path::new(&p)) {
-                        Ok(g) => g,
-                        Err(e) => {
-                            eprintln!("Load graph failed: {e}");
-                            return 1;
+                QueryCommands::Hubs {
+                    path,
+                    config,
+                    no_ignore,
+                    graph: graph_path,
+                    metric,
+                    top,
+                    weighted,
+                    format,
+                    offset,
+                    limit,
+                } => {
+                    use crate::query::{CentralityMetric, HubsQuery};
+                    let mut graph = if let Some(p) = graph_path {
+                        match load_graph(&p, graph_format, root.as_deref()) {
+                            Ok(g) => g,
+                            Err(e) => {
+                                eprintln!("Load graph failed: {e}");
+                                return 1;
+                            }
                         }
-                    }
-                } else {
-                    if no_ignore {
-                        std::env::set_var("KNOWLEDGE_RS_NO_IGNORE", "1");
-                    }
-                    let res = match KnowledgeGraph::build_from_directory(
-                        path.as_ref().unwrap().as_path(),
-                    ) {
-                        Ok(g) => g,
-                        Err(e) => {
-                            eprintln!("Build failed: {e}");
-                            if no_ignore {
-                                std::env::remove_var("KNOWLEDGE_RS_NO_IGNORE");
+                    } else {
+                        let opts = build
+                            .options(vec![path.clone().unwrap()], discovery.options(no_ignore));
+                        match graph_builder(opts, cli.verbose, cli.quiet).build() {
+                            Ok(g) => g,
+                            Err(e) => {
+                                eprintln!("Build failed: {e}");
+                                return 1;
                             }
-                            return 1;
                         }
                     };
-                    if no_ignore {
-                        std::env::remove_var("KNOWLEDGE_RS_NO_IGNORE");
+                    graph.retain_min_confidence(min_confidence);
+                    if let Some(f) = &filter {
+                        graph = graph.subgraph(f);
                     }
-                    res
-                };
-                let m = match metric {
-                    crate::cli::CentralityMetricArg::In => CentralityMetric::In,
-                    crate::cli::CentralityMetricArg::Out => CentralityMetric::Out,
-                    crate::cli::CentralityMetricArg::Total => CentralityMetric::Total,
-                };
-                let q = HubsQuery::new(m, top);
-                let rows = q.run(&graph);
-                let fmt = if let Some(cfg_path) = config.as_ref() {
-                    if let Some(cfg) =
-                        crate::utils::config::load_config_at(std::path::Path::new(cfg_path))
-                    {
-                        match cfg.query.and_then(|q| q.default_format).as_deref() {
-                            Some("json") => OutputFormat::Json,
-                            Some("text") => OutputFormat::Text,
-                            _ => format,
+                    let m = match metric {
+                        crate::cli::CentralityMetricArg::In => CentralityMetric::In,
+                        crate::cli::CentralityMetricArg::Out => CentralityMetric::Out,
+                        crate::cli::CentralityMetricArg::Total => CentralityMetric::Total,
+                    };
+                    let q = HubsQuery::new(m, top).weighted(weighted);
+                    let rows = q.run(&graph);
+                    let fmt = if let Some(cfg_path) = config.as_ref() {
+                        if let Some(cfg) =
+                            crate::utils::config::load_config_at(std::path::Path::new(cfg_path))
+                        {
+                            match cfg.query.and_then(|q| q.default_format).as_deref() {
+                                Some("json") => OutputFormat::Json,
+                                Some("text") => OutputFormat::Text,
+                                _ => format,
+                            }
+                        } else {
+                            format
                         }
                     } else {
                         format
-                    }
-                } else {
-                    format
-                };
-                let start = offset.min(rows.len());
-                let end = match limit {
-                    Some(l) => (start + l).min(rows.len()),
-                    None => rows.len(),
-                };
-                let page = &rows[start..end];
-                if matches!(fmt, OutputFormat::Json) {
-                    #[derive(serde::Serialize)]
-                    struct HubRow {
-                        path: String,
-                        indegree: usize,
-                        outdegree: usize,
-                    }
-                    let out: Vec<HubRow> = page
-                        .iter()
-                        .map(|(p, i, o)| HubRow {
-                            path: p.display().to_string(),
-                            indegree: *i,
-                            outdegree: *o,
-                        })
-                        .collect();
-                    match serde_json::to_string_pretty(&out) {
-                        Ok(s) => println!("{s}"),
-                        Err(e) => {
-                            eprintln!("JSON encode error: {e}");
-                            return 1;
+                    };
+                    let start = offset.min(rows.len());
+                    let end = match limit {
+                        Some(l) => (start + l).min(rows.len()),
+                        None => rows.len(),
+                    };
+                    let page = &rows[start..end];
+                    if matches!(fmt, OutputFormat::Json) {
+                        #[derive(serde::Serialize)]
+                        struct HubRow {
+                            path: String,
+                            indegree: f64,
+                            outdegree: f64,
                         }
-                    }
-                } else {
-                    let body: Vec<Vec<String>> = if cli.verbose == 0 {
-                        page.iter()
-                            .map(|(p, i, o)| vec![p.display().to_string(), (i + o).to_string()])
-                            .collect()
-                    } else {
-                        page.iter()
-                            .map(|(p, i, o)| {
-                                vec![
-                                    p.display().to_string(),
-                                    i.to_string(),
-                                    o.to_string(),
-                                    (i + o).to_string(),
-                                ]
+                        let out: Vec<HubRow> = page
+                            .iter()
+                            .map(|(p, i, o)| HubRow {
+                                path: p.display().to_string(),
+                                indegree: *i,
+                                outdegree: *o,
                             })
-                            .collect()
-                    };
-                    let headers: &[&str] = if cli.verbose == 0 {
-                        &["Path", "Total"]
+                            .collect();
+                        match serde_json::to_string_pretty(&out) {
+                            Ok(s) => println!("{s}"),
+                            Err(e) => {
+                                eprintln!("JSON encode error: {e}");
+                                return 1;
+                            }
+                        }
                     } else {
-                        &["Path", "In", "Out", "Total"]
-                    };
-                    let table = crate::utils::table::render(headers, &body);
-                    println!("{table}");
+                        let body: Vec<Vec<String>> = if cli.verbose == 0 {
+                            page.iter()
+                                .map(|(p, i, o)| vec![p.display().to_string(), degree(i + o)])
+                                .collect()
+                        } else {
+                            page.iter()
+                                .map(|(p, i, o)| {
+                                    vec![
+                                        p.display().to_string(),
+                                        degree(*i),
+                                        degree(*o),
+                                        degree(i + o),
+                                    ]
+                                })
+                                .collect()
+                        };
+                        let headers: &[&str] = if cli.verbose == 0 {
+                            &["Path", "Total"]
+                        } else {
+                            &["Path", "In", "Out", "Total"]
+                        };
+                        let table = crate::utils::table::render(headers, &body);
+                        println!("{table}");
+                    }
+                    0
                 }
-                0
-            }
-            QueryCommands::ModuleCentrality {
-                path,
-                config,
-                no_ignore,
-                graph: graph_path,
-                metric,
-                top,
-                format,
-                offset,
-                limit,
-            } => {
-                use crate::query::{CentralityMetric, ModuleCentralityQuery};
-                let graph = if let Some(p) = graph_path {
-                    match KnowledgeGraph::load_json(std::path::Path::new(&p)) {
-                        Ok(g) => g,
-                        Err(e) => {
-                            eprintln!("Load graph failed: {e}");
-                            return 1;
+                QueryCommands::ModuleCentrality {
+                    path,
+                    config,
+                    no_ignore,
+                    graph: graph_path,
+                    metric,
+                    top,
+                    weighted,
+                    format,
+                    offset,
+                    limit,
+                } => {
+                    use crate::query::{CentralityMetric, ModuleCentralityQuery};
+                    let mut graph = if let Some(p) = graph_path {
+                        match load_graph(&p, graph_format, root.as_deref()) {
+                            Ok(g) => g,
+                            Err(e) => {
+                                eprintln!("Load graph failed: {e}");
+                                return 1;
+                            }
                         }
-                    }
-                } else {
-                    if no_ignore {
-                        std::env::set_var("KNOWLEDGE_RS_NO_IGNORE", "1");
-                    }
-                    let res = match KnowledgeGraph::build_from_directory(
-                        path.as_ref().unwrap().as_path(),
-                    ) {
-                        Ok(g) => g,
-                        Err(e) => {
-                            eprintln!("Build failed: {e}");
-                            if no_ignore {
-                                std::env::remove_var("KNOWLEDGE_RS_NO_IGNORE");
+                    } else {
+                        let opts = build
+                            .options(vec![path.clone().unwrap()], discovery.options(no_ignore));
+                        match graph_builder(opts, cli.verbose, cli.quiet).build() {
+                            Ok(g) => g,
+                            Err(e) => {
+                                eprintln!("Build failed: {e}");
+                                return 1;
                             }
-                            return 1;
                         }
                     };
-                    if no_ignore {
-                        std::env::remove_var("KNOWLEDGE_RS_NO_IGNORE");
+                    graph.retain_min_confidence(min_confidence);
+                    if let Some(f) = &filter {
+                        graph = graph.subgraph(f);
                     }
-                    res
-                };
-                let m = match metric {
-                    crate::cli::CentralityMetricArg::In => CentralityMetric::In,
-                    crate::cli::CentralityMetricArg::Out => CentralityMetric::Out,
-                    crate::cli::CentralityMetricArg::Total => CentralityMetric::Total,
-                };
-                let q = ModuleCentralityQuery::new(m, top);
-                let rows = q.run(&graph);
-                let fmt = if let Some(cfg_path) = config.as_ref() {
-                    if let Some(cfg) =
-                        crate::utils::config::load_config_at(std::path::Path::new(cfg_path))
-                    {
-                        match cfg.query.and_then(|q| q.default_format).as_deref() {
-                            Some("json") => OutputFormat::Json,
-                            Some("text") => OutputFormat::Text,
-                            _ => format,
+                    let m = match metric {
+                        crate::cli::CentralityMetricArg::In => CentralityMetric::In,
+                        crate::cli::CentralityMetricArg::Out => CentralityMetric::Out,
+                        crate::cli::CentralityMetricArg::Total => CentralityMetric::Total,
+                    };
+                    let q = ModuleCentralityQuery::new(m, top).weighted(weighted);
+                    let rows = q.run(&graph);
+                    let fmt = if let Some(cfg_path) = config.as_ref() {
+                        if let Some(cfg) =
+                            crate::utils::config::load_config_at(std::path::Path::new(cfg_path))
+                        {
+                            match cfg.query.and_then(|q| q.default_format).as_deref() {
+                                Some("json") => OutputFormat::Json,
+                                Some("text") => OutputFormat::Text,
+                                _ => format,
+                            }
+                        } else {
+                            format
                         }
                     } else {
                         format
-                    }
-                } else {
-                    format
-                };
-                let start = offset.min(rows.len());
-                let end = match limit {
-                    Some(l) => (start + l).min(rows.len()),
-                    None => rows.len(),
-                };
-                let page = &rows[start..end];
-                if matches!(fmt, OutputFormat::Json) {
-                    #[derive(serde::Serialize)]
-                    struct Row {
-                        module: String,
-                        indegree: usize,
-                        outdegree: usize,
-                    }
-                    let out: Vec<Row> = page
-                        .iter()
-                        .map(|(p, i, o)| Row {
-                            module: p.display().to_string(),
-                            indegree: *i,
-                            outdegree: *o,
-                        })
-                        .collect();
-                    match serde_json::to_string_pretty(&out) {
-                        Ok(s) => println!("{s}"),
-                        Err(e) => {
-                            eprintln!("JSON encode error: {e}");
-                            return 1;
+                    };
+                    let start = offset.min(rows.len());
+                    let end = match limit {
+                        Some(l) => (start + l).min(rows.len()),
+                        None => rows.len(),
+                    };
+                    let page = &rows[start..end];
+                    if matches!(fmt, OutputFormat::Json) {
+                        #[derive(serde::Serialize)]
+                        struct Row {
+                            module: String,
+                            indegree: f64,
+                            outdegree: f64,
                         }
-                    }
-                } else {
-                    let body: Vec<Vec<String>> = if cli.verbose == 0 {
-                        page.iter()
-                            .map(|(p, i, o)| vec![p.display().to_string(), (i + o).to_string()])
-                            .collect()
-                    } else {
-                        page.iter()
-                            .map(|(p, i, o)| {
-                                vec![
-                                    p.display().to_string(),
-                                    i.to_string(),
-                                    o.to_string(),
-                                    (i + o).to_string(),
-                                ]
+                        let out: Vec<Row> = page
+                            .iter()
+                            .map(|(p, i, o)| Row {
+                                module: p.display().to_string(),
+                                indegree: *i,
+                                outdegree: *o,
                             })
-                            .collect()
-                    };
-                    let headers: &[&str] = if cli.verbose == 0 {
-                        &["Module", "Total"]
+                            .collect();
+                        match serde_json::to_string_pretty(&out) {
+                            Ok(s) => println!("{s}"),
+                            Err(e) => {
+                                eprintln!("JSON encode error: {e}");
+                                return 1;
+                            }
+                        }
                     } else {
-                        &["Module", "In", "Out", "Total"]
-                    };
-                    let table = crate::utils::table::render(headers, &body);
-                    println!("{table}");
+                        let body: Vec<Vec<String>> = if cli.verbose == 0 {
+                            page.iter()
+                                .map(|(p, i, o)| vec![p.display().to_string(), degree(i + o)])
+                                .collect()
+                        } else {
+                            page.iter()
+                                .map(|(p, i, o)| {
+                                    vec![
+                                        p.display().to_string(),
+                                        degree(*i),
+                                        degree(*o),
+                                        degree(i + o),
+                                    ]
+                                })
+                                .collect()
+                        };
+                        let headers: &[&str] = if cli.verbose == 0 {
+                            &["Module", "Total"]
+                        } else {
+                            &["Module", "In", "Out", "Total"]
+                        };
+                        let table = crate::utils::table::render(headers, &body);
+                        println!("{table}");
+                    }
+                    0
                 }
-                0
-            }
-            QueryCommands::TraitImpls {
-                path,
-                config,
-                no_ignore,
-                r#trait,
-                graph: graph_path,
-                format,
-                offset,
-                limit,
-            } => {
-                use crate::query::TraitImplsQuery;
-                let graph = if let Some(p) = graph_path {
-                    match KnowledgeGraph::load_json(std::path::Path::new(&p)) {
-                        Ok(g) => g,
-                        Err(e) => {
-                            eprintln!("Load graph failed: {e}");
-                            return 1;
+                QueryCommands::TraitImpls {
+                    path,
+                    config,
+                    no_ignore,
+                    r#trait,
+                    graph: graph_path,
+                    format,
+                    offset,
+                    limit,
+                } => {
+                    use crate::query::TraitImplsQuery;
+                    let mut graph = if let Some(p) = graph_path {
+                        match load_graph(&p, graph_format, root.as_deref()) {
+                            Ok(g) => g,
+                            Err(e) => {
+                                eprintln!("Load graph failed: {e}");
+                                return 1;
+                            }
                         }
-                    }
-                } else {
-                    if no_ignore {
-                        std::env::set_var("KNOWLEDGE_RS_NO_IGNORE", "1");
-                    }
-                    let res = match KnowledgeGraph::build_from_directory(
-                        path.as_ref().unwrap().as_path(),
-                    ) {
-                        Ok(g) => g,
-                        Err(e) => {
-                            eprintln!("Build failed: {e}");
-                            if no_ignore {
-                                std::env::remove_var("KNOWLEDGE_RS_NO_IGNORE");
+                    } else {
+                        let opts = build
+                            .options(vec![path.clone().unwrap()], discovery.options(no_ignore));
+                        match graph_builder(opts, cli.verbose, cli.quiet).build() {
+                            Ok(g) => g,
+                            Err(e) => {
+                                eprintln!("Build failed: {e}");
+                                return 1;
                             }
-                            return 1;
                         }
                     };
-                    if no_ignore {
-                        std::env::remove_var("KNOWLEDGE_RS_NO_IGNORE");
+                    graph.retain_min_confidence(min_confidence);
+                    if let Some(f) = &filter {
+                        graph = graph.subgraph(f);
                     }
-                    res
-                };
-                let q = TraitImplsQuery::new(&r#trait);
-                let rows = q.run(&graph);
-                let fmt = if let Some(cfg_path) = config.as_ref() {
-                    if let Some(cfg) =
-                        crate::utils::config::load_config_at(std::path::Path::new(cfg_path))
-                    {
-                        match cfg.query.and_then(|q| q.default_format).as_deref() {
-                            Some("json") => OutputFormat::Json,
-                            Some("text") => OutputFormat::Text,
-                            _ => format,
+                    let q = TraitImplsQuery::new(&r#trait);
+                    let rows = q.run(&graph);
+                    let fmt = if let Some(cfg_path) = config.as_ref() {
+                        if let Some(cfg) =
+                            crate::utils::config::load_config_at(std::path::Path::new(cfg_path))
+                        {
+                            match cfg.query.and_then(|q| q.default_format).as_deref() {
+                                Some("json") => OutputFormat::Json,
+                                Some("text") => OutputFormat::Text,
+                                _ => format,
+                            }
+                        } else {
+                            format
                         }
                     } else {
                         format
-                    }
-                } else {
-                    format
-                };
-                let start = offset.min(rows.len());
-                let end = match limit {
-                    Some(l) => (start + l).min(rows.len()),
-                    None => rows.len(),
-                };
-                let page = &rows[start..end];
-                if matches!(fmt, OutputFormat::Json) {
-                    #[derive(serde::Serialize)]
-                    struct Row {
-                        path: String,
-                        r#type: String,
-                    }
-                    let out: Vec<Row> = page
-                        .iter()
-                        .map(|(p, t)| Row { path: p.display().to_string(), r#type: t.to_string() })
-                        .collect();
-                    match serde_json::to_string_pretty(&out) {
-                        Ok(s) => println!("{s}"),
-                        Err(e) => {
-                            eprintln!("JSON encode error: {e}");
-                            return 1;
+                    };
+                    let start = offset.min(rows.len());
+                    let end = match limit {
+                        Some(l) => (start + l).min(rows.len()),
+                        None => rows.len(),
+                    };
+                    let page = &rows[start..end];
+                    if matches!(fmt, OutputFormat::Json) {
+                        #[derive(serde::Serialize)]
+                        struct Row {
+                            path: String,
+                            r#type: String,
                         }
-                    }
-                } else if rows.is_empty() {
-                    println!("<no implementations found>");
-                } else {
-                    let body: Vec<Vec<String>> = if cli.verbose == 0 {
-                        page.iter().map(|(p, _t)| vec![p.display().to_string()]).collect()
+                        let out: Vec<Row> = page
+                            .iter()
+                            .map(|(p, t)| Row {
+                                path: p.display().to_string(),
+                                r#type: t.to_string(),
+                            })
+                            .collect();
+                        match serde_json::to_string_pretty(&out) {
+                            Ok(s) => println!("{s}"),
+                            Err(e) => {
+                                eprintln!("JSON encode error: {e}");
+                                return 1;
+                            }
+                        }
+                    } else if rows.is_empty() {
+                        println!("<no implementations found>");
                     } else {
-                        page.iter().map(|(p, t)| vec![p.display().to_string(), t.clone()]).collect()
-                    };
-                    let headers: &[&str] =
-                        if cli.verbose == 0 { &["Path"] } else { &["Path", "Type"] };
-                    let table = crate::utils::table::render(headers, &body);
-                    println!("{table}");
+                        let body: Vec<Vec<String>> = if cli.verbose == 0 {
+                            page.iter().map(|(p, _t)| vec![p.display().to_string()]).collect()
+                        } else {
+                            page.iter()
+                                .map(|(p, t)| vec![p.display().to_string(), t.clone()])
+                                .collect()
+                        };
+                        let headers: &[&str] =
+                            if cli.verbose == 0 { &["Path"] } else { &["Path", "Type"] };
+                        let table = crate::utils::table::render(headers, &body);
+                        println!("{table}");
+                    }
+                    0
                 }
-                0
-            }
-            QueryCommands::UnreferencedItems {
-                path,
-                config,
-                no_ignore,
-                include_public,
-                exclude,
-                graph: graph_path,
-                format,
-                offset,
-                limit,
-            } => {
-                use crate::query::UnreferencedItemsQuery;
-                let graph = if let Some(p) = graph_path {
-                    match KnowledgeGraph::load_json(std::path::Path::new(&p)) {
-                        Ok(g) => g,
-                        Err(e) => {
-                            eprintln!("Load graph failed: {e}");
-                            return 1;
+                QueryCommands::UnreferencedItems {
+                    path,
+                    config,
+                    no_ignore,
+                    include_public,
+                    exclude,
+                    graph: graph_path,
+                    format,
+                    offset,
+                    limit,
+                } => {
+                    use crate::query::UnreferencedItemsQuery;
+                    let mut graph = if let Some(p) = graph_path {
+                        match load_graph(&p, graph_format, root.as_deref()) {
+                            Ok(g) => g,
+                            Err(e) => {
+                                eprintln!("Load graph failed: {e}");
+                                return 1;
+                            }
                         }
-                    }
-                } else {
-                    if no_ignore {
-                        std::env::set_var("KNOWLEDGE_RS_NO_IGNORE", "1");
-                    }
-                    let res = match KnowledgeGraph::build_from_directory(
-                        path.as_ref().unwrap().as_path(),
-                    ) {
-                        Ok(g) => g,
-                        Err(e) => {
-                            eprintln!("Build failed: {e}");
-                            if no_ignore {
-                                std::env::remove_var("KNOWLEDGE_RS_NO_IGNORE");
+                    } else {
+                        let opts = build
+                            .options(vec![path.clone().unwrap()], discovery.options(no_ignore));
+                        match graph_builder(opts, cli.verbose, cli.quiet).build() {
+                            Ok(g) => g,
+                            Err(e) => {
+                                eprintln!("Build failed: {e}");
+                                return 1;
                             }
-                            return 1;
                         }
                     };
-                    if no_ignore {
-                        std::env::remove_var("KNOWLEDGE_RS_NO_IGNORE");
+                    graph.retain_min_confidence(min_confidence);
+                    if let Some(f) = &filter {
+                        graph = graph.subgraph(f);
                     }
-                    res
-                };
-                let exclude_re = if let Some(pat) = exclude.as_ref() {
-                    match regex::Regex::new(pat) {
-                        Ok(r) => Some(r),
-                        Err(e) => {
-                            eprintln!("Invalid --exclude regex: {e}");
-                            return 1;
+                    let exclude_re = if let Some(pat) = exclude.as_ref() {
+                        match regex::Regex::new(pat) {
+                            Ok(r) => Some(r),
+                            Err(e) => {
+                                eprintln!("Invalid --exclude regex: {e}");
+                                return 1;
+                            }
                         }
-                    }
-                } else {
-                    None
-                };
-                let q = UnreferencedItemsQuery::new(include_public, exclude_re);
-                let rows = q.run(&graph);
-                let fmt = if let Some(cfg_path) = config.as_ref() {
-                    if let Some(cfg) =
-                        crate::utils::config::load_config_at(std::path::Path::new(cfg_path))
-                    {
-                        match cfg.query.and_then(|q| q.default_format).as_deref() {
-                            Some("json") => OutputFormat::Json,
-                            Some("text") => OutputFormat::Text,
-                            _ => format,
+                    } else {
+                        None
+                    };
+                    let q = UnreferencedItemsQuery::new(include_public, exclude_re);
+                    let rows = q.run(&graph);
+                    let fmt = if let Some(cfg_path) = config.as_ref() {
+                        if let Some(cfg) =
+                            crate::utils::config::load_config_at(std::path::Path::new(cfg_path))
+                        {
+                            match cfg.query.and_then(|q| q.default_format).as_deref() {
+                                Some("json") => OutputFormat::Json,
+                                Some("text") => OutputFormat::Text,
+                                _ => format,
+                            }
+                        } else {
+                            format
                         }
                     } else {
                         format
-                    }
-                } else {
-                    format
-                };
-                let start = offset.min(rows.len());
-                let end = match limit {
-                    Some(l) => (start + l).min(rows.len()),
-                    None => rows.len(),
-                };
-                let page = &rows[start..end];
-                if matches!(fmt, OutputFormat::Json) {
-                    #[derive(serde::Serialize)]
-                    struct Row {
-                        path: String,
-                        id: String,
-                        name: String,
-                        kind: String,
-                        visibility: String,
-                    }
-                    let out: Vec<Row> = page
-                        .iter()
-                        .map(|(p, id, name, kind, vis)| Row {
-                            path: p.display().to_string(),
-                            id: id.clone(),
-                            name: name.clone(),
-                            kind: kind.clone(),
-                            visibility: vis.clone(),
-                        })
-                        .collect();
-                    match serde_json::to_string_pretty(&out) {
-                        Ok(s) => println!("{s}"),
-                        Err(e) => {
-                            eprintln!("JSON encode error: {e}");
-                            return 1;
+                    };
+                    let start = offset.min(rows.len());
+                    let end = match limit {
+                        Some(l) => (start + l).min(rows.len()),
+                        None => rows.len(),
+                    };
+                    let page = &rows[start..end];
+                    if matches!(fmt, OutputFormat::Json) {
+                        #[derive(serde::Serialize)]
+                        struct Row {
+                            path: String,
+                            id: String,
+                            name: String,
+                            kind: String,
+                            visibility: String,
                         }
-                    }
-                } else if rows.is_empty() {
-                    println!("<no unreferenced items>");
-                } else {
-                    let body: Vec<Vec<String>> = if cli.verbose == 0 {
-                        page.iter()
-                            .map(|(p, _id, name, _kind, _vis)| {
-                                vec![p.display().to_string(), name.clone()]
+                        let out: Vec<Row> = page
+                            .iter()
+                            .map(|(p, id, name, kind, vis)| Row {
+                                path: p.display().to_string(),
+                                id: id.clone(),
+                                name: name.clone(),
+                                kind: kind.clone(),
+                                visibility: vis.clone(),
                             })
-                            .collect()
+                            .collect();
+                        match serde_json::to_string_pretty(&out) {
+                            Ok(s) => println!("{s}"),
+                            Err(e) => {
+                                eprintln!("JSON encode error: {e}");
+                                return 1;
+                            }
+                        }
+                    } else if rows.is_empty() {
+                        println!("<no unreferenced items>");
                     } else {
-                        page.iter()
-                            .map(|(p, id, name, kind, vis)| {
-                                vec![
-                                    p.display().to_string(),
-                                    id.clone(),
-                                    name.clone(),
-                                    kind.clone(),
-                                    vis.clone(),
-                                ]
-                            })
-                            .collect()
+                        let body: Vec<Vec<String>> = if cli.verbose == 0 {
+                            page.iter()
+                                .map(|(p, _id, name, _kind, _vis)| {
+                                    vec![p.display().to_string(), name.clone()]
+                                })
+                                .collect()
+                        } else {
+                            page.iter()
+                                .map(|(p, id, name, kind, vis)| {
+                                    vec![
+                                        p.display().to_string(),
+                                        id.clone(),
+                                        name.clone(),
+                                        kind.clone(),
+                                        vis.clone(),
+                                    ]
+                                })
+                                .collect()
+                        };
+                        let headers: &[&str] = if cli.verbose == 0 {
+                            &["Path", "Name"]
+                        } else {
+                            &["Path", "ItemId", "Name", "Kind", "Vis"]
+                        };
+                        let table = crate::utils::table::render(headers, &body);
+                        println!("{table}");
+                    }
+                    0
+                }
+                QueryCommands::PublicApi {
+                    path,
+                    config,
+                    no_ignore,
+                    graph: graph_path,
+                    format,
+                    offset,
+                    limit,
+                } => {
+                    let mut graph = if let Some(p) = graph_path {
+                        match load_graph(&p, graph_format, root.as_deref()) {
+                            Ok(g) => g,
+                            Err(e) => {
+                                eprintln!("Load graph failed: {e}");
+                                return 1;
+                            }
+                        }
+                    } else {
+                        let opts = build
+                            .options(vec![path.clone().unwrap()], discovery.options(no_ignore));
+                        match graph_builder(opts, cli.verbose, cli.quiet).build() {
+                            Ok(g) => g,
+                            Err(e) => {
+                                eprintln!("Build failed: {e}");
+                                return 1;
+                            }
+                        }
                     };
-                    let headers: &[&str] = if cli.verbose == 0 {
-                        &["Path", "Name"]
+                    graph.retain_min_confidence(min_confidence);
+                    if let Some(f) = &filter {
+                        graph = graph.subgraph(f);
+                    }
+                    let rows = crate::query::PublicApiQuery::new().run(&graph);
+                    let fmt = if let Some(cfg_path) = config.as_ref() {
+                        if let Some(cfg) =
+                            crate::utils::config::load_config_at(std::path::Path::new(cfg_path))
+                        {
+                            match cfg.query.and_then(|q| q.default_format).as_deref() {
+                                Some("json") => OutputFormat::Json,
+                                Some("text") => OutputFormat::Text,
+                                _ => format,
+                            }
+                        } else {
+                            format
+                        }
                     } else {
-                        &["Path", "ItemId", "Name", "Kind", "Vis"]
+                        format
                     };
-                    let table = crate::utils::table::render(headers, &body);
-                    println!("{table}");
-                }
-                0
-            }
-            QueryCommands::ItemInfo {
-                path,
-                config,
-                no_ignore,
-                item_id,
-                name,
-                kind,
-                graph: graph_path,
-                show_code,
-                format,
-            } => {
-                use crate::query::ItemInfoQuery;
-                let graph = if let Some(p) = graph_path {
-                    match KnowledgeGraph::load_json(std::path::Path::new(&p)) {
-                        Ok(g) => g,
-                        Err(e) => {
-                            eprintln!("Load graph failed: {e}");
-                            return 1;
+                    let start = offset.min(rows.len());
+                    let end = match limit {
+                        Some(l) => (start + l).min(rows.len()),
+                        None => rows.len(),
+                    };
+                    let page = &rows[start..end];
+                    if matches!(fmt, OutputFormat::Json) {
+                        match serde_json::to_string_pretty(page) {
+                            Ok(s) => println!("{s}"),
+                            Err(e) => {
+                                eprintln!("JSON encode error: {e}");
+                                return 1;
+                            }
+                        }
+                    } else {
+                        // One line per path, diffable like a `cargo public-api` baseline
+                        for e in page {
+                            if cli.verbose == 0 {
+                                println!("pub {} {}", e.kind, e.path);
+                            } else {
+                                println!("pub {} {}  ({})", e.kind, e.path, e.id);
+                            }
                         }
                     }
-                } else {
-                    if no_ignore {
-                        std::env::set_var("KNOWLEDGE_RS_NO_IGNORE", "1");
-                    }
-                    let res = match KnowledgeGraph::build_from_directory(
-                        path.as_ref().unwrap().as_path(),
-                    ) {
-                        Ok(g) => g,
-                        Err(e) => {
-                            eprintln!("Build failed: {e}");
-                            if no_ignore {
-                                std::env::remove_var("KNOWLEDGE_RS_NO_IGNORE");
+                    0
+                }
+                QueryCommands::ItemInfo {
+                    path,
+                    config,
+                    no_ignore,
+                    item_id,
+                    name,
+                    kind,
+                    graph: graph_path,
+                    show_code,
+                    format,
+                } => {
+                    use crate::query::ItemInfoQuery;
+                    let mut graph = if let Some(p) = graph_path {
+                        match load_graph(&p, graph_format, root.as_deref()) {
+                            Ok(g) => g,
+                            Err(e) => {
+                                eprintln!("Load graph failed: {e}");
+                                return 1;
+                            }
+                        }
+                    } else {
+                        let opts = build
+                            .options(vec![path.clone().unwrap()], discovery.options(no_ignore));
+                        match graph_builder(opts, cli.verbose, cli.quiet).build() {
+                            Ok(g) => g,
+                            Err(e) => {
+                                eprintln!("Build failed: {e}");
+                                return 1;
                             }
-                            return 1;
                         }
                     };
-                    if no_ignore {
-                        std::env::remove_var("KNOWLEDGE_RS_NO_IGNORE");
+                    graph.retain_min_confidence(min_confidence);
+                    if let Some(f) = &filter {
+                        graph = graph.subgraph(f);
                     }
-                    res
-                };
-                // Determine target ItemId: prefer explicit --item-id, else resolve by --name/--kind
-                let id = if let Some(raw_id) = item_id {
-                    crate::graph::ItemId(raw_id)
-                } else if let Some(nm) = name {
-                    use crate::graph::{resolver::Resolver, ItemId, ItemType};
-                    use std::path::Path;
+                    // Determine target ItemId: prefer explicit --item-id, else resolve by --name/--kind
+                    let id = if let Some(raw_id) = item_id {
+                        // Accepts current ids and the line-based ids of earlier versions
+                        let mut ids = graph.find_item_ids(&raw_id);
+                        if ids.len() > 1 {
+                            eprintln!("Item id '{raw_id}' is ambiguous; candidates:");
+                            for id in &ids {
+                                eprintln!("  {}", id.0);
+                            }
+                            return 1;
+                        }
+                        ids.pop().unwrap_or(crate::graph::ItemId(raw_id))
+                    } else if let Some(nm) = name {
+                        use crate::graph::{resolver::Resolver, ItemId, ItemType};
+                        use std::path::Path;
 
-                    let resolver = Resolver::new(&graph);
-                    let mut ids: Vec<ItemId> = resolver.find_by_name(&nm);
-                    if let Some(k) = kind {
-                        ids.retain(|id| {
-                            matches!(
-                                (k, resolver.item_kind(id)),
-                                (ItemKindArg::Module, Some(ItemType::Module { .. }))
-                                    | (ItemKindArg::Function, Some(ItemType::Function { .. }))
-                                    | (ItemKindArg::Struct, Some(ItemType::Struct { .. }))
-                                    | (ItemKindArg::Enum, Some(ItemType::Enum { .. }))
-                                    | (ItemKindArg::Trait, Some(ItemType::Trait { .. }))
-                                    | (ItemKindArg::Impl, Some(ItemType::Impl { .. }))
-                                    | (ItemKindArg::Const, Some(ItemType::Const))
-                                    | (ItemKindArg::Static, Some(ItemType::Static { .. }))
-                                    | (ItemKindArg::Type, Some(ItemType::Type))
-                                    | (ItemKindArg::Macro, Some(ItemType::Macro))
-                            )
-                        });
-                    }
-                    if ids.is_empty() {
-                        eprintln!("No item found with name '{nm}'.");
+                        let resolver = Resolver::new(&graph);
+                        let mut ids: Vec<ItemId> = resolver.find_by_name(&nm);
                         if let Some(k) = kind {
-                            eprintln!("Hint: try a different --kind (current: {:?})", k);
+                            ids.retain(|id| {
+                                matches!(
+                                    (k, resolver.item_kind(id)),
+                                    (ItemKindArg::Module, Some(ItemType::Module { .. }))
+                                        | (ItemKindArg::Function, Some(ItemType::Function { .. }))
+                                        | (ItemKindArg::Struct, Some(ItemType::Struct { .. }))
+                                        | (ItemKindArg::Enum, Some(ItemType::Enum { .. }))
+                                        | (ItemKindArg::Trait, Some(ItemType::Trait { .. }))
+                                        | (ItemKindArg::Impl, Some(ItemType::Impl { .. }))
+                                        | (ItemKindArg::Const, Some(ItemType::Const))
+                                        | (ItemKindArg::Static, Some(ItemType::Static { .. }))
+                                        | (ItemKindArg::Type, Some(ItemType::Type))
+                                        | (ItemKindArg::Macro, Some(ItemType::Macro))
+                                )
+                            });
+                        }
+                        if ids.is_empty() {
+                            eprintln!("No item found with name '{nm}'.");
+                            if let Some(k) = kind {
+                                eprintln!("Hint: try a different --kind (current: {:?})", k);
+                            }
+                            return 1;
                         }
-                        return 1;
-                    }
 
-                    // Build candidate tuples (id, kind_str, path)
-                    let mut candidates: Vec<(ItemId, String, std::path::PathBuf)> =
-                        Vec::with_capacity(ids.len());
-                    for id in ids.into_iter() {
-                        let kind_s = match resolver.item_kind(&id) {
-                            Some(ItemType::Module { .. }) => "module",
-                            Some(ItemType::Function { .. }) => "function",
-                            Some(ItemType::Struct { .. }) => "struct",
-                            Some(ItemType::Enum { .. }) => "enum",
-                            Some(ItemType::Trait { .. }) => "trait",
-                            Some(ItemType::Impl { .. }) => "impl",
-                            Some(ItemType::Const) => "const",
-                            Some(ItemType::Static { .. }) => "static",
-                            Some(ItemType::Type) => "type",
-                            Some(ItemType::Macro) => "macro",
-                            None => "?",
-                        };
-                        if let Some(p) = resolver.item_path(&id) {
-                            candidates.push((id, kind_s.to_string(), p.clone()));
+                        // Build candidate tuples (id, kind_str, path)
+                        let mut candidates: Vec<(ItemId, String, std::path::PathBuf)> =
+                            Vec::with_capacity(ids.len());
+                        for id in ids.into_iter() {
+                            let kind_s = match resolver.item_kind(&id) {
+                                Some(ItemType::Module { .. }) => "module",
+                                Some(ItemType::Function { .. }) => "function",
+                                Some(ItemType::Struct { .. }) => "struct",
+                                Some(ItemType::Enum { .. }) => "enum",
+                                Some(ItemType::Trait { .. }) => "trait",
+                                Some(ItemType::Impl { .. }) => "impl",
+                                Some(ItemType::Const) => "const",
+                                Some(ItemType::Static { .. }) => "static",
+                                Some(ItemType::Type) => "type",
+                                Some(ItemType::Macro) => "macro",
+                                None => "?",
+                            };
+                            if let Some(p) = resolver.item_path(&id) {
+                                candidates.push((id, kind_s.to_string(), p.clone()));
+                            }
+                        }
+                        if candidates.is_empty() {
+                            eprintln!("No item found with name '{nm}'.");
+                            return 1;
                         }
-                    }
-                    if candidates.is_empty() {
-                        eprintln!("No item found with name '{nm}'.");
-                        return 1;
-                    }
 
-                    // Prefer matches in current crate src/ if path is known
-                    let root_src: Option<std::path::PathBuf> = path
-                        .as_ref()
-                        .map(|pb| pb.join("src"))
-                        .or_else(|| std::env::current_dir().ok().map(|d| d.join("src")));
+                        // Prefer matches in current crate src/ if path is known
+                        let root_src: Option<std::path::PathBuf> = path
+                            .as_ref()
+                            .map(|pb| pb.join("src"))
+                            .or_else(|| std::env::current_dir().ok().map(|d| d.join("src")));
 
-                    // Rank: (in_root_src desc, shallower depth asc, path lex asc)
-                    let mut ranked = candidates;
-                    if let Some(root_src_path) = root_src.as_ref() {
-                        let root_src_canon = root_src_path;
-                        ranked.sort_by(|a, b| {
-                            let a_in = a.2.starts_with(root_src_canon);
-                            let b_in = b.2.starts_with(root_src_canon);
-                            let in_cmp = b_in.cmp(&a_in); // true first
-                            if in_cmp != std::cmp::Ordering::Equal {
-                                return in_cmp;
-                            }
-                            let depth = |p: &Path| -> usize {
-                                let comps: Vec<_> = p.components().collect();
-                                let mut seen_src = false;
-                                let mut c = 0usize;
-                                for comp in comps {
-                                    if let std::path::Component::Normal(os) = comp {
-                                        if os.to_str() == Some("src") {
-                                            seen_src = true;
-                                            continue;
-                                        }
-                                        if seen_src {
-                                            c += 1;
+                        // Rank: (in_root_src desc, shallower depth asc, path lex asc)
+                        let mut ranked = candidates;
+                        if let Some(root_src_path) = root_src.as_ref() {
+                            let root_src_canon = root_src_path;
+                            ranked.sort_by(|a, b| {
+                                let a_in = a.2.starts_with(root_src_canon);
+                                let b_in = b.2.starts_with(root_src_canon);
+                                let in_cmp = b_in.cmp(&a_in); // true first
+                                if in_cmp != std::cmp::Ordering::Equal {
+                                    return in_cmp;
+                                }
+                                let depth = |p: &Path| -> usize {
+                                    let comps: Vec<_> = p.components().collect();
+                                    let mut seen_src = false;
+                                    let mut c = 0usize;
+                                    for comp in comps {
+                                        if let std::path::Component::Normal(os) = comp {
+                                            if os.to_str() == Some("src") {
+                                                seen_src = true;
+                                                continue;
+                                            }
+                                            if seen_src {
+                                                c += 1;
+                                            }
                                         }
                                     }
+                                    c
+                                };
+                                let a_d = depth(&a.2);
+                                let b_d = depth(&b.2);
+                                let d_cmp = a_d.cmp(&b_d);
+                                if d_cmp != std::cmp::Ordering::Equal {
+                                    return d_cmp;
                                 }
-                                c
-                            };
-                            let a_d = depth(&a.2);
-                            let b_d = depth(&b.2);
-                            let d_cmp = a_d.cmp(&b_d);
-                            if d_cmp != std::cmp::Ordering::Equal {
-                                return d_cmp;
-                            }
-                            a.2.cmp(&b.2)
-                        });
-                    } else {
-                        ranked.sort_by(|a, b| a.2.cmp(&b.2));
-                    }
+                                a.2.cmp(&b.2)
+                            });
+                        } else {
+                            ranked.sort_by(|a, b| a.2.cmp(&b.2));
+                        }
 
-                    // If still multiple and top two tie in rank dimensions, present ambiguity
-                    let top = &ranked[0];
-                    let same_rank = ranked
-                        .iter()
-                        .take_while(|cand| {
-                            let a = cand;
-                            let b = top;
-                            let a_in = if let Some(r) = root_src.as_ref() {
-                                a.2.starts_with(r)
-                            } else {
-                                false
-                            };
-                            let b_in = if let Some(r) = root_src.as_ref() {
-                                b.2.starts_with(r)
-                            } else {
-                                false
-                            };
-                            let depth = |p: &Path| -> usize {
-                                let comps: Vec<_> = p.components().collect();
-                                let mut seen_src = false;
-                                let mut c = 0usize;
-                                for comp in comps {
-                                    if let std::path::Component::Normal(os) = comp {
-                                        if os.to_str() == Some("src") {
-                                            seen_src = true;
-                                            continue;
-                                        }
-                                        if seen_src {
-                                            c += 1;
+                        // If still multiple and top two tie in rank dimensions, present ambiguity
+                        let top = &ranked[0];
+                        let same_rank = ranked
+                            .iter()
+                            .take_while(|cand| {
+                                let a = cand;
+                                let b = top;
+                                let a_in = if let Some(r) = root_src.as_ref() {
+                                    a.2.starts_with(r)
+                                } else {
+                                    false
+                                };
+                                let b_in = if let Some(r) = root_src.as_ref() {
+                                    b.2.starts_with(r)
+                                } else {
+                                    false
+                                };
+                                let depth = |p: &Path| -> usize {
+                                    let comps: Vec<_> = p.components().collect();
+                                    let mut seen_src = false;
+                                    let mut c = 0usize;
+                                    for comp in comps {
+                                        if let std::path::Component::Normal(os) = comp {
+                                            if os.to_str() == Some("src") {
+                                                seen_src = true;
+                                                continue;
+                                            }
+                                            if seen_src {
+                                                c += 1;
+                                            }
                                         }
                                     }
-                                }
-                                c
-                            };
-                            a_in == b_in && depth(&a.2) == depth(&b.2)
-                        })
-                        .count();
-                    if ranked.len() > 1 && same_rank > 1 {
-                        eprintln!("Ambiguous name '{nm}'. Top matches:");
-                        for (cid, ck, cp) in ranked.iter().take(10) {
-                            eprintln!("- id={}  kind={}  path={}", cid.0, ck, cp.display());
-                        }
-                        eprintln!("Disambiguate by providing --item-id or add --kind.");
-                        return 1;
-                    }
-                    top.0.clone()
-                } else {
-                    eprintln!("Missing --item-id or --name for item-info.");
-                    return 1;
-                };
-                let q = ItemInfoQuery::new(id, show_code);
-                let result = q.run(&graph);
-                let fmt = if let Some(cfg_path) = config.as_ref() {
-                    if let Some(cfg) =
-                        crate::utils::config::load_config_at(std::path::Path::new(cfg_path))
-                    {
-                        match cfg.query.and_then(|q| q.default_format).as_deref() {
-                            Some("json") => OutputFormat::Json,
-                            Some("text") => OutputFormat::Text,
-                            _ => format,
+                                    c
+                                };
+                                a_in == b_in && depth(&a.2) == depth(&b.2)
+                            })
+                            .count();
+                        if ranked.len() > 1 && same_rank > 1 {
+                            eprintln!("Ambiguous name '{nm}'. Top matches:");
+                            for (cid, ck, cp) in ranked.iter().take(10) {
+                                eprintln!("- id={}  kind={}  path={}", cid.0, ck, cp.display());
+                            }
+                            eprintln!("Disambiguate by providing --item-id or add --kind.");
+                            return 1;
                         }
+                        top.0.clone()
                     } else {
-                        format
-                    }
-                } else {
-                    format
-                };
-                if matches!(fmt, OutputFormat::Json) {
-                    // Trim heavy fields when not verbose: drop code and relation contexts
-                    let result = if cli.verbose == 0 {
-                        result.map(|mut info| {
-                            info.code = None;
-                            for r in &mut info.inbound {
-                                r.context.clear();
-                            }
-                            for r in &mut info.outbound {
-                                r.context.clear();
+                        eprintln!("Missing --item-id or --name for item-info.");
+                        return 1;
+                    };
+                    let q = ItemInfoQuery::new(id, show_code);
+                    let result = q.run(&graph);
+                    let fmt = if let Some(cfg_path) = config.as_ref() {
+                        if let Some(cfg) =
+                            crate::utils::config::load_config_at(std::path::Path::new(cfg_path))
+                        {
+                            match cfg.query.and_then(|q| q.default_format).as_deref() {
+                                Some("json") => OutputFormat::Json,
+                                Some("text") => OutputFormat::Text,
+                                _ => format,
                             }
-                            info
-                        })
+                        } else {
+                            format
+                        }
                     } else {
-                        result
+                        format
                     };
-                    match serde_json::to_string_pretty(&result) {
-                        Ok(s) => println!("{s}"),
-                        Err(e) => {
-                            eprintln!("JSON encode error: {e}");
-                            return 1;
+                    if matches!(fmt, OutputFormat::Json) {
+                        // Trim heavy fields when not verbose: drop code and relation contexts
+                        let result = if cli.verbose == 0 {
+                            result.map(|mut info| {
+                                info.code = None;
+                                for r in info.inbound.iter_mut().chain(&mut info.outbound) {
+                                    r.context.clear();
+                                    r.evidence.clear();
+                                }
+                                info
+                            })
+                        } else {
+                            result
+                        };
+                        match serde_json::to_string_pretty(&result) {
+                            Ok(s) => println!("{s}"),
+                            Err(e) => {
+                                eprintln!("JSON encode error: {e}");
+                                return 1;
+                            }
                         }
-                    }
-                } else {
-                    match result {
-                        None => println!("<item not found>"),
-                        Some(info) => {
-                            println!("Item: {}", info.name);
-                            println!("Id: {}", info.id);
-                            println!("Kind: {}", info.kind);
-                            println!("Vis: {}", info.visibility);
-                            println!(
-                                "Location: {}:{}-{}",
-                                info.path, info.line_start, info.line_end
-                            );
-                            if cli.verbose == 0 {
-                                let callers: String = if info.inbound.is_empty() {
-                                    "<none>".to_string()
-                                } else {
-                                    info.inbound
-                                        .iter()
-                                        .map(|r| r.id.clone())
-                                        .collect::<Vec<_>>()
-                                        .join(", ")
-                                };
-                                let callees: String = if info.outbound.is_empty() {
-                                    "<none>".to_string()
-                                } else {
-                                    info.outbound
-                                        .iter()
-                                        .map(|r| r.id.clone())
-                                        .collect::<Vec<_>>()
-                                        .join(", ")
-                                };
-                                println!("\nCallers: {}", callers);
-                                println!("Callees: {}", callees);
-                            } else {
-                                if show_code {
-                                    if let Some(code) = info.code.as_deref() {
-                                        println!("\n--- code ---\n{}\n--- end code ---", code);
-                                    }
+                    } else {
+                        match result {
+                            None => println!("<item not found>"),
+                            Some(info) => {
+                                println!("Item: {}", info.name);
+                                println!("Id: {}", info.id);
+                                println!("Kind: {}", info.kind);
+                                println!("Vis: {}", info.visibility);
+                                if let Some(canonical) = info.canonical_path.as_deref() {
+                                    println!("Path: {canonical}");
                                 }
-                                if info.inbound.is_empty() {
-                                    println!("\nCallers: <none>");
-                                } else {
-                                    println!("\nCallers:");
-                                    for r in info.inbound {
-                                        println!(
-                                            "- [{}] {} ({}) @ {} :: {}",
-                                            r.relation, r.name, r.id, r.path, r.context
-                                        );
-                                    }
+                                for p in &info.public_paths {
+                                    println!("Public: {p}");
                                 }
-                                if info.outbound.is_empty() {
-                                    println!("\nCallees: <none>");
+                                println!(
+                                    "Location: {}:{}-{}",
+                                    info.path, info.line_start, info.line_end
+                                );
+                                if cli.verbose == 0 {
+                                    let callers: String = if info.inbound.is_empty() {
+                                        "<none>".to_string()
+                                    } else {
+                                        info.inbound
+                                            .iter()
+                                            .map(|r| r.id.clone())
+                                            .collect::<Vec<_>>()
+                                            .join(", ")
+                                    };
+                                    let callees: String = if info.outbound.is_empty() {
+                                        "<none>".to_string()
+                                    } else {
+                                        info.outbound
+                                            .iter()
+                                            .map(|r| r.id.clone())
+                                            .collect::<Vec<_>>()
+                                            .join(", ")
+                                    };
+                                    println!("\nCallers: {}", callers);
+                                    println!("Callees: {}", callees);
                                 } else {
-                                    println!("\nCallees:");
-                                    for r in info.outbound {
-                                        println!(
-                                            "- [{}] {} ({}) @ {} :: {}",
-                                            r.relation, r.name, r.id, r.path, r.context
-                                        );
+                                    if show_code {
+                                        if let Some(code) = info.code.as_deref() {
+                                            println!("\n--- code ---\n{}\n--- end code ---", code);
+                                        }
+                                    }
+                                    if info.inbound.is_empty() {
+                                        println!("\nCallers: <none>");
+                                    } else {
+                                        println!("\nCallers:");
+                                        for r in info.inbound {
+                                            println!(
+                                                "- [{}] {} ({}) @ {} :: {}{}",
+                                                r.relation,
+                                                r.name,
+                                                r.id,
+                                                r.path,
+                                                r.context,
+                                                times(r.count)
+                                            );
+                                            print_evidence("    ", &r.evidence);
+                                        }
+                                    }
+                                    if info.outbound.is_empty() {
+                                        println!("\nCallees: <none>");
+                                    } else {
+                                        println!("\nCallees:");
+                                        for r in info.outbound {
+                                            println!(
+                                                "- [{}] {} ({}) @ {} :: {}{}",
+                                                r.relation,
+                                                r.name,
+                                                r.id,
+                                                r.path,
+                                                r.context,
+                                                times(r.count)
+                                            );
+                                            print_evidence("    ", &r.evidence);
+                                        }
                                     }
                                 }
                             }
                         }
                     }
+                    0
                 }
-                0
             }
-        },
+        }
     }
 }
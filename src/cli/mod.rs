@@ -1,5 +1,6 @@
 use crate::utils::project_root::effective_path_opt;
-use clap::{ArgAction, Parser, Subcommand, ValueEnum};
+use clap::builder::BoolishValueParser;
+use clap::{ArgAction, Args, Parser, Subcommand, ValueEnum};
 use clap_complete::Shell;
 use serde::Deserialize;
 use std::path::PathBuf;
@@ -81,6 +82,14 @@ struct ConfigFile {
     svg: SvgConfig,
     #[serde(default)]
     query: QueryConfig,
+    #[serde(default)]
+    filter: crate::utils::config::FilterConfig,
+    #[serde(default)]
+    diff: crate::utils::config::DiffConfig,
+    #[serde(default)]
+    cache: crate::utils::config::CacheConfig,
+    #[serde(default)]
+    build: crate::utils::config::BuildConfig,
 }
 
 #[derive(Debug, Default, Deserialize)]
@@ -91,6 +100,7 @@ struct DotConfig {
     rankdir: Option<String>, // "LR" | "TB"
     splines: Option<String>, // "curved" | "ortho" | "polyline"
     rounded: Option<bool>,
+    weighted: Option<bool>,
 }
 
 #[derive(Debug, Default, Deserialize)]
@@ -108,6 +118,21 @@ fn load_config(path: &str) -> Option<ConfigFile> {
     toml::from_str::<ConfigFile>(&content).ok()
 }
 
+fn query_config_path(q: &QueryCommands) -> Option<&str> {
+    match q {
+        QueryCommands::ConnectedFiles { config, .. }
+        | QueryCommands::ItemInfo { config, .. }
+        | QueryCommands::FunctionUsage { config, .. }
+        | QueryCommands::Cycles { config, .. }
+        | QueryCommands::Path { config, .. }
+        | QueryCommands::Hubs { config, .. }
+        | QueryCommands::ModuleCentrality { config, .. }
+        | QueryCommands::TraitImpls { config, .. }
+        | QueryCommands::UnreferencedItems { config, .. }
+        | QueryCommands::PublicApi { config, .. } => config.as_deref(),
+    }
+}
+
 fn on_off(b: bool) -> OnOffArg {
     if b {
         OnOffArg::On
@@ -144,6 +169,332 @@ fn parse_format(s: &str) -> Option<OutputFormat> {
         _ => None,
     }
 }
+fn parse_min_confidence(s: &str) -> Result<f64, String> {
+    let v: f64 = s.parse().map_err(|_| format!("`{s}` is not a number"))?;
+    if (0.0..=1.0).contains(&v) {
+        Ok(v)
+    } else {
+        Err(format!("{v} is outside 0.0..=1.0"))
+    }
+}
+
+/// Cache location shared by `build` and `cache`; see `CacheLocation`.
+#[derive(Debug, Clone, Default, Args)]
+pub struct CacheLocationArgs {
+    /// Keep the cache in this directory, one file per project, instead of the per-user cache
+    /// directory
+    #[arg(long, value_name = "DIR", global = true, env = "RRE_CACHE_DIR")]
+    pub cache_dir: Option<PathBuf>,
+    /// Keep the cache in `.knowledge_cache/` in the project root
+    #[arg(long, default_value_t = false, global = true)]
+    pub project_cache: bool,
+}
+
+impl CacheLocationArgs {
+    // Take the `[cache]` config section when no location flag was given.
+    fn fill_from(&mut self, cfg: &crate::utils::config::CacheConfig) {
+        if self.cache_dir.is_some() || self.project_cache {
+            return;
+        }
+        self.cache_dir = cfg.dir.as_ref().map(PathBuf::from);
+        self.project_cache = cfg.project.unwrap_or(false);
+    }
+
+    /// The selected location; `--cache-dir` wins over `--project-cache`, and
+    /// `rust-relations-explorer/` in the per-user cache directory is the default.
+    #[must_use]
+    pub fn location(&self) -> crate::utils::cache::CacheLocation {
+        use crate::utils::cache::CacheLocation;
+        match &self.cache_dir {
+            Some(dir) => CacheLocation::Dir(dir.clone()),
+            None if self.project_cache => CacheLocation::Project,
+            None => CacheLocation::Dir(
+                user_cache_dir().unwrap_or_else(std::env::temp_dir).join("rust-relations-explorer"),
+            ),
+        }
+    }
+}
+
+/// The platform's per-user cache directory: `$XDG_CACHE_HOME` or `~/.cache` on Unix,
+/// `~/Library/Caches` on macOS and `%LOCALAPPDATA%` on Windows.
+#[must_use]
+pub fn user_cache_dir() -> Option<PathBuf> {
+    let var = |k: &str| std::env::var_os(k).filter(|v| !v.is_empty()).map(PathBuf::from);
+    if cfg!(windows) {
+        return var("LOCALAPPDATA");
+    }
+    if cfg!(target_os = "macos") {
+        return var("HOME").map(|h| h.join("Library/Caches"));
+    }
+    var("XDG_CACHE_HOME").or_else(|| var("HOME").map(|h| h.join(".cache")))
+}
+
+/// Cache and worker settings of every command that builds a graph; see `BuildOptions`.
+#[derive(Debug, Clone, Default, Args)]
+pub struct BuildArgs {
+    /// Ignore cache when building (neither reuse nor write cached files)
+    #[arg(long, default_value_t = false, global = true)]
+    pub no_cache: bool,
+    /// Rebuild cache from scratch (clears previous cache)
+    #[arg(long, default_value_t = false, global = true)]
+    pub rebuild: bool,
+    /// Where to keep the build cache
+    #[command(flatten)]
+    pub cache: CacheLocationArgs,
+    /// Worker threads for parsing and analysis (default: one per CPU)
+    #[arg(long, value_name = "N", global = true, env = "RRE_THREADS")]
+    pub threads: Option<usize>,
+    /// Analysis features to run, comma-separated: method-calls, trait-dispatch (default: all)
+    #[arg(long, value_name = "LIST", value_delimiter = ',', global = true)]
+    pub features: Option<Vec<String>>,
+}
+
+impl BuildArgs {
+    /// `--rebuild` wins over `--no-cache`.
+    #[must_use]
+    pub fn mode(&self) -> crate::utils::cache::CacheMode {
+        use crate::utils::cache::CacheMode;
+        if self.rebuild {
+            CacheMode::Rebuild
+        } else if self.no_cache {
+            CacheMode::Ignore
+        } else {
+            CacheMode::Use
+        }
+    }
+
+    /// Options for building `roots` with these settings and `discovery`.
+    #[must_use]
+    pub fn options(
+        &self,
+        roots: Vec<PathBuf>,
+        discovery: crate::utils::file_walker::DiscoveryOptions,
+    ) -> crate::graph::builder::BuildOptions {
+        let defaults = crate::graph::builder::BuildOptions::default();
+        crate::graph::builder::BuildOptions {
+            roots,
+            cache_mode: self.mode(),
+            // `--no-cache` leaves the cache alone; `--rebuild` rewrites it
+            write_cache: self.rebuild || !self.no_cache,
+            cache_location: self.cache.location(),
+            discovery,
+            // `--features=` turns every feature off
+            features: self.features.as_ref().map_or(defaults.features.clone(), |f| {
+                f.iter().filter(|f| !f.is_empty()).cloned().collect()
+            }),
+            threads: self.threads,
+            ..defaults
+        }
+    }
+}
+
+/// File discovery rules of `build`; see `DiscoveryOptions`.
+#[derive(Debug, Clone, Args)]
+pub struct DiscoveryArgs {
+    /// Scan only files matching this glob (relative to the project root); repeatable
+    #[arg(long, value_name = "GLOB")]
+    pub include: Vec<String>,
+    /// Skip files matching this glob (relative to the project root); repeatable
+    #[arg(long, value_name = "GLOB")]
+    pub exclude: Vec<String>,
+    /// Skip Cargo build directories (on/off)
+    #[arg(long, value_enum, default_value_t = OnOffArg::On)]
+    pub skip_target: OnOffArg,
+    /// Skip build-script output and files with an `@generated` header (on/off)
+    #[arg(long, value_enum, default_value_t = OnOffArg::On)]
+    pub skip_generated: OnOffArg,
+    /// Skip vendored crates (on/off)
+    #[arg(long, value_enum, default_value_t = OnOffArg::On)]
+    pub skip_vendored: OnOffArg,
+}
+
+impl Default for DiscoveryArgs {
+    fn default() -> Self {
+        Self {
+            include: Vec::new(),
+            exclude: Vec::new(),
+            skip_target: OnOffArg::On,
+            skip_generated: OnOffArg::On,
+            skip_vendored: OnOffArg::On,
+        }
+    }
+}
+
+impl DiscoveryArgs {
+    // Take the `[build]` config section for glob lists not given as flags and for
+    // switches left at their default.
+    fn fill_from(&mut self, cfg: &crate::utils::config::BuildConfig) {
+        if self.include.is_empty() {
+            self.include.clone_from(&cfg.include);
+        }
+        if self.exclude.is_empty() {
+            self.exclude.clone_from(&cfg.exclude);
+        }
+        for (arg, value) in [
+            (&mut self.skip_target, cfg.skip_target),
+            (&mut self.skip_generated, cfg.skip_generated),
+            (&mut self.skip_vendored, cfg.skip_vendored),
+        ] {
+            if let Some(b) = value {
+                if *arg == OnOffArg::On {
+                    *arg = on_off(b);
+                }
+            }
+        }
+    }
+
+    /// The selected rules, with ignore files bypassed or not.
+    #[must_use]
+    pub fn options(&self, no_ignore: bool) -> crate::utils::file_walker::DiscoveryOptions {
+        crate::utils::file_walker::DiscoveryOptions {
+            no_ignore,
+            include: self.include.clone(),
+            exclude: self.exclude.clone(),
+            skip_target: self.skip_target == OnOffArg::On,
+            skip_generated: self.skip_generated == OnOffArg::On,
+            skip_vendored: self.skip_vendored == OnOffArg::On,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Copy, ValueEnum, PartialEq, Eq)]
+pub enum VisibilityArg {
+    Public,
+    Crate,
+}
+
+#[derive(Clone, Debug, Copy, ValueEnum, PartialEq, Eq)]
+pub enum RelKindArg {
+    Uses,
+    Implements,
+    Contains,
+    Extends,
+    Calls,
+}
+
+/// Subgraph selection shared by `build` (DOT/SVG output) and `query`; see `GraphFilter`.
+#[derive(Debug, Clone, Args)]
+pub struct FilterArgs {
+    /// Keep files matching this glob (relative to the project root); repeatable
+    #[arg(long = "filter-path", value_name = "GLOB", global = true)]
+    pub paths: Vec<String>,
+    /// Keep items under this module path, e.g. `crate::graph`; repeatable
+    #[arg(long = "filter-module", value_name = "PREFIX", global = true)]
+    pub modules: Vec<String>,
+    /// Keep items of this kind; repeatable
+    #[arg(long = "filter-kind", value_enum, global = true)]
+    pub kinds: Vec<ItemKindArg>,
+    /// Keep items at least this visible
+    #[arg(long = "filter-visibility", value_enum, global = true)]
+    pub visibility: Option<VisibilityArg>,
+    /// Keep relationships of this kind; repeatable
+    #[arg(long = "filter-rel", value_enum, global = true)]
+    pub relationships: Vec<RelKindArg>,
+    /// Keep items within `--hops` relationships of this item id; repeatable
+    #[arg(long, value_name = "ID", global = true)]
+    pub focus: Vec<String>,
+    /// Neighbourhood radius around `--focus` items
+    #[arg(long, default_value_t = 1, global = true)]
+    pub hops: usize,
+}
+
+impl Default for FilterArgs {
+    fn default() -> Self {
+        Self {
+            paths: Vec::new(),
+            modules: Vec::new(),
+            kinds: Vec::new(),
+            visibility: None,
+            relationships: Vec::new(),
+            focus: Vec::new(),
+            hops: 1,
+        }
+    }
+}
+
+impl FilterArgs {
+    fn is_empty(&self) -> bool {
+        self.paths.is_empty()
+            && self.modules.is_empty()
+            && self.kinds.is_empty()
+            && self.visibility.is_none()
+            && self.relationships.is_empty()
+            && self.focus.is_empty()
+    }
+
+    // Take the `[filter]` config section when no filter flag was given.
+    fn fill_from(&mut self, cfg: &crate::utils::config::FilterConfig) {
+        if !self.is_empty() {
+            return;
+        }
+        self.paths.clone_from(&cfg.paths);
+        self.modules.clone_from(&cfg.modules);
+        self.kinds = cfg.kinds.iter().filter_map(|k| ItemKindArg::from_str(k, true).ok()).collect();
+        self.visibility =
+            cfg.visibility.as_deref().and_then(|v| VisibilityArg::from_str(v, true).ok());
+        self.relationships =
+            cfg.relationships.iter().filter_map(|r| RelKindArg::from_str(r, true).ok()).collect();
+        self.focus.clone_from(&cfg.focus);
+        if let Some(h) = cfg.hops {
+            self.hops = h;
+        }
+    }
+
+    /// The selected filter, or `None` when no criterion is set.
+    ///
+    /// # Errors
+    /// Returns `KnowledgeGraphError::Query` for an invalid path glob.
+    pub fn to_filter(
+        &self,
+    ) -> Result<Option<crate::graph::filter::GraphFilter>, crate::errors::KnowledgeGraphError> {
+        use crate::graph::filter::{GraphFilter, VisibilityFilter};
+        use crate::graph::index::RelKind;
+        if self.is_empty() {
+            return Ok(None);
+        }
+        let kinds = self.kinds.iter().map(|k| match k {
+            ItemKindArg::Module => "mod",
+            ItemKindArg::Function => "fn",
+            ItemKindArg::Struct => "struct",
+            ItemKindArg::Enum => "enum",
+            ItemKindArg::Trait => "trait",
+            ItemKindArg::Impl => "impl",
+            ItemKindArg::Const => "const",
+            ItemKindArg::Static => "static",
+            ItemKindArg::Type => "type",
+            ItemKindArg::Macro => "macro",
+        });
+        let rels = self.relationships.iter().map(|r| match r {
+            RelKindArg::Uses => RelKind::Uses,
+            RelKindArg::Implements => RelKind::Implements,
+            RelKindArg::Contains => RelKind::Contains,
+            RelKindArg::Extends => RelKind::Extends,
+            RelKindArg::Calls => RelKind::Calls,
+        });
+        let mut filter = GraphFilter::new()
+            .paths(&self.paths)?
+            .modules(&self.modules)
+            .kinds(kinds)?
+            .relationships(rels);
+        if let Some(v) = self.visibility {
+            filter = filter.visibility(match v {
+                VisibilityArg::Public => VisibilityFilter::Public,
+                VisibilityArg::Crate => VisibilityFilter::Crate,
+            });
+        }
+        if !self.focus.is_empty() {
+            filter = filter.around(self.focus.iter().cloned(), self.hops);
+        }
+        Ok(Some(filter))
+    }
+}
+
+/// Saved graph encoding; defaults to the file extension (`.bin`/`.rrg` binary, else JSON).
+#[derive(Clone, Debug, Copy, ValueEnum, PartialEq, Eq)]
+pub enum GraphFormatArg {
+    Json,
+    Binary,
+}
 
 #[derive(Clone, Debug, Copy, ValueEnum)]
 pub enum ItemKindArg {
@@ -163,9 +514,10 @@ pub enum ItemKindArg {
 pub enum Commands {
     /// Build the knowledge graph from a source directory
     Build {
-        /// Path to the Rust project root (directory containing src/)
+        /// Path to the Rust project root (directory containing src/); repeat to build one
+        /// graph over several roots
         #[arg(short, long, env = "RRE_PATH")]
-        path: Option<PathBuf>,
+        path: Vec<PathBuf>,
         /// Path to a TOML configuration file
         #[arg(short = 'c', long)]
         config: Option<String>,
@@ -174,15 +526,23 @@ pub enum Commands {
             short='I', long,
             visible_aliases=["no-gitignore","all","ni"],
             default_value_t = false,
+            env = "KNOWLEDGE_RS_NO_IGNORE",
+            value_parser = BoolishValueParser::new(),
             help = "Include files even if matched by .gitignore/.ignore. Global git excludes are always disabled for determinism."
         )]
         no_ignore: bool,
-        /// Ignore cache when building (do not reuse cached files)
+        /// Which files to scan beyond ignore rules
+        #[command(flatten)]
+        discovery: DiscoveryArgs,
+        /// Print the files a build would scan and why others are skipped, then exit
         #[arg(long, default_value_t = false)]
-        no_cache: bool,
-        /// Rebuild cache from scratch (clears previous cache)
-        #[arg(long, default_value_t = false)]
-        rebuild: bool,
+        list_files: bool,
+        /// Build from this git revision (commit, branch or tag) instead of the working tree
+        #[arg(long, value_name = "REV", conflicts_with = "watch")]
+        rev: Option<String>,
+        /// Cache and worker threads
+        #[command(flatten)]
+        build: BuildArgs,
         /// Output JSON file path
         #[arg(long)]
         json: Option<String>,
@@ -210,17 +570,108 @@ pub enum Commands {
         /// DOT: rounded node corners (on/off)
         #[arg(long, value_enum, default_value_t = OnOffArg::On)]
         dot_rounded: OnOffArg,
+        /// DOT: scale edge width by confidence times number of sites (on/off)
+        #[arg(long, value_enum, default_value_t = OnOffArg::Off)]
+        dot_weighted: OnOffArg,
         /// SVG: add interactive enhancements (on/off)
         #[arg(long, value_enum, default_value_t = OnOffArg::On)]
         svg_interactive: OnOffArg,
-        /// Save built graph to JSON file path
+        /// Save built graph to this file path (JSON, or binary for `.bin`/`.rrg`)
         #[arg(long)]
         save: Option<String>,
+        /// Encoding of the `--save` file, overriding the extension
+        #[arg(long, value_enum)]
+        graph_format: Option<GraphFormatArg>,
+        /// Draw only a subgraph in `--dot`/`--svg` output
+        #[command(flatten)]
+        filter: FilterArgs,
+        /// Print file counts, items and relationships per kind, and per-phase timings
+        #[arg(long, default_value_t = false)]
+        stats: bool,
+        /// Keep running: rebuild incrementally and rewrite the outputs whenever sources change
+        #[arg(long, default_value_t = false)]
+        watch: bool,
+        /// Watch by re-scanning the sources instead of filesystem notifications
+        #[arg(long, default_value_t = false, requires = "watch")]
+        poll: bool,
+        /// Milliseconds between scans with `--poll`
+        #[arg(long, default_value_t = 1000, requires = "poll")]
+        poll_interval: u64,
+        /// Milliseconds without further changes before rebuilding
+        #[arg(long, default_value_t = 200, requires = "watch")]
+        debounce: u64,
     },
     /// Run queries over the knowledge graph
     Query {
         #[command(subcommand)]
         query: QueryCommands,
+        /// Drop edges whose confidence score (strength, 0.0-1.0) is below this value
+        #[arg(long, global = true, default_value_t = 0.0, value_parser = parse_min_confidence)]
+        min_confidence: f64,
+        /// Encoding of the `--graph` file, overriding the extension
+        #[arg(long, global = true, value_enum)]
+        graph_format: Option<GraphFormatArg>,
+        /// Project checkout to resolve `--graph` file paths against (default: the root the
+        /// graph was built from)
+        #[arg(long, global = true)]
+        root: Option<PathBuf>,
+        /// Run the query on a subgraph
+        #[command(flatten)]
+        filter: FilterArgs,
+        /// Cache and worker threads when the graph is built rather than loaded
+        #[command(flatten)]
+        build: BuildArgs,
+        /// Which files to scan; the `[build]` section of the query's `--config`
+        #[arg(skip)]
+        discovery: DiscoveryArgs,
+    },
+    /// Compare two saved graphs: items, relationships, new cycles and centrality changes.
+    /// Exits with status 3 when a `--max-*` limit is exceeded
+    Diff {
+        /// Graph before the change (JSON, or binary for `.bin`/`.rrg`)
+        old: String,
+        /// Graph after the change
+        new: String,
+        /// Encoding of both graph files, overriding the extension
+        #[arg(long, value_enum)]
+        graph_format: Option<GraphFormatArg>,
+        /// Path to a TOML configuration file (`[diff]` limits)
+        #[arg(short = 'c', long)]
+        config: Option<String>,
+        /// Output format: text or json
+        #[arg(short='f', long, value_enum, default_value_t = OutputFormat::Text, env = "RRE_FORMAT")]
+        format: OutputFormat,
+        /// Fail when more than this many new cycles appear
+        #[arg(long)]
+        max_new_cycles: Option<usize>,
+        /// Fail when more than this many items are removed
+        #[arg(long)]
+        max_removed_items: Option<usize>,
+        /// Fail when more than this many relationships are added
+        #[arg(long)]
+        max_added_relationships: Option<usize>,
+        /// Fail when more than this many relationships are removed
+        #[arg(long)]
+        max_removed_relationships: Option<usize>,
+        /// Fail when any file's degree changes by more than this
+        #[arg(long)]
+        max_centrality_delta: Option<usize>,
+    },
+    /// Inspect, check or remove a project's build cache
+    Cache {
+        #[command(subcommand)]
+        action: CacheCommands,
+        /// Path to the Rust project root whose cache to use
+        #[arg(short, long, global = true, env = "RRE_PATH")]
+        path: Option<PathBuf>,
+        /// Path to a TOML configuration file (`[cache]` location)
+        #[arg(short = 'c', long, global = true)]
+        config: Option<String>,
+        #[command(flatten)]
+        location: CacheLocationArgs,
+        /// Output format: text or json
+        #[arg(short='f', long, global = true, value_enum, default_value_t = OutputFormat::Text, env = "RRE_FORMAT")]
+        format: OutputFormat,
     },
     /// Generate shell completion scripts
     Completions {
@@ -230,6 +681,17 @@ pub enum Commands {
     },
 }
 
+#[derive(Debug, Clone, Copy, Subcommand, PartialEq, Eq)]
+pub enum CacheCommands {
+    /// Show the cache file's location, version and size
+    Stats,
+    /// Delete the cache file
+    Clear,
+    /// Check that the cache decodes and is current; list stale and missing files.
+    /// Exits with status 1 when the cache is unusable
+    Verify,
+}
+
 #[derive(Debug, Subcommand)]
 pub enum QueryCommands {
     /// List files connected to the given file via relationships
@@ -245,6 +707,8 @@ pub enum QueryCommands {
             short='I', long,
             visible_aliases=["no-gitignore","all","ni"],
             default_value_t = false,
+            env = "KNOWLEDGE_RS_NO_IGNORE",
+            value_parser = BoolishValueParser::new(),
             help = "Include files even if matched by .gitignore/.ignore. Global git excludes are always disabled for determinism."
         )]
         no_ignore: bool,
@@ -254,6 +718,9 @@ pub enum QueryCommands {
         /// The file to analyze (absolute or relative)
         #[arg(long)]
         file: Option<String>,
+        /// Show the relationships behind each connection with their `path:line` evidence
+        #[arg(long, default_value_t = false)]
+        explain: bool,
         /// Optional path to a prebuilt graph JSON (skips rebuild)
         #[arg(long, env = "RRE_GRAPH")]
         graph: Option<String>,
@@ -276,7 +743,7 @@ pub enum QueryCommands {
         #[arg(short = 'c', long)]
         config: Option<String>,
         /// Bypass ignore rules (.gitignore/.ignore) when discovering files
-        #[arg(short='I', long, visible_aliases=["no-gitignore","all","ni"], default_value_t = false)]
+        #[arg(short='I', long, visible_aliases=["no-gitignore","all","ni"], default_value_t = false, env = "KNOWLEDGE_RS_NO_IGNORE", value_parser = BoolishValueParser::new())]
         no_ignore: bool,
         /// ItemId (e.g., fn:createIcons:6). Optional when --name is provided
         #[arg(long, value_name = "ID")]
@@ -306,7 +773,7 @@ pub enum QueryCommands {
         #[arg(short = 'c', long)]
         config: Option<String>,
         /// Bypass ignore rules (.gitignore/.ignore) when discovering files
-        #[arg(short='I', long, visible_aliases=["no-gitignore","all","ni"], default_value_t = false)]
+        #[arg(short='I', long, visible_aliases=["no-gitignore","all","ni"], default_value_t = false, env = "KNOWLEDGE_RS_NO_IGNORE", value_parser = BoolishValueParser::new())]
         no_ignore: bool,
         /// Function name to analyze
         #[arg(long)]
@@ -314,6 +781,9 @@ pub enum QueryCommands {
         /// Direction: callers or callees
         #[arg(long, value_enum, default_value_t = Direction::Callers)]
         direction: Direction,
+        /// Follow trait dispatch between trait method declarations and their implementations
+        #[arg(long, default_value_t = false)]
+        through_traits: bool,
         /// Optional path to a prebuilt graph JSON (skips rebuild)
         #[arg(long, env = "RRE_GRAPH")]
         graph: Option<String>,
@@ -336,7 +806,7 @@ pub enum QueryCommands {
         #[arg(short = 'c', long)]
         config: Option<String>,
         /// Bypass ignore rules (.gitignore/.ignore) when discovering files
-        #[arg(short='I', long, visible_aliases=["no-gitignore","all","ni"], default_value_t = false)]
+        #[arg(short='I', long, visible_aliases=["no-gitignore","all","ni"], default_value_t = false, env = "KNOWLEDGE_RS_NO_IGNORE", value_parser = BoolishValueParser::new())]
         no_ignore: bool,
         /// Optional path to a prebuilt graph JSON (skips rebuild)
         #[arg(long, env = "RRE_GRAPH")]
@@ -360,7 +830,7 @@ pub enum QueryCommands {
         #[arg(short = 'c', long)]
         config: Option<String>,
         /// Bypass ignore rules (.gitignore/.ignore) when discovering files
-        #[arg(short='I', long, visible_aliases=["no-gitignore","all","ni"], default_value_t = false)]
+        #[arg(short='I', long, visible_aliases=["no-gitignore","all","ni"], default_value_t = false, env = "KNOWLEDGE_RS_NO_IGNORE", value_parser = BoolishValueParser::new())]
         no_ignore: bool,
         /// Source file path
         #[arg(long)]
@@ -390,7 +860,7 @@ pub enum QueryCommands {
         #[arg(short = 'c', long)]
         config: Option<String>,
         /// Bypass ignore rules (.gitignore/.ignore) when discovering files
-        #[arg(short='I', long, visible_aliases=["no-gitignore","all","ni"], default_value_t = false)]
+        #[arg(short='I', long, visible_aliases=["no-gitignore","all","ni"], default_value_t = false, env = "KNOWLEDGE_RS_NO_IGNORE", value_parser = BoolishValueParser::new())]
         no_ignore: bool,
         /// Optional path to a prebuilt graph JSON (skips rebuild)
         #[arg(long, env = "RRE_GRAPH")]
@@ -401,6 +871,9 @@ pub enum QueryCommands {
         /// Top N results
         #[arg(short = 't', long, default_value_t = 10)]
         top: usize,
+        /// Count each relationship once per call site or import instead of once
+        #[arg(long, default_value_t = false)]
+        weighted: bool,
         /// Output format: text or json
         #[arg(short='f', long, value_enum, default_value_t = OutputFormat::Text, env = "RRE_FORMAT")]
         format: OutputFormat,
@@ -420,7 +893,7 @@ pub enum QueryCommands {
         #[arg(short = 'c', long)]
         config: Option<String>,
         /// Bypass ignore rules (.gitignore/.ignore) when discovering files
-        #[arg(short='I', long, visible_aliases=["no-gitignore","all","ni"], default_value_t = false)]
+        #[arg(short='I', long, visible_aliases=["no-gitignore","all","ni"], default_value_t = false, env = "KNOWLEDGE_RS_NO_IGNORE", value_parser = BoolishValueParser::new())]
         no_ignore: bool,
         /// Optional path to a prebuilt graph JSON (skips rebuild)
         #[arg(long, env = "RRE_GRAPH")]
@@ -431,6 +904,9 @@ pub enum QueryCommands {
         /// Top N results
         #[arg(short = 't', long, default_value_t = 10)]
         top: usize,
+        /// Count each relationship once per call site or import instead of once
+        #[arg(long, default_value_t = false)]
+        weighted: bool,
         /// Output format: text or json
         #[arg(short='f', long, value_enum, default_value_t = OutputFormat::Text, env = "RRE_FORMAT")]
         format: OutputFormat,
@@ -450,7 +926,7 @@ pub enum QueryCommands {
         #[arg(short = 'c', long)]
         config: Option<String>,
         /// Bypass ignore rules (.gitignore/.ignore) when discovering files
-        #[arg(short='I', long, visible_aliases=["no-gitignore","all","ni"], default_value_t = false)]
+        #[arg(short='I', long, visible_aliases=["no-gitignore","all","ni"], default_value_t = false, env = "KNOWLEDGE_RS_NO_IGNORE", value_parser = BoolishValueParser::new())]
         no_ignore: bool,
         /// Trait name (e.g., Display)
         #[arg(long, value_name = "NAME")]
@@ -477,7 +953,7 @@ pub enum QueryCommands {
         #[arg(short = 'c', long)]
         config: Option<String>,
         /// Bypass ignore rules (.gitignore/.ignore) when discovering files
-        #[arg(short='I', long, visible_aliases=["no-gitignore","all","ni"], default_value_t = false)]
+        #[arg(short='I', long, visible_aliases=["no-gitignore","all","ni"], default_value_t = false, env = "KNOWLEDGE_RS_NO_IGNORE", value_parser = BoolishValueParser::new())]
         no_ignore: bool,
         /// Include public items as well (by default public items are excluded)
         #[arg(long, default_value_t = false)]
@@ -498,6 +974,30 @@ pub enum QueryCommands {
         #[arg(long)]
         limit: Option<usize>,
     },
+    /// List the crate's public API as sorted paths (including `pub use` re-exports)
+    PublicApi {
+        /// Path to project root (directory containing src/)
+        #[arg(short, long, env = "RRE_PATH")]
+        path: Option<PathBuf>,
+        /// Path to a TOML configuration file
+        #[arg(short = 'c', long)]
+        config: Option<String>,
+        /// Bypass ignore rules (.gitignore/.ignore) when discovering files
+        #[arg(short='I', long, visible_aliases=["no-gitignore","all","ni"], default_value_t = false, env = "KNOWLEDGE_RS_NO_IGNORE", value_parser = BoolishValueParser::new())]
+        no_ignore: bool,
+        /// Optional path to a prebuilt graph JSON (skips rebuild)
+        #[arg(long, env = "RRE_GRAPH")]
+        graph: Option<String>,
+        /// Output format: text or json
+        #[arg(short='f', long, value_enum, default_value_t = OutputFormat::Text, env = "RRE_FORMAT")]
+        format: OutputFormat,
+        /// Pagination offset (number of rows to skip)
+        #[arg(long, default_value_t = 0)]
+        offset: usize,
+        /// Pagination limit (max number of rows to show)
+        #[arg(long)]
+        limit: Option<usize>,
+    },
 }
 
 #[must_use]
@@ -508,8 +1008,10 @@ pub fn parse() -> Cli {
             path,
             config,
             no_ignore: _,
-            no_cache: _,
-            rebuild: _,
+            discovery,
+            list_files: _,
+            rev: _,
+            build,
             json: _,
             dot: _,
             svg: _,
@@ -519,11 +1021,24 @@ pub fn parse() -> Cli {
             dot_rankdir,
             dot_splines,
             dot_rounded,
+            dot_weighted,
             svg_interactive,
             save: _,
+            graph_format: _,
+            filter,
+            stats: _,
+            watch: _,
+            poll: _,
+            poll_interval: _,
+            debounce: _,
         } => {
-            let p = effective_path_opt(path.as_deref());
-            *path = Some(p);
+            if path.is_empty() {
+                path.push(effective_path_opt(None));
+            } else {
+                for p in path.iter_mut() {
+                    *p = effective_path_opt(Some(p.as_path()));
+                }
+            }
             // Apply config if provided (only to defaulted values)
             if let Some(cfg_path) = config.as_deref() {
                 if let Some(cfg) = load_config(cfg_path) {
@@ -557,177 +1072,245 @@ pub fn parse() -> Cli {
                             *dot_rounded = on_off(b);
                         }
                     }
+                    if let Some(b) = cfg.dot.weighted {
+                        if *dot_weighted == OnOffArg::Off {
+                            *dot_weighted = on_off(b);
+                        }
+                    }
                     if let Some(b) = cfg.svg.interactive {
                         if *svg_interactive == OnOffArg::On {
                             *svg_interactive = on_off(b);
                         }
                     }
+                    filter.fill_from(&cfg.filter);
+                    build.cache.fill_from(&cfg.cache);
+                    discovery.fill_from(&cfg.build);
                 }
             }
             if cli.verbose > 0 && !cli.quiet {
-                eprintln!("Using project root: {}", path.as_ref().unwrap().display());
+                for p in path.iter() {
+                    eprintln!("Using project root: {}", p.display());
+                }
             }
         }
-        Commands::Query { query } => match query {
-            QueryCommands::ConnectedFiles { path, file_pos, file, config, format, .. } => {
-                let p = effective_path_opt(path.as_deref());
-                *path = Some(p);
-                if let Some(cfg_path) = config.as_deref() {
-                    if let Some(cfg) = load_config(cfg_path) {
-                        if let Some(f) = cfg.query.default_format.as_deref().and_then(parse_format)
-                        {
-                            if *format == OutputFormat::Text {
-                                *format = f;
+        Commands::Query { query, filter, build, discovery, .. } => {
+            if let Some(cfg) = query_config_path(query).and_then(load_config) {
+                filter.fill_from(&cfg.filter);
+                build.cache.fill_from(&cfg.cache);
+                discovery.fill_from(&cfg.build);
+            }
+            match query {
+                QueryCommands::ConnectedFiles { path, file_pos, file, config, format, .. } => {
+                    let p = effective_path_opt(path.as_deref());
+                    *path = Some(p);
+                    if let Some(cfg_path) = config.as_deref() {
+                        if let Some(cfg) = load_config(cfg_path) {
+                            if let Some(f) =
+                                cfg.query.default_format.as_deref().and_then(parse_format)
+                            {
+                                if *format == OutputFormat::Text {
+                                    *format = f;
+                                }
                             }
                         }
                     }
+                    if cli.verbose > 0 && !cli.quiet {
+                        eprintln!("Using project root: {}", path.as_ref().unwrap().display());
+                    }
+                    // Normalize positional <file> vs --file
+                    let merged = if file.is_none() { file_pos.clone() } else { file.clone() };
+                    if let Some(f) = merged {
+                        *file = Some(f);
+                    }
                 }
-                if cli.verbose > 0 && !cli.quiet {
-                    eprintln!("Using project root: {}", path.as_ref().unwrap().display());
-                }
-                // Normalize positional <file> vs --file
-                let merged = if file.is_none() { file_pos.clone() } else { file.clone() };
-                if let Some(f) = merged {
-                    *file = Some(f);
-                }
-            }
-            QueryCommands::ItemInfo { path, config, format, .. } => {
-                let p = effective_path_opt(path.as_deref());
-                *path = Some(p);
-                if let Some(cfg_path) = config.as_deref() {
-                    if let Some(cfg) = load_config(cfg_path) {
-                        if let Some(f) = cfg.query.default_format.as_deref().and_then(parse_format)
-                        {
-                            if *format == OutputFormat::Text {
-                                *format = f;
+                QueryCommands::ItemInfo { path, config, format, .. } => {
+                    let p = effective_path_opt(path.as_deref());
+                    *path = Some(p);
+                    if let Some(cfg_path) = config.as_deref() {
+                        if let Some(cfg) = load_config(cfg_path) {
+                            if let Some(f) =
+                                cfg.query.default_format.as_deref().and_then(parse_format)
+                            {
+                                if *format == OutputFormat::Text {
+                                    *format = f;
+                                }
                             }
                         }
                     }
+                    if cli.verbose > 0 && !cli.quiet {
+                        eprintln!("Using project root: {}", path.as_ref().unwrap().display());
+                    }
                 }
-                if cli.verbose > 0 && !cli.quiet {
-                    eprintln!("Using project root: {}", path.as_ref().unwrap().display());
-                }
-            }
-            QueryCommands::FunctionUsage { path, config, format, .. } => {
-                let p = effective_path_opt(path.as_deref());
-                *path = Some(p);
-                if let Some(cfg_path) = config.as_deref() {
-                    if let Some(cfg) = load_config(cfg_path) {
-                        if let Some(f) = cfg.query.default_format.as_deref().and_then(parse_format)
-                        {
-                            if *format == OutputFormat::Text {
-                                *format = f;
+                QueryCommands::FunctionUsage { path, config, format, .. } => {
+                    let p = effective_path_opt(path.as_deref());
+                    *path = Some(p);
+                    if let Some(cfg_path) = config.as_deref() {
+                        if let Some(cfg) = load_config(cfg_path) {
+                            if let Some(f) =
+                                cfg.query.default_format.as_deref().and_then(parse_format)
+                            {
+                                if *format == OutputFormat::Text {
+                                    *format = f;
+                                }
                             }
                         }
                     }
+                    if cli.verbose > 0 && !cli.quiet {
+                        eprintln!("Using project root: {}", path.as_ref().unwrap().display());
+                    }
                 }
-                if cli.verbose > 0 && !cli.quiet {
-                    eprintln!("Using project root: {}", path.as_ref().unwrap().display());
-                }
-            }
-            QueryCommands::Cycles { path, config, format, .. } => {
-                let p = effective_path_opt(path.as_deref());
-                *path = Some(p);
-                if let Some(cfg_path) = config.as_deref() {
-                    if let Some(cfg) = load_config(cfg_path) {
-                        if let Some(f) = cfg.query.default_format.as_deref().and_then(parse_format)
-                        {
-                            if *format == OutputFormat::Text {
-                                *format = f;
+                QueryCommands::Cycles { path, config, format, .. } => {
+                    let p = effective_path_opt(path.as_deref());
+                    *path = Some(p);
+                    if let Some(cfg_path) = config.as_deref() {
+                        if let Some(cfg) = load_config(cfg_path) {
+                            if let Some(f) =
+                                cfg.query.default_format.as_deref().and_then(parse_format)
+                            {
+                                if *format == OutputFormat::Text {
+                                    *format = f;
+                                }
                             }
                         }
                     }
+                    if cli.verbose > 0 && !cli.quiet {
+                        eprintln!("Using project root: {}", path.as_ref().unwrap().display());
+                    }
                 }
-                if cli.verbose > 0 && !cli.quiet {
-                    eprintln!("Using project root: {}", path.as_ref().unwrap().display());
-                }
-            }
-            QueryCommands::Path { path, config, format, .. } => {
-                let p = effective_path_opt(path.as_deref());
-                *path = Some(p);
-                if let Some(cfg_path) = config.as_deref() {
-                    if let Some(cfg) = load_config(cfg_path) {
-                        if let Some(f) = cfg.query.default_format.as_deref().and_then(parse_format)
-                        {
-                            if *format == OutputFormat::Text {
-                                *format = f;
+                QueryCommands::Path { path, config, format, .. } => {
+                    let p = effective_path_opt(path.as_deref());
+                    *path = Some(p);
+                    if let Some(cfg_path) = config.as_deref() {
+                        if let Some(cfg) = load_config(cfg_path) {
+                            if let Some(f) =
+                                cfg.query.default_format.as_deref().and_then(parse_format)
+                            {
+                                if *format == OutputFormat::Text {
+                                    *format = f;
+                                }
                             }
                         }
                     }
+                    if cli.verbose > 0 && !cli.quiet {
+                        eprintln!("Using project root: {}", path.as_ref().unwrap().display());
+                    }
                 }
-                if cli.verbose > 0 && !cli.quiet {
-                    eprintln!("Using project root: {}", path.as_ref().unwrap().display());
-                }
-            }
-            QueryCommands::Hubs { path, config, format, .. } => {
-                let p = effective_path_opt(path.as_deref());
-                *path = Some(p);
-                if let Some(cfg_path) = config.as_deref() {
-                    if let Some(cfg) = load_config(cfg_path) {
-                        if let Some(f) = cfg.query.default_format.as_deref().and_then(parse_format)
-                        {
-                            if *format == OutputFormat::Text {
-                                *format = f;
+                QueryCommands::Hubs { path, config, format, .. } => {
+                    let p = effective_path_opt(path.as_deref());
+                    *path = Some(p);
+                    if let Some(cfg_path) = config.as_deref() {
+                        if let Some(cfg) = load_config(cfg_path) {
+                            if let Some(f) =
+                                cfg.query.default_format.as_deref().and_then(parse_format)
+                            {
+                                if *format == OutputFormat::Text {
+                                    *format = f;
+                                }
                             }
                         }
                     }
+                    if cli.verbose > 0 && !cli.quiet {
+                        eprintln!("Using project root: {}", path.as_ref().unwrap().display());
+                    }
                 }
-                if cli.verbose > 0 && !cli.quiet {
-                    eprintln!("Using project root: {}", path.as_ref().unwrap().display());
-                }
-            }
-            QueryCommands::ModuleCentrality { path, config, format, .. } => {
-                let p = effective_path_opt(path.as_deref());
-                *path = Some(p);
-                if let Some(cfg_path) = config.as_deref() {
-                    if let Some(cfg) = load_config(cfg_path) {
-                        if let Some(f) = cfg.query.default_format.as_deref().and_then(parse_format)
-                        {
-                            if *format == OutputFormat::Text {
-                                *format = f;
+                QueryCommands::ModuleCentrality { path, config, format, .. } => {
+                    let p = effective_path_opt(path.as_deref());
+                    *path = Some(p);
+                    if let Some(cfg_path) = config.as_deref() {
+                        if let Some(cfg) = load_config(cfg_path) {
+                            if let Some(f) =
+                                cfg.query.default_format.as_deref().and_then(parse_format)
+                            {
+                                if *format == OutputFormat::Text {
+                                    *format = f;
+                                }
                             }
                         }
                     }
+                    if cli.verbose > 0 && !cli.quiet {
+                        eprintln!("Using project root: {}", path.as_ref().unwrap().display());
+                    }
                 }
-                if cli.verbose > 0 && !cli.quiet {
-                    eprintln!("Using project root: {}", path.as_ref().unwrap().display());
-                }
-            }
-            QueryCommands::TraitImpls { path, config, format, .. } => {
-                let p = effective_path_opt(path.as_deref());
-                *path = Some(p);
-                if let Some(cfg_path) = config.as_deref() {
-                    if let Some(cfg) = load_config(cfg_path) {
-                        if let Some(f) = cfg.query.default_format.as_deref().and_then(parse_format)
-                        {
-                            if *format == OutputFormat::Text {
-                                *format = f;
+                QueryCommands::TraitImpls { path, config, format, .. } => {
+                    let p = effective_path_opt(path.as_deref());
+                    *path = Some(p);
+                    if let Some(cfg_path) = config.as_deref() {
+                        if let Some(cfg) = load_config(cfg_path) {
+                            if let Some(f) =
+                                cfg.query.default_format.as_deref().and_then(parse_format)
+                            {
+                                if *format == OutputFormat::Text {
+                                    *format = f;
+                                }
                             }
                         }
                     }
+                    if cli.verbose > 0 && !cli.quiet {
+                        eprintln!("Using project root: {}", path.as_ref().unwrap().display());
+                    }
                 }
-                if cli.verbose > 0 && !cli.quiet {
-                    eprintln!("Using project root: {}", path.as_ref().unwrap().display());
-                }
-            }
-            QueryCommands::UnreferencedItems { path, config, format, .. } => {
-                let p = effective_path_opt(path.as_deref());
-                *path = Some(p);
-                if let Some(cfg_path) = config.as_deref() {
-                    if let Some(cfg) = load_config(cfg_path) {
-                        if let Some(f) = cfg.query.default_format.as_deref().and_then(parse_format)
-                        {
-                            if *format == OutputFormat::Text {
-                                *format = f;
+                QueryCommands::UnreferencedItems { path, config, format, .. }
+                | QueryCommands::PublicApi { path, config, format, .. } => {
+                    let p = effective_path_opt(path.as_deref());
+                    *path = Some(p);
+                    if let Some(cfg_path) = config.as_deref() {
+                        if let Some(cfg) = load_config(cfg_path) {
+                            if let Some(f) =
+                                cfg.query.default_format.as_deref().and_then(parse_format)
+                            {
+                                if *format == OutputFormat::Text {
+                                    *format = f;
+                                }
                             }
                         }
                     }
+                    if cli.verbose > 0 && !cli.quiet {
+                        eprintln!("Using project root: {}", path.as_ref().unwrap().display());
+                    }
                 }
-                if cli.verbose > 0 && !cli.quiet {
-                    eprintln!("Using project root: {}", path.as_ref().unwrap().display());
+            }
+        }
+        Commands::Diff {
+            config,
+            format,
+            max_new_cycles,
+            max_removed_items,
+            max_added_relationships,
+            max_removed_relationships,
+            max_centrality_delta,
+            ..
+        } => {
+            if let Some(cfg) = config.as_deref().and_then(load_config) {
+                if let Some(f) = cfg.query.default_format.as_deref().and_then(parse_format) {
+                    if *format == OutputFormat::Text {
+                        *format = f;
+                    }
+                }
+                let d = cfg.diff;
+                for (flag, value) in [
+                    (max_new_cycles, d.max_new_cycles),
+                    (max_removed_items, d.max_removed_items),
+                    (max_added_relationships, d.max_added_relationships),
+                    (max_removed_relationships, d.max_removed_relationships),
+                    (max_centrality_delta, d.max_centrality_delta),
+                ] {
+                    if flag.is_none() {
+                        *flag = value;
+                    }
                 }
             }
-        },
+        }
+        Commands::Cache { path, config, location, format, .. } => {
+            *path = Some(effective_path_opt(path.as_deref()));
+            if let Some(cfg) = config.as_deref().and_then(load_config) {
+                location.fill_from(&cfg.cache);
+                if let Some(f) = cfg.query.default_format.as_deref().and_then(parse_format) {
+                    if *format == OutputFormat::Text {
+                        *format = f;
+                    }
+                }
+            }
+        }
         Commands::Completions { .. } => {
             // No path normalization or config backfilling needed here
         }
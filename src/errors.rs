@@ -19,9 +19,38 @@ pub enum KnowledgeGraphError {
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 
+    #[error("Cannot read {file}: {source}")]
+    Read { file: PathBuf, source: std::io::Error },
+
+    #[error("{file} changed while the graph was being built; build again")]
+    SourceChanged { file: PathBuf },
+
+    #[error("git: {0}")]
+    Git(String),
+
+    #[error("Build feature `{feature}` is not available in this build")]
+    UnsupportedFeature { feature: String },
+
+    #[error("Invalid glob `{pattern}`: {message}")]
+    InvalidGlob { pattern: String, message: String },
+
     #[error("Invalid query: {0}")]
     Query(String),
 
     #[error("Visualization error: {0}")]
     Visualization(String),
+
+    #[error("Invalid graph file: {0}")]
+    Format(String),
+
+    #[error(
+        "Graph schema version {found} (written by {tool_version}) is newer than supported version {supported}; rebuild the graph"
+    )]
+    UnsupportedSchema { found: u32, supported: u32, tool_version: String },
+
+    #[error("Graph was built with different settings ({}); rebuild the graph", .differences.join(", "))]
+    FingerprintMismatch { differences: Vec<String> },
+
+    #[error("Cannot merge graphs: {} item ids are defined by both ({})", .ids.len(), .ids.join(", "))]
+    MergeConflict { ids: Vec<String> },
 }
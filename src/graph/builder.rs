@@ -0,0 +1,385 @@
+//! One entry point for building graphs.
+//!
+//! [`GraphBuilder`] takes every build setting explicitly, as a [`BuildOptions`] or through its
+//! setters: project roots, cache mode and location, file discovery rules, git revision, parser
+//! backend, analysis features, worker threads, a source provider and a progress callback.
+//! Nothing is read from the environment, and the defaults do not depend on it: the cache
+//! lives in the project (`CacheLocation::Project`) unless another location is given. The CLI
+//! maps its flags and variables, including the per-user cache directory, onto the same
+//! options.
+//!
+//! ```no_run
+//! use rust_relations_explorer::graph::builder::GraphBuilder;
+//! use rust_relations_explorer::utils::cache::CacheMode;
+//!
+//! let graph = GraphBuilder::new(".")
+//!     .cache_mode(CacheMode::Ignore)
+//!     .exclude("benches/**")
+//!     .threads(4)
+//!     .build()
+//!     .unwrap();
+//! println!("{} files", graph.files.len());
+//! ```
+use std::path::{Path, PathBuf};
+
+use crate::errors::KnowledgeGraphError;
+use crate::graph::{schema, KnowledgeGraph};
+use crate::source::{FsSource, SourceProvider};
+use crate::utils::cache::{CacheLocation, CacheMode, CacheStore};
+use crate::utils::file_walker::DiscoveryOptions;
+
+/// Source parser used to extract items.
+///
+/// Reserved for alternative parsers: [`Self::Regex`] is the only backend so far, and choosing
+/// it only records its name in the build fingerprint.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ParserBackend {
+    /// Line- and regex-based parser over masked source text.
+    #[default]
+    Regex,
+}
+
+impl ParserBackend {
+    /// Name recorded in [`crate::graph::BuildFingerprint::parser`].
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Regex => "regex",
+        }
+    }
+}
+
+/// A step of a build, as reported to [`GraphBuilder::progress`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum BuildProgress<'p> {
+    /// File discovery of a root finished.
+    Discovered { root: &'p Path, files: usize },
+    /// A file was parsed or taken from the cache; `done` of the root's `total` files.
+    Parsed { path: &'p Path, done: usize, total: usize },
+    /// Relationship analysis started over the parsed files.
+    Analyzing { files: usize },
+    /// The graph is complete.
+    Finished { files: usize, relationships: usize },
+}
+
+// Progress callback as threaded through the build phases.
+pub(crate) type Progress<'p> = Option<&'p (dyn Fn(&BuildProgress<'_>) + Sync)>;
+
+pub(crate) fn report(progress: Progress<'_>, event: &BuildProgress<'_>) {
+    if let Some(f) = progress {
+        f(event);
+    }
+}
+
+/// Settings of a build; see [`GraphBuilder`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuildOptions {
+    /// Project roots; several roots give one graph as in [`KnowledgeGraph::merge`].
+    pub roots: Vec<PathBuf>,
+    pub cache_mode: CacheMode,
+    /// Save the cache after the build, in every [`CacheMode`]; `true` by default. The CLI's
+    /// `--no-cache` turns it off.
+    pub write_cache: bool,
+    /// Where each root keeps its cache file; `.knowledge_cache/` in the root by default.
+    pub cache_location: CacheLocation,
+    /// Which files to scan: ignore rules, include and exclude globs, skipped directories.
+    pub discovery: DiscoveryOptions,
+    /// Build from this git revision of the single root instead of its working tree.
+    pub revision: Option<String>,
+    pub parser: ParserBackend,
+    /// Analysis features to run, out of [`schema::BUILD_FEATURES`]; all of them by default.
+    pub features: Vec<String>,
+    /// Worker threads for parsing and analysis; `None` or 0 uses one per CPU.
+    pub threads: Option<usize>,
+}
+
+impl Default for BuildOptions {
+    fn default() -> Self {
+        Self {
+            roots: Vec::new(),
+            cache_mode: CacheMode::Use,
+            write_cache: true,
+            cache_location: CacheLocation::default(),
+            discovery: DiscoveryOptions::default(),
+            revision: None,
+            parser: ParserBackend::default(),
+            features: schema::BUILD_FEATURES.iter().map(|f| (*f).to_string()).collect(),
+            threads: None,
+        }
+    }
+}
+
+type ProgressFn<'a> = Box<dyn Fn(&BuildProgress<'_>) + Send + Sync + 'a>;
+
+/// Builds a [`KnowledgeGraph`] from explicit [`BuildOptions`].
+///
+/// Sources come from the filesystem unless [`Self::provider`] sets another
+/// [`SourceProvider`]; a [`BuildOptions::revision`] reads them from git instead. In
+/// [`CacheMode::Rebuild`] each root's cache file is removed before the build writes a fresh
+/// one.
+pub struct GraphBuilder<'a> {
+    options: BuildOptions,
+    // `None` reads the filesystem
+    provider: Option<&'a dyn SourceProvider>,
+    progress: Option<ProgressFn<'a>>,
+}
+
+impl std::fmt::Debug for GraphBuilder<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GraphBuilder")
+            .field("options", &self.options)
+            .field("progress", &self.progress.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'a> GraphBuilder<'a> {
+    /// A builder for the project at `root` with the default options.
+    #[must_use]
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self::from_options(BuildOptions { roots: vec![root.into()], ..BuildOptions::default() })
+    }
+
+    #[must_use]
+    pub fn from_options(options: BuildOptions) -> Self {
+        Self { options, provider: None, progress: None }
+    }
+
+    #[must_use]
+    pub fn options(&self) -> &BuildOptions {
+        &self.options
+    }
+
+    /// Add another project root to the same graph.
+    #[must_use]
+    pub fn add_root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.options.roots.push(root.into());
+        self
+    }
+
+    #[must_use]
+    pub fn cache_mode(mut self, mode: CacheMode) -> Self {
+        self.options.cache_mode = mode;
+        self
+    }
+
+    /// Whether to save the cache after the build.
+    #[must_use]
+    pub fn write_cache(mut self, write: bool) -> Self {
+        self.options.write_cache = write;
+        self
+    }
+
+    #[must_use]
+    pub fn cache_location(mut self, location: CacheLocation) -> Self {
+        self.options.cache_location = location;
+        self
+    }
+
+    /// Replace all file discovery rules.
+    #[must_use]
+    pub fn discovery(mut self, discovery: DiscoveryOptions) -> Self {
+        self.options.discovery = discovery;
+        self
+    }
+
+    /// Bypass `.gitignore`/`.ignore` rules.
+    #[must_use]
+    pub fn no_ignore(mut self, no_ignore: bool) -> Self {
+        self.options.discovery.no_ignore = no_ignore;
+        self
+    }
+
+    /// Scan only files matching `glob` (relative to the root); repeatable.
+    #[must_use]
+    pub fn include(mut self, glob: impl Into<String>) -> Self {
+        self.options.discovery.include.push(glob.into());
+        self
+    }
+
+    /// Skip files matching `glob` (relative to the root); repeatable.
+    #[must_use]
+    pub fn exclude(mut self, glob: impl Into<String>) -> Self {
+        self.options.discovery.exclude.push(glob.into());
+        self
+    }
+
+    /// Build from a git revision (commit, branch or tag) of the single root.
+    ///
+    /// A revision build reads its sources from git and cannot take a [`Self::provider`].
+    #[must_use]
+    pub fn revision(mut self, rev: impl Into<String>) -> Self {
+        self.options.revision = Some(rev.into());
+        self
+    }
+
+    #[must_use]
+    pub fn parser(mut self, parser: ParserBackend) -> Self {
+        self.options.parser = parser;
+        self
+    }
+
+    /// Run exactly these analysis features.
+    #[must_use]
+    pub fn features<S: Into<String>>(mut self, features: impl IntoIterator<Item = S>) -> Self {
+        self.options.features = features.into_iter().map(Into::into).collect();
+        self
+    }
+
+    #[must_use]
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.options.threads = Some(threads);
+        self
+    }
+
+    /// Read sources through `provider` instead of the filesystem.
+    ///
+    /// Unless [`SourceProvider::on_disk`] holds, its sources may differ from the files on
+    /// disk, so the build reuses cached parses whose content hash matches but never writes or
+    /// clears the cache. It cannot be combined with a [`Self::revision`].
+    #[must_use]
+    pub fn provider(mut self, provider: &'a dyn SourceProvider) -> Self {
+        self.provider = Some(provider);
+        self
+    }
+
+    /// Call `f` as the build advances; it may run on worker threads.
+    #[must_use]
+    pub fn progress(mut self, f: impl Fn(&BuildProgress<'_>) + Send + Sync + 'a) -> Self {
+        self.progress = Some(Box::new(f));
+        self
+    }
+
+    /// Run the build.
+    ///
+    /// Saving the cache is best-effort: a build whose cache cannot be written still succeeds.
+    /// [`CacheMode::Ignore`] reads nothing from the cache but still saves the fresh state
+    /// unless [`Self::write_cache`] is off.
+    ///
+    /// # Errors
+    /// Returns `KnowledgeGraphError::UnsupportedFeature` for a feature this build does not
+    /// implement, `Git` for a revision build over several roots, with a provider or of an
+    /// unknown revision,
+    /// `InvalidGlob` for a malformed include or exclude glob, `MergeConflict` when two roots
+    /// define the same item id, `Io` when the thread pool cannot start, and otherwise the
+    /// errors of reading and parsing sources.
+    pub fn build(&self) -> Result<KnowledgeGraph, KnowledgeGraphError> {
+        let mut opts = &self.options;
+        if opts.revision.is_some() && self.provider.is_some() {
+            return Err(KnowledgeGraphError::Git(
+                "a revision build reads from git; provider is not supported".to_string(),
+            ));
+        }
+        let provider = self.provider.unwrap_or(&FsSource);
+        // Parses of unsaved buffers or generated text must not pass for the files on disk
+        let uncached;
+        if !provider.on_disk() && opts.write_cache {
+            uncached = BuildOptions { write_cache: false, ..opts.clone() };
+            opts = &uncached;
+        }
+        let mut features = opts.features.clone();
+        if let Some(f) = features.iter().find(|f| !schema::BUILD_FEATURES.contains(&f.as_str())) {
+            return Err(KnowledgeGraphError::UnsupportedFeature { feature: f.clone() });
+        }
+        features.sort();
+        features.dedup();
+        let progress: Progress<'_> = self.progress.as_deref().map(|f| f as _);
+
+        let run = || {
+            if opts.cache_mode == CacheMode::Rebuild && opts.write_cache {
+                for root in &opts.roots {
+                    let _ = CacheStore::new(root, &opts.cache_location).clear();
+                }
+            }
+            match (&opts.revision, opts.roots.as_slice()) {
+                (Some(rev), [root]) => {
+                    KnowledgeGraph::build_revision(root, rev, opts, &features, progress)
+                }
+                (Some(_), _) => {
+                    Err(KnowledgeGraphError::Git("a revision build takes one root".to_string()))
+                }
+                (None, _) => KnowledgeGraph::build_roots(provider, opts, &features, progress),
+            }
+        };
+        let mut graph = match opts.threads.filter(|n| *n > 0) {
+            Some(n) => rayon::ThreadPoolBuilder::new()
+                .num_threads(n)
+                .build()
+                .map_err(|e| KnowledgeGraphError::Io(std::io::Error::other(e.to_string())))?
+                .install(run)?,
+            None => run()?,
+        };
+        graph.metadata.options.parser = opts.parser.name().to_string();
+        report(
+            progress,
+            &BuildProgress::Finished {
+                files: graph.files.len(),
+                relationships: graph.relationships.len(),
+            },
+        );
+        Ok(graph)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::MemorySource;
+    use std::sync::Mutex;
+
+    #[test]
+    fn reports_progress_and_records_options() {
+        let sources: MemorySource = [
+            ("/p/src/lib.rs", "mod m;\npub fn a() {\n    m::b();\n}\n"),
+            ("/p/src/m.rs", "pub fn b() {}\n"),
+        ]
+        .into_iter()
+        .collect();
+        let events = Mutex::new(Vec::new());
+        let graph = GraphBuilder::new("/p")
+            .provider(&sources)
+            .cache_location(CacheLocation::Disabled)
+            .threads(2)
+            .progress(|e| {
+                let name = match e {
+                    BuildProgress::Discovered { files, .. } => format!("discovered {files}"),
+                    BuildProgress::Parsed { done, total, .. } => format!("parsed {done}/{total}"),
+                    BuildProgress::Analyzing { files } => format!("analyzing {files}"),
+                    BuildProgress::Finished { files, .. } => format!("finished {files}"),
+                };
+                events.lock().unwrap().push(name);
+            })
+            .build()
+            .unwrap();
+        assert_eq!(graph.metadata.options.parser, "regex");
+        let mut events = events.into_inner().unwrap();
+        events.sort();
+        assert_eq!(
+            events,
+            ["analyzing 2", "discovered 2", "finished 2", "parsed 1/2", "parsed 2/2"]
+        );
+    }
+
+    #[test]
+    fn rejects_features_and_revisions_it_cannot_build() {
+        let err = GraphBuilder::new("/p")
+            .provider(&MemorySource::new())
+            .features(["no-such-feature"])
+            .build()
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            KnowledgeGraphError::UnsupportedFeature { feature } if feature == "no-such-feature"
+        ));
+        let err = GraphBuilder::new("/a").add_root("/b").revision("HEAD").build().unwrap_err();
+        assert!(matches!(err, KnowledgeGraphError::Git(_)));
+        let err = GraphBuilder::new("/p")
+            .provider(&MemorySource::new())
+            .revision("HEAD")
+            .build()
+            .unwrap_err();
+        assert!(matches!(err, KnowledgeGraphError::Git(m) if m.contains("provider")));
+    }
+}
@@ -0,0 +1,388 @@
+//! Structural difference between two graphs of the same project.
+//!
+//! Items are matched by their path-based id; an item whose id or file changed is reported as
+//! moved when its kind and name identify it uniquely on both sides. Relationships are matched
+//! by endpoint ids (old ids mapped through moves) and kind, so an edit that only shifts lines
+//! produces an empty diff. `Contains` edges are left out since they follow from the items.
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::graph::index::RelKind;
+use crate::graph::{paths, Item, KnowledgeGraph, Visibility};
+use crate::query::{CycleDetectionQuery, Query};
+
+/// Changes from an old graph to a new one; see [`KnowledgeGraph::diff`].
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct GraphDiff {
+    pub added_items: Vec<DiffItem>,
+    pub removed_items: Vec<DiffItem>,
+    pub moved_items: Vec<MovedItem>,
+    pub visibility_changes: Vec<VisibilityChange>,
+    pub added_relationships: Vec<DiffRelationship>,
+    pub removed_relationships: Vec<DiffRelationship>,
+    /// File-level call cycles present only in the new graph.
+    pub new_cycles: Vec<Vec<PathBuf>>,
+    /// Files whose total degree changed, largest change first.
+    pub centrality: Vec<CentralityDelta>,
+}
+
+/// An item, with its file relative to the graph root.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct DiffItem {
+    pub id: String,
+    pub kind: String,
+    pub name: String,
+    pub file: PathBuf,
+    pub line: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MovedItem {
+    pub from: DiffItem,
+    pub to: DiffItem,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct VisibilityChange {
+    pub id: String,
+    pub before: String,
+    pub after: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct DiffRelationship {
+    pub from: String,
+    pub to: String,
+    /// `uses`, `implements`, `extends` or `calls`.
+    pub kind: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CentralityDelta {
+    pub file: PathBuf,
+    pub before: usize,
+    pub after: usize,
+    pub delta: i64,
+}
+
+/// Limits checked by [`GraphDiff::exceeded`]; unset limits are not checked.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DiffThresholds {
+    pub max_new_cycles: Option<usize>,
+    pub max_removed_items: Option<usize>,
+    pub max_added_relationships: Option<usize>,
+    pub max_removed_relationships: Option<usize>,
+    /// Largest allowed change in any file's total degree.
+    pub max_centrality_delta: Option<usize>,
+}
+
+impl GraphDiff {
+    /// True when the graphs are structurally the same.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.added_items.is_empty()
+            && self.removed_items.is_empty()
+            && self.moved_items.is_empty()
+            && self.visibility_changes.is_empty()
+            && self.added_relationships.is_empty()
+            && self.removed_relationships.is_empty()
+            && self.new_cycles.is_empty()
+            && self.centrality.is_empty()
+    }
+
+    /// One-line count of the changes, e.g. `+2 items, -1 items, +5/-3 relationships`.
+    #[must_use]
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        let counts = [
+            ("+", self.added_items.len(), "items"),
+            ("-", self.removed_items.len(), "items"),
+            ("", self.moved_items.len(), "moved"),
+            ("", self.visibility_changes.len(), "visibility changes"),
+        ];
+        for (sign, n, what) in counts {
+            if n > 0 {
+                parts.push(format!("{sign}{n} {what}"));
+            }
+        }
+        let (added, removed) = (self.added_relationships.len(), self.removed_relationships.len());
+        if added + removed > 0 {
+            parts.push(format!("+{added}/-{removed} relationships"));
+        }
+        if !self.new_cycles.is_empty() {
+            parts.push(format!("{} new cycles", self.new_cycles.len()));
+        }
+        if parts.is_empty() {
+            "no structural changes".to_string()
+        } else {
+            parts.join(", ")
+        }
+    }
+
+    /// One message per threshold the diff exceeds.
+    #[must_use]
+    pub fn exceeded(&self, limits: &DiffThresholds) -> Vec<String> {
+        let largest = self.centrality.iter().map(|c| c.delta.unsigned_abs()).max().unwrap_or(0);
+        let checks = [
+            ("new cycles", limits.max_new_cycles, self.new_cycles.len() as u64),
+            ("removed items", limits.max_removed_items, self.removed_items.len() as u64),
+            (
+                "added relationships",
+                limits.max_added_relationships,
+                self.added_relationships.len() as u64,
+            ),
+            (
+                "removed relationships",
+                limits.max_removed_relationships,
+                self.removed_relationships.len() as u64,
+            ),
+            ("centrality delta", limits.max_centrality_delta, largest),
+        ];
+        checks
+            .into_iter()
+            .filter_map(|(what, max, found)| {
+                let max = max?;
+                (found > max as u64).then(|| format!("{what}: {found} exceeds limit {max}"))
+            })
+            .collect()
+    }
+}
+
+impl KnowledgeGraph {
+    /// Structural changes from `old` to `new`.
+    #[must_use]
+    pub fn diff(old: &KnowledgeGraph, new: &KnowledgeGraph) -> GraphDiff {
+        let (old_root, new_root) = (old.root_or_inferred(), new.root_or_inferred());
+        let old_items = items(old, &old_root);
+        let new_items = items(new, &new_root);
+        let mut out = GraphDiff::default();
+
+        // Same id: moved when the file changed, otherwise check visibility
+        let mut removed: Vec<&DiffItem> = Vec::new();
+        for (id, (before, vis_before)) in &old_items {
+            match new_items.get(id) {
+                Some((after, vis_after)) => {
+                    if before.file != after.file {
+                        out.moved_items.push(MovedItem { from: before.clone(), to: after.clone() });
+                    }
+                    if vis_before != vis_after {
+                        out.visibility_changes.push(VisibilityChange {
+                            id: id.clone(),
+                            before: vis_before.clone(),
+                            after: vis_after.clone(),
+                        });
+                    }
+                }
+                None => removed.push(before),
+            }
+        }
+        let added: Vec<&DiffItem> = new_items
+            .iter()
+            .filter(|(id, _)| !old_items.contains_key(*id))
+            .map(|(_, (it, _))| it)
+            .collect();
+
+        // Different id: pair items that are the only ones of their kind and name on each side
+        let by_name = |list: &[&DiffItem]| {
+            let mut m: HashMap<(String, String), Vec<usize>> = HashMap::new();
+            for (i, it) in list.iter().enumerate() {
+                m.entry((it.kind.clone(), it.name.clone())).or_default().push(i);
+            }
+            m
+        };
+        let (removed_by, added_by) = (by_name(&removed), by_name(&added));
+        let mut renamed: HashMap<String, String> = HashMap::new();
+        let (mut paired_removed, mut paired_added) = (BTreeSet::new(), BTreeSet::new());
+        for (key, r) in &removed_by {
+            if let (&[ri], Some(&[ai])) = (r.as_slice(), added_by.get(key).map(Vec::as_slice)) {
+                out.moved_items
+                    .push(MovedItem { from: removed[ri].clone(), to: added[ai].clone() });
+                renamed.insert(removed[ri].id.clone(), added[ai].id.clone());
+                paired_removed.insert(ri);
+                paired_added.insert(ai);
+            }
+        }
+        out.removed_items = removed
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !paired_removed.contains(i))
+            .map(|(_, it)| (*it).clone())
+            .collect();
+        out.added_items = added
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !paired_added.contains(i))
+            .map(|(_, it)| (*it).clone())
+            .collect();
+        out.added_items.sort();
+        out.removed_items.sort();
+        out.moved_items.sort_by(|a, b| a.from.cmp(&b.from));
+        out.visibility_changes.sort_by(|a, b| a.id.cmp(&b.id));
+
+        let old_rels = relationships(old, &renamed);
+        let new_rels = relationships(new, &HashMap::new());
+        out.added_relationships = new_rels.difference(&old_rels).cloned().collect();
+        out.removed_relationships = old_rels.difference(&new_rels).cloned().collect();
+
+        let old_cycles = cycles(old, &old_root);
+        out.new_cycles =
+            cycles(new, &new_root).into_iter().filter(|c| !old_cycles.contains(c)).collect();
+
+        let (before, after) = (degrees(old, &old_root), degrees(new, &new_root));
+        let files: BTreeSet<&PathBuf> = before.keys().chain(after.keys()).collect();
+        out.centrality = files
+            .into_iter()
+            .filter_map(|f| {
+                let (b, a) =
+                    (before.get(f).copied().unwrap_or(0), after.get(f).copied().unwrap_or(0));
+                let delta = i64::try_from(a).unwrap_or(i64::MAX) - i64::try_from(b).unwrap_or(0);
+                (delta != 0).then(|| CentralityDelta {
+                    file: f.clone(),
+                    before: b,
+                    after: a,
+                    delta,
+                })
+            })
+            .collect();
+        out.centrality.sort_by(|a, b| b.delta.abs().cmp(&a.delta.abs()).then(a.file.cmp(&b.file)));
+        out
+    }
+}
+
+fn relative(path: &Path, root: &Path) -> PathBuf {
+    path.strip_prefix(root).unwrap_or(path).to_path_buf()
+}
+
+fn visibility_label(v: &Visibility) -> String {
+    match v {
+        Visibility::Public => "public".to_string(),
+        Visibility::Private => "private".to_string(),
+        Visibility::PubCrate => "pub(crate)".to_string(),
+        Visibility::PubSuper => "pub(super)".to_string(),
+        Visibility::PubIn(p) => format!("pub(in {p})"),
+    }
+}
+
+// Items by id with their visibility; the first of duplicate ids wins.
+fn items(g: &KnowledgeGraph, root: &Path) -> BTreeMap<String, (DiffItem, String)> {
+    let mut out = BTreeMap::new();
+    for node in g.files.values() {
+        let file = relative(&node.path, root);
+        for it in &node.items {
+            out.entry(it.id.0.clone())
+                .or_insert_with(|| (diff_item(it, &file), visibility_label(&it.visibility)));
+        }
+    }
+    out
+}
+
+fn diff_item(it: &Item, file: &Path) -> DiffItem {
+    DiffItem {
+        id: it.id.0.clone(),
+        kind: paths::item_keyword(&it.item_type).to_string(),
+        name: it.name.to_string(),
+        file: file.to_path_buf(),
+        line: it.location.line_start,
+    }
+}
+
+fn relationships(
+    g: &KnowledgeGraph,
+    renamed: &HashMap<String, String>,
+) -> BTreeSet<DiffRelationship> {
+    let id = |s: &str| renamed.get(s).cloned().unwrap_or_else(|| s.to_string());
+    g.relationships
+        .iter()
+        .filter_map(|r| {
+            let kind = match RelKind::of(&r.relationship_type) {
+                RelKind::Contains => return None,
+                RelKind::Uses => "uses",
+                RelKind::Implements => "implements",
+                RelKind::Extends => "extends",
+                RelKind::Calls => "calls",
+            };
+            Some(DiffRelationship {
+                from: id(&r.from_item.0),
+                to: id(&r.to_item.0),
+                kind: kind.to_string(),
+            })
+        })
+        .collect()
+}
+
+// Cycles with root-relative paths, rotated to start at their smallest path.
+fn cycles(g: &KnowledgeGraph, root: &Path) -> Vec<Vec<PathBuf>> {
+    let mut out: Vec<Vec<PathBuf>> = CycleDetectionQuery::new()
+        .run(g)
+        .into_iter()
+        .map(|c| {
+            let mut c: Vec<PathBuf> = c.iter().map(|p| relative(p, root)).collect();
+            if let Some(min) = c.iter().enumerate().min_by(|a, b| a.1.cmp(b.1)).map(|(i, _)| i) {
+                c.rotate_left(min);
+            }
+            c
+        })
+        .collect();
+    out.sort();
+    out.dedup();
+    out
+}
+
+// Total (in + out) file degree by root-relative path.
+fn degrees(g: &KnowledgeGraph, root: &Path) -> HashMap<PathBuf, usize> {
+    let idx = g.index();
+    (0..idx.file_count())
+        .map(|f| {
+            let (i, o) = idx.file_degree(f);
+            (relative(idx.file_path(f), root), i + o)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn thresholds_report_each_exceeded_limit() {
+        let diff = GraphDiff {
+            new_cycles: vec![vec![PathBuf::from("src/a.rs"), PathBuf::from("src/b.rs")]],
+            centrality: vec![CentralityDelta {
+                file: PathBuf::from("src/a.rs"),
+                before: 5,
+                after: 1,
+                delta: -4,
+            }],
+            ..GraphDiff::default()
+        };
+        assert!(diff.exceeded(&DiffThresholds::default()).is_empty());
+        let limits = DiffThresholds {
+            max_new_cycles: Some(0),
+            max_removed_items: Some(0),
+            max_centrality_delta: Some(3),
+            ..DiffThresholds::default()
+        };
+        assert_eq!(
+            diff.exceeded(&limits),
+            vec!["new cycles: 1 exceeds limit 0", "centrality delta: 4 exceeds limit 3"]
+        );
+    }
+
+    #[test]
+    fn summary_counts_only_what_changed() {
+        assert_eq!(GraphDiff::default().summary(), "no structural changes");
+        let rel = DiffRelationship {
+            from: "fn:crate::a".into(),
+            to: "fn:crate::b".into(),
+            kind: "calls".into(),
+        };
+        let diff = GraphDiff {
+            added_relationships: vec![rel.clone(), rel.clone()],
+            removed_relationships: vec![rel],
+            ..GraphDiff::default()
+        };
+        assert_eq!(diff.summary(), "+2/-1 relationships");
+    }
+}
@@ -0,0 +1,353 @@
+//! Subgraph extraction by file, module, item kind, visibility, relationship kind and
+//! neighbourhood.
+//!
+//! A [`GraphFilter`] describes the slice and [`KnowledgeGraph::subgraph`] cuts it out as a new,
+//! self-consistent graph: relationships whose ends were dropped go too, and module maps only
+//! mention the files that remain.
+use std::collections::VecDeque;
+use std::path::Path;
+
+use globset::{Glob, GlobSet, GlobSetBuilder};
+
+use crate::errors::KnowledgeGraphError;
+use crate::graph::index::RelKind;
+use crate::graph::{paths, FileMetrics, FileNode, Item, KnowledgeGraph, Visibility};
+
+/// Minimum visibility of the items a [`GraphFilter`] keeps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VisibilityFilter {
+    /// Only `pub` items.
+    Public,
+    /// Items visible outside their module: `pub`, `pub(crate)`, `pub(super)`, `pub(in ..)`.
+    Crate,
+}
+
+/// Selection of items and relationships; see [`KnowledgeGraph::subgraph`].
+///
+/// Criteria left empty keep everything. An item is kept when it passes every item criterion
+/// (path, module, kind, visibility) and, with [`Self::around`], lies within `hops`
+/// relationships of a seed. A file's `file:` item is kept whenever any of its items is, so
+/// kept items always have their file. A relationship is kept when its kind is selected and
+/// both ends are kept.
+#[derive(Debug, Clone, Default)]
+pub struct GraphFilter {
+    globs: Option<GlobSet>,
+    modules: Vec<Vec<String>>,
+    kinds: Vec<&'static str>,
+    visibility: Option<VisibilityFilter>,
+    relationships: Vec<RelKind>,
+    seeds: Vec<String>,
+    hops: usize,
+}
+
+impl GraphFilter {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keep files matching any of these globs. Globs match the path relative to the graph's
+    /// root (`src/graph/**`) or the full path.
+    ///
+    /// # Errors
+    /// Returns `KnowledgeGraphError::Query` for an invalid glob.
+    pub fn paths<I, S>(mut self, globs: I) -> Result<Self, KnowledgeGraphError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut builder = GlobSetBuilder::new();
+        let mut any = false;
+        for g in globs {
+            let glob = Glob::new(g.as_ref()).map_err(|e| {
+                KnowledgeGraphError::Query(format!("invalid path glob '{}': {e}", g.as_ref()))
+            })?;
+            builder.add(glob);
+            any = true;
+        }
+        if any {
+            let set = builder.build().map_err(|e| KnowledgeGraphError::Query(e.to_string()))?;
+            self.globs = Some(set);
+        }
+        Ok(self)
+    }
+
+    /// Keep items under any of these module paths, written as in item ids (`crate::graph`).
+    #[must_use]
+    pub fn modules<I, S>(mut self, prefixes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.modules.extend(
+            prefixes.into_iter().map(|p| p.as_ref().split("::").map(str::to_string).collect()),
+        );
+        self
+    }
+
+    /// Keep items of these kinds, by id keyword (`fn`, `struct`) or name (`function`).
+    ///
+    /// # Errors
+    /// Returns `KnowledgeGraphError::Query` for an unknown kind.
+    pub fn kinds<I, S>(mut self, kinds: I) -> Result<Self, KnowledgeGraphError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        for k in kinds {
+            let kw = kind_keyword(k.as_ref()).ok_or_else(|| {
+                KnowledgeGraphError::Query(format!("unknown item kind '{}'", k.as_ref()))
+            })?;
+            self.kinds.push(kw);
+        }
+        Ok(self)
+    }
+
+    /// Keep items at least this visible.
+    #[must_use]
+    pub fn visibility(mut self, visibility: VisibilityFilter) -> Self {
+        self.visibility = Some(visibility);
+        self
+    }
+
+    /// Keep relationships of these kinds.
+    #[must_use]
+    pub fn relationships(mut self, kinds: impl IntoIterator<Item = RelKind>) -> Self {
+        self.relationships.extend(kinds);
+        self
+    }
+
+    /// Keep items within `hops` relationships (either direction) of the seed items. Seeds are
+    /// item ids, including the legacy `kind:name:line` form.
+    #[must_use]
+    pub fn around<I, S>(mut self, seeds: I, hops: usize) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.seeds.extend(seeds.into_iter().map(Into::into));
+        self.hops = hops;
+        self
+    }
+
+    /// True when the filter keeps the whole graph.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.globs.is_none()
+            && self.modules.is_empty()
+            && self.kinds.is_empty()
+            && self.visibility.is_none()
+            && self.relationships.is_empty()
+            && self.seeds.is_empty()
+    }
+
+    fn keeps_path(&self, path: &Path, root: &Path) -> bool {
+        self.globs.as_ref().is_none_or(|g| {
+            g.is_match(path) || path.strip_prefix(root).is_ok_and(|rel| g.is_match(rel))
+        })
+    }
+
+    fn keeps_item(&self, it: &Item, module: &[String]) -> bool {
+        let kind = paths::item_keyword(&it.item_type);
+        if !self.kinds.is_empty() && !self.kinds.contains(&kind) {
+            return false;
+        }
+        let visible = match self.visibility {
+            None => true,
+            Some(VisibilityFilter::Public) => matches!(it.visibility, Visibility::Public),
+            Some(VisibilityFilter::Crate) => !matches!(it.visibility, Visibility::Private),
+        };
+        if !visible {
+            return false;
+        }
+        if self.modules.is_empty() {
+            return true;
+        }
+        // `file:` ids carry no module path; use the file's module instead
+        let segs: Vec<&str> = if it.id.0.starts_with("file:") {
+            module.iter().map(String::as_str).collect()
+        } else {
+            let path = it.id.0.split_once(':').map_or("", |(_, p)| p);
+            path.split('#').next().unwrap_or(path).split("::").collect()
+        };
+        self.modules
+            .iter()
+            .any(|m| segs.len() >= m.len() && segs.iter().zip(m).all(|(a, b)| a == b))
+    }
+
+    fn keeps_rel(&self, kind: RelKind) -> bool {
+        self.relationships.is_empty() || self.relationships.contains(&kind)
+    }
+}
+
+/// Id keyword of an item kind given as keyword (`fn`) or name (`function`).
+#[must_use]
+pub fn kind_keyword(name: &str) -> Option<&'static str> {
+    Some(match name.to_ascii_lowercase().as_str() {
+        "mod" | "module" => "mod",
+        "fn" | "function" => "fn",
+        "struct" => "struct",
+        "enum" => "enum",
+        "trait" => "trait",
+        "impl" => "impl",
+        "const" => "const",
+        "static" => "static",
+        "type" => "type",
+        "macro" => "macro",
+        _ => return None,
+    })
+}
+
+/// Relationship kind by name (`uses`, `implements`, `contains`, `extends`, `calls`).
+#[must_use]
+pub fn rel_kind(name: &str) -> Option<RelKind> {
+    Some(match name.to_ascii_lowercase().as_str() {
+        "uses" => RelKind::Uses,
+        "implements" => RelKind::Implements,
+        "contains" => RelKind::Contains,
+        "extends" => RelKind::Extends,
+        "calls" => RelKind::Calls,
+        _ => return None,
+    })
+}
+
+impl KnowledgeGraph {
+    /// The part of the graph selected by `filter`, as a new graph.
+    #[must_use]
+    pub fn subgraph(&self, filter: &GraphFilter) -> KnowledgeGraph {
+        let idx = self.index();
+        let root = self.root_or_inferred();
+        let mut crates = paths::CrateNames::new(self);
+        let mut keep = vec![false; idx.node_count()];
+        for f in 0..idx.file_count() {
+            let path = idx.file_path(f);
+            if !filter.keeps_path(path, &root) {
+                continue;
+            }
+            let module = if filter.modules.is_empty() {
+                Vec::new()
+            } else {
+                paths::id_module(self, &mut crates, &root, path)
+            };
+            for node in idx.file_nodes(f) {
+                keep[node] = idx.item(self, node).is_some_and(|it| filter.keeps_item(it, &module));
+            }
+        }
+
+        if !filter.seeds.is_empty() {
+            // Breadth-first over kept items and selected relationship kinds
+            let mut dist = vec![usize::MAX; keep.len()];
+            let mut queue = VecDeque::new();
+            for seed in &filter.seeds {
+                for id in self.find_item_ids(seed) {
+                    if let Some(n) = idx.node(&id).filter(|&n| keep[n] && dist[n] == usize::MAX) {
+                        dist[n] = 0;
+                        queue.push_back(n);
+                    }
+                }
+            }
+            while let Some(n) = queue.pop_front() {
+                if dist[n] == filter.hops {
+                    continue;
+                }
+                for kind in RelKind::ALL.into_iter().filter(|&k| filter.keeps_rel(k)) {
+                    for e in idx.outgoing(n, kind).iter().chain(idx.incoming(n, kind)) {
+                        if keep[e.node] && dist[e.node] == usize::MAX {
+                            dist[e.node] = dist[n] + 1;
+                            queue.push_back(e.node);
+                        }
+                    }
+                }
+            }
+            for (k, d) in keep.iter_mut().zip(&dist) {
+                *k = *d != usize::MAX;
+            }
+        }
+
+        let mut out =
+            KnowledgeGraph { metadata: self.metadata.clone(), ..KnowledgeGraph::default() };
+        // The build's stats describe the whole graph, not this part of it
+        out.metadata.stats = None;
+        for f in 0..idx.file_count() {
+            let nodes = idx.file_nodes(f);
+            if !nodes.clone().any(|n| keep[n]) {
+                continue;
+            }
+            let Some(node) = self.files.get(idx.file_path(f)) else { continue };
+            let first = nodes.start;
+            if node.items.first().is_some_and(|it| it.id.0.starts_with("file:")) {
+                keep[first] = true;
+            }
+            let items: Vec<Item> = nodes
+                .filter(|&n| keep[n])
+                .filter_map(|n| node.items.get(n - first).cloned())
+                .collect();
+            let metrics = FileMetrics { item_count: items.len(), import_count: node.imports.len() };
+            out.files.insert(
+                node.path.clone(),
+                FileNode { path: node.path.clone(), items, imports: node.imports.clone(), metrics },
+            );
+        }
+
+        out.relationships = self
+            .relationships
+            .iter()
+            .filter(|r| filter.keeps_rel(RelKind::of(&r.relationship_type)))
+            .filter(|r| {
+                let kept = |id| idx.node(id).is_some_and(|n| keep[n]);
+                kept(&r.from_item) && kept(&r.to_item)
+            })
+            .cloned()
+            .collect();
+
+        let has = |p: &Path| out.files.contains_key(p);
+        let module_parent = self
+            .module_parent
+            .iter()
+            .filter(|(c, p)| has(c) && has(p))
+            .map(|(c, p)| (c.clone(), p.clone()))
+            .collect();
+        let module_children = self
+            .module_children
+            .iter()
+            .filter(|(p, _)| has(p))
+            .map(|(p, kids)| (p.clone(), kids.iter().filter(|k| has(k)).cloned().collect()))
+            .filter(|(_, kids): &(_, Vec<_>)| !kids.is_empty())
+            .collect();
+        let module_segments = self
+            .module_segments
+            .iter()
+            .filter(|(p, _)| has(p))
+            .map(|(p, s)| (p.clone(), s.clone()))
+            .collect();
+        let import_segments = self
+            .import_segments
+            .iter()
+            .filter(|(p, _)| has(p))
+            .map(|(p, s)| (p.clone(), s.clone()))
+            .collect();
+        out.module_parent = module_parent;
+        out.module_children = module_children;
+        out.module_segments = module_segments;
+        out.import_segments = import_segments;
+        // Module paths of the subgraph keep naming crates as the build did
+        out.crate_names = self.crate_names.clone();
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kind_and_relationship_names() {
+        assert_eq!(kind_keyword("Function"), Some("fn"));
+        assert_eq!(kind_keyword("mod"), Some("mod"));
+        assert_eq!(kind_keyword("union"), None);
+        assert_eq!(rel_kind("CALLS"), Some(RelKind::Calls));
+        assert!(GraphFilter::new().is_empty());
+        assert!(matches!(GraphFilter::new().paths(["src/[a"]), Err(KnowledgeGraphError::Query(_))));
+        assert!(matches!(GraphFilter::new().kinds(["widget"]), Err(KnowledgeGraphError::Query(_))));
+    }
+}
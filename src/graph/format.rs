@@ -0,0 +1,669 @@
+//! On-disk graph formats: pretty JSON and a compact binary encoding.
+//!
+//! The binary format starts with the magic `RRXG` and a version byte, followed by a string
+//! table holding every path, id, name and context once. Everything after it refers to
+//! strings by table index, items by their position in file order, and encodes integers as
+//! LEB128 varints. Relationship endpoints are item numbers rather than id strings, so a load
+//! only decodes each string once and never looks ids up by hash. Paths are resolved against
+//! the recorded root once per string while decoding.
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::errors::KnowledgeGraphError;
+use crate::graph::stats::{BuildStats, PhaseTimings};
+use crate::graph::{
+    schema, BuildFingerprint, Confidence, Evidence, FileMetrics, FileNode, GraphMetadata, Import,
+    Item, ItemId, ItemType, KnowledgeGraph, Location, Relationship, RelationshipType, Visibility,
+};
+
+const MAGIC: &[u8; 4] = b"RRXG";
+const VERSION: u8 = 4;
+// Oldest version still read. Version 1 has no import positions, site counts or evidence;
+// version 2 has no build stats; version 3 kept them as JSON text, which is skipped.
+const MIN_VERSION: u8 = 1;
+
+/// Serialization format of a saved graph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphFormat {
+    /// Pretty-printed JSON, readable and diffable.
+    Json,
+    /// Compact binary encoding; much smaller and faster to load.
+    Binary,
+}
+
+impl GraphFormat {
+    /// Format implied by a file name: `.bin` and `.rrg` are binary, anything else JSON.
+    #[must_use]
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("bin" | "rrg") => GraphFormat::Binary,
+            _ => GraphFormat::Json,
+        }
+    }
+}
+
+impl KnowledgeGraph {
+    /// Save the graph in the given format.
+    ///
+    /// # Errors
+    /// Returns `KnowledgeGraphError::Io` if encoding or writing fails and
+    /// `KnowledgeGraphError::Format` for a path the binary format cannot hold.
+    pub fn save(&self, path: &Path, format: GraphFormat) -> Result<(), KnowledgeGraphError> {
+        match format {
+            GraphFormat::Json => self.save_json(path),
+            GraphFormat::Binary => Ok(std::fs::write(path, self.to_binary()?)?),
+        }
+    }
+
+    /// Load a graph saved in the given format.
+    ///
+    /// # Errors
+    /// Returns `KnowledgeGraphError::Io` if reading fails, `KnowledgeGraphError::Format`
+    /// if the content is not a valid graph, and `KnowledgeGraphError::UnsupportedSchema` if a
+    /// newer version wrote it.
+    pub fn load(path: &Path, format: GraphFormat) -> Result<Self, KnowledgeGraphError> {
+        match format {
+            GraphFormat::Json => Self::load_json(path),
+            GraphFormat::Binary => Self::from_binary(&std::fs::read(path)?),
+        }
+    }
+
+    /// Encode the graph in the compact binary format, with file paths relative to the
+    /// recorded root as in JSON.
+    ///
+    /// # Errors
+    /// Returns `KnowledgeGraphError::Format` for a path that is not valid UTF-8.
+    pub fn to_binary(&self) -> Result<Vec<u8>, KnowledgeGraphError> {
+        self.portable().encode()
+    }
+
+    fn encode(&self) -> Result<Vec<u8>, KnowledgeGraphError> {
+        let mut files: Vec<&FileNode> = self.files.values().collect();
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        let mut strings = Strings::default();
+        let mut body = Vec::new();
+        // Item id -> position in file order (first occurrence for duplicate ids)
+        let mut item_no: HashMap<&str, usize> = HashMap::new();
+        let mut position = 0usize;
+
+        let meta = &self.metadata;
+        put_str(&mut body, &mut strings, &meta.generated_at);
+        put_uint(&mut body, meta.schema_version as usize);
+        put_str(&mut body, &mut strings, &meta.tool_version);
+        body.push(u8::from(meta.options.no_ignore));
+        put_uint(&mut body, meta.options.features.len());
+        for f in &meta.options.features {
+            put_str(&mut body, &mut strings, f);
+        }
+        put_str(&mut body, &mut strings, &meta.options.parser);
+        let root = meta.root.as_deref().map(path_str).transpose()?;
+        put_opt_str(&mut body, &mut strings, root);
+        body.push(u8::from(meta.stats.is_some()));
+        if let Some(stats) = &meta.stats {
+            put_stats(&mut body, &mut strings, stats);
+        }
+        put_uint(&mut body, files.len());
+        for file in &files {
+            put_str(&mut body, &mut strings, path_str(&file.path)?);
+            put_uint(&mut body, file.items.len());
+            for it in &file.items {
+                item_no.entry(it.id.0.as_str()).or_insert(position);
+                position += 1;
+                put_item(&mut body, &mut strings, it, &file.path)?;
+            }
+            put_uint(&mut body, file.imports.len());
+            for imp in &file.imports {
+                put_str(&mut body, &mut strings, &imp.path);
+                put_opt_str(&mut body, &mut strings, imp.alias.as_deref());
+                body.push(u8::from(imp.is_pub));
+                put_uint(&mut body, imp.line);
+                put_uint(&mut body, imp.column);
+            }
+            put_uint(&mut body, file.metrics.item_count);
+            put_uint(&mut body, file.metrics.import_count);
+        }
+
+        put_uint(&mut body, self.relationships.len());
+        for rel in &self.relationships {
+            for id in [&rel.from_item, &rel.to_item] {
+                // Even: item number; odd: string index of an id no item carries
+                match item_no.get(id.0.as_str()) {
+                    Some(&n) => put_uint(&mut body, n << 1),
+                    None => put_uint(&mut body, (strings.index(&id.0) << 1) | 1),
+                }
+            }
+            let (tag, payload) = match &rel.relationship_type {
+                RelationshipType::Uses { import_type } => (0, import_type),
+                RelationshipType::Implements { trait_name } => (1, trait_name),
+                RelationshipType::Contains { containment_type } => (2, containment_type),
+                RelationshipType::Extends { extension_type } => (3, extension_type),
+                RelationshipType::Calls { call_type } => (4, call_type),
+            };
+            body.push(tag);
+            put_str(&mut body, &mut strings, payload);
+            body.extend_from_slice(&rel.strength.to_le_bytes());
+            put_str(&mut body, &mut strings, &rel.context);
+            body.push(match rel.confidence {
+                Confidence::NameOnly => 0,
+                Confidence::Inferred => 1,
+                Confidence::Exact => 2,
+            });
+            put_uint(&mut body, rel.line.map_or(0, |l| l + 1));
+            put_uint(&mut body, rel.count);
+            put_uint(&mut body, rel.evidence.len());
+            for ev in &rel.evidence {
+                put_str(&mut body, &mut strings, path_str(&ev.file)?);
+                put_uint(&mut body, ev.line);
+                put_uint(&mut body, ev.column);
+                put_str(&mut body, &mut strings, &ev.snippet);
+            }
+        }
+
+        let mut parents: Vec<(&PathBuf, &PathBuf)> = self.module_parent.iter().collect();
+        parents.sort();
+        put_uint(&mut body, parents.len());
+        for (child, parent) in parents {
+            put_str(&mut body, &mut strings, path_str(child)?);
+            put_str(&mut body, &mut strings, path_str(parent)?);
+        }
+        let mut children: Vec<(&PathBuf, &Vec<PathBuf>)> = self.module_children.iter().collect();
+        children.sort();
+        put_uint(&mut body, children.len());
+        for (parent, kids) in children {
+            put_str(&mut body, &mut strings, path_str(parent)?);
+            put_uint(&mut body, kids.len());
+            for k in kids {
+                put_str(&mut body, &mut strings, path_str(k)?);
+            }
+        }
+        let mut segments: Vec<(&PathBuf, &Vec<String>)> = self.module_segments.iter().collect();
+        segments.sort();
+        put_uint(&mut body, segments.len());
+        for (path, segs) in segments {
+            put_str(&mut body, &mut strings, path_str(path)?);
+            put_uint(&mut body, segs.len());
+            for s in segs {
+                put_str(&mut body, &mut strings, s);
+            }
+        }
+
+        let mut out = Vec::with_capacity(body.len() + strings.bytes + 16);
+        out.extend_from_slice(MAGIC);
+        out.push(VERSION);
+        put_uint(&mut out, strings.list.len());
+        for s in &strings.list {
+            put_uint(&mut out, s.len());
+            out.extend_from_slice(s.as_bytes());
+        }
+        out.extend_from_slice(&body);
+        Ok(out)
+    }
+
+    /// Decode a graph from the compact binary format.
+    ///
+    /// # Errors
+    /// Returns `KnowledgeGraphError::Format` if `data` is not a graph in a supported binary
+    /// version and `KnowledgeGraphError::UnsupportedSchema` if its schema is newer.
+    #[allow(clippy::too_many_lines)]
+    pub fn from_binary(data: &[u8]) -> Result<Self, KnowledgeGraphError> {
+        let mut r = Reader { data, pos: 0 };
+        if r.bytes(4)? != MAGIC {
+            return Err(format_err("not a binary graph file"));
+        }
+        let version = r.byte()?;
+        if !(MIN_VERSION..=VERSION).contains(&version) {
+            return Err(format_err(&format!("unsupported binary graph version {version}")));
+        }
+        let count = r.uint()?;
+        let mut strings: Vec<Arc<str>> = Vec::with_capacity(count.min(data.len()));
+        for _ in 0..count {
+            let len = r.uint()?;
+            let s = std::str::from_utf8(r.bytes(len)?)
+                .map_err(|_| format_err("invalid UTF-8 in string table"))?;
+            strings.push(Arc::from(s));
+        }
+        let paths = vec![None; strings.len()];
+        let mut r = Tables { r, strings, root: None, paths };
+
+        let generated_at = r.str()?.to_string();
+        let schema_version =
+            u32::try_from(r.r.uint()?).map_err(|_| format_err("bad schema version"))?;
+        let tool_version = r.str()?.to_string();
+        schema::check(schema_version, &tool_version)?;
+        let no_ignore = r.flag()?;
+        let features = (0..r.r.uint()?)
+            .map(|_| r.str().map(|f| f.to_string()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let options = BuildFingerprint { no_ignore, features, parser: r.str()?.to_string() };
+        let root = r.opt_str()?.map(|p| PathBuf::from(&*p));
+        r.root.clone_from(&root);
+        let stats = match version {
+            ..=2 => None,
+            3 => {
+                r.opt_str()?;
+                None
+            }
+            _ => r.flag()?.then(|| r.stats()).transpose()?,
+        };
+        let mut graph = KnowledgeGraph {
+            metadata: GraphMetadata {
+                generated_at,
+                schema_version,
+                tool_version,
+                options,
+                root,
+                stats,
+            },
+            ..KnowledgeGraph::default()
+        };
+        let file_count = r.r.uint()?;
+        graph.files.reserve(file_count.min(data.len()));
+        let mut ids: Vec<ItemId> = Vec::new();
+        for _ in 0..file_count {
+            let path = r.path()?;
+            let n = r.r.uint()?;
+            let mut items = Vec::with_capacity(n.min(data.len()));
+            for _ in 0..n {
+                let it = r.item(&path)?;
+                ids.push(it.id.clone());
+                items.push(it);
+            }
+            let n = r.r.uint()?;
+            let mut imports = Vec::with_capacity(n.min(data.len()));
+            for _ in 0..n {
+                imports.push(Import {
+                    path: r.str()?,
+                    alias: r.opt_str()?,
+                    is_pub: r.r.byte()? != 0,
+                    line: if version >= 2 { r.r.uint()? } else { 0 },
+                    column: if version >= 2 { r.r.uint()? } else { 0 },
+                });
+            }
+            let metrics = FileMetrics { item_count: r.r.uint()?, import_count: r.r.uint()? };
+            graph.files.insert(path.clone(), FileNode { path, items, imports, metrics });
+        }
+
+        let n = r.r.uint()?;
+        graph.relationships.reserve(n.min(data.len()));
+        for _ in 0..n {
+            let from_item = r.endpoint(&ids)?;
+            let to_item = r.endpoint(&ids)?;
+            let tag = r.r.byte()?;
+            let payload = r.str()?.to_string();
+            let relationship_type = match tag {
+                0 => RelationshipType::Uses { import_type: payload },
+                1 => RelationshipType::Implements { trait_name: payload },
+                2 => RelationshipType::Contains { containment_type: payload },
+                3 => RelationshipType::Extends { extension_type: payload },
+                4 => RelationshipType::Calls { call_type: payload },
+                _ => return Err(format_err("bad relationship tag")),
+            };
+            let strength = r.r.f64()?;
+            let context = r.str()?.to_string();
+            let confidence = match r.r.byte()? {
+                0 => Confidence::NameOnly,
+                1 => Confidence::Inferred,
+                2 => Confidence::Exact,
+                _ => return Err(format_err("bad confidence tag")),
+            };
+            let line = r.r.uint()?.checked_sub(1);
+            let (count, n) = if version >= 2 { (r.r.uint()?, r.r.uint()?) } else { (1, 0) };
+            let mut evidence = Vec::with_capacity(n.min(data.len()));
+            for _ in 0..n {
+                evidence.push(Evidence {
+                    file: r.path()?,
+                    line: r.r.uint()?,
+                    column: r.r.uint()?,
+                    snippet: r.str()?.to_string(),
+                });
+            }
+            graph.relationships.push(Relationship {
+                from_item,
+                to_item,
+                relationship_type,
+                strength,
+                context,
+                confidence,
+                line,
+                count,
+                evidence,
+            });
+        }
+
+        for _ in 0..r.r.uint()? {
+            let child = r.path()?;
+            graph.module_parent.insert(child, r.path()?);
+        }
+        for _ in 0..r.r.uint()? {
+            let parent = r.path()?;
+            let n = r.r.uint()?;
+            let mut kids = Vec::with_capacity(n.min(data.len()));
+            for _ in 0..n {
+                kids.push(r.path()?);
+            }
+            graph.module_children.insert(parent, kids);
+        }
+        for _ in 0..r.r.uint()? {
+            let path = r.path()?;
+            let n = r.r.uint()?;
+            let mut segs = Vec::with_capacity(n.min(data.len()));
+            for _ in 0..n {
+                segs.push(r.str()?.to_string());
+            }
+            graph.module_segments.insert(path, segs);
+        }
+        if r.r.pos != data.len() {
+            return Err(format_err("trailing bytes after graph"));
+        }
+        schema::migrate(&mut graph)?;
+        Ok(graph)
+    }
+}
+
+fn format_err(msg: &str) -> KnowledgeGraphError {
+    KnowledgeGraphError::Format(msg.to_string())
+}
+
+fn path_str(p: &Path) -> Result<&str, KnowledgeGraphError> {
+    p.to_str().ok_or_else(|| format_err(&format!("path is not valid UTF-8: {}", p.display())))
+}
+
+// String table under construction.
+#[derive(Default)]
+struct Strings {
+    list: Vec<String>,
+    index: HashMap<String, usize>,
+    bytes: usize,
+}
+
+impl Strings {
+    fn index(&mut self, s: &str) -> usize {
+        if let Some(&i) = self.index.get(s) {
+            return i;
+        }
+        let i = self.list.len();
+        self.list.push(s.to_string());
+        self.index.insert(s.to_string(), i);
+        self.bytes += s.len() + 2;
+        i
+    }
+}
+
+fn put_uint(out: &mut Vec<u8>, mut v: usize) {
+    while v >= 0x80 {
+        // Low seven bits with the continuation flag; truncation is intended
+        #[allow(clippy::cast_possible_truncation)]
+        out.push((v as u8 & 0x7f) | 0x80);
+        v >>= 7;
+    }
+    #[allow(clippy::cast_possible_truncation)]
+    out.push(v as u8);
+}
+
+fn put_str(out: &mut Vec<u8>, strings: &mut Strings, s: &str) {
+    put_uint(out, strings.index(s));
+}
+
+// 0 for `None`, else string index + 1
+fn put_opt_str(out: &mut Vec<u8>, strings: &mut Strings, s: Option<&str>) {
+    put_uint(out, s.map_or(0, |s| strings.index(s) + 1));
+}
+
+fn put_stats(out: &mut Vec<u8>, strings: &mut Strings, stats: &BuildStats) {
+    put_uint(out, stats.files_discovered);
+    put_uint(out, stats.files_reused);
+    put_uint(out, stats.files_parsed);
+    for counts in [&stats.items_by_kind, &stats.relationships_by_type] {
+        put_uint(out, counts.len());
+        for (kind, n) in counts {
+            put_str(out, strings, kind);
+            put_uint(out, *n);
+        }
+    }
+    put_uint(out, stats.unresolved_imports);
+    for ms in timings(&stats.timings) {
+        out.extend_from_slice(&ms.to_le_bytes());
+    }
+    // 0 when unknown, else 1 and the byte count
+    match stats.peak_memory_bytes {
+        Some(bytes) => {
+            out.push(1);
+            out.extend_from_slice(&bytes.to_le_bytes());
+        }
+        None => out.push(0),
+    }
+}
+
+// Phase timings in encoding order.
+fn timings(t: &PhaseTimings) -> [f64; 8] {
+    [
+        t.discovery_ms,
+        t.cache_load_ms,
+        t.parsing_ms,
+        t.module_hierarchy_ms,
+        t.dependency_tracking_ms,
+        t.import_analysis_ms,
+        t.call_analysis_ms,
+        t.total_ms,
+    ]
+}
+
+fn put_item(
+    out: &mut Vec<u8>,
+    strings: &mut Strings,
+    it: &Item,
+    file: &Path,
+) -> Result<(), KnowledgeGraphError> {
+    put_str(out, strings, &it.id.0);
+    match &it.item_type {
+        ItemType::Module { is_inline } => out.extend([0, u8::from(*is_inline)]),
+        ItemType::Function { is_async, is_const } => {
+            out.extend([1, u8::from(*is_async), u8::from(*is_const)]);
+        }
+        ItemType::Struct { is_tuple } => out.extend([2, u8::from(*is_tuple)]),
+        ItemType::Enum { variant_count } => {
+            out.push(3);
+            put_uint(out, *variant_count);
+        }
+        ItemType::Trait { is_object_safe } => out.extend([4, u8::from(*is_object_safe)]),
+        ItemType::Impl { trait_name, type_name } => {
+            out.push(5);
+            put_opt_str(out, strings, trait_name.as_deref());
+            put_str(out, strings, type_name);
+        }
+        ItemType::Const => out.push(6),
+        ItemType::Static { is_mut } => out.extend([7, u8::from(*is_mut)]),
+        ItemType::Type => out.push(8),
+        ItemType::Macro => out.push(9),
+    }
+    put_str(out, strings, &it.name);
+    match &it.visibility {
+        Visibility::Public => out.push(0),
+        Visibility::Private => out.push(1),
+        Visibility::PubCrate => out.push(2),
+        Visibility::PubSuper => out.push(3),
+        Visibility::PubIn(p) => {
+            out.push(4);
+            put_str(out, strings, p);
+        }
+    }
+    // The location file is nearly always the file itself: 0, else string index + 1
+    if it.location.file == file {
+        put_uint(out, 0);
+    } else {
+        put_opt_str(out, strings, Some(path_str(&it.location.file)?));
+    }
+    put_uint(out, it.location.line_start);
+    put_uint(out, it.location.line_end);
+    put_uint(out, it.attributes.len());
+    for a in &it.attributes {
+        put_str(out, strings, a);
+    }
+    Ok(())
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, n: usize) -> Result<&'a [u8], KnowledgeGraphError> {
+        let end = self.pos.checked_add(n).filter(|&e| e <= self.data.len());
+        let end = end.ok_or_else(|| format_err("unexpected end of graph file"))?;
+        let s = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(s)
+    }
+
+    fn byte(&mut self) -> Result<u8, KnowledgeGraphError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn f64(&mut self) -> Result<f64, KnowledgeGraphError> {
+        Ok(f64::from_le_bytes(self.bytes(8)?.try_into().unwrap_or_default()))
+    }
+
+    fn uint(&mut self) -> Result<usize, KnowledgeGraphError> {
+        let mut v: usize = 0;
+        for shift in (0..usize::BITS).step_by(7) {
+            let b = self.byte()?;
+            v |= usize::from(b & 0x7f) << shift;
+            if b & 0x80 == 0 {
+                return Ok(v);
+            }
+        }
+        Err(format_err("varint overflow"))
+    }
+}
+
+// Reader plus the decoded string table, and the paths resolved from it so far.
+struct Tables<'a> {
+    r: Reader<'a>,
+    strings: Vec<Arc<str>>,
+    // Recorded root that relative paths are joined onto
+    root: Option<PathBuf>,
+    // Resolved path of each string index read as a path
+    paths: Vec<Option<PathBuf>>,
+}
+
+impl Tables<'_> {
+    fn string_at(&self, i: usize) -> Result<Arc<str>, KnowledgeGraphError> {
+        self.strings.get(i).cloned().ok_or_else(|| format_err("bad string index"))
+    }
+
+    // Inverse of `portable`: the path joined onto the recorded root, once per string.
+    fn path_at(&mut self, i: usize) -> Result<PathBuf, KnowledgeGraphError> {
+        if let Some(Some(p)) = self.paths.get(i) {
+            return Ok(p.clone());
+        }
+        let s = self.string_at(i)?;
+        let path = match &self.root {
+            Some(root) => root.join(&*s),
+            None => PathBuf::from(&*s),
+        };
+        self.paths[i] = Some(path.clone());
+        Ok(path)
+    }
+
+    fn path(&mut self) -> Result<PathBuf, KnowledgeGraphError> {
+        let i = self.r.uint()?;
+        self.path_at(i)
+    }
+
+    fn stats(&mut self) -> Result<BuildStats, KnowledgeGraphError> {
+        let mut stats = BuildStats {
+            files_discovered: self.r.uint()?,
+            files_reused: self.r.uint()?,
+            files_parsed: self.r.uint()?,
+            ..BuildStats::default()
+        };
+        for counts in [&mut stats.items_by_kind, &mut stats.relationships_by_type] {
+            for _ in 0..self.r.uint()? {
+                counts.insert(self.str()?.to_string(), self.r.uint()?);
+            }
+        }
+        stats.unresolved_imports = self.r.uint()?;
+        stats.timings = PhaseTimings {
+            discovery_ms: self.r.f64()?,
+            cache_load_ms: self.r.f64()?,
+            parsing_ms: self.r.f64()?,
+            module_hierarchy_ms: self.r.f64()?,
+            dependency_tracking_ms: self.r.f64()?,
+            import_analysis_ms: self.r.f64()?,
+            call_analysis_ms: self.r.f64()?,
+            total_ms: self.r.f64()?,
+        };
+        stats.peak_memory_bytes = match self.r.byte()? {
+            0 => None,
+            1 => Some(u64::from_le_bytes(self.r.bytes(8)?.try_into().unwrap_or_default())),
+            _ => return Err(format_err("bad peak memory tag")),
+        };
+        Ok(stats)
+    }
+
+    fn str(&mut self) -> Result<Arc<str>, KnowledgeGraphError> {
+        let i = self.r.uint()?;
+        self.string_at(i)
+    }
+
+    fn opt_str(&mut self) -> Result<Option<Arc<str>>, KnowledgeGraphError> {
+        match self.r.uint()? {
+            0 => Ok(None),
+            i => self.string_at(i - 1).map(Some),
+        }
+    }
+
+    // Even: item number; odd: string index of an id no item carries
+    fn endpoint(&mut self, ids: &[ItemId]) -> Result<ItemId, KnowledgeGraphError> {
+        let v = self.r.uint()?;
+        if v & 1 == 0 {
+            ids.get(v >> 1).cloned().ok_or_else(|| format_err("bad item number"))
+        } else {
+            Ok(ItemId(self.string_at(v >> 1)?.to_string()))
+        }
+    }
+
+    fn flag(&mut self) -> Result<bool, KnowledgeGraphError> {
+        Ok(self.r.byte()? != 0)
+    }
+
+    fn item(&mut self, file: &Path) -> Result<Item, KnowledgeGraphError> {
+        let id = ItemId(self.str()?.to_string());
+        let item_type = match self.r.byte()? {
+            0 => ItemType::Module { is_inline: self.flag()? },
+            1 => ItemType::Function { is_async: self.flag()?, is_const: self.flag()? },
+            2 => ItemType::Struct { is_tuple: self.flag()? },
+            3 => ItemType::Enum { variant_count: self.r.uint()? },
+            4 => ItemType::Trait { is_object_safe: self.flag()? },
+            5 => ItemType::Impl { trait_name: self.opt_str()?, type_name: self.str()? },
+            6 => ItemType::Const,
+            7 => ItemType::Static { is_mut: self.flag()? },
+            8 => ItemType::Type,
+            9 => ItemType::Macro,
+            _ => return Err(format_err("bad item kind tag")),
+        };
+        let name = self.str()?;
+        let visibility = match self.r.byte()? {
+            0 => Visibility::Public,
+            1 => Visibility::Private,
+            2 => Visibility::PubCrate,
+            3 => Visibility::PubSuper,
+            4 => Visibility::PubIn(self.str()?),
+            _ => return Err(format_err("bad visibility tag")),
+        };
+        let loc_file = match self.r.uint()? {
+            0 => file.to_path_buf(),
+            i => self.path_at(i - 1)?,
+        };
+        let location =
+            Location { file: loc_file, line_start: self.r.uint()?, line_end: self.r.uint()? };
+        let n = self.r.uint()?;
+        let mut attributes = Vec::with_capacity(n.min(self.r.data.len()));
+        for _ in 0..n {
+            attributes.push(self.str()?.to_string());
+        }
+        Ok(Item { id, item_type, name, visibility, location, attributes })
+    }
+}
@@ -0,0 +1,508 @@
+//! Incremental relationship analysis.
+//!
+//! Every build-cache entry keeps the analysis of its file: type facts, `mod` declarations,
+//! the outgoing `Uses` and `Calls` edges, a fingerprint of what other files can find under
+//! each name the file defines, and the identifiers the file mentions. A file is analyzed again
+//! when its content or item ids changed, or when it mentions a name whose fingerprint changed
+//! elsewhere; [`DepIndex`] maps names back to the files that mention them. Edges belong to the
+//! file they start from, so every other file keeps its cached edges as they are.
+//!
+//! Mentions over-approximate what a file resolves against: the import and call passes only
+//! look up names written in the file (path segments, called and method names, types), and
+//! changes to a module's imports are keyed by its path segments, `crate` and `super`. A glob
+//! import can bring any name into scope, so a changed one reaches every file.
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use std::time::Instant;
+
+use rayon::prelude::*;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::errors::KnowledgeGraphError;
+use crate::graph::infer::{FileTypes, TypeIndex};
+use crate::graph::stats::{millis, PhaseTimings};
+use crate::graph::{
+    paths, schema, CallAnalyzer, FileNode, ItemType, KnowledgeGraph, Relationship, Visibility,
+};
+use crate::parser::{RustParser, SourceText};
+use crate::source::{FsSource, SourceProvider};
+use crate::utils::cache;
+
+// Name under which glob imports are fingerprinted
+const GLOB: &str = "*";
+
+/// Analysis of one file, kept in its cache entry.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FileAnalysis {
+    /// [`cache::content_hash`] of the analyzed text.
+    pub hash: u64,
+    /// Hash of the file's item ids, which can change without an edit (`#2` suffixes).
+    pub ids: u64,
+    pub types: FileTypes,
+    /// `mod name;` declarations with their visibility.
+    pub modules: Vec<(Arc<str>, Visibility)>,
+    /// Fingerprint of what lookups by each name can find in this file.
+    pub symbols: BTreeMap<String, u64>,
+    /// Identifiers written in the file (outside comments and literals), sorted.
+    pub mentions: Vec<String>,
+    /// Outgoing `Uses` edges.
+    pub uses: Vec<Relationship>,
+    /// Outgoing `Calls` edges.
+    pub calls: Vec<Relationship>,
+}
+
+impl FileAnalysis {
+    // Everything but the edges, which need the whole graph.
+    fn collect(parser: &RustParser, path: &Path, file: &FileNode, source: &SourceText) -> Self {
+        let types = FileTypes::collect(file, source.masked());
+        Self {
+            hash: cache::content_hash(source.raw().as_bytes()),
+            ids: ids_hash(file),
+            modules: parser.module_declarations(source),
+            symbols: symbols(path, file, &types),
+            mentions: mentions(source.masked()),
+            types,
+            uses: Vec::new(),
+            calls: Vec::new(),
+        }
+    }
+}
+
+/// Reverse dependency index: the files that mention each name.
+pub(crate) struct DepIndex<'a> {
+    by_name: HashMap<&'a str, Vec<&'a Path>>,
+    files: Vec<&'a Path>,
+}
+
+impl<'a> DepIndex<'a> {
+    pub(crate) fn build(mentions: impl IntoIterator<Item = (&'a Path, &'a [String])>) -> Self {
+        let mut by_name: HashMap<&str, Vec<&Path>> = HashMap::new();
+        let mut files = Vec::new();
+        for (path, names) in mentions {
+            files.push(path);
+            for name in names {
+                by_name.entry(name.as_str()).or_default().push(path);
+            }
+        }
+        Self { by_name, files }
+    }
+
+    /// Files mentioning any of `names`.
+    pub(crate) fn dependents(&self, names: &HashSet<String>) -> HashSet<&'a Path> {
+        if names.contains(GLOB) {
+            return self.files.iter().copied().collect();
+        }
+        names.iter().filter_map(|n| self.by_name.get(n.as_str())).flatten().copied().collect()
+    }
+}
+
+/// Source text of the files in one build, each read at most once from its provider.
+///
+/// The parse phase stores what it read; files it reused from the cache without reading are
+/// recorded with the hash of the content they were parsed from. Analysis reads those on
+/// demand and fails with [`KnowledgeGraphError::SourceChanged`] when the content no longer
+/// matches its parse, so edges never come from other text than items.
+pub(crate) struct Sources<'a> {
+    provider: &'a dyn SourceProvider,
+    texts: HashMap<PathBuf, SourceText>,
+    unread: HashMap<PathBuf, u64>,
+}
+
+impl Default for Sources<'_> {
+    fn default() -> Self {
+        Self::new(&FsSource)
+    }
+}
+
+impl<'a> Sources<'a> {
+    pub(crate) fn new(provider: &'a dyn SourceProvider) -> Self {
+        Self { provider, texts: HashMap::new(), unread: HashMap::new() }
+    }
+
+    pub(crate) fn insert(&mut self, path: PathBuf, text: SourceText) {
+        self.unread.remove(&path);
+        self.texts.insert(path, text);
+    }
+
+    /// Note a file parsed from content with `hash` that was not read in this build.
+    pub(crate) fn defer(&mut self, path: PathBuf, hash: u64) {
+        self.unread.insert(path, hash);
+    }
+
+    pub(crate) fn extend(&mut self, other: Sources<'_>) {
+        self.unread.extend(other.unread);
+        self.texts.extend(other.texts);
+    }
+
+    fn get(&self, path: &Path) -> Option<&SourceText> {
+        self.texts.get(path)
+    }
+
+    // Read the given files that are not loaded yet, in parallel.
+    fn load(&mut self, paths: &[&Path]) -> Result<(), KnowledgeGraphError> {
+        let missing: Vec<&Path> =
+            paths.iter().copied().filter(|p| !self.texts.contains_key(*p)).collect();
+        let (unread, provider) = (&self.unread, self.provider);
+        let read: Vec<(PathBuf, SourceText)> = missing
+            .into_par_iter()
+            .map(|path| {
+                let raw = provider.read(path)?;
+                if unread.get(path).is_some_and(|h| *h != cache::content_hash(raw.as_bytes())) {
+                    return Err(KnowledgeGraphError::SourceChanged { file: path.to_path_buf() });
+                }
+                Ok((path.to_path_buf(), SourceText::new(raw)))
+            })
+            .collect::<Result<_, _>>()?;
+        for (path, text) in read {
+            self.insert(path, text);
+        }
+        Ok(())
+    }
+}
+
+impl KnowledgeGraph {
+    // Analyze relationships beyond containment, reusing the analyses in `cache` that are still
+    // valid and storing fresh ones back into it. `removed` are the entries of deleted files;
+    // file contents come from `sources`. Returns the files whose edges were recomputed.
+    pub(crate) fn analyze_incremental(
+        &mut self,
+        cache: &mut cache::Cache,
+        removed: &[cache::CacheEntry],
+        sources: &mut Sources<'_>,
+    ) -> Result<HashSet<PathBuf>, KnowledgeGraphError> {
+        let mut timings = PhaseTimings::default();
+        let started = Instant::now();
+        self.analyze_module_hierarchy();
+        timings.module_hierarchy_ms = millis(started.elapsed());
+
+        let started = Instant::now();
+        // Keep analyses made from the current content and ids with the same features;
+        // remember the symbols of the rest
+        let same_features = cache.features == self.metadata.options.features;
+        cache.features.clone_from(&self.metadata.options.features);
+        let mut analyses: HashMap<PathBuf, FileAnalysis> = HashMap::new();
+        let mut outdated: HashMap<PathBuf, Option<BTreeMap<String, u64>>> = HashMap::new();
+        for (path, file) in &self.files {
+            let entry = cache.entries.get_mut(path);
+            let hash = entry.as_ref().map_or(0, |e| e.meta.hash);
+            match entry.and_then(|e| e.analysis.take()) {
+                Some(a)
+                    if same_features
+                        && a.hash != 0
+                        && a.hash == hash
+                        && a.ids == ids_hash(file) =>
+                {
+                    analyses.insert(path.clone(), a);
+                }
+                previous => {
+                    outdated.insert(path.clone(), previous.map(|a| a.symbols));
+                }
+            }
+        }
+
+        let parser = RustParser::new();
+        let files = &self.files;
+        let outdated_paths: Vec<&Path> = outdated.keys().map(PathBuf::as_path).collect();
+        sources.load(&outdated_paths)?;
+        let loaded = &*sources;
+        let fresh: Vec<(PathBuf, FileAnalysis)> = outdated_paths
+            .into_par_iter()
+            .filter_map(|path| {
+                let source = loaded.get(path)?;
+                let analysis = FileAnalysis::collect(&parser, path, &files[path], source);
+                Some((path.to_path_buf(), analysis))
+            })
+            .collect();
+
+        // Names whose fingerprint changed in edited, new or removed files
+        let mut changed: HashSet<String> = HashSet::new();
+        for (path, analysis) in &fresh {
+            let before = outdated.get(path).and_then(Option::as_ref);
+            changed.extend(changed_names(before, &analysis.symbols));
+        }
+        for entry in removed {
+            if let Some(a) = &entry.analysis {
+                changed.extend(a.symbols.keys().cloned());
+            }
+        }
+
+        let mut affected: HashSet<PathBuf> = HashSet::with_capacity(fresh.len());
+        for (path, analysis) in fresh {
+            affected.insert(path.clone());
+            analyses.insert(path, analysis);
+        }
+        if !changed.is_empty() {
+            let deps =
+                DepIndex::build(analyses.iter().map(|(p, a)| (p.as_path(), a.mentions.as_slice())));
+            let reached: Vec<PathBuf> = deps
+                .dependents(&changed)
+                .into_iter()
+                .filter(|p| !affected.contains(*p))
+                .map(Path::to_path_buf)
+                .collect();
+            sources.load(&reached.iter().map(PathBuf::as_path).collect::<Vec<_>>())?;
+            affected.extend(reached);
+        }
+
+        let declared: HashMap<&Path, &[(Arc<str>, Visibility)]> =
+            analyses.iter().map(|(p, a)| (p.as_path(), a.modules.as_slice())).collect();
+        self.analyze_module_visibility(&declared);
+        timings.dependency_tracking_ms = millis(started.elapsed());
+
+        let started = Instant::now();
+        let types = TypeIndex::from_files(self, |p| analyses.get(p).map(|a| &a.types));
+        let mut call_time = started.elapsed();
+        {
+            let started = Instant::now();
+            let calls = CallAnalyzer::new(self, &types);
+            call_time += started.elapsed();
+
+            let started = Instant::now();
+            let uses: Vec<(&PathBuf, Vec<Relationship>)> = affected
+                .par_iter()
+                .filter_map(|path| {
+                    Some((path, Self::import_uses(&calls.res, path, self.files.get(path)?)))
+                })
+                .collect();
+            for (path, uses) in uses {
+                if let Some(a) = analyses.get_mut(path) {
+                    a.uses = uses;
+                }
+            }
+            timings.import_analysis_ms = millis(started.elapsed());
+
+            let started = Instant::now();
+            let edges: Vec<(&PathBuf, Vec<Relationship>)> = affected
+                .par_iter()
+                .filter_map(|path| {
+                    let file = self.files.get(path)?;
+                    let s = sources.get(path)?;
+                    Some((path, calls.file_edges(path, file, s.masked(), s.raw())))
+                })
+                .collect();
+            for (path, edges) in edges {
+                if let Some(a) = analyses.get_mut(path) {
+                    a.calls = edges;
+                }
+            }
+            call_time += started.elapsed();
+        }
+
+        // Uses edges of every file, then call edges, in path order
+        let mut order: Vec<&PathBuf> = analyses.keys().collect();
+        order.sort();
+        for path in &order {
+            self.relationships.extend(analyses[*path].uses.iter().cloned());
+        }
+        for path in &order {
+            self.relationships.extend(analyses[*path].calls.iter().cloned());
+        }
+        let started = Instant::now();
+        if self.metadata.options.has_feature(schema::TRAIT_DISPATCH) {
+            self.analyze_trait_dispatch(&types);
+        }
+        timings.call_analysis_ms = millis(call_time + started.elapsed());
+        self.metadata.stats.get_or_insert_with(Default::default).timings.add(&timings);
+
+        for (path, analysis) in analyses {
+            if let Some(entry) = cache.entries.get_mut(&path) {
+                entry.analysis = Some(analysis);
+            }
+        }
+        self.invalidate_index();
+        Ok(affected)
+    }
+}
+
+fn ids_hash(file: &FileNode) -> u64 {
+    let ids: Vec<&str> = file.items.iter().map(|it| it.id.0.as_str()).collect();
+    cache::content_hash(ids.join("\n").as_bytes())
+}
+
+// Fingerprints of what lookups by name can find in `file`: its items, signatures and struct
+// fields under their names, and its imports under the bound name and the module's names.
+fn symbols(path: &Path, file: &FileNode, types: &FileTypes) -> BTreeMap<String, u64> {
+    let mut facts: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let mut add = |name: &str, fact: String| facts.entry(name.to_string()).or_default().push(fact);
+    let modules = module_names(path, file);
+    for it in &file.items {
+        let fact =
+            format!("{} {} {:?}", it.id.0, paths::item_keyword(&it.item_type), it.visibility);
+        match &it.item_type {
+            ItemType::Impl { trait_name, type_name } => {
+                if let Some(t) = trait_name {
+                    add(t, fact.clone());
+                }
+                add(type_name, fact);
+            }
+            _ => add(&it.name, fact),
+        }
+    }
+    for imp in &file.imports {
+        let stmt = imp.statement();
+        let bound = match imp.alias.as_deref() {
+            Some(alias) => alias,
+            None => imp.path.rsplit("::").next().unwrap_or(&imp.path),
+        };
+        add(bound, stmt.clone());
+        for m in &modules {
+            add(m, stmt.clone());
+        }
+    }
+    // A file appearing or disappearing changes what its module path resolves to
+    if let Some(it) = file.items.first() {
+        for m in &modules {
+            add(m, it.id.0.clone());
+        }
+    }
+    for f in &types.fns {
+        add(&f.name, format!("fn {} {:?} {:?} {:?}", f.id.0, f.owner, f.owner_trait, f.ret));
+    }
+    for (name, fields) in &types.fields {
+        let sorted: BTreeMap<&String, &String> = fields.iter().collect();
+        add(name, format!("fields {sorted:?}"));
+    }
+    facts
+        .into_iter()
+        .map(|(name, mut v)| {
+            v.sort();
+            (name, cache::content_hash(v.join("\n").as_bytes()))
+        })
+        .collect()
+}
+
+// Names a path to `file`'s module can be written with: its path components, the crate
+// segment of its ids, `crate` and `super`.
+fn module_names(path: &Path, file: &FileNode) -> BTreeSet<String> {
+    let mut out: BTreeSet<String> = ["crate", "super"].map(str::to_string).into();
+    out.extend(path.with_extension("").components().filter_map(|c| match c {
+        std::path::Component::Normal(os) => os.to_str().map(|s| s.replace('-', "_")),
+        _ => None,
+    }));
+    out.extend(
+        file.items
+            .iter()
+            .skip(1)
+            .filter_map(|it| it.id.0.split_once(':')?.1.split("::").next())
+            .map(str::to_string),
+    );
+    out
+}
+
+fn changed_names(
+    before: Option<&BTreeMap<String, u64>>,
+    after: &BTreeMap<String, u64>,
+) -> Vec<String> {
+    let Some(before) = before else { return after.keys().cloned().collect() };
+    let keys: BTreeSet<&String> = before.keys().chain(after.keys()).collect();
+    keys.into_iter().filter(|k| before.get(*k) != after.get(*k)).cloned().collect()
+}
+
+fn mentions(masked: &str) -> Vec<String> {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    let re = RE.get_or_init(|| Regex::new(r"\b[A-Za-z_][A-Za-z0-9_]*\b").unwrap());
+    let names: BTreeSet<&str> = re.find_iter(masked).map(|m| m.as_str()).collect();
+    names.into_iter().map(str::to_string).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::ParsedRoot;
+    use crate::utils::cache::CacheMode;
+    use crate::utils::file_walker::DiscoveryOptions;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn only_files_mentioning_changed_names_are_reanalyzed() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("src");
+        fs::create_dir_all(&src).unwrap();
+        fs::write(src.join("lib.rs"), "mod a;\nmod b;\nmod c;\n").unwrap();
+        fs::write(src.join("a.rs"), "pub fn helper() {}\n").unwrap();
+        fs::write(src.join("b.rs"), "use crate::a::helper;\npub fn run() { helper(); }\n").unwrap();
+        fs::write(src.join("c.rs"), "pub fn other() {}\n").unwrap();
+        let root = dir.path();
+        let store = cache::CacheStore::new(root, &cache::CacheLocation::Project);
+        let build = || {
+            let parsed = KnowledgeGraph::parse_directory(
+                &FsSource,
+                root,
+                CacheMode::Use,
+                &DiscoveryOptions::default(),
+                &store,
+                None,
+            )
+            .unwrap();
+            let ParsedRoot { mut graph, cache: mut state, removed, mut sources } = parsed;
+            let analyzed = graph.analyze_incremental(&mut state, &removed, &mut sources).unwrap();
+            store.save(&state).unwrap();
+            let mut names: Vec<String> = analyzed
+                .iter()
+                .filter_map(|p| p.file_name()?.to_str().map(str::to_string))
+                .collect();
+            names.sort();
+            names
+        };
+        assert_eq!(build(), ["a.rs", "b.rs", "c.rs", "lib.rs"]);
+        assert!(build().is_empty());
+
+        // A body edit changes no fingerprint
+        fs::write(src.join("c.rs"), "pub fn other() { let _x = 1; }\n").unwrap();
+        assert_eq!(build(), ["c.rs"]);
+        // A new signature reaches the files mentioning `helper`
+        fs::write(src.join("a.rs"), "pub fn helper() -> u8 { 0 }\n").unwrap();
+        assert_eq!(build(), ["a.rs", "b.rs"]);
+    }
+
+    #[test]
+    fn analysis_uses_the_text_read_while_parsing() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("src");
+        fs::create_dir_all(&src).unwrap();
+        fs::write(src.join("lib.rs"), "mod a;\nmod b;\n").unwrap();
+        fs::write(src.join("a.rs"), "pub fn helper() {}\n").unwrap();
+        fs::write(src.join("b.rs"), "use crate::a::helper;\npub fn run() { helper(); }\n").unwrap();
+        let root = dir.path();
+        let store = cache::CacheStore::new(root, &cache::CacheLocation::Project);
+        let parsed = KnowledgeGraph::parse_directory(
+            &FsSource,
+            root,
+            CacheMode::Ignore,
+            &DiscoveryOptions::default(),
+            &store,
+            None,
+        )
+        .unwrap();
+        let ParsedRoot { mut graph, cache: mut state, removed, mut sources } = parsed;
+
+        // Edits after parsing cannot leak into the edges
+        fs::write(src.join("b.rs"), "pub fn run() {}\n").unwrap();
+        graph.analyze_incremental(&mut state, &removed, &mut sources).unwrap();
+        assert!(graph.relationships.iter().any(|r| r.to_item.0 == "fn:crate::a::helper"
+            && matches!(r.relationship_type, crate::graph::RelationshipType::Calls { .. })));
+    }
+
+    #[test]
+    fn unread_sources_must_match_their_parse() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("a.rs");
+        fs::write(&file, "pub fn a() {}\n").unwrap();
+        let mut sources = Sources::default();
+        sources.defer(file.clone(), cache::content_hash(b"pub fn a() {}\n"));
+        sources.load(&[file.as_path()]).unwrap();
+        assert_eq!(sources.get(&file).map(SourceText::raw), Some("pub fn a() {}\n"));
+
+        let mut sources = Sources::default();
+        sources.defer(file.clone(), cache::content_hash(b"pub fn b() {}\n"));
+        let err = sources.load(&[file.as_path()]).unwrap_err();
+        assert!(matches!(err, KnowledgeGraphError::SourceChanged { file: f } if f == file));
+
+        let gone = dir.path().join("gone.rs");
+        let err = Sources::default().load(&[gone.as_path()]).unwrap_err();
+        assert!(matches!(err, KnowledgeGraphError::Read { file: f, .. } if f == gone));
+    }
+}
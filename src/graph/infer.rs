@@ -0,0 +1,1035 @@
+//! Local type inference used to resolve method calls.
+//!
+//! The regex parser only knows item names and spans. This module recovers just enough type
+//! information from (masked) source text to answer "which impl does `receiver.method(..)` land
+//! in?": function parameter types, `self` inside impls, `let x: T`, `let x = T::new()`, struct
+//! literals, known return types and struct field types.
+//!
+//! Types and traits are keyed by the path of their definition (`crate::a::Shape`), resolved
+//! from the name as written through the file's own definitions and imports, so two types with
+//! the same name in different modules stay apart. Names the graph does not define (`Vec`,
+//! `Display`) are keyed by the name itself.
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::graph::resolver::Resolver;
+use crate::graph::{Confidence, FileNode, ItemId, ItemType, KnowledgeGraph};
+use crate::parser::base_type_name;
+
+/// Signature facts for one function or method.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FnInfo {
+    pub id: ItemId,
+    pub name: Arc<str>,
+    /// Self type of the enclosing `impl` block, if any.
+    pub owner: Option<Arc<str>>,
+    /// Trait of the enclosing `impl Trait for T` or `trait Trait` block, if any.
+    pub owner_trait: Option<Arc<str>>,
+    /// `(binding, base type)` for simple `name: Type` parameters (`Self` already substituted).
+    pub params: Vec<(String, String)>,
+    /// Base type of the return type (`Self` already substituted).
+    pub ret: Option<String>,
+    /// Byte range of the body braces `{ .. }` in the masked source.
+    pub body: Option<(usize, usize)>,
+}
+
+/// A method call whose target has been looked up.
+#[derive(Debug, Clone)]
+pub struct MethodCall {
+    pub caller: ItemId,
+    pub targets: Vec<ItemId>,
+    pub confidence: Confidence,
+    /// Receiver expression as written (e.g. `self.graph`).
+    pub receiver: String,
+    pub method: String,
+    /// Byte offset of the method name in the masked source.
+    pub offset: usize,
+}
+
+/// Crate-wide type facts: methods per type, struct fields and function signatures.
+///
+/// Every type and trait in the index is a key from [`Self::type_key`].
+#[derive(Debug, Default)]
+pub struct TypeIndex {
+    // type and trait names as written per file -> keys
+    keys: TypeKeys,
+    // (self type, method name) -> method ids
+    methods: HashMap<(Arc<str>, Arc<str>), Vec<ItemId>>,
+    // method name -> every method with that name (name-only fallback)
+    methods_by_name: HashMap<Arc<str>, Vec<ItemId>>,
+    // free function name -> ids
+    free_fns: HashMap<Arc<str>, Vec<ItemId>>,
+    // struct -> field -> base type
+    fields: HashMap<Arc<str>, HashMap<String, String>>,
+    // per-file function facts
+    fns: HashMap<PathBuf, Vec<FnInfo>>,
+    // per-file function bodies sorted by start, for enclosing-function lookups
+    bodies: HashMap<PathBuf, BodySpans>,
+    // self types of every impl block with at least one method
+    method_types: HashSet<Arc<str>>,
+    // fn id -> base return type
+    returns: HashMap<ItemId, String>,
+    // ids of functions defined inside impl or trait blocks
+    method_ids: HashSet<ItemId>,
+    // (trait, method) -> declarations inside the trait block
+    trait_methods: HashMap<(Arc<str>, Arc<str>), Vec<ItemId>>,
+    // (trait, method) -> implementations inside `impl Trait for T` blocks
+    trait_impls: HashMap<(Arc<str>, Arc<str>), Vec<ItemId>>,
+    // self type -> traits it implements
+    impl_traits: HashMap<Arc<str>, Vec<Arc<str>>>,
+}
+
+impl TypeIndex {
+    /// Build the index from a graph and the masked source of each file.
+    #[must_use]
+    pub fn build(graph: &KnowledgeGraph, masked: &HashMap<PathBuf, String>) -> Self {
+        let facts: HashMap<PathBuf, FileTypes> = graph
+            .files
+            .iter()
+            .filter_map(|(path, file)| {
+                masked.get(path).map(|src| (path.clone(), FileTypes::collect(file, src)))
+            })
+            .collect();
+        Self::from_files(graph, |path| facts.get(path))
+    }
+
+    /// Build the index from per-file facts collected earlier, e.g. kept in the build cache.
+    #[must_use]
+    pub fn from_files<'a>(
+        graph: &KnowledgeGraph,
+        facts: impl Fn(&Path) -> Option<&'a FileTypes>,
+    ) -> Self {
+        let mut idx = TypeIndex { keys: TypeKeys::build(graph), ..TypeIndex::default() };
+        for (path, file) in &graph.files {
+            for it in &file.items {
+                if let ItemType::Impl { trait_name: Some(tr), type_name } = &it.item_type {
+                    let tr = idx.keys.key(path, tr);
+                    idx.impl_traits.entry(idx.keys.key(path, type_name)).or_default().push(tr);
+                }
+            }
+            let Some(types) = facts(path) else { continue };
+            for (name, fields) in &types.fields {
+                let fields =
+                    fields.iter().map(|(f, t)| (f.clone(), idx.keys.key(path, t).to_string()));
+                idx.fields.entry(idx.keys.key(path, name)).or_default().extend(fields);
+            }
+            let fns: Vec<FnInfo> = types.fns.iter().map(|f| idx.keys.resolve_fn(path, f)).collect();
+            for info in &fns {
+                if let Some(ret) = &info.ret {
+                    idx.returns.insert(info.id.clone(), ret.clone());
+                }
+                if info.owner.is_some() || info.owner_trait.is_some() {
+                    idx.method_ids.insert(info.id.clone());
+                }
+                if let Some(tr) = &info.owner_trait {
+                    let key = (tr.clone(), info.name.clone());
+                    let map = if info.owner.is_some() {
+                        &mut idx.trait_impls
+                    } else {
+                        &mut idx.trait_methods
+                    };
+                    map.entry(key).or_default().push(info.id.clone());
+                }
+                match &info.owner {
+                    Some(ty) => {
+                        idx.method_types.insert(ty.clone());
+                        idx.methods
+                            .entry((ty.clone(), info.name.clone()))
+                            .or_default()
+                            .push(info.id.clone());
+                        idx.methods_by_name
+                            .entry(info.name.clone())
+                            .or_default()
+                            .push(info.id.clone());
+                    }
+                    None if info.owner_trait.is_some() => {
+                        // Trait method declarations are dispatch targets, not receivers' methods
+                        idx.methods_by_name
+                            .entry(info.name.clone())
+                            .or_default()
+                            .push(info.id.clone());
+                    }
+                    None => {
+                        idx.free_fns.entry(info.name.clone()).or_default().push(info.id.clone());
+                    }
+                }
+            }
+            idx.bodies.insert(path.clone(), BodySpans::new(&fns));
+            idx.fns.insert(path.clone(), fns);
+        }
+        idx
+    }
+
+    /// Key of the type or trait `name` as written in `path`: the path of its definition
+    /// (`crate::a::Shape`), or `name` itself when the graph does not define it.
+    #[must_use]
+    pub fn type_key(&self, path: &Path, name: &str) -> Arc<str> {
+        self.keys.key(path, name)
+    }
+
+    /// Function facts for a file, in source order, with type names replaced by their keys.
+    #[must_use]
+    pub fn fns_in(&self, path: &Path) -> &[FnInfo] {
+        self.fns.get(path).map_or(&[], Vec::as_slice)
+    }
+
+    /// Methods named `method` defined in impls of the type keyed `ty` (inherent or trait impls).
+    #[must_use]
+    pub fn methods_of(&self, ty: &str, method: &str) -> &[ItemId] {
+        self.methods.get(&(Arc::from(ty), Arc::from(method))).map_or(&[], Vec::as_slice)
+    }
+
+    /// Declarations of `method` inside the `trait Trait { .. }` block keyed `trait_name`.
+    #[must_use]
+    pub fn trait_methods_of(&self, trait_name: &str, method: &str) -> &[ItemId] {
+        self.trait_methods
+            .get(&(Arc::from(trait_name), Arc::from(method)))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// Every `(trait key, method, implementations)` group found in `impl Trait for T` blocks.
+    pub fn trait_impl_methods(&self) -> impl Iterator<Item = (&str, &str, &[ItemId])> {
+        self.trait_impls.iter().map(|((t, m), ids)| (t.as_ref(), m.as_ref(), ids.as_slice()))
+    }
+
+    // Dispatch targets of `ty.method(..)`: methods of the type itself, the declaration when
+    // `ty` is a trait (`dyn Trait`/`impl Trait`), else provided methods of traits `ty` implements.
+    fn dispatch_targets(&self, ty: &str, method: &str) -> Vec<ItemId> {
+        let own = self.methods_of(ty, method);
+        if !own.is_empty() {
+            return own.to_vec();
+        }
+        let decls = self.trait_methods_of(ty, method);
+        if !decls.is_empty() {
+            return decls.to_vec();
+        }
+        self.impl_traits
+            .get(ty)
+            .into_iter()
+            .flatten()
+            .flat_map(|tr| self.trait_methods_of(tr, method).iter().cloned())
+            .collect()
+    }
+
+    /// Whether the type keyed `ty` has any methods or fields recorded in the index.
+    #[must_use]
+    pub fn is_known_type(&self, ty: &str) -> bool {
+        self.fields.contains_key(ty) || self.method_types.contains(ty)
+    }
+
+    /// Free functions (outside impls and traits) with the given name.
+    #[must_use]
+    pub fn free_fns_named(&self, name: &str) -> &[ItemId] {
+        self.free_fns.get(name).map_or(&[], Vec::as_slice)
+    }
+
+    /// Whether the function is defined inside an impl or trait block.
+    #[must_use]
+    pub fn is_method(&self, id: &ItemId) -> bool {
+        self.method_ids.contains(id)
+    }
+
+    /// Innermost function whose body contains the byte `offset` of `path`.
+    #[must_use]
+    pub fn enclosing_fn(&self, path: &Path, offset: usize) -> Option<&FnInfo> {
+        let k = self.bodies.get(path)?.innermost(offset)?;
+        self.fns_in(path).get(k)
+    }
+
+    /// Resolve every `receiver.method(..)` call inside the function bodies of `path`.
+    ///
+    /// Calls whose receiver type cannot be inferred fall back to every method with the same
+    /// name, marked `Confidence::NameOnly`.
+    #[must_use]
+    pub fn resolve_method_calls(&self, path: &Path, masked: &str) -> Vec<MethodCall> {
+        let mut out = Vec::new();
+        let Some(spans) = self.bodies.get(path) else { return out };
+        for (k, f) in self.fns_in(path).iter().enumerate() {
+            let Some((open, close)) = f.body else { continue };
+            let body = &masked[open..=close];
+            let env = self.bindings(path, f, body);
+            for cap in method_call_re().captures_iter(body) {
+                let Some(method) = cap.name("method") else { continue };
+                let recv = cap.name("recv");
+                // Skip calls nested in other function bodies (closures are fine, fns are not)
+                let abs = open + method.start();
+                if spans.innermost(abs) != Some(k) {
+                    continue;
+                }
+                // `a.b().c(` / `x::y.z(`: receiver is an expression we cannot type
+                let typed = recv.and_then(|r| {
+                    let before = body[..r.start()].trim_end();
+                    if before.ends_with('.') || before.ends_with(':') {
+                        None
+                    } else {
+                        self.receiver_type(r.as_str(), r.start(), f, &env)
+                    }
+                });
+                let (targets, confidence) = match typed {
+                    Some((ty, conf)) => {
+                        let found = self.dispatch_targets(&ty, method.as_str());
+                        if found.is_empty() {
+                            // Known type without such method (e.g. std trait method): no edge
+                            continue;
+                        }
+                        let conf =
+                            if found.len() > 1 { conf.min(Confidence::Inferred) } else { conf };
+                        (found, conf)
+                    }
+                    None => match self.methods_by_name.get(method.as_str()) {
+                        Some(ids) => (ids.clone(), Confidence::NameOnly),
+                        None => continue,
+                    },
+                };
+                out.push(MethodCall {
+                    caller: f.id.clone(),
+                    targets,
+                    confidence,
+                    receiver: recv.map(|r| compact(r.as_str())).unwrap_or_default(),
+                    method: method.as_str().to_string(),
+                    offset: abs,
+                });
+            }
+        }
+        out
+    }
+
+    // Type of a receiver chain `x.field.other` at `pos` within the body of `f`.
+    fn receiver_type(
+        &self,
+        recv: &str,
+        pos: usize,
+        f: &FnInfo,
+        env: &[Binding],
+    ) -> Option<(String, Confidence)> {
+        let mut segs = recv.split('.').map(str::trim);
+        let head = segs.next()?;
+        let (mut ty, conf) = if head == "self" {
+            (f.owner.as_deref()?.to_string(), Confidence::Exact)
+        } else {
+            let b = env.iter().rev().find(|b| b.name == head && b.pos <= pos)?;
+            (b.ty.clone(), b.confidence)
+        };
+        for field in segs {
+            ty = self.fields.get(ty.as_str())?.get(field)?.clone();
+        }
+        Some((ty, conf))
+    }
+
+    // Key of a type written in `path`, with `Self` standing for the impl's own type key.
+    fn resolve(&self, path: &Path, ty: &str, owner: Option<&str>) -> String {
+        match (ty, owner) {
+            ("Self", Some(o)) => o.to_string(),
+            _ => self.type_key(path, ty).to_string(),
+        }
+    }
+
+    // Collect parameter and `let` bindings for a function body, in source order.
+    fn bindings(&self, path: &Path, f: &FnInfo, body: &str) -> Vec<Binding> {
+        let mut env: Vec<Binding> = f
+            .params
+            .iter()
+            .map(|(n, t)| Binding {
+                name: n.clone(),
+                ty: t.clone(),
+                confidence: Confidence::Exact,
+                pos: 0,
+            })
+            .collect();
+        for cap in let_re().captures_iter(body) {
+            let (Some(name), Some(m0)) = (cap.name("name"), cap.get(0)) else { continue };
+            let annotated = cap.name("ty").and_then(|t| base_type_name(t.as_str()));
+            let typed = match annotated {
+                Some(t) => Some((self.resolve(path, t, f.owner.as_deref()), Confidence::Exact)),
+                None => self.infer_init(path, &body[m0.end()..], f, &env, m0.end()),
+            };
+            if let Some((ty, confidence)) = typed {
+                env.push(Binding {
+                    name: name.as_str().to_string(),
+                    ty,
+                    confidence,
+                    pos: m0.end(),
+                });
+            }
+        }
+        env
+    }
+
+    // Infer the type of a `let` initializer from its leading tokens.
+    fn infer_init(
+        &self,
+        path: &Path,
+        init: &str,
+        f: &FnInfo,
+        env: &[Binding],
+        pos: usize,
+    ) -> Option<(String, Confidence)> {
+        let owner = f.owner.as_deref();
+        let cap = init_re().captures(init)?;
+        let segs: Vec<&str> = cap.name("path")?.as_str().split("::").map(str::trim).collect();
+        let last = *segs.last()?;
+        match cap.name("tail").map_or("", |m| m.as_str()) {
+            // Struct literal: `Foo { .. }`, `Self { .. }`, `Enum::Variant { .. }`
+            "{" => {
+                let ty = if segs.len() >= 2
+                    && !self.fields.contains_key(self.resolve(path, last, owner).as_str())
+                {
+                    segs[segs.len() - 2]
+                } else {
+                    last
+                };
+                is_type_like(ty).then(|| (self.resolve(path, ty, owner), Confidence::Exact))
+            }
+            // Call: `Type::ctor(..)` or `free_fn(..)`
+            "(" => {
+                if segs.len() >= 2 {
+                    let seg = segs[segs.len() - 2];
+                    if !is_type_like(seg) {
+                        return None;
+                    }
+                    let ty = self.resolve(path, seg, owner);
+                    let ret = self
+                        .methods_of(&ty, last)
+                        .iter()
+                        .find_map(|id| self.returns.get(id))
+                        .map(|r| if r == "Self" { ty.clone() } else { r.clone() });
+                    match ret {
+                        Some(r) => Some((r, Confidence::Inferred)),
+                        None if last == "new" || last == "default" || last.starts_with("with_") => {
+                            Some((ty, Confidence::Inferred))
+                        }
+                        None => None,
+                    }
+                } else {
+                    let ids = self.free_fns_named(last);
+                    let ret = ids.iter().find_map(|id| self.returns.get(id))?;
+                    (ids.len() == 1).then(|| (ret.clone(), Confidence::Inferred))
+                }
+            }
+            // Plain alias of another binding: `let y = x;` / `let y = &mut x;`
+            // or a unit struct: `let y = Unit;`
+            _ if segs.len() == 1 => {
+                env.iter().rev().find(|b| b.name == last && b.pos <= pos).map_or_else(
+                    || {
+                        let ty = self.resolve(path, last, owner);
+                        (is_type_like(last) && self.is_known_type(&ty))
+                            .then_some((ty, Confidence::Exact))
+                    },
+                    |b| Some((b.ty.clone(), b.confidence)),
+                )
+            }
+            _ => None,
+        }
+    }
+}
+
+// Keys for type and trait names as written in each file. A name defined once in the graph
+// always maps to that definition; a name defined in several modules maps, per file, to the
+// definition the file declares or imports (explicitly or through a glob).
+#[derive(Debug, Default)]
+struct TypeKeys {
+    // name -> key, for names with a single definition
+    unique: HashMap<Arc<str>, Arc<str>>,
+    // file -> name as bound in that file -> key, for ambiguous names and renaming imports
+    scoped: HashMap<PathBuf, HashMap<Arc<str>, Arc<str>>>,
+}
+
+impl TypeKeys {
+    fn build(graph: &KnowledgeGraph) -> Self {
+        // definition id -> (name, key)
+        let mut defs: HashMap<&ItemId, (Arc<str>, Arc<str>)> = HashMap::new();
+        let mut by_name: HashMap<&str, usize> = HashMap::new();
+        for it in graph.files.values().flat_map(|f| &f.items) {
+            if matches!(
+                it.item_type,
+                ItemType::Struct { .. }
+                    | ItemType::Enum { .. }
+                    | ItemType::Trait { .. }
+                    | ItemType::Type
+            ) {
+                let key: Arc<str> =
+                    Arc::from(it.id.0.split_once(':').map_or(&*it.id.0, |(_, p)| p));
+                defs.insert(&it.id, (it.name.clone(), key));
+                *by_name.entry(&it.name).or_default() += 1;
+            }
+        }
+        let mut keys = TypeKeys::default();
+        for (name, key) in defs.values() {
+            if by_name[name.as_ref()] == 1 {
+                keys.unique.insert(name.clone(), key.clone());
+            }
+        }
+        let ambiguous = |name: &str| by_name.get(name).is_some_and(|&n| n > 1);
+        let res = Resolver::new(graph);
+        for (path, file) in &graph.files {
+            let mut scope: HashMap<Arc<str>, Arc<str>> = HashMap::new();
+            // Globs first: explicit imports and the file's own definitions take precedence
+            for module in file.imports.iter().filter_map(|imp| imp.path.strip_suffix("::*")) {
+                for m in res.resolve_import(path, module) {
+                    let Some(items) = res
+                        .is_file_level_module(&m)
+                        .then(|| res.item_path(&m).and_then(|p| graph.files.get(p)))
+                        .flatten()
+                    else {
+                        continue;
+                    };
+                    for (name, key) in items.items.iter().filter_map(|it| defs.get(&it.id)) {
+                        if ambiguous(name) {
+                            scope.insert(name.clone(), key.clone());
+                        }
+                    }
+                }
+            }
+            for imp in file.imports.iter().filter(|imp| !imp.path.ends_with("::*")) {
+                let last = imp.path.rsplit("::").next().unwrap_or(&imp.path);
+                let bound = match imp.alias.as_deref() {
+                    Some("_") => continue,
+                    Some(alias) => alias,
+                    None => last,
+                };
+                if !by_name.contains_key(last) || (bound == last && !ambiguous(last)) {
+                    continue;
+                }
+                let targets: Vec<&(Arc<str>, Arc<str>)> = res
+                    .resolve_import(path, &imp.path)
+                    .iter()
+                    .filter_map(|id| defs.get(id))
+                    .collect();
+                if let [(_, key)] = targets.as_slice() {
+                    scope.insert(Arc::from(bound), key.clone());
+                }
+            }
+            for (name, key) in file.items.iter().filter_map(|it| defs.get(&it.id)) {
+                if ambiguous(name) {
+                    scope.insert(name.clone(), key.clone());
+                }
+            }
+            if !scope.is_empty() {
+                keys.scoped.insert(path.clone(), scope);
+            }
+        }
+        keys
+    }
+
+    fn key(&self, path: &Path, name: &str) -> Arc<str> {
+        self.scoped
+            .get(path)
+            .and_then(|s| s.get(name))
+            .or_else(|| self.unique.get(name))
+            .cloned()
+            .unwrap_or_else(|| Arc::from(name))
+    }
+
+    // Copy of `f` with its owner, trait, parameter and return types replaced by keys.
+    fn resolve_fn(&self, path: &Path, f: &FnInfo) -> FnInfo {
+        let key = |t: &str| self.key(path, t);
+        FnInfo {
+            owner: f.owner.as_deref().map(key),
+            owner_trait: f.owner_trait.as_deref().map(key),
+            params: f.params.iter().map(|(n, t)| (n.clone(), key(t).to_string())).collect(),
+            ret: f.ret.as_deref().map(|t| key(t).to_string()),
+            ..f.clone()
+        }
+    }
+}
+
+/// Type facts recovered from one file's masked source: its functions' signatures and its
+/// structs' field types. [`TypeIndex`] combines these across files.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileTypes {
+    pub fns: Vec<FnInfo>,
+    /// Struct name -> field -> base type.
+    pub fields: HashMap<Arc<str>, HashMap<String, String>>,
+}
+
+impl FileTypes {
+    /// Collect the facts of `file` from its masked source.
+    #[must_use]
+    pub fn collect(file: &FileNode, masked: &str) -> Self {
+        let starts = line_starts(masked);
+        let mut fields = HashMap::new();
+        collect_fields(file, masked, &starts, &mut fields);
+        Self { fns: collect_fns(file, masked, &starts), fields }
+    }
+}
+
+// Function bodies of one file sorted by their opening brace. Bodies are brace ranges, so they
+// nest properly and each one knows the body directly enclosing it.
+#[derive(Debug, Default)]
+struct BodySpans {
+    // (open, close, index into the file's `FnInfo`s, enclosing span)
+    spans: Vec<(usize, usize, usize, Option<usize>)>,
+}
+
+impl BodySpans {
+    fn new(fns: &[FnInfo]) -> Self {
+        let mut sorted: Vec<(usize, usize, usize)> =
+            fns.iter().enumerate().filter_map(|(k, f)| f.body.map(|(o, c)| (o, c, k))).collect();
+        sorted.sort_unstable();
+        let mut spans: Vec<(usize, usize, usize, Option<usize>)> = Vec::with_capacity(sorted.len());
+        let mut open: Vec<usize> = Vec::new();
+        for (o, c, k) in sorted {
+            while open.last().is_some_and(|&p| spans[p].1 < o) {
+                open.pop();
+            }
+            spans.push((o, c, k, open.last().copied()));
+            open.push(spans.len() - 1);
+        }
+        Self { spans }
+    }
+
+    // Index (into the file's `FnInfo`s) of the innermost body strictly containing `offset`.
+    fn innermost(&self, offset: usize) -> Option<usize> {
+        let mut i = self.spans.partition_point(|s| s.0 < offset).checked_sub(1)?;
+        loop {
+            let (_, close, k, parent) = self.spans[i];
+            if offset < close {
+                return Some(k);
+            }
+            i = parent?;
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Binding {
+    name: String,
+    ty: String,
+    confidence: Confidence,
+    pos: usize,
+}
+
+fn method_call_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(
+            r"(?:(?P<recv>\b[A-Za-z_][A-Za-z0-9_]*(?:\s*\.\s*[A-Za-z_][A-Za-z0-9_]*)*)\s*)?\.\s*(?P<method>[A-Za-z_][A-Za-z0-9_]*)\s*(?:::\s*<[^()]*>\s*)?\(",
+        )
+        .unwrap()
+    })
+}
+
+fn let_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(
+            r"\blet\s+(?:mut\s+)?(?P<name>[A-Za-z_][A-Za-z0-9_]*)\s*(?::\s*(?P<ty>[^=;]+?))?\s*=\s*",
+        )
+        .unwrap()
+    })
+}
+
+fn init_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(
+            r"^&?\s*(?:mut\s+)?(?P<path>[A-Za-z_][A-Za-z0-9_]*(?:\s*::\s*[A-Za-z_][A-Za-z0-9_]*)*)\s*(?:::\s*<[^()]*>\s*)?(?P<tail>[({;]?)",
+        )
+        .unwrap()
+    })
+}
+
+fn fn_name_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"\bfn\s+(?P<name>[A-Za-z_][A-Za-z0-9_]*)").unwrap())
+}
+
+fn is_type_like(s: &str) -> bool {
+    s.chars().next().is_some_and(char::is_uppercase)
+}
+
+fn self_subst(ty: &str, owner: Option<&str>) -> String {
+    match (ty, owner) {
+        ("Self", Some(o)) => o.to_string(),
+        _ => ty.to_string(),
+    }
+}
+
+fn compact(s: &str) -> String {
+    s.chars().filter(|c| !c.is_whitespace()).collect()
+}
+
+/// Byte offset of the start of every line (index 0 is line 1).
+#[must_use]
+pub fn line_starts(s: &str) -> Vec<usize> {
+    let mut v = vec![0];
+    v.extend(s.bytes().enumerate().filter(|&(_, b)| b == b'\n').map(|(i, _)| i + 1));
+    v
+}
+
+// Record `name: Type` fields of brace structs.
+fn collect_fields(
+    file: &FileNode,
+    src: &str,
+    starts: &[usize],
+    out: &mut HashMap<Arc<str>, HashMap<String, String>>,
+) {
+    for it in &file.items {
+        if !matches!(it.item_type, ItemType::Struct { .. }) {
+            continue;
+        }
+        let Some(&from) = starts.get(it.location.line_start.saturating_sub(1)) else { continue };
+        let to = starts.get(it.location.line_end).copied().unwrap_or(src.len());
+        let text = &src[from..to];
+        let Some(open) = text.find("struct").and_then(|s| text[s..].find('{').map(|o| s + o))
+        else {
+            continue;
+        };
+        let Some(close) = text.rfind('}') else { continue };
+        if close <= open {
+            continue;
+        }
+        let entry = out.entry(it.name.clone()).or_default();
+        for field in split_top_level(&text[open + 1..close], ',') {
+            let Some((name, ty)) = field.split_once(':') else { continue };
+            let name = name.trim().rsplit(char::is_whitespace).next().unwrap_or("").trim();
+            if let Some(t) = base_type_name(ty) {
+                if !name.is_empty() {
+                    entry.insert(name.to_string(), t.to_string());
+                }
+            }
+        }
+    }
+}
+
+// (line_start, line_end, self type, trait name) of an impl or trait block
+type Container = (usize, usize, Option<Arc<str>>, Option<Arc<str>>);
+
+// Extract signature facts for every function in a file.
+fn collect_fns(file: &FileNode, src: &str, starts: &[usize]) -> Vec<FnInfo> {
+    // Containers (impl/trait blocks) with their line spans
+    let containers: Vec<Container> = file
+        .items
+        .iter()
+        .filter_map(|it| match &it.item_type {
+            ItemType::Impl { trait_name, type_name } => Some((
+                it.location.line_start,
+                it.location.line_end,
+                Some(type_name.clone()),
+                trait_name.clone(),
+            )),
+            ItemType::Trait { .. } => {
+                Some((it.location.line_start, it.location.line_end, None, Some(it.name.clone())))
+            }
+            _ => None,
+        })
+        .collect();
+
+    let mut out = Vec::new();
+    for it in &file.items {
+        if !matches!(it.item_type, ItemType::Function { .. }) {
+            continue;
+        }
+        let line = it.location.line_start;
+        // Innermost container that strictly encloses the function
+        let container = containers
+            .iter()
+            .filter(|(s, e, _, _)| {
+                *s <= line && line <= *e && (*s, *e) != (line, it.location.line_end)
+            })
+            .min_by_key(|(s, e, _, _)| e - s);
+        let (owner, owner_trait) =
+            container.map_or((None, None), |(_, _, ty, tr)| (ty.clone(), tr.clone()));
+        let Some(&from) = starts.get(line.saturating_sub(1)) else { continue };
+        let Some(m) = fn_name_re()
+            .captures_iter(&src[from..])
+            .find(|c| c.name("name").is_some_and(|n| n.as_str() == it.name.as_ref()))
+            .and_then(|c| c.get(0))
+        else {
+            continue;
+        };
+        let (params, ret, body) = parse_signature(src, from + m.end(), owner.as_deref());
+        out.push(FnInfo {
+            id: it.id.clone(),
+            name: it.name.clone(),
+            owner,
+            owner_trait,
+            params,
+            ret,
+            body,
+        });
+    }
+    out
+}
+
+type Signature = (Vec<(String, String)>, Option<String>, Option<(usize, usize)>);
+
+// Parse `<generics>(params) -> Ret where .. { body }` starting right after the fn name.
+fn parse_signature(src: &str, from: usize, owner: Option<&str>) -> Signature {
+    let bytes = src.as_bytes();
+    let mut i = from;
+    // Generic parameter list
+    let mut angle = 0i32;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'<' => angle += 1,
+            b'>' if angle > 0 => angle -= 1,
+            b'(' if angle == 0 => break,
+            _ => {}
+        }
+        i += 1;
+    }
+    let Some(params_close) = matching(bytes, i, b'(', b')') else {
+        return (Vec::new(), None, None);
+    };
+    let params = split_top_level(&src[i + 1..params_close], ',')
+        .into_iter()
+        .filter_map(|p| {
+            let (pat, ty) = p.split_once(':')?;
+            let pat = pat.trim().trim_start_matches("mut ").trim();
+            if !pat.chars().all(|c| c.is_alphanumeric() || c == '_') || pat.is_empty() {
+                return None;
+            }
+            let t = base_type_name(ty)?;
+            Some((pat.to_string(), self_subst(t, owner)))
+        })
+        .collect();
+    // Return type up to the body or `;`
+    let mut j = params_close + 1;
+    let mut depth = 0i32;
+    while j < bytes.len() {
+        match bytes[j] {
+            b'(' | b'[' | b'<' => depth += 1,
+            b')' | b']' => depth -= 1,
+            b'>' if j > 0 && bytes[j - 1] != b'-' => depth -= 1,
+            b'{' | b';' if depth <= 0 => break,
+            _ => {}
+        }
+        j += 1;
+    }
+    let tail = &src[params_close + 1..j.min(src.len())];
+    let ret = tail
+        .trim()
+        .strip_prefix("->")
+        .map(|r| r.split(" where").next().unwrap_or(r))
+        .and_then(base_type_name)
+        .map(|t| self_subst(t, owner));
+    let body = (bytes.get(j) == Some(&b'{'))
+        .then(|| matching(bytes, j, b'{', b'}').map(|c| (j, c)))
+        .flatten();
+    (params, ret, body)
+}
+
+fn matching(bytes: &[u8], open: usize, o: u8, c: u8) -> Option<usize> {
+    if bytes.get(open) != Some(&o) {
+        return None;
+    }
+    let mut depth = 0usize;
+    for (k, &b) in bytes.iter().enumerate().skip(open) {
+        if b == o {
+            depth += 1;
+        } else if b == c {
+            depth -= 1;
+            if depth == 0 {
+                return Some(k);
+            }
+        }
+    }
+    None
+}
+
+// Split on `sep` outside of (), [], {} and <>.
+fn split_top_level(s: &str, sep: char) -> Vec<&str> {
+    let mut out = Vec::new();
+    let mut depth = 0i32;
+    let mut last = 0;
+    let mut prev = ' ';
+    for (i, ch) in s.char_indices() {
+        match ch {
+            '(' | '[' | '{' | '<' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            '>' if prev != '-' => depth -= 1,
+            c if c == sep && depth == 0 => {
+                out.push(&s[last..i]);
+                last = i + ch.len_utf8();
+            }
+            _ => {}
+        }
+        prev = ch;
+    }
+    if last < s.len() {
+        out.push(&s[last..]);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{mask_non_code, RustParser};
+
+    fn index_for(src: &str) -> (KnowledgeGraph, TypeIndex, PathBuf, String) {
+        let path = PathBuf::from("/t/src/lib.rs");
+        let node = RustParser::new().parse_file(src, &path).unwrap();
+        let mut g = KnowledgeGraph::default();
+        g.files.insert(path.clone(), node);
+        let masked = mask_non_code(src);
+        let sources: HashMap<PathBuf, String> = [(path.clone(), masked.clone())].into();
+        let idx = TypeIndex::build(&g, &sources);
+        (g, idx, path, masked)
+    }
+
+    fn target_names(g: &KnowledgeGraph, call: &MethodCall) -> Vec<String> {
+        let items = &g.files.values().next().unwrap().items;
+        call.targets
+            .iter()
+            .filter_map(|t| items.iter().find(|i| &i.id == t))
+            .map(|i| format!("{}@{}", i.name, i.location.line_start))
+            .collect()
+    }
+
+    #[test]
+    fn resolves_receivers_from_params_lets_self_and_fields() {
+        let src = r"
+struct A { b: B }
+struct B;
+impl A {
+    fn new() -> Self { A { b: B } }
+    fn run(&self) { self.b.go(); self.helper(); }
+    fn helper(&self) {}
+}
+impl B {
+    fn go(&self) {}
+}
+struct C;
+impl C {
+    fn go(&self) {}
+}
+fn f(b: &B) {
+    b.go();
+    let a = A::new();
+    a.run();
+    let c: C = make();
+    c.go();
+    unknown().go();
+}
+";
+        let (g, idx, path, masked) = index_for(src);
+        let calls = idx.resolve_method_calls(&path, &masked);
+        let find = |recv: &str, m: &str| {
+            calls.iter().find(|c| c.receiver == recv && c.method == m).cloned().unwrap()
+        };
+        let c = find("self.b", "go");
+        assert_eq!(target_names(&g, &c), vec!["go@10"]);
+        assert_eq!(c.confidence, Confidence::Exact);
+        assert_eq!(target_names(&g, &find("self", "helper")), vec!["helper@7"]);
+        assert_eq!(target_names(&g, &find("b", "go")), vec!["go@10"]);
+        let a_run = find("a", "run");
+        assert_eq!(target_names(&g, &a_run), vec!["run@6"]);
+        assert_eq!(a_run.confidence, Confidence::Inferred);
+        let c_go = find("c", "go");
+        assert_eq!(target_names(&g, &c_go), vec!["go@14"]);
+        assert_eq!(c_go.confidence, Confidence::Exact);
+        // Receiver is a call expression: falls back to every `go`
+        let fallback = find("", "go");
+        assert_eq!(fallback.confidence, Confidence::NameOnly);
+        assert_eq!(fallback.targets.len(), 2);
+    }
+
+    #[test]
+    fn infers_struct_literals_and_constructor_return_types() {
+        let src = r"
+struct A;
+impl A {
+    fn go(&self) {}
+}
+struct B { a: A }
+impl B {
+    fn build() -> A { A }
+    fn go(&self) {}
+}
+fn make_b() -> B { B { a: A } }
+fn f() {
+    let x = A {};
+    x.go();
+    let y = B::build();
+    y.go();
+    let z = make_b();
+    z.go();
+    let w = B { a: A };
+    w.a.go();
+}
+";
+        let (g, idx, path, masked) = index_for(src);
+        let calls = idx.resolve_method_calls(&path, &masked);
+        let find = |recv: &str| calls.iter().find(|c| c.receiver == recv).cloned().unwrap();
+        let x = find("x");
+        assert_eq!(target_names(&g, &x), vec!["go@4"]);
+        assert_eq!(x.confidence, Confidence::Exact);
+        // Declared return type wins over the `Type::ctor` guess
+        let y = find("y");
+        assert_eq!(target_names(&g, &y), vec!["go@4"]);
+        assert_eq!(y.confidence, Confidence::Inferred);
+        assert_eq!(target_names(&g, &find("z")), vec!["go@9"]);
+        assert_eq!(target_names(&g, &find("w.a")), vec!["go@4"]);
+    }
+
+    #[test]
+    fn shadowed_lets_use_the_binding_in_scope_at_the_call() {
+        let src = r"
+struct A;
+impl A {
+    fn go(&self) {}
+}
+struct B;
+impl B {
+    fn go(&self) {}
+}
+fn f(v: A) {
+    v.go();
+    let v = B;
+    v.go();
+    let v: A = todo();
+    v.go();
+}
+";
+        let (g, idx, path, masked) = index_for(src);
+        let calls = idx.resolve_method_calls(&path, &masked);
+        let lines: Vec<Vec<String>> = calls.iter().map(|c| target_names(&g, c)).collect();
+        assert_eq!(lines, vec![vec!["go@4"], vec!["go@8"], vec!["go@4"]]);
+    }
+
+    #[test]
+    fn untyped_receivers_fall_back_to_every_method_with_the_name() {
+        let src = r"
+struct A;
+impl A {
+    fn go(&self) {}
+    fn only_a(&self) {}
+}
+struct B;
+impl B {
+    fn go(&self) {}
+}
+fn f() {
+    let x = external::get();
+    let y = x;
+    y.go();
+    x.only_a();
+    x.missing();
+    fn inner(a: A) {
+        a.go();
+    }
+}
+";
+        let (g, idx, path, masked) = index_for(src);
+        let calls = idx.resolve_method_calls(&path, &masked);
+        let y = calls.iter().find(|c| c.receiver == "y").unwrap();
+        assert_eq!(y.confidence, Confidence::NameOnly);
+        assert_eq!(target_names(&g, y), vec!["go@4", "go@9"]);
+        let only = calls.iter().find(|c| c.method == "only_a").unwrap();
+        assert_eq!(only.confidence, Confidence::NameOnly);
+        assert_eq!(target_names(&g, only), vec!["only_a@5"]);
+        // No method anywhere with that name: no edge
+        assert!(calls.iter().all(|c| c.method != "missing"));
+        // The nested fn's call is attributed to `inner` only, not also to `f`
+        let a_go: Vec<&MethodCall> = calls.iter().filter(|c| c.receiver == "a").collect();
+        assert_eq!(a_go.len(), 1);
+        assert_eq!(idx.enclosing_fn(&path, a_go[0].offset).unwrap().name.as_ref(), "inner");
+        assert_eq!(a_go[0].confidence, Confidence::Exact);
+    }
+}
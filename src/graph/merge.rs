@@ -0,0 +1,100 @@
+//! Combining graphs built from different project roots.
+//!
+//! Item ids are only unique within one root (`fn:crate::run`, `file:src/lib.rs`), so both
+//! graphs are first re-keyed against their common root: crates are then named by their crate
+//! name and files by their path below that root. Ids that still collide are reported as a
+//! conflict instead of one graph's items replacing the other's.
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use crate::errors::KnowledgeGraphError;
+use crate::graph::{paths, schema, KnowledgeGraph, RelationshipType};
+
+impl KnowledgeGraph {
+    /// Merge `other` into this graph and re-resolve imports across both, so
+    /// `use other_crate::X` connects to `X` in `other`.
+    ///
+    /// Calls between the graphs are not re-resolved since that needs the sources; build both
+    /// roots together with [`GraphBuilder::add_root`](super::builder::GraphBuilder::add_root)
+    /// for those.
+    ///
+    /// # Errors
+    /// Returns `KnowledgeGraphError::MergeConflict` listing the item ids both graphs define.
+    /// This graph is left unchanged then.
+    pub fn merge(&mut self, other: KnowledgeGraph) -> Result<(), KnowledgeGraphError> {
+        if self.files.is_empty() {
+            *self = other;
+            return Ok(());
+        }
+        self.union(other)?;
+        // The stats of either build no longer describe the merged graph
+        self.metadata.stats = None;
+        self.relationships
+            .retain(|r| !matches!(r.relationship_type, RelationshipType::Uses { .. }));
+        self.analyze_import_uses();
+        self.invalidate_index();
+        Ok(())
+    }
+
+    // Re-key both graphs against their common root and take the union of files,
+    // relationships and module maps, without any re-analysis. On a conflict `self` is left
+    // as it was: its new ids are only applied once the union is known to succeed.
+    pub(crate) fn union(&mut self, mut other: KnowledgeGraph) -> Result<(), KnowledgeGraphError> {
+        let root = common_root(&self.root_or_inferred(), &other.root_or_inferred());
+        other.reassign_item_ids(&root);
+
+        let planned = paths::item_ids(self, &root);
+        let ours: HashSet<&str> = planned.iter().map(|(_, _, id)| id.0.as_str()).collect();
+        let mut conflicts: Vec<String> = other
+            .files
+            .values()
+            .flat_map(|f| f.items.iter().map(|it| it.id.0.as_str()))
+            .filter(|id| ours.contains(id))
+            .map(str::to_string)
+            .collect();
+        if !conflicts.is_empty() {
+            conflicts.sort();
+            conflicts.dedup();
+            return Err(KnowledgeGraphError::MergeConflict { ids: conflicts });
+        }
+
+        self.reassign_item_ids(&root);
+        self.metadata.root = Some(root);
+        self.files.extend(other.files);
+        self.relationships.extend(other.relationships);
+        self.module_parent.extend(other.module_parent);
+        self.module_children.extend(other.module_children);
+        self.module_segments.extend(other.module_segments);
+        self.import_segments.extend(other.import_segments);
+        self.crate_names.extend(other.crate_names);
+        self.invalidate_index();
+        Ok(())
+    }
+
+    pub(crate) fn root_or_inferred(&self) -> PathBuf {
+        self.metadata.root.clone().unwrap_or_else(|| schema::infer_root(self))
+    }
+}
+
+// Longest shared leading path of `a` and `b`.
+fn common_root(a: &Path, b: &Path) -> PathBuf {
+    a.components().zip(b.components()).take_while(|(x, y)| x == y).map(|(x, _)| x).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn common_root_of_sibling_checkouts() {
+        assert_eq!(
+            common_root(Path::new("/work/app"), Path::new("/work/lib")),
+            PathBuf::from("/work")
+        );
+        assert_eq!(
+            common_root(Path::new("/work/app"), Path::new("/work/app")),
+            Path::new("/work/app")
+        );
+        assert_eq!(common_root(Path::new("a"), Path::new("b")), PathBuf::new());
+    }
+}
@@ -4,26 +4,66 @@
 //! (`KnowledgeGraph`, `FileNode`, `Item`, `Relationship`) and the analysis
 //! passes that populate relationships (module hierarchy, import uses, calls).
 //!
-//! You typically construct a graph via `KnowledgeGraph::build_from_directory_*`
-//! and then pass it to queries in `crate::query`.
-use crate::utils::cache;
+//! You typically construct a graph with `builder::GraphBuilder` and then pass it
+//! to queries in `crate::query`.
+use crate::parser::SourceText;
+use crate::source::SourceProvider;
+use crate::utils::{cache, file_walker};
+use builder::{BuildProgress, GraphBuilder, Progress};
 use rayon::prelude::*;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
-
+use std::time::Instant;
+
+pub mod builder;
+pub mod diff;
+pub mod filter;
+pub mod format;
+pub mod incremental;
+pub mod index;
+pub mod infer;
+pub mod merge;
+pub mod paths;
 pub mod resolver;
+pub mod schema;
+pub mod stats;
 
 // Type aliases to keep signatures concise and satisfy clippy::type_complexity
 type Segments = Vec<Arc<str>>;
 type ImportSegments = Vec<(Segments, Option<Arc<str>>)>;
-type ParsedEntry = (PathBuf, FileNode, Vec<Relationship>, cache::CacheEntry);
+// Path, parse, cache entry, text read, and whether the file was parsed (not reused)
+type ParsedEntry = (PathBuf, FileNode, cache::CacheEntry, SourceText, bool);
+
+// One parsed root with its cache state, not yet saved, the entries of files that are gone,
+// and the text read while parsing.
+struct ParsedRoot<'a> {
+    graph: KnowledgeGraph,
+    cache: cache::Cache,
+    removed: Vec<cache::CacheEntry>,
+    sources: incremental::Sources<'a>,
+}
 
+/// Stable item identifier derived from kind and path, e.g. `fn:crate::graph::Resolver::new`.
+///
+/// See [`KnowledgeGraph::find_item_ids`] for looking up items by the line-based ids
+/// (`fn:new:10`) of earlier versions.
 #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq, Hash)]
 pub struct ItemId(pub String);
 
+// Split a legacy `kind:name:line` id.
+fn parse_legacy_id(raw: &str) -> Option<(&str, &str, usize)> {
+    let (rest, line) = raw.rsplit_once(':')?;
+    let (kind, name) = rest.split_once(':')?;
+    if name.is_empty() || name.contains(':') {
+        return None;
+    }
+    Some((kind, name, line.parse().ok()?))
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Location {
     pub file: PathBuf,
@@ -68,6 +108,26 @@ pub struct Item {
 pub struct Import {
     pub path: Arc<str>,
     pub alias: Option<Arc<str>>,
+    /// True for `pub use` re-exports.
+    #[serde(default)]
+    pub is_pub: bool,
+    /// 1-based line and column of the `use` keyword; 0 when unknown (older caches).
+    #[serde(default)]
+    pub line: usize,
+    #[serde(default)]
+    pub column: usize,
+}
+
+impl Import {
+    /// The statement as written, normalized to one line: `pub use a::b as c;`.
+    #[must_use]
+    pub fn statement(&self) -> String {
+        let vis = if self.is_pub { "pub " } else { "" };
+        match &self.alias {
+            Some(a) => format!("{vis}use {} as {a};", self.path),
+            None => format!("{vis}use {};", self.path),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -79,13 +139,135 @@ pub enum RelationshipType {
     Calls { call_type: String },
 }
 
+/// How certain the analysis is that an edge points at the right target.
+///
+/// Ordered from weakest to strongest so callers can filter with `>=`.
+#[derive(
+    Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, Default,
+)]
+pub enum Confidence {
+    /// Matched by name only; any function or method with that name is a candidate.
+    ///
+    /// Also the default, so edges saved without a confidence never load as more certain
+    /// than they were.
+    #[default]
+    NameOnly,
+    /// Target found through inferred types (initializer expressions, field types, return types).
+    Inferred,
+    /// Target determined by explicit syntax: a resolved path, an annotated type or `self`.
+    Exact,
+}
+
+/// Confidence scores stored in [`Relationship::strength`] for `Calls` edges.
+///
+/// Structural edges (`Contains`, `Uses`) are read straight from the source and keep a
+/// strength of `1.0` (`0.8` for whole-module imports). Call edges are inferred, so their
+/// strength says how likely the edge is to point at the function actually invoked. A bare
+/// call `foo()` is resolved against the narrowest scope that defines `foo`, in this order:
+///
+/// | Score | Resolution |
+/// |-------|------------|
+/// | 0.95  | [`SAME_MODULE`]: defined in the calling file |
+/// | 0.9   | [`EXACT`]: resolved path, annotated receiver type or `self` |
+/// | 0.85  | [`IMPORTED`]: bound by a `use` in the calling file, including globs |
+/// | 0.6   | [`INFERRED`]: several path targets, or a receiver type inferred from an initializer |
+/// | 0.5   | [`REEXPORTED`]: exposed by a `pub use` elsewhere in the crate |
+/// | 0.3   | [`SAME_CRATE`]: any other free function of that name in the same crate |
+/// | 0.2   | [`NAME_ONLY`]: unresolved path call matched on its last segment |
+///
+/// Only the best scope with a match produces edges, so a common name such as `new` defined
+/// locally no longer links to every other `new` in the crate.
+///
+/// [`SAME_MODULE`]: call_score::SAME_MODULE
+/// [`EXACT`]: call_score::EXACT
+/// [`IMPORTED`]: call_score::IMPORTED
+/// [`INFERRED`]: call_score::INFERRED
+/// [`REEXPORTED`]: call_score::REEXPORTED
+/// [`SAME_CRATE`]: call_score::SAME_CRATE
+/// [`NAME_ONLY`]: call_score::NAME_ONLY
+pub mod call_score {
+    pub const SAME_MODULE: f64 = 0.95;
+    pub const EXACT: f64 = 0.9;
+    pub const IMPORTED: f64 = 0.85;
+    pub const INFERRED: f64 = 0.6;
+    pub const REEXPORTED: f64 = 0.5;
+    pub const SAME_CRATE: f64 = 0.3;
+    pub const NAME_ONLY: f64 = 0.2;
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Relationship {
     pub from_item: ItemId,
     pub to_item: ItemId,
     pub relationship_type: RelationshipType,
+    /// Confidence score in `0.0..=1.0`; see [`call_score`] for how call edges are scored.
     pub strength: f64,
     pub context: String,
+    #[serde(default)]
+    pub confidence: Confidence,
+    /// Line of the site that produced the edge (the call site for `Calls`), if known.
+    #[serde(default)]
+    pub line: Option<usize>,
+    /// Number of sites producing this edge, e.g. repeated calls from one function.
+    #[serde(default = "one")]
+    pub count: usize,
+    /// Source locations of the sites producing this edge (call sites, `use` statements).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub evidence: Vec<Evidence>,
+}
+
+fn one() -> usize {
+    1
+}
+
+impl Relationship {
+    /// Confidence-weighted number of sites: `strength * count`. A call made 200 times weighs
+    /// far more than one made once, and a guessed edge less than a resolved one.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn weight(&self) -> f64 {
+        self.strength * self.count as f64
+    }
+
+    /// Lines of every recorded site: each piece of evidence, plus `line` for edges saved
+    /// without evidence. May repeat a line; collect into a set for distinct lines.
+    pub fn site_lines(&self) -> impl Iterator<Item = usize> + '_ {
+        self.line.into_iter().chain(self.evidence.iter().map(|e| e.line))
+    }
+}
+
+/// A source location that produced a relationship.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Evidence {
+    pub file: PathBuf,
+    /// 1-based line and column.
+    pub line: usize,
+    pub column: usize,
+    /// The trimmed source line.
+    pub snippet: String,
+}
+
+impl Evidence {
+    // Longest snippet kept; longer lines are cut at a char boundary.
+    const MAX_SNIPPET: usize = 160;
+
+    /// Evidence for byte `offset` of `content`, given the byte offsets of its line starts.
+    #[must_use]
+    pub fn at(file: &Path, content: &str, starts: &[usize], offset: usize) -> Self {
+        let line = starts.partition_point(|&s| s <= offset);
+        let start = starts.get(line.saturating_sub(1)).copied().unwrap_or(0);
+        let end = content[start..].find('\n').map_or(content.len(), |e| start + e);
+        let column = content.get(start..offset).map_or(1, |pre| pre.chars().count() + 1);
+        let mut snippet = content[start..end].trim();
+        if snippet.len() > Self::MAX_SNIPPET {
+            let mut cut = Self::MAX_SNIPPET;
+            while !snippet.is_char_boundary(cut) {
+                cut -= 1;
+            }
+            snippet = &snippet[..cut];
+        }
+        Self { file: file.to_path_buf(), line, column, snippet: snippet.to_string() }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -102,9 +284,65 @@ pub struct FileNode {
     pub metrics: FileMetrics,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+/// Build settings that affect graph content, recorded so a saved graph can be compared with
+/// the settings of a later build.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct BuildFingerprint {
+    /// `.gitignore` rules were bypassed while walking files.
+    pub no_ignore: bool,
+    /// Analysis features the build ran, sorted; see [`schema::BUILD_FEATURES`].
+    pub features: Vec<String>,
+    /// Source parser backend.
+    pub parser: String,
+}
+
+impl BuildFingerprint {
+    #[must_use]
+    pub fn current(no_ignore: bool) -> Self {
+        Self {
+            no_ignore,
+            features: schema::BUILD_FEATURES.iter().map(|f| (*f).to_string()).collect(),
+            parser: schema::PARSER_BACKEND.to_string(),
+        }
+    }
+
+    /// Whether the build ran analysis feature `name`.
+    #[must_use]
+    pub fn has_feature(&self, name: &str) -> bool {
+        self.features.iter().any(|f| f == name)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GraphMetadata {
     pub generated_at: String,
+    /// Layout and id scheme of the graph; 0 for graphs saved before versioning (0.1.x).
+    #[serde(default)]
+    pub schema_version: u32,
+    /// Version of the tool that built the graph.
+    #[serde(default)]
+    pub tool_version: String,
+    #[serde(default)]
+    pub options: BuildFingerprint,
+    /// Directory the graph was built from.
+    #[serde(default)]
+    pub root: Option<PathBuf>,
+    /// Counts and phase timings of the build that produced the graph.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stats: Option<stats::BuildStats>,
+}
+
+impl Default for GraphMetadata {
+    fn default() -> Self {
+        Self {
+            generated_at: String::new(),
+            schema_version: schema::SCHEMA_VERSION,
+            tool_version: env!("CARGO_PKG_VERSION").to_string(),
+            options: BuildFingerprint::default(),
+            root: None,
+            stats: None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -113,212 +351,389 @@ pub struct KnowledgeGraph {
     pub relationships: Vec<Relationship>,
     pub metadata: GraphMetadata,
     // Module hierarchy tracking: parent and children maps keyed by file paths
+    #[serde(default)]
     pub module_parent: HashMap<PathBuf, PathBuf>,
+    #[serde(default)]
     pub module_children: HashMap<PathBuf, Vec<PathBuf>>,
     // Cached module path segments per file (relative to src/), to avoid recomputation
+    #[serde(default)]
     pub module_segments: HashMap<PathBuf, Vec<String>>,
     // Precomputed import segments per file: Vec of (segments, alias), using Arc<str> pool for deduplication
     #[serde(skip, default)]
     pub import_segments: HashMap<PathBuf, ImportSegments>,
+    // Crate name per `src/` directory, from the manifests the build read (serde-skipped)
+    #[serde(skip, default)]
+    pub(crate) crate_names: HashMap<PathBuf, String>,
     // Global string pool for interning hot strings across phases (serde-skipped)
     #[serde(skip, default)]
     pub string_pool: std::sync::Arc<Mutex<HashMap<String, Arc<str>>>>,
+    // Adjacency index shared by queries, built on first use (serde-skipped)
+    #[serde(skip, default)]
+    index: std::sync::OnceLock<index::GraphIndex>,
 }
 
 impl KnowledgeGraph {
     /// Build a knowledge graph from a directory with explicit cache mode and ignore behavior.
     ///
-    /// Arguments:
-    /// - `path`: Root directory to scan for Rust source files.
-    /// - `mode`: Cache usage policy (see `utils::cache::CacheMode`).
-    /// - `no_ignore`: When true, bypasses `.gitignore`/`.ignore` rules in file discovery.
-    ///
-    /// Returns a fully built `KnowledgeGraph`, loading from and/or updating the on-disk cache
-    /// according to `mode`. File discovery is performed via `utils::file_walker::rust_files_with_options`.
-    ///
     /// # Errors
-    /// Returns `KnowledgeGraphError` if file discovery, I/O, cache read/write, or parsing fails during build.
-    #[allow(clippy::too_many_lines)]
+    /// Returns the errors of [`GraphBuilder::build`].
+    #[deprecated(note = "use `graph::builder::GraphBuilder`")]
     pub fn build_from_directory_with_cache_opts(
         path: &std::path::Path,
         mode: cache::CacheMode,
         no_ignore: bool,
     ) -> Result<Self, crate::errors::KnowledgeGraphError> {
-        use crate::errors::KnowledgeGraphError;
-        use crate::parser::RustParser;
-        use crate::utils::file_walker;
-        use std::fs;
+        GraphBuilder::new(path).cache_mode(mode).no_ignore(no_ignore).build()
+    }
 
-        let files =
-            file_walker::rust_files_with_options(path.to_string_lossy().as_ref(), no_ignore);
+    // Build from the roots of `opts`, listing and reading files through `provider`; see
+    // `GraphBuilder::build`. One root is analyzed incrementally against its cache, several
+    // are parsed with their own caches, combined as in `merge` and analyzed together.
+    pub(crate) fn build_roots(
+        provider: &dyn SourceProvider,
+        opts: &builder::BuildOptions,
+        features: &[String],
+        progress: Progress<'_>,
+    ) -> Result<Self, crate::errors::KnowledgeGraphError> {
+        let started = Instant::now();
+        let (paths, mode, discovery, location) =
+            (opts.roots.as_slice(), opts.cache_mode, &opts.discovery, &opts.cache_location);
+        if let [path] = paths {
+            let store = cache::CacheStore::new(path, location);
+            let ParsedRoot { mut graph, cache: mut cache_state, removed, mut sources } =
+                Self::parse_directory(provider, path, mode, discovery, &store, progress)?;
+            graph.metadata.options.features = features.to_vec();
+            builder::report(progress, &BuildProgress::Analyzing { files: graph.files.len() });
+            // Analyze relationships beyond file containment, reusing cached per-file results
+            graph.analyze_incremental(&mut cache_state, &removed, &mut sources)?;
+            // Even in Ignore/Rebuild, we save freshly built state unless told not to; an
+            // unwritable cache only costs speed
+            if opts.write_cache {
+                let _ = store.save(&cache_state);
+            }
+            graph.finish_stats(started);
+            return Ok(graph);
+        }
+        let mut graph = KnowledgeGraph::default();
+        let mut sources = incremental::Sources::new(provider);
+        let mut totals = stats::BuildStats::default();
+        for (i, path) in paths.iter().enumerate() {
+            let store = cache::CacheStore::new(path, location);
+            let ParsedRoot { graph: mut parsed, cache: cache_state, sources: read, .. } =
+                Self::parse_directory(provider, path, mode, discovery, &store, progress)?;
+            sources.extend(read);
+            if let Some(s) = parsed.metadata.stats.take() {
+                totals.absorb(&s);
+            }
+            // Ids are re-keyed against the common root below, so only parses are cached
+            if opts.write_cache {
+                let _ = store.save(&cache_state);
+            }
+            if i == 0 {
+                graph = parsed;
+            } else {
+                graph.union(parsed)?;
+            }
+        }
+        graph.metadata.stats = Some(totals);
+        graph.metadata.options.features = features.to_vec();
+        builder::report(progress, &BuildProgress::Analyzing { files: graph.files.len() });
+        graph.analyze_relationships(&mut sources)?;
+        graph.finish_stats(started);
+        Ok(graph)
+    }
 
-        // Load or ignore cache based on mode
-        let root_dir = path.to_path_buf();
+    // Build from the Rust files of the project at `root` as of the git revision `rev`, read
+    // from the object database; the working tree is neither read nor changed. File ids and
+    // paths are those of a checkout at `root`. Parses are cached by blob id and path
+    // (`Cache::revisions`) and shared by every revision where a file is unchanged;
+    // relationships are analyzed over all files.
+    pub(crate) fn build_revision(
+        root: &Path,
+        rev: &str,
+        opts: &builder::BuildOptions,
+        features: &[String],
+        progress: Progress<'_>,
+    ) -> Result<Self, crate::errors::KnowledgeGraphError> {
+        let started = Instant::now();
+        let (mode, discovery, location) = (opts.cache_mode, &opts.discovery, &opts.cache_location);
+        let mut stats = stats::BuildStats::default();
+        let revision = crate::utils::git::Revision::open(root, rev, discovery)?;
+        // Crate names come from the revision's manifests, not the working tree's
+        let manifests: crate::source::MemorySource =
+            revision.manifests().iter().map(|m| (m.path.clone(), m.text.clone())).collect();
+        let total = revision.files().len();
+        stats.files_discovered = total;
+        stats.timings.discovery_ms = stats::millis(started.elapsed());
+        builder::report(progress, &BuildProgress::Discovered { root, files: total });
+        let phase = Instant::now();
+        let store = cache::CacheStore::new(root, location);
         let mut cache_state = match mode {
-            cache::CacheMode::Use => cache::load_cache(&root_dir).unwrap_or_default(),
+            cache::CacheMode::Use => store.load().unwrap_or_default(),
             cache::CacheMode::Ignore | cache::CacheMode::Rebuild => cache::Cache::default(),
         };
+        stats.timings.cache_load_ms = stats::millis(phase.elapsed());
+
+        let phase = Instant::now();
+        let cached = &cache_state;
+        let reuse = matches!(mode, cache::CacheMode::Use);
+        let done = AtomicUsize::new(0);
+        let parsed: Vec<(String, cache::CacheEntry, SourceText, bool)> = revision
+            .into_files()
+            .into_par_iter()
+            .map(|file| {
+                let rel = file.path.strip_prefix(root).unwrap_or(&file.path);
+                let key = cache::revision_key(&file.blob, rel);
+                let meta = cache::CacheEntryMeta {
+                    len: file.text.len() as u64,
+                    ..cache::CacheEntryMeta::default()
+                }
+                .with_hash(file.text.as_bytes());
+                let text = SourceText::new(file.text);
+                let entry = match cached.revisions.get(&key).filter(|_| reuse) {
+                    Some(entry) => (
+                        key,
+                        cache::CacheEntry { meta, node: entry.node.clone(), analysis: None },
+                        text,
+                        false,
+                    ),
+                    None => {
+                        let node = Self::parse_text(&text, &file.path)?;
+                        (key, cache::CacheEntry { meta, node, analysis: None }, text, true)
+                    }
+                };
+                let done = done.fetch_add(1, Ordering::Relaxed) + 1;
+                builder::report(progress, &BuildProgress::Parsed { path: &file.path, done, total });
+                Ok(entry)
+            })
+            .collect::<Result<_, crate::errors::KnowledgeGraphError>>()?;
 
-        // Collect file metadata for change detection
-        let infos: Vec<(String, cache::CacheEntryMeta)> = files
+        let mut graph = KnowledgeGraph::default();
+        let mut sources = incremental::Sources::default();
+        for (key, entry, text, was_parsed) in parsed {
+            if was_parsed {
+                stats.files_parsed += 1;
+            } else {
+                stats.files_reused += 1;
+            }
+            sources.insert(entry.node.path.clone(), text);
+            graph.files.insert(entry.node.path.clone(), entry.node.clone());
+            cache_state.revisions.insert(key, entry);
+        }
+        graph.finish_parse(root, discovery, &manifests);
+        graph.metadata.options.features = features.to_vec();
+        stats.timings.parsing_ms = stats::millis(phase.elapsed());
+        graph.metadata.stats = Some(stats);
+
+        builder::report(progress, &BuildProgress::Analyzing { files: graph.files.len() });
+        graph.analyze_relationships(&mut sources)?;
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        cache_state
+            .revisions
+            .retain(|_, e| now.saturating_sub(e.meta.checked_at) < cache::REVISION_TTL_SECS);
+        if opts.write_cache {
+            let _ = store.save(&cache_state);
+        }
+        graph.finish_stats(started);
+        Ok(graph)
+    }
+
+    // Complete the build stats once the graph is final.
+    fn finish_stats(&mut self, started: Instant) {
+        let mut stats = self.metadata.stats.take().unwrap_or_default();
+        stats.finish(self, started.elapsed());
+        self.metadata.stats = Some(stats);
+    }
+
+    // Discover and parse the files under `path` and assign ids and containment edges;
+    // relationship analysis and saving the cache are left to the caller.
+    #[allow(clippy::too_many_lines)]
+    fn parse_directory<'a>(
+        provider: &'a dyn SourceProvider,
+        path: &std::path::Path,
+        mode: cache::CacheMode,
+        discovery: &file_walker::DiscoveryOptions,
+        store: &cache::CacheStore,
+        progress: Progress<'_>,
+    ) -> Result<ParsedRoot<'a>, crate::errors::KnowledgeGraphError> {
+        use crate::errors::KnowledgeGraphError;
+
+        let mut stats = stats::BuildStats::default();
+        let started = Instant::now();
+        let files: Vec<String> = provider
+            .discover(path, discovery)?
+            .files
             .iter()
-            .map(|f| {
-                let p = std::path::Path::new(f);
-                let meta = fs::metadata(p).ok();
-                let len = meta.as_ref().map_or(0u64, std::fs::Metadata::len);
-                let mtime = meta
-                    .and_then(|m| m.modified().ok())
-                    .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
-                    .map_or(0u64, |d| d.as_secs());
-                (f.clone(), cache::CacheEntryMeta { mtime, len })
-            })
+            .filter_map(|p| p.to_str().map(String::from))
             .collect();
+        let total = files.len();
+        stats.files_discovered = total;
+        stats.timings.discovery_ms = stats::millis(started.elapsed());
+        builder::report(progress, &BuildProgress::Discovered { root: path, files: total });
+
+        // Load or ignore cache based on mode
+        let started = Instant::now();
+        let mut cache_state = match mode {
+            cache::CacheMode::Use => store.load().unwrap_or_default(),
+            cache::CacheMode::Ignore | cache::CacheMode::Rebuild => cache::Cache::default(),
+        };
+        stats.timings.cache_load_ms = stats::millis(started.elapsed());
+        let started = Instant::now();
+
+        // Collect file metadata for change detection; files without one are always read
+        let infos: Vec<(String, Option<cache::CacheEntryMeta>)> =
+            files.iter().map(|f| (f.clone(), provider.stat(std::path::Path::new(f)))).collect();
 
         // Prune cache entries for files that no longer exist in the walk
+        let mut removed = Vec::new();
         if matches!(mode, cache::CacheMode::Use) {
-            use std::collections::HashSet;
             let present: HashSet<PathBuf> =
                 files.iter().map(|f| std::path::Path::new(f).to_path_buf()).collect();
-            cache_state.entries.retain(|k, _| present.contains(k));
+            let gone: Vec<PathBuf> =
+                cache_state.entries.keys().filter(|k| !present.contains(*k)).cloned().collect();
+            removed.extend(gone.iter().filter_map(|k| cache_state.entries.remove(k)));
         }
 
-        // Reuse from cache when metadata matches (only in Use mode)
-        let mut reused: Vec<(PathBuf, FileNode, Vec<Relationship>)> = Vec::new();
-        let mut to_parse: Vec<(String, cache::CacheEntryMeta)> = Vec::new();
+        // Reuse entries whose stat pre-check passes (only in Use mode); read the rest
+        let mut reused: Vec<(PathBuf, FileNode)> = Vec::new();
+        let mut sources = incremental::Sources::new(provider);
+        let mut to_read: Vec<(String, Option<cache::CacheEntryMeta>)> = Vec::new();
         for (file, meta) in &infos {
             let key = std::path::Path::new(file).to_path_buf();
-            if matches!(mode, cache::CacheMode::Use) {
+            if let (cache::CacheMode::Use, Some(meta)) = (mode, meta) {
                 if let Some(entry) = cache_state.entries.get(&key) {
-                    if entry.meta == *meta {
+                    if entry.meta.unchanged(meta) {
                         let node = entry.node.clone();
-                        // Rebuild contains edges for cached node
-                        let file_id = ItemId(format!("file:{}", node.path.display()));
-                        let mut edges = Vec::new();
-                        for it in node.items.iter().skip(1) {
-                            edges.push(Relationship {
-                                from_item: file_id.clone(),
-                                to_item: it.id.clone(),
-                                relationship_type: RelationshipType::Contains {
-                                    containment_type: "file_contains".to_string(),
-                                },
-                                strength: 1.0,
-                                context: "auto".to_string(),
-                            });
-                        }
-                        reused.push((node.path.clone(), node, edges));
+                        sources.defer(node.path.clone(), entry.meta.hash);
+                        let done = reused.len() + 1;
+                        builder::report(
+                            progress,
+                            &BuildProgress::Parsed { path: &key, done, total },
+                        );
+                        reused.push((node.path.clone(), node));
                         continue;
                     }
                 }
             }
-            to_parse.push((file.clone(), meta.clone()));
+            to_read.push((file.clone(), meta.clone()));
         }
 
-        // Parse files in parallel. Each task returns (path, node, contains_edges)
-        let parsed: Result<Vec<ParsedEntry>, KnowledgeGraphError> = to_parse
+        // Read and hash in parallel; parse only when the content hash differs from the cache.
+        // Files whose content turns out to be `@generated` are dropped here, where the text
+        // is read anyway, rather than during discovery.
+        let reading: Vec<PathBuf> = to_read.iter().map(|(f, _)| PathBuf::from(f)).collect();
+        let cached = &cache_state;
+        let done = AtomicUsize::new(reused.len());
+        let parsed: Result<Vec<Option<ParsedEntry>>, KnowledgeGraphError> = to_read
             .into_par_iter()
             .map(|(file, meta)| {
                 let p = std::path::Path::new(&file);
-                let content = fs::read_to_string(p)?;
-                let p = std::path::Path::new(&file).to_path_buf();
-                let mut node = RustParser::new().parse_file(&content, &p).map_err(|source| {
-                    KnowledgeGraphError::ParseError { file: p.clone(), source }
-                })?;
-
-                // Create a synthetic file-level module item
-                let file_id = ItemId(format!("file:{}", node.path.display()));
-                let file_item = Item {
-                    id: file_id.clone(),
-                    item_type: ItemType::Module { is_inline: false },
-                    name: Arc::from(
-                        node.path.file_stem().and_then(|s| s.to_str()).unwrap_or("(file)"),
-                    ),
-                    visibility: Visibility::PubCrate,
-                    location: Location { file: node.path.clone(), line_start: 1, line_end: 1 },
-                    attributes: vec![],
-                };
-
-                // Prepend the file item
-                let mut items_with_file = Vec::with_capacity(node.items.len() + 1);
-                items_with_file.push(file_item);
-                items_with_file.extend(node.items);
-                node.metrics.item_count = items_with_file.len();
-                node.items = items_with_file;
-
-                // Build Contains relationships from file item to each other item
-                let mut contains_edges: Vec<Relationship> = Vec::new();
-                for it in node.items.iter().skip(1) {
-                    contains_edges.push(Relationship {
-                        from_item: file_id.clone(),
-                        to_item: it.id.clone(),
-                        relationship_type: RelationshipType::Contains {
-                            containment_type: "file_contains".to_string(),
-                        },
-                        strength: 1.0,
-                        context: "auto".to_string(),
-                    });
+                let content = provider.read(p)?;
+                let done = done.fetch_add(1, Ordering::Relaxed) + 1;
+                builder::report(progress, &BuildProgress::Parsed { path: p, done, total });
+                if discovery.skip_generated && file_walker::generated_marker(content.as_bytes()) {
+                    return Ok(None);
                 }
-
-                let cache_entry = cache::CacheEntry { meta, node: node.clone() };
-                Ok::<_, KnowledgeGraphError>((node.path.clone(), node, contains_edges, cache_entry))
+                let meta = meta
+                    .unwrap_or_else(|| cache::CacheEntryMeta {
+                        len: content.len() as u64,
+                        ..cache::CacheEntryMeta::default()
+                    })
+                    .with_hash(content.as_bytes());
+                let text = SourceText::new(content);
+                let previous = cached.entries.get(p);
+                if let Some(entry) = previous.filter(|e| e.meta.hash == meta.hash) {
+                    let node = entry.node.clone();
+                    let analysis = entry.analysis.clone();
+                    let cache_entry = cache::CacheEntry { meta, node: node.clone(), analysis };
+                    return Ok(Some((node.path.clone(), node, cache_entry, text, false)));
+                }
+                // The outdated analysis stays so the next one can tell what changed
+                let analysis = previous.and_then(|e| e.analysis.clone());
+                let node = Self::parse_text(&text, p)?;
+                let cache_entry = cache::CacheEntry { meta, node: node.clone(), analysis };
+                Ok(Some((node.path.clone(), node, cache_entry, text, true)))
             })
             .collect();
 
         let mut graph = KnowledgeGraph::default();
+        stats.files_reused = reused.len();
         // Insert reused nodes
-        for (path, node, edges) in reused {
+        for (path, node) in reused {
             graph.files.insert(path, node);
-            graph.relationships.extend(edges);
         }
         // Insert newly parsed nodes and update cache
-        for (path, node, edges, cache_entry) in parsed? {
+        for (file, entry) in reading.into_iter().zip(parsed?) {
+            let Some((path, node, cache_entry, text, was_parsed)) = entry else {
+                // An earlier build may have parsed the file before it was generated
+                removed.extend(cache_state.entries.remove(&file));
+                continue;
+            };
+            if was_parsed {
+                stats.files_parsed += 1;
+            } else {
+                stats.files_reused += 1;
+            }
+            sources.insert(path.clone(), text);
             graph.files.insert(path, node);
-            graph.relationships.extend(edges);
             cache_state.entries.insert(cache_entry.node.path.clone(), cache_entry);
         }
 
-        // Precompute module path segments for all files (relative to src/)
-        // Avoid recomputing in resolver/analyses. Mirrors logic in resolver::module_segments_for.
-        graph.module_segments = {
-            let mut map: HashMap<PathBuf, Vec<String>> = HashMap::with_capacity(graph.files.len());
-            for p in graph.files.keys() {
-                // Find index of "src" in the path components
-                let comps: Vec<_> = p.components().collect();
-                let mut src_idx: Option<usize> = None;
-                for (i, c) in comps.iter().enumerate() {
-                    if let std::path::Component::Normal(os) = c {
-                        if os.to_str() == Some("src") {
-                            src_idx = Some(i);
-                            break;
-                        }
-                    }
-                }
-                let mut segs: Vec<String> = Vec::new();
-                if let Some(i) = src_idx {
-                    // Directories after src up to (but excluding) the file name
-                    for c in &comps[i + 1..comps.len().saturating_sub(1)] {
-                        if let std::path::Component::Normal(os) = c {
-                            if let Some(s) = os.to_str() {
-                                segs.push(s.to_string());
-                            }
-                        }
-                    }
-                    // File as module: include file stem unless mod.rs/lib.rs
-                    if let Some(file_os) = p.file_name() {
-                        let file = file_os.to_string_lossy();
-                        if file != "mod.rs" && file != "lib.rs" {
-                            if let Some(stem) = p.file_stem().and_then(|s| s.to_str()) {
-                                segs.push(stem.to_string());
-                            }
-                        }
-                    }
-                }
-                map.insert(p.clone(), segs);
-            }
-            map
+        graph.finish_parse(path, discovery, provider);
+        stats.timings.parsing_ms = stats::millis(started.elapsed());
+        graph.metadata.stats = Some(stats);
+        Ok(ParsedRoot { graph, cache: cache_state, removed, sources })
+    }
+
+    // Parse one file and prepend its synthetic file-level module item.
+    fn parse_text(
+        text: &SourceText,
+        path: &Path,
+    ) -> Result<FileNode, crate::errors::KnowledgeGraphError> {
+        let mut node =
+            crate::parser::RustParser::new().parse_source(text, path).map_err(|source| {
+                crate::errors::KnowledgeGraphError::ParseError { file: path.to_path_buf(), source }
+            })?;
+
+        // Create a synthetic file-level module item
+        let file_item = Item {
+            id: ItemId(format!("file:{}", node.path.display())),
+            item_type: ItemType::Module { is_inline: false },
+            name: Arc::from(node.path.file_stem().and_then(|s| s.to_str()).unwrap_or("(file)")),
+            visibility: Visibility::PubCrate,
+            location: Location { file: node.path.clone(), line_start: 1, line_end: 1 },
+            attributes: vec![],
         };
 
+        // Prepend the file item
+        let mut items_with_file = Vec::with_capacity(node.items.len() + 1);
+        items_with_file.push(file_item);
+        items_with_file.extend(node.items);
+        node.metrics.item_count = items_with_file.len();
+        node.items = items_with_file;
+        Ok(node)
+    }
+
+    // Turn freshly parsed files under `root` into a graph with stable ids, containment edges
+    // and build metadata, ready for relationship analysis. Crate manifests are read through
+    // `provider`.
+    fn finish_parse(
+        &mut self,
+        root: &Path,
+        discovery: &file_walker::DiscoveryOptions,
+        provider: &dyn SourceProvider,
+    ) {
+        self.compute_module_segments();
+        self.crate_names = paths::read_crate_names(self, provider);
+
+        // Stable ids need module paths; the cache keeps the parser's provisional ids
+        paths::assign_item_ids(self, root);
+        self.add_file_contains_edges();
+
         // Precompute import segments and alias arcs per file with a shared Arc<str> pool
-        graph.import_segments = {
+        self.import_segments = {
             let mut pool: HashMap<String, Arc<str>> = HashMap::new();
             let mut intern = |s: &str| -> Arc<str> {
                 if let Some(a) = pool.get(s) {
@@ -329,8 +744,8 @@ impl KnowledgeGraph {
                 a
             };
             let mut map: HashMap<PathBuf, ImportSegments> =
-                HashMap::with_capacity(graph.files.len());
-            for (p, f) in &graph.files {
+                HashMap::with_capacity(self.files.len());
+            for (p, f) in &self.files {
                 if f.imports.is_empty() {
                     continue;
                 }
@@ -351,17 +766,112 @@ impl KnowledgeGraph {
         };
 
         // Set generation timestamp (seconds since epoch) without extra deps
-        graph.metadata.generated_at =
+        self.metadata.generated_at =
             match std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH) {
                 Ok(d) => format!("{}", d.as_secs()),
                 Err(_) => "0".to_string(),
             };
-        // Analyze relationships beyond file containment
-        graph.analyze_relationships();
+        self.metadata.options = BuildFingerprint::current(discovery.no_ignore);
+        self.metadata.root = Some(root.to_path_buf());
+    }
 
-        // Save cache (best-effort). Even in Ignore/Rebuild, we save freshly parsed state.
-        cache::save_cache(&root_dir, &cache_state);
-        Ok(graph)
+    // Replace item ids with path-based ids relative to `root` and point relationships at them.
+    fn reassign_item_ids(&mut self, root: &Path) {
+        let renamed = paths::assign_item_ids(self, root);
+        for rel in &mut self.relationships {
+            for id in [&mut rel.from_item, &mut rel.to_item] {
+                if let Some(new) = renamed.get(id) {
+                    id.clone_from(new);
+                }
+            }
+        }
+        self.invalidate_index();
+    }
+
+    // Module path segments for all files (relative to src/), so resolver and analyses
+    // avoid recomputing them. Mirrors logic in resolver::module_segments_for.
+    fn compute_module_segments(&mut self) {
+        let mut map: HashMap<PathBuf, Vec<String>> = HashMap::with_capacity(self.files.len());
+        for p in self.files.keys() {
+            // Find index of "src" in the path components
+            let comps: Vec<_> = p.components().collect();
+            let mut src_idx: Option<usize> = None;
+            for (i, c) in comps.iter().enumerate() {
+                if let std::path::Component::Normal(os) = c {
+                    if os.to_str() == Some("src") {
+                        src_idx = Some(i);
+                        break;
+                    }
+                }
+            }
+            let mut segs: Vec<String> = Vec::new();
+            if let Some(i) = src_idx {
+                // Directories after src up to (but excluding) the file name
+                for c in &comps[i + 1..comps.len().saturating_sub(1)] {
+                    if let std::path::Component::Normal(os) = c {
+                        if let Some(s) = os.to_str() {
+                            segs.push(s.to_string());
+                        }
+                    }
+                }
+                // File as module: include file stem unless mod.rs/lib.rs
+                if let Some(file_os) = p.file_name() {
+                    let file = file_os.to_string_lossy();
+                    if file != "mod.rs" && file != "lib.rs" {
+                        if let Some(stem) = p.file_stem().and_then(|s| s.to_str()) {
+                            segs.push(stem.to_string());
+                        }
+                    }
+                }
+            }
+            map.insert(p.clone(), segs);
+        }
+        self.module_segments = map;
+    }
+
+    // Contains edges from each file-level module item to the file's other items
+    fn add_file_contains_edges(&mut self) {
+        for node in self.files.values() {
+            let Some(file_item) = node.items.first() else { continue };
+            for it in node.items.iter().skip(1) {
+                self.relationships.push(Relationship {
+                    from_item: file_item.id.clone(),
+                    to_item: it.id.clone(),
+                    relationship_type: RelationshipType::Contains {
+                        containment_type: "file_contains".to_string(),
+                    },
+                    strength: 1.0,
+                    context: "auto".to_string(),
+                    confidence: Confidence::Exact,
+                    line: None,
+                    count: 1,
+                    evidence: Vec::new(),
+                });
+            }
+        }
+    }
+
+    /// Ids of items matching `raw`: the item with that exact id, or, for the line-based form
+    /// of earlier versions (`fn:new:10`), every item of that kind and name starting on that
+    /// line. Several results mean the legacy id is ambiguous across files.
+    #[must_use]
+    pub fn find_item_ids(&self, raw: &str) -> Vec<ItemId> {
+        let items = || self.files.values().flat_map(|f| f.items.iter());
+        if let Some(it) = items().find(|it| it.id.0 == raw) {
+            return vec![it.id.clone()];
+        }
+        let Some((kind, name, line)) = parse_legacy_id(raw) else { return Vec::new() };
+        let mut ids: Vec<ItemId> = items()
+            .filter(|it| it.location.line_start == line)
+            .filter(|it| paths::item_keyword(&it.item_type) == kind)
+            .filter(|it| match &it.item_type {
+                ItemType::Impl { type_name, .. } => &**type_name == name,
+                _ => &*it.name == name,
+            })
+            .map(|it| it.id.clone())
+            .collect();
+        ids.sort_by(|a, b| a.0.cmp(&b.0));
+        ids
     }
 
     // Module hierarchy helpers
@@ -377,45 +887,150 @@ impl KnowledgeGraph {
             None => &[],
         }
     }
-    /// Backward-compatible builder: reads env var `KNOWLEDGE_RS_NO_IGNORE` for ignore bypass.
+
+    /// Drop `Calls` relationships whose confidence score (`strength`) is below `min`.
+    ///
+    /// Structural edges (`Contains`, `Uses`, `Implements`, `Extends`) are read from the source
+    /// rather than guessed, so they are kept whatever their strength.
+    pub fn retain_min_confidence(&mut self, min: f64) {
+        if min > 0.0 {
+            self.relationships.retain(|r| {
+                !matches!(r.relationship_type, RelationshipType::Calls { .. }) || r.strength >= min
+            });
+            self.invalidate_index();
+        }
+    }
+
+    /// Node and adjacency index over the current items and relationships, built on first
+    /// call and reused by every query until [`Self::invalidate_index`].
+    pub fn index(&self) -> &index::GraphIndex {
+        self.index.get_or_init(|| index::GraphIndex::build(self))
+    }
+
+    /// Drop the cached index. Call after mutating `files` or `relationships` directly.
+    pub fn invalidate_index(&mut self) {
+        self.index = std::sync::OnceLock::new();
+    }
+
+    /// Move every file path from the recorded root (`metadata.root`) to `root`, e.g. to read
+    /// sources of a graph built on another machine from a local checkout.
+    pub fn rebase(&mut self, root: &Path) {
+        let old = self.metadata.root.clone().unwrap_or_default();
+        self.map_paths(|p| root.join(p.strip_prefix(&old).unwrap_or(p)));
+        self.metadata.root = Some(root.to_path_buf());
+    }
+
+    /// Path of a graph file given either as stored or relative to the recorded root.
+    #[must_use]
+    pub fn resolve_file<'a>(&self, path: &'a Path) -> std::borrow::Cow<'a, Path> {
+        if !self.files.contains_key(path) {
+            if let Some(root) = &self.metadata.root {
+                let joined = root.join(path);
+                if self.files.contains_key(&joined) {
+                    return joined.into();
+                }
+            }
+        }
+        path.into()
+    }
+
+    // Copy with file paths relative to the recorded root, as written to disk.
+    fn portable(&self) -> std::borrow::Cow<'_, Self> {
+        let Some(root) = self.metadata.root.clone() else {
+            return std::borrow::Cow::Borrowed(self);
+        };
+        let mut g = KnowledgeGraph {
+            files: self.files.clone(),
+            relationships: self.relationships.clone(),
+            metadata: self.metadata.clone(),
+            module_parent: self.module_parent.clone(),
+            module_children: self.module_children.clone(),
+            module_segments: self.module_segments.clone(),
+            ..KnowledgeGraph::default()
+        };
+        g.map_paths(|p| p.strip_prefix(&root).unwrap_or(p).to_path_buf());
+        std::borrow::Cow::Owned(g)
+    }
+
+    // Inverse of `portable` after loading: join relative paths onto the recorded root.
+    fn resolve_paths(&mut self) {
+        if let Some(root) = self.metadata.root.clone() {
+            self.map_paths(|p| root.join(p));
+        }
+    }
+
+    fn map_paths(&mut self, f: impl Fn(&Path) -> PathBuf) {
+        self.files = std::mem::take(&mut self.files)
+            .into_values()
+            .map(|mut node| {
+                node.path = f(&node.path);
+                for it in &mut node.items {
+                    it.location.file = f(&it.location.file);
+                }
+                (node.path.clone(), node)
+            })
+            .collect();
+        for ev in self.relationships.iter_mut().flat_map(|r| &mut r.evidence) {
+            ev.file = f(&ev.file);
+        }
+        self.module_parent =
+            std::mem::take(&mut self.module_parent).iter().map(|(c, p)| (f(c), f(p))).collect();
+        self.module_children = std::mem::take(&mut self.module_children)
+            .iter()
+            .map(|(p, kids)| (f(p), kids.iter().map(|k| f(k)).collect()))
+            .collect();
+        self.module_segments = std::mem::take(&mut self.module_segments)
+            .into_iter()
+            .map(|(p, segs)| (f(&p), segs))
+            .collect();
+        self.import_segments = std::mem::take(&mut self.import_segments)
+            .into_iter()
+            .map(|(p, segs)| (f(&p), segs))
+            .collect();
+        self.crate_names = std::mem::take(&mut self.crate_names)
+            .into_iter()
+            .map(|(p, name)| (f(&p), name))
+            .collect();
+        self.invalidate_index();
+    }
+
+    /// Build with an explicit cache mode, respecting ignore rules.
     ///
     /// # Errors
-    /// Returns `KnowledgeGraphError` when directory walking, file I/O, or parsing fails during build.
+    /// Returns the errors of [`GraphBuilder::build`].
+    #[deprecated(note = "use `graph::builder::GraphBuilder`")]
     pub fn build_from_directory_with_cache(
         path: &std::path::Path,
         mode: cache::CacheMode,
     ) -> Result<Self, crate::errors::KnowledgeGraphError> {
-        let no_ignore = std::env::var("KNOWLEDGE_RS_NO_IGNORE")
-            .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
-            .unwrap_or(false);
-        Self::build_from_directory_with_cache_opts(path, mode, no_ignore)
+        GraphBuilder::new(path).cache_mode(mode).build()
     }
 
-    /// Backward-compatible builder: `CacheMode::Use` and env-derived ignore bypass.
+    /// Build with `CacheMode::Use`, respecting ignore rules.
     ///
     /// # Errors
-    /// Returns `KnowledgeGraphError` on I/O or parsing failures during build.
+    /// Returns the errors of [`GraphBuilder::build`].
+    #[deprecated(note = "use `graph::builder::GraphBuilder`")]
     pub fn build_from_directory(
         path: &std::path::Path,
     ) -> Result<Self, crate::errors::KnowledgeGraphError> {
-        let no_ignore = std::env::var("KNOWLEDGE_RS_NO_IGNORE")
-            .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
-            .unwrap_or(false);
-        Self::build_from_directory_with_cache_opts(path, cache::CacheMode::Use, no_ignore)
+        GraphBuilder::new(path).build()
     }
 
-    /// Convenience builder: explicit ignore bypass with default `CacheMode::Use`.
+    /// Build with `CacheMode::Use` and explicit ignore bypass.
     ///
     /// # Errors
-    /// Returns `KnowledgeGraphError` on I/O or parsing failures during build.
+    /// Returns the errors of [`GraphBuilder::build`].
+    #[deprecated(note = "use `graph::builder::GraphBuilder`")]
     pub fn build_from_directory_opts(
         path: &std::path::Path,
         no_ignore: bool,
     ) -> Result<Self, crate::errors::KnowledgeGraphError> {
-        Self::build_from_directory_with_cache_opts(path, cache::CacheMode::Use, no_ignore)
+        GraphBuilder::new(path).no_ignore(no_ignore).build()
     }
 
-    /// Save the graph as pretty-printed JSON.
+    /// Save the graph as pretty-printed JSON. File paths are written relative to the recorded
+    /// root so the file can be loaded from another checkout.
     ///
     /// # Errors
     /// Returns `KnowledgeGraphError::Io` if serialization or writing the file fails.
@@ -423,7 +1038,7 @@ impl KnowledgeGraph {
         &self,
         path: &std::path::Path,
     ) -> Result<(), crate::errors::KnowledgeGraphError> {
-        let data = serde_json::to_string_pretty(self).map_err(|e| {
+        let data = serde_json::to_string_pretty(&*self.portable()).map_err(|e| {
             crate::errors::KnowledgeGraphError::Io(std::io::Error::other(e.to_string()))
         })?;
         std::fs::write(path, data)?;
@@ -432,22 +1047,80 @@ impl KnowledgeGraph {
 
     /// Load a graph from JSON file.
     ///
+    /// Graphs saved by older versions are migrated to the current schema on load, and
+    /// relative file paths are resolved against the recorded root (see [`Self::rebase`]).
+    ///
     /// # Errors
-    /// Returns `KnowledgeGraphError::Io` if reading the file fails or JSON is invalid.
+    /// Returns `KnowledgeGraphError::Io` if reading the file fails,
+    /// `KnowledgeGraphError::UnsupportedSchema` if a newer version wrote the graph, and
+    /// `KnowledgeGraphError::Format` if the JSON is not a graph.
     pub fn load_json(path: &std::path::Path) -> Result<Self, crate::errors::KnowledgeGraphError> {
         let data = std::fs::read_to_string(path)?;
-        let graph: KnowledgeGraph = serde_json::from_str(&data).map_err(|e| {
-            crate::errors::KnowledgeGraphError::Io(std::io::Error::other(e.to_string()))
-        })?;
+        // Check the version first: a newer layout may not deserialize at all
+        schema::check_json(&data)?;
+        let mut graph: KnowledgeGraph = serde_json::from_str(&data)
+            .map_err(|e| crate::errors::KnowledgeGraphError::Format(e.to_string()))?;
+        schema::migrate(&mut graph)?;
+        graph.resolve_paths();
         Ok(graph)
     }
 }
 
 impl KnowledgeGraph {
-    fn analyze_relationships(&mut self) {
-        self.analyze_module_hierarchy();
-        self.analyze_import_uses();
-        self.analyze_calls_heuristic();
+    fn analyze_relationships(
+        &mut self,
+        sources: &mut incremental::Sources,
+    ) -> Result<(), crate::errors::KnowledgeGraphError> {
+        self.analyze_incremental(&mut cache::Cache::default(), &[], sources).map(|_| ())
+    }
+
+    // Give each file-level module item the visibility of its `mod name;` declaration.
+    // `lib.rs` is the public crate root; files nobody declares keep `pub(crate)`.
+    // `declared` holds each file's `mod name;` declarations.
+    fn analyze_module_visibility(&mut self, declared: &HashMap<&Path, &[(Arc<str>, Visibility)]>) {
+        let mut updates: Vec<(PathBuf, Visibility)> = Vec::new();
+        for path in self.files.keys() {
+            let file_name = path.file_name().and_then(|s| s.to_str()).unwrap_or("");
+            let Some(dir) = path.parent() else { continue };
+            if dir.file_name().and_then(|s| s.to_str()) == Some("src") {
+                match file_name {
+                    "lib.rs" => {
+                        updates.push((path.clone(), Visibility::Public));
+                        continue;
+                    }
+                    "main.rs" => continue,
+                    _ => {}
+                }
+            }
+            // `a/mod.rs` is declared as `a` next to the `a` directory; `a.rs` as `a` beside it
+            let (name, decl_dir) = if file_name == "mod.rs" {
+                (dir.file_name().and_then(|s| s.to_str()), dir.parent())
+            } else {
+                (path.file_stem().and_then(|s| s.to_str()), Some(dir))
+            };
+            let (Some(name), Some(decl_dir)) = (name, decl_dir) else { continue };
+            let candidates = [
+                decl_dir.join("mod.rs"),
+                decl_dir.join("lib.rs"),
+                decl_dir.join("main.rs"),
+                decl_dir.with_extension("rs"),
+            ];
+            let vis = candidates.iter().find_map(|c| {
+                declared
+                    .get(c.as_path())?
+                    .iter()
+                    .find(|(n, _)| n.as_ref() == name)
+                    .map(|(_, v)| v.clone())
+            });
+            if let Some(vis) = vis {
+                updates.push((path.clone(), vis));
+            }
+        }
+        for (path, vis) in updates {
+            if let Some(it) = self.files.get_mut(&path).and_then(|f| f.items.first_mut()) {
+                it.visibility = vis;
+            }
+        }
     }
 
     // Establish module hierarchy using filesystem layout.
@@ -523,6 +1196,10 @@ impl KnowledgeGraph {
                         },
                         strength: 1.0,
                         context: "fs".to_string(),
+                        confidence: Confidence::Exact,
+                        line: None,
+                        count: 1,
+                        evidence: Vec::new(),
                     });
                     // Hierarchy maps
                     if let Some(pp) = id_to_path.get(&parent_id).cloned() {
@@ -535,162 +1212,418 @@ impl KnowledgeGraph {
     }
 
     fn analyze_import_uses(&mut self) {
-        // Build edges using a Resolver and parallelize over files with per-file batching
         let res = resolver::Resolver::new(self);
         let produced: Vec<Relationship> = self
             .files
             .par_iter()
-            .map(|(path, file)| {
-                let mut edges: Vec<Relationship> = Vec::with_capacity(file.imports.len());
-                if file.items.is_empty() {
-                    return edges;
+            .flat_map_iter(|(path, file)| Self::import_uses(&res, path, file))
+            .collect();
+        self.relationships.extend(produced);
+    }
+
+    // One `Uses` edge per import target of `path`; repeated imports of a target add evidence.
+    fn import_uses(res: &resolver::Resolver, path: &Path, file: &FileNode) -> Vec<Relationship> {
+        let mut edges: Vec<Relationship> = Vec::with_capacity(file.imports.len());
+        if file.items.is_empty() {
+            return edges;
+        }
+        let file_id = file.items[0].id.clone();
+        let mut seen: HashMap<ItemId, usize> = HashMap::new();
+        for imp in &file.imports {
+            let targets = res.resolve_import(path, &imp.path);
+            if targets.is_empty() {
+                continue;
+            }
+            let evidence = (imp.line > 0).then(|| Evidence {
+                file: path.to_path_buf(),
+                line: imp.line,
+                column: imp.column,
+                snippet: imp.statement(),
+            });
+            for to in targets {
+                if to == file_id {
+                    continue;
                 }
-                let file_id = file.items[0].id.clone();
-                for imp in &file.imports {
-                    let targets = res.resolve_import(path, &imp.path);
-                    if targets.is_empty() {
-                        continue;
-                    }
-                    for to in targets {
-                        if to == file_id {
-                            continue;
-                        }
-                        let import_type = if res.is_file_level_module(&to) {
-                            "import-module"
-                        } else {
-                            "import-item"
-                        };
-                        edges.push(Relationship {
-                            from_item: file_id.clone(),
-                            to_item: to,
-                            relationship_type: RelationshipType::Uses {
-                                import_type: import_type.to_string(),
-                            },
-                            strength: if import_type == "import-item" { 1.0 } else { 0.8 },
-                            context: imp.path.to_string(),
-                        });
-                    }
+                if let Some(&i) = seen.get(&to) {
+                    let e: &mut Relationship = &mut edges[i];
+                    e.count += 1;
+                    e.evidence.extend(evidence.clone());
+                    continue;
                 }
-                edges
+                let import_type =
+                    if res.is_file_level_module(&to) { "import-module" } else { "import-item" };
+                seen.insert(to.clone(), edges.len());
+                edges.push(Relationship {
+                    from_item: file_id.clone(),
+                    to_item: to,
+                    relationship_type: RelationshipType::Uses {
+                        import_type: import_type.to_string(),
+                    },
+                    strength: if import_type == "import-item" { 1.0 } else { 0.8 },
+                    context: imp.path.to_string(),
+                    confidence: Confidence::Exact,
+                    line: (imp.line > 0).then_some(imp.line),
+                    count: 1,
+                    evidence: evidence.clone().into_iter().collect(),
+                });
+            }
+        }
+        edges
+    }
+
+    // Link each trait method implementation to the trait's declaration of that method, so
+    // queries can follow a call on `dyn Trait` or `T: Trait` to every implementation.
+    fn analyze_trait_dispatch(&mut self, types: &infer::TypeIndex) {
+        let produced: Vec<Relationship> = types
+            .trait_impl_methods()
+            .flat_map(|(trait_key, method, impls)| {
+                // Edges name the trait as written; the key only decides which trait it is
+                let trait_name = trait_key.rsplit("::").next().unwrap_or(trait_key);
+                types.trait_methods_of(trait_key, method).iter().flat_map(move |decl| {
+                    impls.iter().map(move |imp| Relationship {
+                        from_item: imp.clone(),
+                        to_item: decl.clone(),
+                        relationship_type: RelationshipType::Implements {
+                            trait_name: trait_name.to_string(),
+                        },
+                        strength: 1.0,
+                        context: format!("{trait_name}::{method}"),
+                        confidence: Confidence::Exact,
+                        line: None,
+                        count: 1,
+                        evidence: Vec::new(),
+                    })
+                })
             })
-            .reduce(Vec::new, |mut a, mut b| {
-                a.append(&mut b);
-                a
-            });
+            .collect();
         self.relationships.extend(produced);
     }
+}
 
-    fn analyze_calls_heuristic(&mut self) {
-        // Regex for fully qualified paths like a::b::foo(...)
-        let path_call_re =
-            Regex::new(r"\b([A-Za-z_][A-Za-z0-9_]*(?:::[A-Za-z_][A-Za-z0-9_]*)+)\s*\(").unwrap();
-        // Regex for simple names: foo(...)
-        let simple_call_re = Regex::new(r"\b([A-Za-z_][A-Za-z0-9_]*)\s*\(").unwrap();
+// Names visible to bare calls in one file, used to rank same-name candidates.
+struct CallScope<'r, 'g> {
+    res: &'r resolver::Resolver<'g>,
+    path: &'r Path,
+    crate_root: Option<PathBuf>,
+    // Names bound by `use` statements (last segment or alias) -> resolved targets
+    imported: HashMap<&'r str, Vec<ItemId>>,
+    // Files whose items are brought in by glob imports
+    glob_files: Vec<PathBuf>,
+    reexported: &'r HashSet<ItemId>,
+}
 
-        // Build index of function name -> list of ItemIds that are functions
-        // First, estimate capacity
-        let mut func_count = 0usize;
-        for file in self.files.values() {
-            for item in &file.items {
-                if matches!(item.item_type, ItemType::Function { .. }) {
-                    func_count += 1;
-                }
+impl<'r, 'g> CallScope<'r, 'g> {
+    fn new(
+        res: &'r resolver::Resolver<'g>,
+        path: &'r Path,
+        file: &'r FileNode,
+        reexported: &'r HashSet<ItemId>,
+    ) -> Self {
+        let mut imported: HashMap<&str, Vec<ItemId>> = HashMap::new();
+        let mut glob_files = Vec::new();
+        for imp in &file.imports {
+            if let Some(module) = imp.path.strip_suffix("::*") {
+                glob_files.extend(
+                    res.resolve_import(path, module)
+                        .iter()
+                        .filter(|id| res.is_file_level_module(id))
+                        .filter_map(|id| res.item_path(id).cloned()),
+                );
+                continue;
             }
+            let bound = match imp.alias.as_deref() {
+                Some("_") => continue,
+                Some(alias) => alias,
+                None => imp.path.rsplit("::").next().unwrap_or(&imp.path),
+            };
+            imported.entry(bound).or_default().extend(res.resolve_import(path, &imp.path));
+        }
+        let crate_root = resolver::Resolver::base_src_dir(path).map(|(root, _)| root);
+        Self { res, path, crate_root, imported, glob_files, reexported }
+    }
+
+    // Keep only the candidates from the best scope that has any, with that scope's score.
+    fn rank<'c>(&self, name: &str, candidates: &[&'c ItemId]) -> (Vec<&'c ItemId>, f64) {
+        let file_of = |id: &ItemId| self.res.item_path(id).map(PathBuf::as_path);
+        let same_module: Vec<&ItemId> =
+            candidates.iter().copied().filter(|id| file_of(id) == Some(self.path)).collect();
+        if !same_module.is_empty() {
+            return (same_module, call_score::SAME_MODULE);
         }
-        let mut func_index: HashMap<String, Vec<ItemId>> = HashMap::with_capacity(func_count);
-        for file in self.files.values() {
+        let bound = self.imported.get(name);
+        let imported: Vec<&ItemId> = candidates
+            .iter()
+            .copied()
+            .filter(|id| {
+                bound.is_some_and(|b| b.contains(id))
+                    || file_of(id).is_some_and(|f| self.glob_files.iter().any(|g| g == f))
+            })
+            .collect();
+        if !imported.is_empty() {
+            return (imported, call_score::IMPORTED);
+        }
+        let reexported: Vec<&ItemId> =
+            candidates.iter().copied().filter(|id| self.reexported.contains(*id)).collect();
+        if !reexported.is_empty() {
+            return (reexported, call_score::REEXPORTED);
+        }
+        let same_crate: Vec<&ItemId> = candidates
+            .iter()
+            .copied()
+            .filter(|id| {
+                file_of(id).map(|f| resolver::Resolver::base_src_dir(f).map(|(root, _)| root))
+                    == Some(self.crate_root.clone())
+            })
+            .collect();
+        (same_crate, call_score::SAME_CRATE)
+    }
+}
+
+// Lookup tables shared by the call pass over every file.
+struct CallAnalyzer<'g> {
+    // Fully qualified paths like a::b::foo(...)
+    path_call_re: Regex,
+    // Simple names: foo(...)
+    simple_call_re: Regex,
+    // Function name -> ids of functions with that name
+    func_index: HashMap<String, Vec<ItemId>>,
+    res: resolver::Resolver<'g>,
+    // Items re-exported by a `pub use` anywhere in the graph
+    reexported: HashSet<ItemId>,
+    types: &'g infer::TypeIndex,
+    // The `method-calls` feature is enabled
+    method_calls: bool,
+}
+
+impl<'g> CallAnalyzer<'g> {
+    fn new(graph: &'g KnowledgeGraph, types: &'g infer::TypeIndex) -> Self {
+        let mut func_index: HashMap<String, Vec<ItemId>> = HashMap::new();
+        for file in graph.files.values() {
             for item in &file.items {
                 if let ItemType::Function { .. } = item.item_type {
                     func_index.entry(item.name.to_string()).or_default().push(item.id.clone());
                 }
             }
         }
-
-        // Build resolver once per analysis and process files in parallel with per-file batching
-        let res = resolver::Resolver::new(self);
-        let produced: Vec<Relationship> = self
+        let res = resolver::Resolver::new(graph);
+        let reexported: HashSet<ItemId> = graph
             .files
-            .par_iter()
-            .map(|(path, file)| {
-                let mut seen_local: std::collections::HashSet<(String, String)> =
-                    std::collections::HashSet::new();
-                let mut edges: Vec<Relationship> = Vec::with_capacity(16);
-                if file.items.is_empty() {
-                    return edges;
-                }
-                let file_id = file.items[0].id.clone();
-                if let Ok(content) = std::fs::read_to_string(path) {
-                    // 1) Resolve fully qualified calls via Resolver (more precise)
-                    for cap in path_call_re.captures_iter(&content) {
-                        let full = cap.get(1).map_or("", |m| m.as_str());
-                        let mut targets = res.resolve_import(path, full);
-                        if targets.is_empty() {
-                            if let Some(last) = full.rsplit("::").next() {
-                                if let Some(funcs) = func_index.get(last) {
-                                    targets.clone_from(funcs);
-                                }
-                            }
-                        }
-                        for to in targets {
-                            let key = (file_id.0.clone(), to.0.clone());
-                            if seen_local.insert(key) {
-                                edges.push(Relationship {
-                                    from_item: file_id.clone(),
-                                    to_item: to.clone(),
-                                    relationship_type: RelationshipType::Calls {
-                                        call_type: "path".to_string(),
-                                    },
-                                    strength: 0.7,
-                                    context: full.to_string(),
-                                });
-                            }
-                        }
-                    }
+            .iter()
+            .flat_map(|(path, file)| {
+                file.imports
+                    .iter()
+                    .filter(|imp| imp.is_pub)
+                    .flat_map(|imp| res.resolve_import(path, &imp.path))
+            })
+            .collect();
+        Self {
+            path_call_re: Regex::new(
+                r"\b([A-Za-z_][A-Za-z0-9_]*(?:::[A-Za-z_][A-Za-z0-9_]*)+)\s*\(",
+            )
+            .unwrap(),
+            simple_call_re: Regex::new(r"\b([A-Za-z_][A-Za-z0-9_]*)\s*\(").unwrap(),
+            func_index,
+            res,
+            reexported,
+            types,
+            method_calls: graph.metadata.options.has_feature(schema::METHOD_CALLS),
+        }
+    }
 
-                    // 2) Fallback simple name calls, filtering out definitions/macros and keywords
-                    for cap in simple_call_re.captures_iter(&content) {
-                        let Some(m) = cap.get(0) else { continue };
-                        let name = cap.get(1).map_or("", |m| m.as_str());
-                        let start = m.start();
-                        let prefix = &content[start.saturating_sub(8)..start];
-                        if prefix.contains("fn ")
-                            || prefix.contains("struct ")
-                            || prefix.contains("enum ")
-                            || prefix.contains("trait ")
-                        {
-                            continue;
-                        }
-                        if start > 0 {
-                            let prev = content[..start].chars().rev().find(|c| !c.is_whitespace());
-                            if let Some('!') = prev {
-                                continue;
-                            }
-                        }
-                        if let Some(targets) = func_index.get(name) {
-                            for to in targets {
-                                let key = (file_id.0.clone(), to.0.clone());
-                                if seen_local.insert(key) {
-                                    edges.push(Relationship {
-                                        from_item: file_id.clone(),
-                                        to_item: to.clone(),
-                                        relationship_type: RelationshipType::Calls {
-                                            call_type: "heuristic".to_string(),
-                                        },
-                                        strength: 0.5,
-                                        context: name.to_string(),
-                                    });
-                                }
-                            }
-                        }
+    // Call edges out of one file. `content` is masked text for matching; `raw` has the same
+    // offsets and supplies evidence snippets.
+    #[allow(clippy::too_many_lines)]
+    fn file_edges(
+        &self,
+        path: &Path,
+        file: &FileNode,
+        content: &str,
+        raw: &str,
+    ) -> Vec<Relationship> {
+        let mut calls = CallEdges::default();
+        if file.items.is_empty() {
+            return calls.edges;
+        }
+        let file_id = &file.items[0].id;
+        let scope = CallScope::new(&self.res, path, file, &self.reexported);
+        // Calls are attributed to the innermost enclosing function (closures belong to
+        // the function defining them); calls outside any body to the file itself
+        let text = if raw.len() == content.len() { raw } else { content };
+        let starts = infer::line_starts(content);
+        let evidence = |offset: usize| Evidence::at(path, text, &starts, offset);
+        let site = |offset: usize| CallSite {
+            from: self.types.enclosing_fn(path, offset).map_or(file_id, |f| &f.id).clone(),
+            evidence: evidence(offset),
+        };
+
+        // 0) Method calls `receiver.method(..)` via local type inference
+        let method_calls =
+            if self.method_calls { self.types.resolve_method_calls(path, content) } else { vec![] };
+        for call in method_calls {
+            let context = if call.receiver.is_empty() {
+                format!(".{}", call.method)
+            } else {
+                format!("{}.{}", call.receiver, call.method)
+            };
+            let site = CallSite { from: call.caller, evidence: evidence(call.offset) };
+            for to in call.targets {
+                calls.push(&site, to, "method", call.confidence, &context);
+            }
+        }
+
+        // 1) Fully qualified calls: `Type::assoc(..)` via the type index, others via Resolver
+        for cap in self.path_call_re.captures_iter(content) {
+            let Some(m) = cap.get(1) else { continue };
+            let full = m.as_str();
+            let segs: Vec<&str> = full.split("::").collect();
+            let last = segs[segs.len() - 1];
+            let ty_seg = segs[segs.len() - 2];
+            if ty_seg.chars().next().is_some_and(char::is_uppercase) {
+                let ty = if ty_seg == "Self" {
+                    match self.types.enclosing_fn(path, m.start()).and_then(|f| f.owner.clone()) {
+                        Some(o) => o.to_string(),
+                        None => continue,
                     }
+                } else {
+                    self.types.type_key(path, ty_seg).to_string()
+                };
+                // Associated functions of types we do not define (Vec::new, ..) are skipped
+                let targets = self.types.methods_of(&ty, last);
+                let confidence =
+                    if targets.len() == 1 { Confidence::Exact } else { Confidence::Inferred };
+                let site = site(m.start());
+                for to in targets {
+                    calls.push(&site, to.clone(), "path", confidence, full);
                 }
-                edges
-            })
-            .reduce(Vec::new, |mut a, mut b| {
-                a.append(&mut b);
-                a
-            });
-        self.relationships.extend(produced);
+                continue;
+            }
+            let mut targets = self.res.resolve_import(path, full);
+            let mut confidence =
+                if targets.len() == 1 { Confidence::Exact } else { Confidence::Inferred };
+            if targets.is_empty() {
+                if let Some(funcs) = self.func_index.get(last) {
+                    targets.clone_from(funcs);
+                    confidence = Confidence::NameOnly;
+                }
+            }
+            let site = site(m.start());
+            for to in targets {
+                calls.push(&site, to, "path", confidence, full);
+            }
+        }
+
+        // 2) Simple name calls, filtering out definitions/macros and keywords, resolved
+        //    against the narrowest scope that defines the name
+        for cap in self.simple_call_re.captures_iter(content) {
+            let Some(m) = cap.get(0) else { continue };
+            let name = cap.get(1).map_or("", |m| m.as_str());
+            let start = m.start();
+            let prefix = &content[start.saturating_sub(8)..start];
+            if prefix.contains("fn ")
+                || prefix.contains("struct ")
+                || prefix.contains("enum ")
+                || prefix.contains("trait ")
+            {
+                continue;
+            }
+            if start > 0 {
+                let prev = content[..start].chars().rev().find(|c| !c.is_whitespace());
+                // Macros, and method calls / path segments handled by the passes above
+                if let Some('!' | '.' | ':') = prev {
+                    continue;
+                }
+            }
+            let Some(candidates) = self.func_index.get(name) else { continue };
+            // A bare call cannot reach a method
+            let candidates: Vec<&ItemId> =
+                candidates.iter().filter(|id| !self.types.is_method(id)).collect();
+            let (targets, score) = scope.rank(name, &candidates);
+            let confidence = if score >= call_score::IMPORTED {
+                Confidence::Exact
+            } else if score >= call_score::REEXPORTED {
+                Confidence::Inferred
+            } else {
+                Confidence::NameOnly
+            };
+            let site = site(start);
+            for to in targets {
+                calls.push_scored(&site, to.clone(), "heuristic", confidence, score, name);
+            }
+        }
+        calls.edges
+    }
+}
+
+// Calling item and location of one call site.
+struct CallSite {
+    from: ItemId,
+    evidence: Evidence,
+}
+
+// Per-file call edge accumulator: one edge per (from, to), keeping the highest-scoring route
+// and counting distinct call sites.
+#[derive(Default)]
+struct CallEdges {
+    edges: Vec<Relationship>,
+    seen: HashMap<(ItemId, ItemId), usize>,
+}
+
+impl CallEdges {
+    fn push(
+        &mut self,
+        site: &CallSite,
+        to: ItemId,
+        call_type: &str,
+        confidence: Confidence,
+        context: &str,
+    ) {
+        let score = match confidence {
+            Confidence::Exact => call_score::EXACT,
+            Confidence::Inferred => call_score::INFERRED,
+            Confidence::NameOnly => call_score::NAME_ONLY,
+        };
+        self.push_scored(site, to, call_type, confidence, score, context);
+    }
+
+    fn push_scored(
+        &mut self,
+        site: &CallSite,
+        to: ItemId,
+        call_type: &str,
+        confidence: Confidence,
+        score: f64,
+        context: &str,
+    ) {
+        let key = (site.from.clone(), to);
+        let ev = &site.evidence;
+        if let Some(&i) = self.seen.get(&key) {
+            let e = &mut self.edges[i];
+            if score > e.strength {
+                e.relationship_type = RelationshipType::Calls { call_type: call_type.to_string() };
+                e.strength = score;
+                e.confidence = confidence;
+                e.context = context.to_string();
+                e.line = Some(ev.line);
+            }
+            // Passes can match the same site twice
+            if !e.evidence.iter().any(|x| x.line == ev.line && x.column == ev.column) {
+                e.count += 1;
+                e.evidence.push(ev.clone());
+            }
+            return;
+        }
+        self.seen.insert(key.clone(), self.edges.len());
+        self.edges.push(Relationship {
+            from_item: key.0,
+            to_item: key.1,
+            relationship_type: RelationshipType::Calls { call_type: call_type.to_string() },
+            strength: score,
+            context: context.to_string(),
+            confidence,
+            line: Some(ev.line),
+            count: 1,
+            evidence: vec![ev.clone()],
+        });
     }
 }
 
@@ -797,8 +1730,8 @@ mod tests {
             path: f1.clone(),
             items: vec![mk_file_item(&f1)],
             imports: vec![
-                Import { path: "foo".into(), alias: None },
-                Import { path: "modx".into(), alias: None },
+                Import { path: "foo".into(), alias: None, is_pub: false, line: 1, column: 1 },
+                Import { path: "modx".into(), alias: None, is_pub: false, line: 2, column: 1 },
             ],
             ..Default::default()
         };
@@ -901,7 +1834,7 @@ mod tests {
         g.files.insert(baz.clone(), baz_node);
 
         // Run call analysis
-        g.analyze_calls_heuristic();
+        g.analyze_relationships(&mut incremental::Sources::default()).unwrap();
 
         let caller_file_id = ItemId(format!("file:{}", caller.display()));
         let mut saw_foo = false;
@@ -928,4 +1861,268 @@ mod tests {
         assert!(saw_baz, "expected call edge to a::b::baz()");
         assert!(!saw_macro, "macro invocations must not create call edges");
     }
+
+    #[test]
+    fn method_calls_resolve_through_receiver_types() {
+        let td = tempdir().unwrap();
+        let src = td.path().join("src");
+        fs::create_dir_all(&src).unwrap();
+        fs::write(
+            src.join("lib.rs"),
+            "pub struct Engine;\nimpl Engine {\n    pub fn start(&self) {}\n}\npub struct Car;\nimpl Car {\n    pub fn start(&self) {}\n}\npub fn drive(e: &Engine) {\n    e.start();\n}\n",
+        )
+        .unwrap();
+        let g = GraphBuilder::new(td.path()).cache_mode(cache::CacheMode::Ignore).build().unwrap();
+        let method_edges: Vec<&Relationship> = g
+            .relationships
+            .iter()
+            .filter(|r| matches!(&r.relationship_type, RelationshipType::Calls { call_type } if call_type == "method"))
+            .collect();
+        // Only Engine::start is linked, not Car::start
+        assert_eq!(method_edges.len(), 1);
+        assert_eq!(method_edges[0].to_item.0, "fn:crate::Engine::start");
+        assert_eq!(method_edges[0].confidence, Confidence::Exact);
+        assert_eq!(method_edges[0].context, "e.start");
+    }
+
+    #[test]
+    fn calls_are_attributed_to_enclosing_functions() {
+        let td = tempdir().unwrap();
+        let src = td.path().join("src");
+        fs::create_dir_all(&src).unwrap();
+        fs::write(
+            src.join("lib.rs"),
+            "pub fn helper() {}\npub fn a() {\n    helper();\n}\npub struct S;\nimpl S {\n    pub fn m(&self) {\n        let f = || helper();\n        f();\n    }\n}\n",
+        )
+        .unwrap();
+        let g = GraphBuilder::new(td.path()).cache_mode(cache::CacheMode::Ignore).build().unwrap();
+        let mut calls: Vec<(&str, &str, Option<usize>)> = g
+            .relationships
+            .iter()
+            .filter(|r| matches!(r.relationship_type, RelationshipType::Calls { .. }))
+            .map(|r| (r.from_item.0.as_str(), r.to_item.0.as_str(), r.line))
+            .collect();
+        calls.sort_unstable();
+        // The closure's call belongs to the method defining it
+        assert_eq!(
+            calls,
+            vec![
+                ("fn:crate::S::m", "fn:crate::helper", Some(8)),
+                ("fn:crate::a", "fn:crate::helper", Some(3)),
+            ]
+        );
+    }
+
+    #[test]
+    fn evidence_points_at_the_site() {
+        let text = "fn a() {\n    héllo(\"x\"); helper();\n}\n";
+        let starts = infer::line_starts(text);
+        let offset = text.find("helper").unwrap();
+        let ev = Evidence::at(Path::new("src/lib.rs"), text, &starts, offset);
+        assert_eq!((ev.line, ev.column), (2, 17));
+        assert_eq!(ev.snippet, "héllo(\"x\"); helper();");
+    }
+
+    #[test]
+    fn bare_calls_prefer_narrowest_scope() {
+        let td = tempdir().unwrap();
+        let src = td.path().join("src");
+        fs::create_dir_all(&src).unwrap();
+        fs::write(
+            src.join("lib.rs"),
+            "mod a;\nmod b;\nmod c;\npub fn new() {}\nfn main() { new(); }\n",
+        )
+        .unwrap();
+        fs::write(src.join("a.rs"), "pub fn new() {}\npub fn run() {}\n").unwrap();
+        fs::write(src.join("b.rs"), "pub fn run() {}\n").unwrap();
+        fs::write(src.join("c.rs"), "use crate::a::run;\nfn go() { run(); new(); }\n").unwrap();
+        let mut g =
+            GraphBuilder::new(td.path()).cache_mode(cache::CacheMode::Ignore).build().unwrap();
+        let calls_from = |g: &KnowledgeGraph, file: &str| -> Vec<(PathBuf, f64)> {
+            let file_of = |id: &ItemId| {
+                g.files
+                    .values()
+                    .find(|f| f.items.iter().any(|it| it.id == *id))
+                    .map(|f| f.path.clone())
+                    .unwrap()
+            };
+            let mut out: Vec<(PathBuf, f64)> = g
+                .relationships
+                .iter()
+                .filter(|r| matches!(r.relationship_type, RelationshipType::Calls { .. }))
+                .filter(|r| file_of(&r.from_item) == src.join(file))
+                .map(|r| (file_of(&r.to_item), r.strength))
+                .collect();
+            out.sort_by(|a, b| a.0.cmp(&b.0).then(b.1.total_cmp(&a.1)));
+            out
+        };
+        // `new()` in lib.rs only links to its own `new`
+        assert_eq!(calls_from(&g, "lib.rs"), vec![(src.join("lib.rs"), call_score::SAME_MODULE)]);
+        // `run` is imported from a.rs; `new` is unscoped and falls back to the whole crate
+        assert_eq!(
+            calls_from(&g, "c.rs"),
+            vec![
+                (src.join("a.rs"), call_score::IMPORTED),
+                (src.join("a.rs"), call_score::SAME_CRATE),
+                (src.join("lib.rs"), call_score::SAME_CRATE),
+            ]
+        );
+        let structural = |g: &KnowledgeGraph| {
+            g.relationships
+                .iter()
+                .filter(|r| !matches!(r.relationship_type, RelationshipType::Calls { .. }))
+                .count()
+        };
+        let before = structural(&g);
+        g.retain_min_confidence(0.5);
+        assert_eq!(calls_from(&g, "c.rs"), vec![(src.join("a.rs"), call_score::IMPORTED)]);
+        // Imports and containment are never filtered, even above their strength
+        g.retain_min_confidence(0.99);
+        assert!(before > 0);
+        assert_eq!(structural(&g), before);
+    }
+
+    #[test]
+    fn trait_impl_methods_link_to_declarations() {
+        let td = tempdir().unwrap();
+        let src = td.path().join("src");
+        fs::create_dir_all(&src).unwrap();
+        fs::write(
+            src.join("lib.rs"),
+            "pub trait Shape {\n    fn area(&self) -> f64;\n}\npub struct Sq;\nimpl Shape for Sq {\n    fn area(&self) -> f64 { 1.0 }\n}\npub struct Circle;\nimpl Shape for Circle {\n    fn area(&self) -> f64 { 3.0 }\n}\npub fn total(s: &dyn Shape) -> f64 {\n    s.area()\n}\n",
+        )
+        .unwrap();
+        let g = GraphBuilder::new(td.path()).cache_mode(cache::CacheMode::Ignore).build().unwrap();
+        let mut implements: Vec<(&str, &str)> = g
+            .relationships
+            .iter()
+            .filter(|r| matches!(&r.relationship_type, RelationshipType::Implements { trait_name } if trait_name == "Shape"))
+            .map(|r| (r.from_item.0.as_str(), r.to_item.0.as_str()))
+            .collect();
+        implements.sort_unstable();
+        assert_eq!(
+            implements,
+            vec![
+                ("fn:crate::<Circle as Shape>::area", "fn:crate::Shape::area"),
+                ("fn:crate::<Sq as Shape>::area", "fn:crate::Shape::area"),
+            ]
+        );
+        // The call on `&dyn Shape` lands on the trait declaration
+        let call = g
+            .relationships
+            .iter()
+            .find(|r| matches!(&r.relationship_type, RelationshipType::Calls { call_type } if call_type == "method"))
+            .unwrap();
+        assert_eq!(call.to_item.0, "fn:crate::Shape::area");
+    }
+
+    #[test]
+    fn same_named_traits_and_types_in_sibling_modules_stay_apart() {
+        let td = tempdir().unwrap();
+        let src = td.path().join("src");
+        fs::create_dir_all(&src).unwrap();
+        fs::write(src.join("lib.rs"), "mod a;\nmod b;\nmod c;\n").unwrap();
+        let module = |ty: &str| {
+            format!(
+                "pub trait Render {{\n    fn draw(&self);\n}}\npub struct {ty};\nimpl Render for {ty} {{\n    fn draw(&self) {{}}\n}}\npub fn show(r: &dyn Render) {{\n    r.draw();\n}}\npub struct Node;\nimpl Node {{\n    pub fn go(&self) {{}}\n}}\npub fn run(n: Node) {{\n    n.go();\n}}\n"
+            )
+        };
+        fs::write(src.join("a.rs"), module("W")).unwrap();
+        fs::write(src.join("b.rs"), module("V")).unwrap();
+        fs::write(
+            src.join("c.rs"),
+            "use crate::b::Render;\npub struct X;\nimpl Render for X {\n    fn draw(&self) {}\n}\n",
+        )
+        .unwrap();
+        let g = GraphBuilder::new(td.path()).cache_mode(cache::CacheMode::Ignore).build().unwrap();
+        let edges = |pick: fn(&RelationshipType) -> bool| {
+            let mut v: Vec<(&str, &str)> = g
+                .relationships
+                .iter()
+                .filter(|r| pick(&r.relationship_type))
+                .filter(|r| r.from_item.0.starts_with("fn:"))
+                .map(|r| (r.from_item.0.as_str(), r.to_item.0.as_str()))
+                .collect();
+            v.sort_unstable();
+            v
+        };
+        assert_eq!(
+            edges(|t| matches!(t, RelationshipType::Implements { .. })),
+            vec![
+                ("fn:crate::a::<W as Render>::draw", "fn:crate::a::Render::draw"),
+                ("fn:crate::b::<V as Render>::draw", "fn:crate::b::Render::draw"),
+                ("fn:crate::c::<X as Render>::draw", "fn:crate::b::Render::draw"),
+            ]
+        );
+        assert_eq!(
+            edges(|t| matches!(t, RelationshipType::Calls { .. })),
+            vec![
+                ("fn:crate::a::run", "fn:crate::a::Node::go"),
+                ("fn:crate::a::show", "fn:crate::a::Render::draw"),
+                ("fn:crate::b::run", "fn:crate::b::Node::go"),
+                ("fn:crate::b::show", "fn:crate::b::Render::draw"),
+            ]
+        );
+    }
+
+    #[test]
+    fn item_ids_are_path_based_and_unique() {
+        let td = tempdir().unwrap();
+        let src = td.path().join("src");
+        fs::create_dir_all(&src).unwrap();
+        fs::write(src.join("lib.rs"), "mod a;\nmod b;\n").unwrap();
+        // Same name on the same line in two files
+        fs::write(src.join("a.rs"), "pub struct S;\nimpl S {\n    pub fn new() -> S { S }\n}\n")
+            .unwrap();
+        fs::write(
+            src.join("b.rs"),
+            "pub struct S;\nimpl S {\n    pub fn new() -> S { S }\n}\n#[cfg(unix)]\nfn os() {}\n#[cfg(windows)]\nfn os() {}\n",
+        )
+        .unwrap();
+        let build =
+            || GraphBuilder::new(td.path()).cache_mode(cache::CacheMode::Ignore).build().unwrap();
+        let ids = |g: &KnowledgeGraph| -> Vec<String> {
+            let mut v: Vec<String> = g
+                .files
+                .values()
+                .flat_map(|f| f.items.iter().skip(1))
+                .map(|it| it.id.0.clone())
+                .collect();
+            v.sort();
+            v
+        };
+        let g = build();
+        assert_eq!(
+            ids(&g),
+            vec![
+                "fn:crate::a::S::new",
+                "fn:crate::b::S::new",
+                "fn:crate::b::os",
+                "fn:crate::b::os#2",
+                "impl:crate::a::S",
+                "impl:crate::b::S",
+                "struct:crate::a::S",
+                "struct:crate::b::S",
+            ]
+        );
+        // Legacy line-based ids are still accepted, and report every match
+        assert_eq!(
+            g.find_item_ids("fn:crate::b::S::new"),
+            vec![ItemId("fn:crate::b::S::new".into())]
+        );
+        assert_eq!(
+            g.find_item_ids("fn:new:3"),
+            vec![ItemId("fn:crate::a::S::new".into()), ItemId("fn:crate::b::S::new".into())]
+        );
+        assert_eq!(g.find_item_ids("fn:os:8"), vec![ItemId("fn:crate::b::os#2".into())]);
+        assert!(g.find_item_ids("fn:new:4").is_empty());
+
+        // Inserting lines above items leaves their ids unchanged
+        fs::write(
+            src.join("a.rs"),
+            "// header\n\npub struct S;\nimpl S {\n    pub fn new() -> S { S }\n}\n",
+        )
+        .unwrap();
+        assert_eq!(ids(&build()), ids(&g));
+    }
 }
@@ -0,0 +1,441 @@
+//! Canonical and public paths of items.
+//!
+//! Every nameable item gets a canonical path built from its crate name, the module path of
+//! its file (relative to `src/`), enclosing inline modules, traits or inherent impls, and its
+//! own name, e.g. `rust_relations_explorer::query::Query::run`. Items that are reachable from
+//! outside the crate also get their public paths: the canonical path when the item and every
+//! enclosing module are `pub`, plus every path a `pub use` in a public module exposes it under.
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+use crate::graph::resolver::Resolver;
+use crate::graph::{Item, ItemId, ItemType, KnowledgeGraph, Visibility};
+use crate::source::{FsSource, SourceProvider};
+
+/// Paths under which an item can be named.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ItemPaths {
+    /// Definition path, whether or not it is reachable from outside the crate.
+    pub canonical: String,
+    /// Sorted paths usable from other crates (canonical path and `pub use` re-exports).
+    pub public: Vec<String>,
+}
+
+/// Compute canonical and public paths for every nameable item in the graph.
+///
+/// Impl blocks and items nested in function bodies have no path and are omitted.
+#[must_use]
+pub fn item_paths(graph: &KnowledgeGraph) -> HashMap<ItemId, ItemPaths> {
+    let mut crates = CrateNames::new(graph);
+    // (src dir, module segments) -> file, to check that every enclosing module is public
+    let mut module_files: HashMap<(PathBuf, Vec<String>), &PathBuf> = HashMap::new();
+    for path in graph.files.keys().filter(|p| !is_bin_root(p)) {
+        if let Some(src) = src_dir(path) {
+            module_files.insert((src, module_segments(graph, path)), path);
+        }
+    }
+    // A binary has no public API; modules it declares are not reachable either
+    let module_is_public = |path: &Path| -> bool {
+        let Some(src) = src_dir(path) else { return false };
+        if is_bin_root(path) {
+            return false;
+        }
+        let segs = module_segments(graph, path);
+        (0..=segs.len()).all(|n| {
+            module_files.get(&(src.clone(), segs[..n].to_vec())).is_some_and(|f| {
+                graph.files[*f].items.first().is_some_and(|it| is_pub(&it.visibility))
+            })
+        })
+    };
+
+    let mut out: HashMap<ItemId, (String, BTreeSet<String>)> = HashMap::new();
+    // Public module path per file, for re-exports below
+    let mut public_modules: HashMap<&PathBuf, String> = HashMap::new();
+    for (path, file) in &graph.files {
+        let Some(src) = src_dir(path) else { continue };
+        let mut module = vec![crates.name(&src)];
+        module.extend(module_segments(graph, path));
+        let module_public = module_is_public(path);
+        if module_public {
+            public_modules.insert(path, module.join("::"));
+        }
+        for (idx, it) in file.items.iter().enumerate() {
+            let local =
+                if idx == 0 { Some((Vec::new(), true)) } else { local_path(&file.items, it) };
+            let Some((segs, visible)) = local else { continue };
+            let mut full = module.clone();
+            full.extend(segs);
+            let canonical = full.join("::");
+            let mut public = BTreeSet::new();
+            if module_public && visible {
+                public.insert(canonical.clone());
+            }
+            out.insert(it.id.clone(), (canonical, public));
+        }
+    }
+
+    // `pub use` re-exports inside public modules
+    let res = Resolver::new(graph);
+    for (path, file) in &graph.files {
+        let Some(prefix) = public_modules.get(path) else { continue };
+        for imp in file.imports.iter().filter(|imp| imp.is_pub) {
+            let glob = imp.path.strip_suffix("::*");
+            let bound = match (glob, imp.alias.as_deref()) {
+                (Some(_), _) | (None, Some("_")) => None,
+                (None, Some(alias)) => Some(alias),
+                (None, None) => imp.path.rsplit("::").next(),
+            };
+            for target in res.resolve_import(path, glob.unwrap_or(&imp.path)) {
+                let is_module = res.is_file_level_module(&target);
+                // A re-exported module also exposes its public top-level items
+                let base = match bound {
+                    Some(b) => format!("{prefix}::{b}"),
+                    None if is_module => prefix.clone(),
+                    None => continue,
+                };
+                if bound.is_some() {
+                    if let Some((_, public)) = out.get_mut(&target) {
+                        public.insert(base.clone());
+                    }
+                }
+                if !is_module {
+                    continue;
+                }
+                let Some(module_file) = res.item_path(&target).and_then(|p| graph.files.get(p))
+                else {
+                    continue;
+                };
+                for it in module_file.items.iter().skip(1) {
+                    if !matches!(local_path(&module_file.items, it), Some((ref s, true)) if s.len() == 1)
+                    {
+                        continue;
+                    }
+                    if let Some((_, public)) = out.get_mut(&it.id) {
+                        public.insert(format!("{base}::{}", it.name));
+                    }
+                }
+            }
+        }
+    }
+
+    out.into_iter()
+        .map(|(id, (canonical, public))| {
+            (id, ItemPaths { canonical, public: public.into_iter().collect() })
+        })
+        .collect()
+}
+
+/// Rust keyword describing an item kind, as used in public API listings (`fn`, `struct`, ..).
+#[must_use]
+pub fn item_keyword(item_type: &ItemType) -> &'static str {
+    match item_type {
+        ItemType::Module { .. } => "mod",
+        ItemType::Function { .. } => "fn",
+        ItemType::Struct { .. } => "struct",
+        ItemType::Enum { .. } => "enum",
+        ItemType::Trait { .. } => "trait",
+        ItemType::Impl { .. } => "impl",
+        ItemType::Const => "const",
+        ItemType::Static { .. } => "static",
+        ItemType::Type => "type",
+        ItemType::Macro => "macro",
+    }
+}
+
+fn is_pub(vis: &Visibility) -> bool {
+    matches!(vis, Visibility::Public)
+}
+
+// Path of `it` inside its file and whether each step is visible outside the module.
+// None for impl blocks and items nested in function bodies.
+fn local_path(items: &[Item], it: &Item) -> Option<(Vec<String>, bool)> {
+    if matches!(it.item_type, ItemType::Impl { .. }) {
+        return None;
+    }
+    let parent = enclosing(items, it);
+    let own = it.name.to_string();
+    let parent = match parent {
+        Some(p) => p,
+        None => return Some((vec![own], is_pub(&it.visibility))),
+    };
+    match &parent.item_type {
+        ItemType::Function { .. } => None,
+        // Trait items share the trait's visibility
+        ItemType::Trait { .. } => local_path(items, parent).map(|(mut segs, vis)| {
+            segs.push(own);
+            (segs, vis)
+        }),
+        ItemType::Impl { trait_name, type_name } => {
+            let (mut segs, _) = local_path_of_container(items, parent);
+            segs.push(type_name.to_string());
+            segs.push(own);
+            // Trait impl items are reached through the trait, not the type
+            Some((segs, trait_name.is_none() && is_pub(&it.visibility)))
+        }
+        _ => local_path(items, parent).map(|(mut segs, vis)| {
+            segs.push(own);
+            (segs, vis && is_pub(&it.visibility))
+        }),
+    }
+}
+
+// Innermost item (other than the file itself) whose span strictly contains `it`.
+fn enclosing<'a>(items: &'a [Item], it: &Item) -> Option<&'a Item> {
+    let (s, e) = (it.location.line_start, it.location.line_end);
+    items
+        .iter()
+        .skip(1)
+        .filter(|p| p.id != it.id)
+        .filter(|p| {
+            let (ps, pe) = (p.location.line_start, p.location.line_end);
+            ps <= s && e <= pe && (ps, pe) != (s, e)
+        })
+        .filter(|p| {
+            matches!(
+                p.item_type,
+                ItemType::Module { .. }
+                    | ItemType::Trait { .. }
+                    | ItemType::Impl { .. }
+                    | ItemType::Function { .. }
+            )
+        })
+        .min_by_key(|p| p.location.line_end - p.location.line_start)
+}
+
+/// Replace the parser's provisional ids with stable, path-based ones.
+///
+/// An id is `{kind}:{module path}::{container}::{name}`, e.g.
+/// `fn:crate::graph::resolver::Resolver::new`. The module path starts with `crate` for the
+/// crate directly under `root`, with the crate name for other crates, and with the file path
+/// relative to `root` for files outside `src/` (`tests::cli`). Impl blocks are named after
+/// their type (`impl:crate::a::Foo`, `impl:crate::a::<Foo as Display>`) and their items hang
+/// off that name. True duplicates (e.g. `#[cfg]` variants) get `#2`, `#3`, .. in file and
+/// line order. File-level items are `file:{path}` with the path relative to `root`, so ids
+/// match across checkouts. Returns the previous id of each renamed item mapped to its new id.
+pub(crate) fn assign_item_ids(graph: &mut KnowledgeGraph, root: &Path) -> HashMap<ItemId, ItemId> {
+    let mut renamed: HashMap<ItemId, ItemId> = HashMap::new();
+    for (path, idx, id) in item_ids(graph, root) {
+        if let Some(it) = graph.files.get_mut(&path).and_then(|f| f.items.get_mut(idx)) {
+            let old = std::mem::replace(&mut it.id, id.clone());
+            renamed.entry(old).or_insert(id);
+        }
+    }
+    renamed
+}
+
+// The ids [`assign_item_ids`] would give, as (file, item index, id), without changing the
+// graph.
+pub(crate) fn item_ids(graph: &KnowledgeGraph, root: &Path) -> Vec<(PathBuf, usize, ItemId)> {
+    let mut crates = CrateNames::new(graph);
+    let mut paths: Vec<&PathBuf> = graph.files.keys().collect();
+    paths.sort();
+    // (file, item index, base id) in file and line order
+    let mut assigned: Vec<(PathBuf, usize, String)> = Vec::new();
+    for path in paths {
+        let module = id_module(graph, &mut crates, root, path);
+        let items = &graph.files[path].items;
+        if items.first().is_some_and(|f| f.id.0.starts_with("file:")) {
+            assigned.push((path.clone(), 0, file_item_id(root, path)));
+        }
+        let mut order: Vec<usize> = (1..items.len()).collect();
+        order.sort_by_key(|&i| (items[i].location.line_start, i));
+        for i in order {
+            let mut segs = module.clone();
+            segs.extend(id_segments(items, &items[i]));
+            let id = format!("{}:{}", item_keyword(&items[i].item_type), segs.join("::"));
+            assigned.push((path.clone(), i, id));
+        }
+    }
+
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    assigned
+        .into_iter()
+        .map(|(path, idx, base)| {
+            let n = seen.entry(base.clone()).or_insert(0);
+            *n += 1;
+            let id = if *n == 1 { ItemId(base) } else { ItemId(format!("{base}#{n}")) };
+            (path, idx, id)
+        })
+        .collect()
+}
+
+// Leading id segments for items of `path`.
+pub(crate) fn id_module(
+    graph: &KnowledgeGraph,
+    crates: &mut CrateNames<'_>,
+    root: &Path,
+    path: &Path,
+) -> Vec<String> {
+    if let Some(src) = src_dir(path) {
+        let first = if src.parent() == Some(root) || src == root.join("src") {
+            "crate".to_string()
+        } else {
+            crates.name(&src)
+        };
+        let mut segs = vec![first];
+        segs.extend(module_segments(graph, path));
+        return segs;
+    }
+    let rel = path.strip_prefix(root).unwrap_or(path).with_extension("");
+    let mut segs: Vec<String> = rel
+        .components()
+        .filter_map(|c| match c {
+            std::path::Component::Normal(os) => os.to_str().map(str::to_string),
+            _ => None,
+        })
+        .collect();
+    if segs.len() > 1 && segs.last().is_some_and(|s| s == "mod") {
+        segs.pop();
+    }
+    segs
+}
+
+// `file:src/graph/mod.rs`: root-relative, `/`-separated on every platform.
+pub(crate) fn file_item_id(root: &Path, path: &Path) -> String {
+    let Ok(rel) = path.strip_prefix(root) else {
+        return format!("file:{}", path.display());
+    };
+    let segs: Vec<&str> = rel
+        .components()
+        .filter_map(|c| match c {
+            std::path::Component::Normal(os) => os.to_str(),
+            _ => None,
+        })
+        .collect();
+    format!("file:{}", segs.join("/"))
+}
+
+// Container path of `it` inside its file, ending with its own segment.
+fn id_segments(items: &[Item], it: &Item) -> Vec<String> {
+    let mut segs = enclosing(items, it).map(|p| id_segments(items, p)).unwrap_or_default();
+    segs.push(match &it.item_type {
+        ItemType::Impl { trait_name: Some(t), type_name } => format!("<{type_name} as {t}>"),
+        ItemType::Impl { trait_name: None, type_name } => type_name.to_string(),
+        _ => it.name.to_string(),
+    });
+    segs
+}
+
+// Module path enclosing an impl block (impls are not nameable themselves).
+fn local_path_of_container(items: &[Item], imp: &Item) -> (Vec<String>, bool) {
+    let (s, e) = (imp.location.line_start, imp.location.line_end);
+    items
+        .iter()
+        .skip(1)
+        .filter(|p| matches!(p.item_type, ItemType::Module { is_inline: true }))
+        .filter(|p| p.location.line_start <= s && e <= p.location.line_end)
+        .min_by_key(|p| p.location.line_end - p.location.line_start)
+        .and_then(|m| local_path(items, m))
+        .unwrap_or((Vec::new(), true))
+}
+
+// Module segments of a file relative to `src/`; a binary's `main.rs` is a crate root.
+fn module_segments(graph: &KnowledgeGraph, path: &Path) -> Vec<String> {
+    if is_bin_root(path) {
+        return Vec::new();
+    }
+    graph.module_segments.get(path).cloned().unwrap_or_default()
+}
+
+fn is_bin_root(path: &Path) -> bool {
+    path.file_name().and_then(|s| s.to_str()) == Some("main.rs")
+        && path.parent().and_then(Path::file_name).and_then(|s| s.to_str()) == Some("src")
+}
+
+fn src_dir(path: &Path) -> Option<PathBuf> {
+    Resolver::base_src_dir(path).map(|(src, _)| src)
+}
+
+// Crate names per `src/` directory: the ones the build read through its source provider
+// (`KnowledgeGraph::crate_names`), else read from the sibling `Cargo.toml` on disk, as for
+// a graph loaded from a file.
+pub(crate) struct CrateNames<'g> {
+    built: &'g HashMap<PathBuf, String>,
+    read: HashMap<PathBuf, String>,
+}
+
+impl<'g> CrateNames<'g> {
+    pub(crate) fn new(graph: &'g KnowledgeGraph) -> Self {
+        Self { built: &graph.crate_names, read: HashMap::new() }
+    }
+
+    pub(crate) fn name(&mut self, src: &Path) -> String {
+        if let Some(name) = self.built.get(src) {
+            return name.clone();
+        }
+        self.read.entry(src.to_path_buf()).or_insert_with(|| crate_name(&FsSource, src)).clone()
+    }
+}
+
+// Names of the crates with files in `graph`, per `src/` directory, with their manifests read
+// through `provider`.
+pub(crate) fn read_crate_names(
+    graph: &KnowledgeGraph,
+    provider: &dyn SourceProvider,
+) -> HashMap<PathBuf, String> {
+    let dirs: BTreeSet<PathBuf> = graph.files.keys().filter_map(|p| src_dir(p)).collect();
+    dirs.into_iter()
+        .map(|src| {
+            let name = crate_name(provider, &src);
+            (src, name)
+        })
+        .collect()
+}
+
+// Package name in the `Cargo.toml` next to `src`, else the directory name; `-` becomes `_`.
+fn crate_name(provider: &dyn SourceProvider, src: &Path) -> String {
+    let root = src.parent().unwrap_or(src);
+    let from_manifest = provider
+        .read(&root.join("Cargo.toml"))
+        .ok()
+        .and_then(|s| s.parse::<toml::Table>().ok())
+        .and_then(|t| t.get("package")?.get("name")?.as_str().map(str::to_string));
+    let name = from_manifest.unwrap_or_else(|| {
+        root.file_name().and_then(|s| s.to_str()).unwrap_or("crate").to_string()
+    });
+    name.replace('-', "_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::builder::GraphBuilder;
+    use crate::utils::cache::CacheMode;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn canonical_and_reexported_paths() {
+        let td = tempdir().unwrap();
+        let src = td.path().join("src");
+        fs::create_dir_all(&src).unwrap();
+        fs::write(td.path().join("Cargo.toml"), "[package]\nname = \"demo-crate\"\n").unwrap();
+        fs::write(
+            src.join("lib.rs"),
+            "pub mod query;\nmod internal;\npub use internal::Engine;\npub use query::*;\n",
+        )
+        .unwrap();
+        fs::write(
+            src.join("query.rs"),
+            "pub trait Query {\n    fn run(&self);\n}\npub struct Hubs;\nimpl Hubs {\n    pub fn new() -> Self { Hubs }\n    fn hidden(&self) {}\n}\nfn private_helper() {}\n",
+        )
+        .unwrap();
+        fs::write(src.join("internal.rs"), "pub struct Engine;\n").unwrap();
+        let g = GraphBuilder::new(td.path()).cache_mode(CacheMode::Ignore).build().unwrap();
+        let paths = item_paths(&g);
+        let of = |id: &str| paths.get(&ItemId(id.to_string())).cloned().unwrap_or_default();
+
+        assert_eq!(of("fn:crate::query::Query::run").canonical, "demo_crate::query::Query::run");
+        assert_eq!(of("fn:crate::query::Query::run").public, vec!["demo_crate::query::Query::run"]);
+        assert_eq!(
+            of("trait:crate::query::Query").public,
+            vec!["demo_crate::Query", "demo_crate::query::Query"]
+        );
+        assert_eq!(of("fn:crate::query::Hubs::new").public, vec!["demo_crate::query::Hubs::new"]);
+        assert!(of("fn:crate::query::Hubs::hidden").public.is_empty());
+        assert!(of("fn:crate::query::private_helper").public.is_empty());
+        // Defined in a private module, public only through the re-export
+        assert_eq!(of("struct:crate::internal::Engine").canonical, "demo_crate::internal::Engine");
+        assert_eq!(of("struct:crate::internal::Engine").public, vec!["demo_crate::Engine"]);
+    }
+}
@@ -16,6 +16,8 @@ pub struct Resolver<'a> {
     alias_map: HashMap<Arc<str>, Vec<Arc<str>>>,
     // per-file exposure of names via non-aliased re-exports: exposed name -> fully-qualified target segments
     exposure_map: HashMap<PathBuf, HashMap<Arc<str>, Vec<Arc<str>>>>,
+    // library crate name -> its src/ directory, for imports of other crates in the graph
+    crates: HashMap<String, PathBuf>,
 }
 
 impl Resolver<'_> {
@@ -184,7 +186,16 @@ impl<'a> Resolver<'a> {
                 }
             }
         }
-        Self { graph, name_index, module_index, item_to_file, alias_map, exposure_map }
+        let mut names = crate::graph::paths::CrateNames::new(graph);
+        let crates: HashMap<String, PathBuf> = graph
+            .files
+            .keys()
+            .filter(|p| p.file_name().and_then(|s| s.to_str()) == Some("lib.rs"))
+            .filter_map(|p| p.parent())
+            .filter(|dir| dir.file_name().and_then(|s| s.to_str()) == Some("src"))
+            .map(|src| (names.name(src), src.to_path_buf()))
+            .collect();
+        Self { graph, name_index, module_index, item_to_file, alias_map, exposure_map, crates }
     }
 
     // Resolve an import path relative to a given file.
@@ -250,6 +261,9 @@ impl<'a> Resolver<'a> {
         if let Some(ids) = self.resolve_scoped_chain(from_file, &scope, &parts_str) {
             return ids;
         }
+        if let Some(ids) = self.resolve_in_crate(from_file, &parts_str) {
+            return ids;
+        }
 
         // Fallback: Try exact item name match on the last segment
         let Some(last) = parts.last() else {
@@ -334,7 +348,10 @@ impl<'a> Resolver<'a> {
             dir.push(seg);
         }
         // Walk all segments except the last as module directories/files
+        // Leaf module file (`<seg>.rs`) reached by the last walked segment, if any
+        let mut leaf_file: Option<PathBuf> = None;
         for seg in &parts[..parts.len().saturating_sub(1)] {
+            leaf_file = None;
             // Try directory seg
             dir.push(seg);
             // Accept if there is either dir/mod.rs or dir/lib.rs in graph
@@ -348,6 +365,7 @@ impl<'a> Resolver<'a> {
                 if self.graph.files.contains_key(&file_rs) {
                     // Now move into that file's dir scope for next segments
                     dir.push(seg);
+                    leaf_file = Some(file_rs);
                 } else {
                     return None;
                 }
@@ -373,10 +391,10 @@ impl<'a> Resolver<'a> {
                 return Some(vec![mid.clone()]);
             }
         }
-        // Next, try dir/mod.rs or dir/lib.rs containing an item named `last`
+        // Next, try the module file itself (<seg>.rs, dir/mod.rs or dir/lib.rs) containing an item named `last`
         let mod_path = dir.join("mod.rs");
         let lib_path = dir.join("lib.rs");
-        for cand in [mod_path, lib_path] {
+        for cand in leaf_file.into_iter().chain([mod_path, lib_path]) {
             if let Some(fnode) = self.graph.files.get(&cand) {
                 let mut ids: Vec<ItemId> = Vec::with_capacity(fnode.items.len());
                 for it in &fnode.items {
@@ -392,8 +410,22 @@ impl<'a> Resolver<'a> {
         None
     }
 
+    // `other_crate::a::X` where `other_crate` is another library crate in the graph.
+    fn resolve_in_crate(&self, from_file: &Path, parts: &[&str]) -> Option<Vec<ItemId>> {
+        let src = self.crates.get(*parts.first()?)?;
+        if Self::base_src_dir(from_file).is_some_and(|(own, _)| &own == src) {
+            return None;
+        }
+        let lib = src.join("lib.rs");
+        if parts.len() == 1 {
+            let file = self.graph.files.get(&lib)?;
+            return file.items.first().map(|it| vec![it.id.clone()]);
+        }
+        self.resolve_scoped_chain(&lib, &[], &parts[1..])
+    }
+
     // Returns (base_src_dir, index_of_src_component) if src is found in the path
-    fn base_src_dir(path: &Path) -> Option<(PathBuf, usize)> {
+    pub(crate) fn base_src_dir(path: &Path) -> Option<(PathBuf, usize)> {
         let comps: Vec<_> = path.components().collect();
         let mut src_idx: Option<usize> = None;
         for (i, c) in comps.iter().enumerate() {
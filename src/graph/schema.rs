@@ -0,0 +1,257 @@
+//! Graph schema versions and migration of graphs saved by older versions.
+//!
+//! Every saved graph records `GraphMetadata::schema_version`. Loading checks it: graphs from a
+//! newer schema are rejected with `KnowledgeGraphError::UnsupportedSchema`, and known older
+//! shapes are migrated in place, one version at a time, so queries see the current layout.
+//! Each change to the saved layout bumps [`SCHEMA_VERSION`] and adds a step to [`migrate`].
+//!
+//! | version | written by  | changes |
+//! |---------|-------------|---------|
+//! | 0       | 0.1.x       | unversioned; ids are `kind:name:line`, paths as built |
+//! | 1       | after 0.1.3 | path-based ids, function-level calls, build metadata |
+//! | 2       | after 0.1.3 | root-relative paths; `file:` ids relative to the root |
+//! | 3       | after 0.1.3 | one edge per target with site `count` and `evidence`; import positions |
+//! | 4       | after 0.1.3 | optional build `stats` |
+//!
+//! The build fingerprint (`GraphMetadata::options`) is not part of the layout; compare it with
+//! [`check_fingerprint`] to find graphs that another build would not reproduce.
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::errors::KnowledgeGraphError;
+use crate::graph::resolver::Resolver;
+use crate::graph::{
+    paths, BuildFingerprint, Confidence, ItemId, KnowledgeGraph, Relationship, RelationshipType,
+};
+
+/// Schema version written by this build.
+pub const SCHEMA_VERSION: u32 = 4;
+
+/// Source parser backend recorded in build fingerprints.
+pub const PARSER_BACKEND: &str = "regex";
+
+/// Analysis feature: resolve `receiver.method(..)` calls through receiver type inference.
+pub const METHOD_CALLS: &str = "method-calls";
+
+/// Analysis feature: link trait method implementations to the trait's declarations.
+pub const TRAIT_DISPATCH: &str = "trait-dispatch";
+
+/// Analysis features this build implements, all enabled by default; a build records the ones
+/// it ran in `BuildFingerprint::features`.
+pub const BUILD_FEATURES: &[&str] = &[METHOD_CALLS, TRAIT_DISPATCH];
+
+#[derive(Deserialize)]
+struct Probe {
+    #[serde(default)]
+    metadata: ProbeMetadata,
+}
+
+#[derive(Deserialize, Default)]
+struct ProbeMetadata {
+    #[serde(default)]
+    schema_version: u32,
+    #[serde(default)]
+    tool_version: String,
+}
+
+/// Reject a JSON graph written with a newer schema before deserializing the whole graph.
+///
+/// # Errors
+/// Returns `KnowledgeGraphError::UnsupportedSchema` for newer schemas and
+/// `KnowledgeGraphError::Format` if `json` is not a JSON object.
+pub fn check_json(json: &str) -> Result<(), KnowledgeGraphError> {
+    let probe: Probe =
+        serde_json::from_str(json).map_err(|e| KnowledgeGraphError::Format(e.to_string()))?;
+    check(probe.metadata.schema_version, &probe.metadata.tool_version)
+}
+
+/// Check that this build can read `version`.
+///
+/// # Errors
+/// Returns `KnowledgeGraphError::UnsupportedSchema` if `version` is newer than
+/// [`SCHEMA_VERSION`].
+pub fn check(version: u32, tool_version: &str) -> Result<(), KnowledgeGraphError> {
+    if version > SCHEMA_VERSION {
+        return Err(KnowledgeGraphError::UnsupportedSchema {
+            found: version,
+            supported: SCHEMA_VERSION,
+            tool_version: if tool_version.is_empty() { "unknown" } else { tool_version }
+                .to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// Bring a loaded graph up to [`SCHEMA_VERSION`].
+///
+/// # Errors
+/// Returns `KnowledgeGraphError::UnsupportedSchema` if the graph uses a newer schema.
+pub fn migrate(graph: &mut KnowledgeGraph) -> Result<(), KnowledgeGraphError> {
+    let version = graph.metadata.schema_version;
+    check(version, &graph.metadata.tool_version)?;
+    if version < 1 {
+        migrate_unversioned(graph);
+    }
+    if version < 2 {
+        relative_file_ids(graph);
+    }
+    if version < 3 {
+        fold_repeated_imports(graph);
+    }
+    // Version 4 added optional build stats, which older graphs simply lack
+    graph.metadata.schema_version = SCHEMA_VERSION;
+    Ok(())
+}
+
+/// Compare the fingerprint a graph was built with against `expected`.
+///
+/// Graphs saved before fingerprints were recorded (no parser name) are accepted.
+///
+/// # Errors
+/// Returns `KnowledgeGraphError::FingerprintMismatch` listing each setting that differs.
+pub fn check_fingerprint(
+    found: &BuildFingerprint,
+    expected: &BuildFingerprint,
+) -> Result<(), KnowledgeGraphError> {
+    if found.parser.is_empty() {
+        return Ok(());
+    }
+    let mut differences = Vec::new();
+    if found.no_ignore != expected.no_ignore {
+        differences.push(format!("no_ignore {} != {}", found.no_ignore, expected.no_ignore));
+    }
+    if found.parser != expected.parser {
+        differences.push(format!("parser `{}` != `{}`", found.parser, expected.parser));
+    }
+    if found.features != expected.features {
+        differences.push(format!(
+            "features [{}] != [{}]",
+            found.features.join(", "),
+            expected.features.join(", ")
+        ));
+    }
+    if differences.is_empty() {
+        Ok(())
+    } else {
+        Err(KnowledgeGraphError::FingerprintMismatch { differences })
+    }
+}
+
+// 0.1.x graphs: replace `kind:name:line` ids with path-based ones and point relationships at
+// them. Call edges stay file-level since call sites cannot be recovered without sources.
+fn migrate_unversioned(graph: &mut KnowledgeGraph) {
+    if graph.module_segments.is_empty() {
+        graph.compute_module_segments();
+    }
+    let root = graph.metadata.root.clone().unwrap_or_else(|| infer_root(graph));
+    graph.reassign_item_ids(&root);
+    // Unversioned graphs carry no confidence; only call edges were ever guessed
+    for rel in &mut graph.relationships {
+        if !matches!(rel.relationship_type, RelationshipType::Calls { .. }) {
+            rel.confidence = Confidence::Exact;
+        }
+    }
+}
+
+// Version 1 graphs: `file:` ids carried the path as built; make them root-relative.
+fn relative_file_ids(graph: &mut KnowledgeGraph) {
+    let root = graph.metadata.root.clone().unwrap_or_else(|| infer_root(graph));
+    let mut renamed: HashMap<ItemId, ItemId> = HashMap::new();
+    for (path, node) in &mut graph.files {
+        let Some(file_item) = node.items.first_mut().filter(|it| it.id.0.starts_with("file:"))
+        else {
+            continue;
+        };
+        let id = ItemId(paths::file_item_id(&root, path));
+        if id != file_item.id {
+            renamed.insert(std::mem::replace(&mut file_item.id, id.clone()), id);
+        }
+    }
+    if renamed.is_empty() {
+        return;
+    }
+    for rel in &mut graph.relationships {
+        for id in [&mut rel.from_item, &mut rel.to_item] {
+            if let Some(new) = renamed.get(id) {
+                id.clone_from(new);
+            }
+        }
+    }
+    graph.invalidate_index();
+}
+
+// Version 2 graphs: a target imported several times from one file had an edge per import;
+// fold them into one edge whose `count` is the number of imports.
+fn fold_repeated_imports(graph: &mut KnowledgeGraph) {
+    let mut seen: HashMap<(ItemId, ItemId, String), usize> = HashMap::new();
+    let mut kept = Vec::with_capacity(graph.relationships.len());
+    for rel in std::mem::take(&mut graph.relationships) {
+        if let RelationshipType::Uses { import_type } = &rel.relationship_type {
+            let key = (rel.from_item.clone(), rel.to_item.clone(), import_type.clone());
+            if let Some(&i) = seen.get(&key) {
+                let e: &mut Relationship = &mut kept[i];
+                e.count += rel.count;
+                e.evidence.extend(rel.evidence);
+                continue;
+            }
+            seen.insert(key, kept.len());
+        }
+        kept.push(rel);
+    }
+    graph.relationships = kept;
+    graph.invalidate_index();
+}
+
+// Parent of the outermost `src/` directory, which is the build root of a single crate.
+pub(crate) fn infer_root(graph: &KnowledgeGraph) -> PathBuf {
+    graph
+        .files
+        .keys()
+        .filter_map(|p| Resolver::base_src_dir(p).map(|(src, _)| src))
+        .min_by_key(|src| (src.components().count(), src.clone()))
+        .and_then(|src| src.parent().map(Path::to_path_buf))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn newer_schema_is_rejected_before_deserializing() {
+        let json = r#"{"metadata": {"schema_version": 99, "tool_version": "9.0.0"}, "files": 1}"#;
+        match check_json(json) {
+            Err(KnowledgeGraphError::UnsupportedSchema { found, supported, tool_version }) => {
+                assert_eq!((found, supported), (99, SCHEMA_VERSION));
+                assert_eq!(tool_version, "9.0.0");
+            }
+            other => panic!("expected UnsupportedSchema, got {other:?}"),
+        }
+        assert!(check_json(r#"{"files": {}}"#).is_ok());
+        assert!(matches!(check_json("[1, 2"), Err(KnowledgeGraphError::Format(_))));
+    }
+
+    #[test]
+    fn fingerprint_mismatches_name_each_setting() {
+        let current = BuildFingerprint::current(false);
+        assert!(check_fingerprint(&current, &current).is_ok());
+        // Graphs from before fingerprints were recorded cannot be compared
+        assert!(check_fingerprint(&BuildFingerprint::default(), &current).is_ok());
+
+        let other =
+            BuildFingerprint { no_ignore: true, features: vec!["x".into()], parser: "syn".into() };
+        match check_fingerprint(&other, &current) {
+            Err(KnowledgeGraphError::FingerprintMismatch { differences }) => assert_eq!(
+                differences,
+                [
+                    "no_ignore true != false",
+                    "parser `syn` != `regex`",
+                    "features [x] != [method-calls, trait-dispatch]"
+                ]
+            ),
+            other => panic!("expected FingerprintMismatch, got {other:?}"),
+        }
+    }
+}
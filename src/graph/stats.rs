@@ -0,0 +1,221 @@
+//! Build statistics: what a build read, reused and produced, and where its time went.
+//!
+//! Every build records a [`BuildStats`] in [`GraphMetadata::stats`]; `build --stats` prints it.
+//!
+//! [`GraphMetadata::stats`]: crate::graph::GraphMetadata::stats
+use std::collections::{BTreeMap, HashSet};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::graph::index::RelKind;
+use crate::graph::{paths, KnowledgeGraph, RelationshipType};
+
+/// Counts and timings of one build.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BuildStats {
+    /// Rust files found by the file walk, including `@generated` files the build then
+    /// leaves out.
+    pub files_discovered: usize,
+    /// Files taken from the build cache without parsing.
+    pub files_reused: usize,
+    /// Files parsed in this build.
+    pub files_parsed: usize,
+    /// Items per kind keyword (`fn`, `struct`, `mod`, ..).
+    pub items_by_kind: BTreeMap<String, usize>,
+    /// Relationships per kind (`uses`, `calls`, `contains`, ..).
+    pub relationships_by_type: BTreeMap<String, usize>,
+    /// `crate::`, `self::` and `super::` imports (globs aside) that resolve to no item in the
+    /// graph.
+    pub unresolved_imports: usize,
+    pub timings: PhaseTimings,
+    /// Peak resident memory of the process in bytes, where the platform reports it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub peak_memory_bytes: Option<u64>,
+}
+
+/// Wall time of each build phase, in milliseconds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct PhaseTimings {
+    /// Walking the roots for Rust files.
+    pub discovery_ms: f64,
+    /// Loading the build cache.
+    pub cache_load_ms: f64,
+    /// Reading, hashing and parsing files, assigning ids and containment.
+    pub parsing_ms: f64,
+    /// Linking modules to their parents.
+    pub module_hierarchy_ms: f64,
+    /// Collecting per-file type facts and finding the files to re-analyze.
+    pub dependency_tracking_ms: f64,
+    /// Resolving `use` statements to items.
+    pub import_analysis_ms: f64,
+    /// Type inference and call resolution, trait dispatch included.
+    pub call_analysis_ms: f64,
+    /// Whole build, cache save included.
+    pub total_ms: f64,
+}
+
+impl PhaseTimings {
+    /// The phases with their names, in pipeline order (total excluded).
+    #[must_use]
+    pub fn phases(&self) -> [(&'static str, f64); 7] {
+        [
+            ("discovery", self.discovery_ms),
+            ("cache load", self.cache_load_ms),
+            ("parsing", self.parsing_ms),
+            ("module hierarchy", self.module_hierarchy_ms),
+            ("dependency tracking", self.dependency_tracking_ms),
+            ("import analysis", self.import_analysis_ms),
+            ("call analysis", self.call_analysis_ms),
+        ]
+    }
+
+    pub(crate) fn add(&mut self, other: &PhaseTimings) {
+        self.discovery_ms += other.discovery_ms;
+        self.cache_load_ms += other.cache_load_ms;
+        self.parsing_ms += other.parsing_ms;
+        self.module_hierarchy_ms += other.module_hierarchy_ms;
+        self.dependency_tracking_ms += other.dependency_tracking_ms;
+        self.import_analysis_ms += other.import_analysis_ms;
+        self.call_analysis_ms += other.call_analysis_ms;
+    }
+}
+
+/// Milliseconds of `d`, rounded to microseconds.
+#[must_use]
+pub fn millis(d: Duration) -> f64 {
+    (d.as_secs_f64() * 1_000_000.0).round() / 1000.0
+}
+
+impl BuildStats {
+    // Add the file counts and phase times of another root's parse.
+    pub(crate) fn absorb(&mut self, other: &BuildStats) {
+        self.files_discovered += other.files_discovered;
+        self.files_reused += other.files_reused;
+        self.files_parsed += other.files_parsed;
+        self.timings.add(&other.timings);
+    }
+
+    /// Fill in the counts that the finished graph determines, the total time and peak memory.
+    pub(crate) fn finish(&mut self, graph: &KnowledgeGraph, total: Duration) {
+        self.items_by_kind.clear();
+        for file in graph.files.values() {
+            for it in &file.items {
+                *self
+                    .items_by_kind
+                    .entry(paths::item_keyword(&it.item_type).into())
+                    .or_default() += 1;
+            }
+        }
+        self.relationships_by_type.clear();
+        for rel in &graph.relationships {
+            *self
+                .relationships_by_type
+                .entry(kind_name(&rel.relationship_type).into())
+                .or_default() += 1;
+        }
+        self.unresolved_imports = unresolved_imports(graph);
+        self.timings.total_ms = millis(total);
+        self.peak_memory_bytes = peak_memory_bytes();
+    }
+}
+
+fn kind_name(rel: &RelationshipType) -> &'static str {
+    match RelKind::of(rel) {
+        RelKind::Uses => "uses",
+        RelKind::Implements => "implements",
+        RelKind::Contains => "contains",
+        RelKind::Extends => "extends",
+        RelKind::Calls => "calls",
+    }
+}
+
+// Crate-relative imports with no `Uses` edge from their file
+fn unresolved_imports(graph: &KnowledgeGraph) -> usize {
+    let resolved: HashSet<(&str, &str)> = graph
+        .relationships
+        .iter()
+        .filter(|r| matches!(r.relationship_type, RelationshipType::Uses { .. }))
+        .map(|r| (r.from_item.0.as_str(), r.context.as_str()))
+        .collect();
+    graph
+        .files
+        .values()
+        .filter_map(|f| Some((f.items.first()?.id.0.as_str(), &f.imports)))
+        .flat_map(|(file_id, imports)| imports.iter().map(move |imp| (file_id, imp)))
+        .filter(|(_, imp)| {
+            let first = imp.path.split("::").next().unwrap_or("");
+            matches!(first, "crate" | "self" | "super") && !imp.path.ends_with('*')
+        })
+        .filter(|(file_id, imp)| !resolved.contains(&(*file_id, &*imp.path)))
+        .count()
+}
+
+/// Peak resident set size of this process (`VmHWM`); `None` where `/proc` is unavailable.
+#[must_use]
+pub fn peak_memory_bytes() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|l| l.starts_with("VmHWM:"))?;
+    let kb: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kb * 1024)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::{FileNode, Import, Item, ItemId, ItemType, Location, Visibility};
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    #[test]
+    fn finish_counts_kinds_and_unresolved_crate_imports() {
+        let path = PathBuf::from("src/lib.rs");
+        let item = |id: &str, item_type| Item {
+            id: ItemId(id.to_string()),
+            item_type,
+            name: Arc::from("x"),
+            visibility: Visibility::Public,
+            location: Location { file: path.clone(), line_start: 1, line_end: 1 },
+            attributes: vec![],
+        };
+        let import =
+            |p: &str| Import { path: p.into(), alias: None, is_pub: false, line: 1, column: 1 };
+        let mut g = KnowledgeGraph::default();
+        g.files.insert(
+            path.clone(),
+            FileNode {
+                path: path.clone(),
+                items: vec![
+                    item("file:src/lib.rs", ItemType::Module { is_inline: false }),
+                    item("fn:crate::a", ItemType::Function { is_async: false, is_const: false }),
+                ],
+                imports: vec![
+                    import("crate::a"),
+                    import("crate::missing"),
+                    import("super::*"),
+                    import("std::fmt"),
+                ],
+                ..FileNode::default()
+            },
+        );
+        g.relationships.push(crate::graph::Relationship {
+            from_item: ItemId("file:src/lib.rs".into()),
+            to_item: ItemId("fn:crate::a".into()),
+            relationship_type: RelationshipType::Uses { import_type: "import-item".into() },
+            strength: 1.0,
+            context: "crate::a".into(),
+            confidence: crate::graph::Confidence::Exact,
+            line: Some(1),
+            count: 1,
+            evidence: Vec::new(),
+        });
+
+        let mut stats = BuildStats::default();
+        stats.finish(&g, Duration::from_micros(1500));
+        assert_eq!(stats.items_by_kind.get("fn"), Some(&1));
+        assert_eq!(stats.items_by_kind.get("mod"), Some(&1));
+        assert_eq!(stats.relationships_by_type.get("uses"), Some(&1));
+        assert_eq!(stats.unresolved_imports, 1);
+        assert!((stats.timings.total_ms - 1.5).abs() < 1e-9);
+    }
+}
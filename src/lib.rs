@@ -6,5 +6,6 @@ pub mod errors;
 pub mod graph;
 pub mod parser;
 pub mod query;
+pub mod source;
 pub mod utils;
 pub mod visualization;
@@ -4,6 +4,36 @@ use regex::Regex;
 use std::path::Path;
 use std::sync::Arc;
 
+/// The text of one source file together with its masked form (see [`mask_non_code`]).
+///
+/// Built once per file read, so parsing and relationship analysis work on the same content
+/// and masking runs once.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceText {
+    raw: String,
+    masked: String,
+}
+
+impl SourceText {
+    #[must_use]
+    pub fn new(raw: String) -> Self {
+        let masked = mask_non_code(&raw);
+        Self { raw, masked }
+    }
+
+    /// The file as read.
+    #[must_use]
+    pub fn raw(&self) -> &str {
+        &self.raw
+    }
+
+    /// Comments and literals blanked out, byte offsets unchanged.
+    #[must_use]
+    pub fn masked(&self) -> &str {
+        &self.masked
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct RustParser {
     patterns: RegexPatterns,
@@ -14,6 +44,9 @@ pub struct RegexPatterns {
     pub fn_sig: Regex,
     pub struct_def: Regex,
     pub enum_def: Regex,
+    pub trait_def: Regex,
+    pub impl_def: Regex,
+    pub mod_def: Regex,
     pub vis_pub_in: Regex,
     pub import_stmt: Regex,
 }
@@ -35,12 +68,21 @@ impl RegexPatterns {
             r"(?m)^\s*(?P<vis>pub(?:\([^)]*\))?\s+)?enum\s+(?P<name>[A-Za-z_][A-Za-z0-9_]*)",
         )
         .unwrap();
+        let trait_def = Regex::new(
+            r"(?m)^\s*(?P<vis>pub(?:\([^)]*\))?\s+)?(?:unsafe\s+)?trait\s+(?P<name>[A-Za-z_][A-Za-z0-9_]*)",
+        )
+        .unwrap();
+        let impl_def = Regex::new(r"(?m)^\s*(?:unsafe\s+)?impl\b").unwrap();
+        let mod_def = Regex::new(
+            r"(?m)^\s*(?P<vis>pub(?:\([^)]*\))?\s+)?mod\s+(?P<name>[A-Za-z_][A-Za-z0-9_]*)\s*(?P<term>[;{])",
+        )
+        .unwrap();
         let vis_pub_in = Regex::new(r"^pub\((?P<sc>[^)]+)\)$").unwrap();
         let import_stmt = Regex::new(
-            r"(?m)^\s*(?:pub\s+)?use\s+([^;{]+?)(?:\s+as\s+([A-Za-z_][A-Za-z0-9_]*))?\s*;\s*$",
+            r"(?m)^\s*(?P<vis>pub(?:\([^)]*\))?\s+)?use\s+(?P<path>[^;{]+?)(?:\s+as\s+(?P<alias>[A-Za-z_][A-Za-z0-9_]*))?\s*;\s*$",
         )
         .unwrap();
-        Self { fn_sig, struct_def, enum_def, vis_pub_in, import_stmt }
+        Self { fn_sig, struct_def, enum_def, trait_def, impl_def, mod_def, vis_pub_in, import_stmt }
     }
 }
 
@@ -61,12 +103,37 @@ impl RustParser {
     /// # Errors
     /// Returns `ParseError` when the input cannot be parsed due to invalid UTF-8 or other parser failures.
     pub fn parse_file(&self, content: &str, path: &Path) -> Result<FileNode, ParseError> {
-        let items = self.extract_items(content, path);
-        let imports = self.extract_imports(content);
+        self.parse_source(&SourceText::new(content.to_string()), path)
+    }
+
+    /// Parse already read (and masked) source text; see [`RustParser::parse_file`].
+    ///
+    /// # Errors
+    /// As for [`RustParser::parse_file`].
+    pub fn parse_source(&self, source: &SourceText, path: &Path) -> Result<FileNode, ParseError> {
+        let items = self.extract_items(source.masked(), path);
+        let imports = self.extract_imports(source.raw());
         let metrics = FileMetrics { item_count: items.len(), import_count: imports.len() };
         Ok(FileNode { path: path.to_path_buf(), items, imports, metrics })
     }
 
+    /// Out-of-line module declarations (`mod name;`) with their visibility, in source order.
+    #[must_use]
+    pub fn module_declarations(&self, source: &SourceText) -> Vec<(Arc<str>, Visibility)> {
+        self.patterns
+            .mod_def
+            .captures_iter(source.masked())
+            .filter(|cap| cap.name("term").is_some_and(|t| t.as_str() == ";"))
+            .map(|cap| {
+                let name = Arc::from(cap.name("name").map_or("", |m| m.as_str()));
+                let vis = cap.name("vis").map_or("", |m| m.as_str().trim());
+                (name, parse_visibility(&self.patterns.vis_pub_in, vis))
+            })
+            .collect()
+    }
+
+    // Matches against masked content so comments and string literals never produce items,
+    // and so brace matching for spans is not confused by `{`/`}` inside literals.
     fn extract_items(&self, content: &str, path: &Path) -> Vec<Item> {
         // Pre-size output using rough counts to reduce reallocations
         let fn_count = self.patterns.fn_sig.captures_iter(content).count();
@@ -80,6 +147,8 @@ impl RustParser {
             let visibility = parse_visibility(&self.patterns.vis_pub_in, vis);
             let m0 = cap.get(0).unwrap();
             let line = line_number_for(content, m0.start());
+            // The signature regex ends at the parameter list's `(`
+            let line_end = line_number_for(content, item_end(content, m0.end() - 1));
             let span = m0.as_str();
             out.push(Item {
                 id: ItemId(format!("fn:{name}:{line}")),
@@ -89,7 +158,7 @@ impl RustParser {
                 },
                 name,
                 visibility,
-                location: Location { file: path.to_path_buf(), line_start: line, line_end: line },
+                location: Location { file: path.to_path_buf(), line_start: line, line_end },
                 attributes: vec![],
             });
         }
@@ -98,13 +167,15 @@ impl RustParser {
             let name = Arc::from(cap.name("name").map_or("", |m| m.as_str()));
             let vis = cap.name("vis").map_or("", |m| m.as_str().trim());
             let visibility = parse_visibility(&self.patterns.vis_pub_in, vis);
-            let line = line_number_for(content, cap.get(0).map_or(0, |m| m.start()));
+            let m0 = cap.get(0).unwrap();
+            let line = line_number_for(content, m0.start());
+            let line_end = line_number_for(content, item_end(content, m0.end()));
             out.push(Item {
                 id: ItemId(format!("struct:{name}:{line}")),
                 item_type: ItemType::Struct { is_tuple: false },
                 name,
                 visibility,
-                location: Location { file: path.to_path_buf(), line_start: line, line_end: line },
+                location: Location { file: path.to_path_buf(), line_start: line, line_end },
                 attributes: vec![],
             });
         }
@@ -113,13 +184,81 @@ impl RustParser {
             let name = Arc::from(cap.name("name").map_or("", |m| m.as_str()));
             let vis = cap.name("vis").map_or("", |m| m.as_str().trim());
             let visibility = parse_visibility(&self.patterns.vis_pub_in, vis);
-            let line = line_number_for(content, cap.get(0).map_or(0, |m| m.start()));
+            let m0 = cap.get(0).unwrap();
+            let line = line_number_for(content, m0.start());
+            let line_end = line_number_for(content, item_end(content, m0.end()));
             out.push(Item {
                 id: ItemId(format!("enum:{name}:{line}")),
                 item_type: ItemType::Enum { variant_count: 0 },
                 name,
                 visibility,
-                location: Location { file: path.to_path_buf(), line_start: line, line_end: line },
+                location: Location { file: path.to_path_buf(), line_start: line, line_end },
+                attributes: vec![],
+            });
+        }
+
+        for cap in self.patterns.trait_def.captures_iter(content) {
+            let name = Arc::from(cap.name("name").map_or("", |m| m.as_str()));
+            let vis = cap.name("vis").map_or("", |m| m.as_str().trim());
+            let visibility = parse_visibility(&self.patterns.vis_pub_in, vis);
+            let m0 = cap.get(0).unwrap();
+            let line = line_number_for(content, m0.start());
+            let end = item_end(content, m0.end());
+            let body = &content[m0.end()..end];
+            // Crude object-safety check: no methods returning `Self` and no generic methods
+            let is_object_safe = !body.contains("-> Self") && !generic_method_re().is_match(body);
+            out.push(Item {
+                id: ItemId(format!("trait:{name}:{line}")),
+                item_type: ItemType::Trait { is_object_safe },
+                name,
+                visibility,
+                location: Location {
+                    file: path.to_path_buf(),
+                    line_start: line,
+                    line_end: line_number_for(content, end),
+                },
+                attributes: vec![],
+            });
+        }
+
+        // Inline modules; `mod name;` declarations are represented by the module's own file
+        for cap in self.patterns.mod_def.captures_iter(content) {
+            if cap.name("term").is_some_and(|t| t.as_str() == ";") {
+                continue;
+            }
+            let name = Arc::from(cap.name("name").map_or("", |m| m.as_str()));
+            let vis = cap.name("vis").map_or("", |m| m.as_str().trim());
+            let visibility = parse_visibility(&self.patterns.vis_pub_in, vis);
+            let m0 = cap.get(0).unwrap();
+            let line = line_number_for(content, m0.start());
+            let line_end = line_number_for(content, item_end(content, m0.end() - 1));
+            out.push(Item {
+                id: ItemId(format!("mod:{name}:{line}")),
+                item_type: ItemType::Module { is_inline: true },
+                name,
+                visibility,
+                location: Location { file: path.to_path_buf(), line_start: line, line_end },
+                attributes: vec![],
+            });
+        }
+
+        for m0 in self.patterns.impl_def.find_iter(content) {
+            let Some((trait_name, type_name)) = parse_impl_header(content, m0.end()) else {
+                continue;
+            };
+            let line = line_number_for(content, m0.start());
+            let line_end = line_number_for(content, item_end(content, m0.end()));
+            let name: Arc<str> = match &trait_name {
+                Some(t) => Arc::from(format!("impl {t} for {type_name}")),
+                None => Arc::from(format!("impl {type_name}")),
+            };
+            out.push(Item {
+                id: ItemId(format!("impl:{type_name}:{line}")),
+                item_type: ItemType::Impl { trait_name, type_name },
+                name,
+                // impl blocks carry no visibility of their own
+                visibility: Visibility::Private,
+                location: Location { file: path.to_path_buf(), line_start: line, line_end },
                 attributes: vec![],
             });
         }
@@ -131,9 +270,17 @@ impl RustParser {
         let import_count = self.patterns.import_stmt.captures_iter(content).count();
         let mut out = Vec::with_capacity(import_count);
         for cap in self.patterns.import_stmt.captures_iter(content) {
-            let path = Arc::from(cap.get(1).map_or("", |m| m.as_str().trim()));
-            let alias = cap.get(2).map(|m| Arc::from(m.as_str()));
-            out.push(Import { path, alias });
+            let path = Arc::from(cap.name("path").map_or("", |m| m.as_str().trim()));
+            let alias = cap.name("alias").map(|m| Arc::from(m.as_str()));
+            // Only a plain `pub use` re-exports; `pub(crate) use` stays internal
+            let is_pub = cap.name("vis").is_some_and(|m| m.as_str().trim() == "pub");
+            // Position of the `use` keyword (after any visibility and indentation)
+            let at = cap.name("path").map_or(0, |m| m.start());
+            let at = content[..at].rfind("use").unwrap_or(at);
+            let line = line_number_for(content, at);
+            let line_start = content[..at].rfind('\n').map_or(0, |i| i + 1);
+            let column = content[line_start..at].chars().count() + 1;
+            out.push(Import { path, alias, is_pub, line, column });
         }
         out
     }
@@ -159,6 +306,262 @@ fn parse_visibility(vis_pub_in: &Regex, vis: &str) -> Visibility {
     Visibility::Private
 }
 
+fn generic_method_re() -> &'static Regex {
+    static RE: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"\bfn\s+[A-Za-z_][A-Za-z0-9_]*\s*<").unwrap())
+}
+
+/// Replace the contents of comments, string literals and char literals with spaces.
+///
+/// Byte offsets and newlines are preserved, so positions and line numbers computed on the
+/// masked text map 1:1 onto the original source.
+#[must_use]
+pub fn mask_non_code(content: &str) -> String {
+    let bytes = content.as_bytes();
+    let mut out = bytes.to_vec();
+    let blank = |out: &mut [u8], from: usize, to: usize| {
+        for b in &mut out[from..to] {
+            if *b != b'\n' {
+                *b = b' ';
+            }
+        }
+    };
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'/' if bytes.get(i + 1) == Some(&b'/') => {
+                let end =
+                    bytes[i..].iter().position(|&b| b == b'\n').map_or(bytes.len(), |p| i + p);
+                blank(&mut out, i, end);
+                i = end;
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                // Block comments nest in Rust
+                let mut depth = 0usize;
+                let mut j = i;
+                while j < bytes.len() {
+                    if bytes[j] == b'/' && bytes.get(j + 1) == Some(&b'*') {
+                        depth += 1;
+                        j += 2;
+                    } else if bytes[j] == b'*' && bytes.get(j + 1) == Some(&b'/') {
+                        depth -= 1;
+                        j += 2;
+                        if depth == 0 {
+                            break;
+                        }
+                    } else {
+                        j += 1;
+                    }
+                }
+                blank(&mut out, i, j);
+                i = j;
+            }
+            b'r' if raw_string_hashes(bytes, i).is_some()
+                && (i == 0
+                    || !is_ident_byte(bytes[i - 1])
+                    || (bytes[i - 1] == b'b' && (i < 2 || !is_ident_byte(bytes[i - 2])))) =>
+            {
+                let hashes = raw_string_hashes(bytes, i).unwrap_or(0);
+                let body_start = i + 2 + hashes;
+                let mut j = body_start;
+                while j < bytes.len() {
+                    if bytes[j] == b'"'
+                        && bytes[j + 1..].iter().take(hashes).filter(|&&b| b == b'#').count()
+                            == hashes
+                    {
+                        break;
+                    }
+                    j += 1;
+                }
+                blank(&mut out, body_start, j.min(bytes.len()));
+                i = (j + 1 + hashes).min(bytes.len());
+            }
+            b'"' => {
+                let mut j = i + 1;
+                while j < bytes.len() && bytes[j] != b'"' {
+                    j += if bytes[j] == b'\\' { 2 } else { 1 };
+                }
+                let j = j.min(bytes.len());
+                blank(&mut out, i + 1, j);
+                i = j + 1;
+            }
+            b'\'' => {
+                // Char literal ('x', '\n', '\u{..}'); lifetimes ('a) are left untouched
+                let close = if bytes.get(i + 1) == Some(&b'\\') {
+                    // The escaped character itself is skipped so that '\'' is handled
+                    bytes.get(i + 3..).and_then(|rest| {
+                        rest.iter().take(10).position(|&b| b == b'\'').map(|p| i + 3 + p)
+                    })
+                } else {
+                    let ch_len = content[i + 1..].chars().next().map_or(1, char::len_utf8);
+                    (bytes.get(i + 1 + ch_len) == Some(&b'\'')).then_some(i + 1 + ch_len)
+                };
+                if let Some(c) = close {
+                    blank(&mut out, i + 1, c);
+                    i = c + 1;
+                } else {
+                    i += 1;
+                }
+            }
+            _ => i += 1,
+        }
+    }
+    // Only ASCII bytes inside whole literals/comments were replaced, so the buffer is valid UTF-8
+    String::from_utf8(out).unwrap_or_else(|_| content.to_string())
+}
+
+fn is_ident_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_'
+}
+
+// `r"..."` / `r#"..."#` / `br"..."`: returns the number of `#` when `bytes[i..]` starts a raw string
+fn raw_string_hashes(bytes: &[u8], i: usize) -> Option<usize> {
+    let mut j = i + 1;
+    while bytes.get(j) == Some(&b'#') {
+        j += 1;
+    }
+    (bytes.get(j) == Some(&b'"')).then_some(j - i - 1)
+}
+
+/// Byte offset where the item starting right before `from` ends.
+///
+/// Scans forward to the first `{` or `;` outside of parentheses/brackets: a `;` ends a
+/// declaration (e.g. a trait method without body or a tuple struct), a `{` opens a body that is
+/// then brace-matched. Expects masked content (see `mask_non_code`).
+#[must_use]
+pub fn item_end(masked: &str, from: usize) -> usize {
+    let bytes = masked.as_bytes();
+    let mut depth = 0i32;
+    let mut i = from;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'(' | b'[' => depth += 1,
+            b')' | b']' => depth -= 1,
+            b';' if depth <= 0 => return i,
+            b'{' if depth <= 0 => return matching_brace(bytes, i),
+            _ => {}
+        }
+        i += 1;
+    }
+    bytes.len()
+}
+
+fn matching_brace(bytes: &[u8], open: usize) -> usize {
+    let mut depth = 0usize;
+    for (j, &b) in bytes.iter().enumerate().skip(open) {
+        if b == b'{' {
+            depth += 1;
+        } else if b == b'}' {
+            depth -= 1;
+            if depth == 0 {
+                return j;
+            }
+        }
+    }
+    bytes.len()
+}
+
+/// Parse an `impl` header starting right after the `impl` keyword.
+///
+/// Returns `(trait_name, type_name)` with generics and paths stripped, e.g.
+/// `impl<T> fmt::Display for Wrapper<T> where T: Debug {` -> `(Some("Display"), "Wrapper")`.
+fn parse_impl_header(masked: &str, from: usize) -> Option<(Option<Arc<str>>, Arc<str>)> {
+    let rest = &masked[from..];
+    let brace = rest.find(['{', ';'])?;
+    let mut header = rest[..brace].trim();
+    // Skip the impl's own generic parameter list
+    if header.starts_with('<') {
+        let mut depth = 0i32;
+        let mut cut = header.len();
+        for (i, c) in header.char_indices() {
+            match c {
+                '<' => depth += 1,
+                '>' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        cut = i + 1;
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+        header = header[cut..].trim();
+    }
+    if let Some(w) = header.find(" where ").or_else(|| header.find("\nwhere")) {
+        header = header[..w].trim();
+    }
+    let (trait_part, type_part) = match split_top_level_for(header) {
+        Some((t, ty)) => (Some(t), ty),
+        None => (None, header),
+    };
+    let type_name = base_type_name(type_part)?;
+    let trait_name = trait_part.and_then(base_type_name);
+    Some((trait_name.map(Arc::from), Arc::from(type_name)))
+}
+
+// Split `Trait<X> for Type` on the ` for ` that sits outside of angle brackets
+fn split_top_level_for(header: &str) -> Option<(&str, &str)> {
+    let mut depth = 0i32;
+    let bytes = header.as_bytes();
+    for (i, &b) in bytes.iter().enumerate() {
+        match b {
+            b'<' => depth += 1,
+            b'>' => depth -= 1,
+            _ if depth == 0
+                && header[i..].starts_with("for")
+                && i > 0
+                && bytes[i - 1].is_ascii_whitespace()
+                && bytes.get(i + 3).is_some_and(u8::is_ascii_whitespace) =>
+            {
+                return Some((header[..i].trim(), header[i + 3..].trim()));
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Reduce a type expression to its base type name.
+///
+/// Strips references, `mut`, `dyn`/`impl`, paths and generic arguments, and unwraps the
+/// auto-deref smart pointers `Box`, `Rc` and `Arc`: `&mut crate::a::Foo<T>` -> `Foo`,
+/// `Arc<Bar>` -> `Bar`. Returns `None` for tuples, slices, arrays and other non-path types.
+#[must_use]
+pub fn base_type_name(ty: &str) -> Option<&str> {
+    let mut t = ty.trim();
+    loop {
+        let before = t;
+        t = t.trim_start_matches('&').trim_start();
+        if let Some(rest) = t.strip_prefix('\'') {
+            // Lifetime in a reference: `&'a T`
+            t = rest.trim_start_matches(|c: char| c.is_alphanumeric() || c == '_').trim_start();
+        }
+        for kw in ["mut ", "dyn ", "impl "] {
+            if let Some(rest) = t.strip_prefix(kw) {
+                t = rest.trim_start();
+            }
+        }
+        if t == before {
+            break;
+        }
+    }
+    let (path, generics) = match t.find('<') {
+        Some(i) => (&t[..i], Some(&t[i + 1..t.rfind('>').unwrap_or(t.len())])),
+        None => (t, None),
+    };
+    let name = path.rsplit("::").next()?.trim();
+    if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        return None;
+    }
+    if matches!(name, "Box" | "Rc" | "Arc") {
+        if let Some(inner) = generics {
+            return base_type_name(inner);
+        }
+    }
+    Some(name)
+}
+
 fn line_number_for(content: &str, byte_idx: usize) -> usize {
     // 1-based line number
     content[..byte_idx].bytes().filter(|&b| b == b'\n').count() + 1
@@ -3,16 +3,17 @@
 //! This module defines the `Query` trait and a collection of ready-to-use
 //! queries such as `ConnectedFilesQuery`, `FunctionUsageQuery`,
 //! `CycleDetectionQuery`, `ShortestPathQuery`, `HubsQuery`,
-//! `ModuleCentralityQuery`, `TraitImplsQuery`, and `UnreferencedItemsQuery`.
+//! `ModuleCentralityQuery`, `TraitImplsQuery`, `UnreferencedItemsQuery`, and `PublicApiQuery`.
 //!
 //! Each query operates on `crate::graph::KnowledgeGraph` and returns results
 //! suitable for CLI or library consumption.
 use regex::Regex;
 use serde::Serialize;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::path::{Path, PathBuf};
 
-use crate::graph::{ItemId, KnowledgeGraph};
+use crate::graph::index::RelKind;
+use crate::graph::{Evidence, ItemId, KnowledgeGraph, RelationshipType};
 
 /// Query trait implemented by all query types.
 ///
@@ -72,49 +73,95 @@ impl ConnectedFilesQuery {
 
 impl Query<Vec<PathBuf>> for ConnectedFilesQuery {
     fn run(&self, graph: &KnowledgeGraph) -> Vec<PathBuf> {
-        // Build item -> file index and file -> items index
-        let mut item_to_file: HashMap<ItemId, PathBuf> = HashMap::new();
-        let mut file_to_items: HashMap<PathBuf, Vec<ItemId>> = HashMap::new();
-        for (path, file) in &graph.files {
-            let ids: Vec<ItemId> = file.items.iter().map(|i| i.id.clone()).collect();
-            for id in &ids {
-                item_to_file.insert(id.clone(), path.clone());
+        let idx = graph.index();
+        let Some(target) = idx.file_id(&graph.resolve_file(&self.file)) else {
+            return Vec::new();
+        };
+
+        // If an edge touches any item in the target file, add the opposing file
+        let mut out: BTreeSet<usize> = BTreeSet::new();
+        for node in idx.file_nodes(target) {
+            for e in idx.outgoing_all(node).chain(idx.incoming_all(node)) {
+                let f = idx.file_of(e.node);
+                if f != target {
+                    out.insert(f);
+                }
             }
-            file_to_items.insert(path.clone(), ids);
         }
+        // File ids follow path order
+        out.into_iter().map(|f| idx.file_path(f).to_path_buf()).collect()
+    }
+}
 
-        // Resolve the canonical path in the graph map
-        let Some((target_path, _node)) = graph.files.iter().find(|(p, _)| p == &&self.file) else {
-            return Vec::new();
-        };
+/// A file connected to the target of a `ConnectedFilesQuery`, with the relationships that
+/// connect them.
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectedFileEntry {
+    pub path: PathBuf,
+    pub relations: Vec<ConnectedRelation>,
+}
 
-        let Some(target_items) = file_to_items.get(target_path) else {
+/// One relationship between an item of the target file and an item of a connected file.
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectedRelation {
+    pub from: String,
+    pub to: String,
+    pub relation: String,
+    pub count: usize,
+    pub evidence: Vec<Evidence>,
+}
+
+impl ConnectedFilesQuery {
+    /// Like `run`, but with the relationships behind each connection and their source
+    /// evidence, in relationship order.
+    #[must_use]
+    pub fn explain(&self, graph: &KnowledgeGraph) -> Vec<ConnectedFileEntry> {
+        let idx = graph.index();
+        let Some(target) = idx.file_id(&graph.resolve_file(&self.file)) else {
             return Vec::new();
         };
-        let target_set: HashSet<ItemId> = target_items.iter().cloned().collect();
-
-        let mut out: HashSet<PathBuf> = HashSet::new();
-        for rel in &graph.relationships {
-            // If edge touches any item in the target file, add the opposing file
-            if target_set.contains(&rel.from_item) {
-                if let Some(fp) = item_to_file.get(&rel.to_item) {
-                    if fp != target_path {
-                        out.insert(fp.clone());
-                    }
-                }
-            }
-            if target_set.contains(&rel.to_item) {
-                if let Some(fp) = item_to_file.get(&rel.from_item) {
-                    if fp != target_path {
-                        out.insert(fp.clone());
-                    }
+        let mut by_file: std::collections::BTreeMap<usize, Vec<usize>> =
+            std::collections::BTreeMap::new();
+        for node in idx.file_nodes(target) {
+            for e in idx.outgoing_all(node).chain(idx.incoming_all(node)) {
+                let f = idx.file_of(e.node);
+                if f != target {
+                    by_file.entry(f).or_default().push(e.rel);
                 }
             }
         }
+        by_file
+            .into_iter()
+            .map(|(f, mut rels)| {
+                rels.sort_unstable();
+                rels.dedup();
+                let relations = rels
+                    .into_iter()
+                    .map(|i| {
+                        let r = &graph.relationships[i];
+                        ConnectedRelation {
+                            from: r.from_item.0.clone(),
+                            to: r.to_item.0.clone(),
+                            relation: relation_label(&r.relationship_type),
+                            count: r.count,
+                            evidence: r.evidence.clone(),
+                        }
+                    })
+                    .collect();
+                ConnectedFileEntry { path: idx.file_path(f).to_path_buf(), relations }
+            })
+            .collect()
+    }
+}
 
-        let mut v: Vec<PathBuf> = out.into_iter().collect();
-        v.sort();
-        v
+// `Kind:detail` label of a relationship type, e.g. `Calls:heuristic`.
+fn relation_label(r: &RelationshipType) -> String {
+    match r {
+        RelationshipType::Uses { import_type } => format!("Uses:{import_type}"),
+        RelationshipType::Implements { trait_name } => format!("Implements:{trait_name}"),
+        RelationshipType::Contains { containment_type } => format!("Contains:{containment_type}"),
+        RelationshipType::Extends { extension_type } => format!("Extends:{extension_type}"),
+        RelationshipType::Calls { call_type } => format!("Calls:{call_type}"),
     }
 }
 
@@ -128,66 +175,120 @@ pub enum UsageDirection {
 pub struct FunctionUsageQuery {
     pub function: String,
     pub direction: UsageDirection,
+    /// Follow trait dispatch: a call to a trait method may land in any implementation, and
+    /// callers of an implementation include callers of the trait method it implements.
+    pub through_traits: bool,
 }
 
 impl FunctionUsageQuery {
     /// Query files containing callers of the specified function name.
     #[must_use]
     pub fn callers(function: &str) -> Self {
-        Self { function: function.to_string(), direction: UsageDirection::Callers }
+        Self {
+            function: function.to_string(),
+            direction: UsageDirection::Callers,
+            through_traits: false,
+        }
     }
     /// Query files containing callees called by the specified function name.
     #[must_use]
     pub fn callees(function: &str) -> Self {
-        Self { function: function.to_string(), direction: UsageDirection::Callees }
+        Self {
+            function: function.to_string(),
+            direction: UsageDirection::Callees,
+            through_traits: false,
+        }
+    }
+    /// Expand results across trait declarations and their implementations.
+    #[must_use]
+    pub fn through_traits(mut self, yes: bool) -> Self {
+        self.through_traits = yes;
+        self
     }
 }
 
-impl Query<Vec<PathBuf>> for FunctionUsageQuery {
-    fn run(&self, graph: &KnowledgeGraph) -> Vec<PathBuf> {
-        // Build indices
-        let mut item_to_file: HashMap<ItemId, PathBuf> = HashMap::new();
-        let mut func_name_to_ids: HashMap<String, Vec<ItemId>> = HashMap::new();
-        for (path, file) in &graph.files {
-            for item in &file.items {
-                item_to_file.insert(item.id.clone(), path.clone());
-                // crude: function ids contain prefix "fn:"; but better match by type and name
-                if let crate::graph::ItemType::Function { .. } = item.item_type {
-                    func_name_to_ids
-                        .entry(item.name.to_string())
-                        .or_default()
-                        .push(item.id.clone());
+/// A caller or callee found by `FunctionUsageQuery`.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct FunctionUsageEntry {
+    /// Calling or called item; top-level calls outside any function come from the file item.
+    pub id: ItemId,
+    pub name: String,
+    pub file: PathBuf,
+    /// Call-site lines linking it to the queried function, in the caller's file.
+    pub lines: Vec<usize>,
+}
+
+impl Query<Vec<FunctionUsageEntry>> for FunctionUsageQuery {
+    fn run(&self, graph: &KnowledgeGraph) -> Vec<FunctionUsageEntry> {
+        let idx = graph.index();
+        let targets: Vec<usize> = idx
+            .named(&self.function)
+            .iter()
+            .copied()
+            .filter(|&n| {
+                idx.item(graph, n).is_some_and(|it| {
+                    matches!(it.item_type, crate::graph::ItemType::Function { .. })
+                })
+            })
+            .collect();
+        let mut target_set: BTreeSet<usize> = targets.iter().copied().collect();
+        if self.through_traits && matches!(self.direction, UsageDirection::Callers) {
+            // A changed implementation or declaration affects every call dispatched through the
+            // trait method: include the declarations and all sibling implementations
+            for &t in &targets {
+                let decls = idx.outgoing(t, RelKind::Implements).iter().map(|e| e.node);
+                for decl in decls.chain([t]) {
+                    target_set.insert(decl);
+                    target_set
+                        .extend(idx.incoming(decl, RelKind::Implements).iter().map(|e| e.node));
                 }
             }
         }
 
-        let Some(target_ids) = func_name_to_ids.get(&self.function) else {
-            return Vec::new();
-        };
-        let target_set: HashSet<ItemId> = target_ids.iter().cloned().collect();
-
-        let mut out: HashSet<PathBuf> = HashSet::new();
-        for rel in &graph.relationships {
+        // Node -> call-site lines
+        let mut out: HashMap<usize, BTreeSet<usize>> = HashMap::new();
+        for &t in &target_set {
             match self.direction {
                 UsageDirection::Callers => {
-                    if target_set.contains(&rel.to_item) {
-                        if let Some(fp) = item_to_file.get(&rel.from_item) {
-                            out.insert(fp.clone());
-                        }
+                    for e in idx.incoming(t, RelKind::Calls) {
+                        out.entry(e.node)
+                            .or_default()
+                            .extend(graph.relationships[e.rel].site_lines());
                     }
                 }
                 UsageDirection::Callees => {
-                    if target_set.contains(&rel.from_item) {
-                        if let Some(fp) = item_to_file.get(&rel.to_item) {
-                            out.insert(fp.clone());
+                    for e in idx.outgoing(t, RelKind::Calls) {
+                        let rel = &graph.relationships[e.rel];
+                        out.entry(e.node).or_default().extend(rel.site_lines());
+                        // A call to a trait method may land in any implementation
+                        if self.through_traits {
+                            for imp in idx.incoming(e.node, RelKind::Implements) {
+                                out.entry(imp.node).or_default().extend(rel.site_lines());
+                            }
                         }
                     }
                 }
             }
         }
 
-        let mut v: Vec<PathBuf> = out.into_iter().collect();
-        v.sort();
+        let mut v: Vec<FunctionUsageEntry> = out
+            .into_iter()
+            .filter_map(|(node, lines)| {
+                let item = idx.item(graph, node)?;
+                Some(FunctionUsageEntry {
+                    id: item.id.clone(),
+                    name: item.name.to_string(),
+                    file: idx.file_path(idx.file_of(node)).to_path_buf(),
+                    lines: lines.into_iter().collect(),
+                })
+            })
+            .collect();
+        v.sort_by(|a, b| {
+            a.file
+                .cmp(&b.file)
+                .then_with(|| a.lines.cmp(&b.lines))
+                .then_with(|| a.id.0.cmp(&b.id.0))
+        });
         v
     }
 }
@@ -239,33 +340,19 @@ fn dfs(
 
 impl Query<Vec<Vec<PathBuf>>> for CycleDetectionQuery {
     fn run(&self, graph: &KnowledgeGraph) -> Vec<Vec<PathBuf>> {
-        // Build file-level adjacency ignoring self loops
-        let mut file_ids: Vec<PathBuf> = graph.files.keys().cloned().collect();
-        file_ids.sort();
-        let index: HashMap<PathBuf, usize> =
-            file_ids.iter().cloned().enumerate().map(|(i, p)| (p, i)).collect();
+        let idx = graph.index();
+        // File ids follow path order
+        let file_ids: Vec<PathBuf> =
+            (0..idx.file_count()).map(|f| idx.file_path(f).to_path_buf()).collect();
 
-        // Map each relationship to file->file edge
+        // Map each call edge to a file->file edge, ignoring self loops
         let mut adj: Vec<Vec<usize>> = vec![Vec::new(); file_ids.len()];
-        // Prepare item->file map
-        let mut item_to_file: HashMap<ItemId, usize> = HashMap::new();
-        for (path, file) in &graph.files {
-            if let Some(&i) = index.get(path) {
-                for it in &file.items {
-                    item_to_file.insert(it.id.clone(), i);
-                }
-            }
-        }
-        for rel in &graph.relationships {
-            // Only consider call edges for cycle detection call-graph
-            if !matches!(rel.relationship_type, crate::graph::RelationshipType::Calls { .. }) {
-                continue;
-            }
-            if let (Some(&u), Some(&v)) =
-                (item_to_file.get(&rel.from_item), item_to_file.get(&rel.to_item))
-            {
-                if u != v {
-                    adj[u].push(v);
+        for u in 0..idx.node_count() {
+            let fu = idx.file_of(u);
+            for e in idx.outgoing(u, RelKind::Calls) {
+                let fv = idx.file_of(e.node);
+                if fu != fv {
+                    adj[fu].push(fv);
                 }
             }
         }
@@ -308,47 +395,39 @@ impl ShortestPathQuery {
 
 impl Query<Vec<PathBuf>> for ShortestPathQuery {
     fn run(&self, graph: &KnowledgeGraph) -> Vec<PathBuf> {
-        // Map files to indices
-        let mut files: Vec<PathBuf> = graph.files.keys().cloned().collect();
-        files.sort();
-        let idx: HashMap<PathBuf, usize> =
-            files.iter().cloned().enumerate().map(|(i, p)| (p, i)).collect();
-
-        let (Some(&src), Some(&dst)) = (idx.get(&self.from), idx.get(&self.to)) else {
+        let idx = graph.index();
+        let n = idx.file_count();
+        let (Some(src), Some(dst)) = (
+            idx.file_id(&graph.resolve_file(&self.from)),
+            idx.file_id(&graph.resolve_file(&self.to)),
+        ) else {
             return Vec::new();
         };
 
-        // Build item->file index and adjacency list
-        let mut item_to_file: HashMap<ItemId, usize> = HashMap::new();
-        for (path, file) in &graph.files {
-            if let Some(&i) = idx.get(path) {
-                for it in &file.items {
-                    item_to_file.insert(it.id.clone(), i);
-                }
-            }
-        }
-        let mut adj: Vec<Vec<usize>> = vec![Vec::new(); files.len()];
-        for rel in &graph.relationships {
-            if let (Some(&u), Some(&v)) =
-                (item_to_file.get(&rel.from_item), item_to_file.get(&rel.to_item))
-            {
-                if u != v {
-                    adj[u].push(v);
-                }
-            }
-        }
+        // Files reached by any edge leaving an item of `f`, in path order
+        let neighbors = |f: usize| -> Vec<usize> {
+            let mut v: Vec<usize> = idx
+                .file_nodes(f)
+                .flat_map(|u| idx.outgoing_all(u))
+                .map(|e| idx.file_of(e.node))
+                .filter(|&g| g != f)
+                .collect();
+            v.sort_unstable();
+            v.dedup();
+            v
+        };
 
         // BFS
-        let mut prev: Vec<Option<usize>> = vec![None; files.len()];
+        let mut prev: Vec<Option<usize>> = vec![None; n];
         let mut q: std::collections::VecDeque<usize> = std::collections::VecDeque::new();
-        let mut visited = vec![false; files.len()];
+        let mut visited = vec![false; n];
         visited[src] = true;
         q.push_back(src);
         while let Some(u) = q.pop_front() {
             if u == dst {
                 break;
             }
-            for &v in &adj[u] {
+            for v in neighbors(u) {
                 if !visited[v] {
                     visited[v] = true;
                     prev[v] = Some(u);
@@ -371,7 +450,7 @@ impl Query<Vec<PathBuf>> for ShortestPathQuery {
         path_indices.push(src);
         path_indices.reverse();
 
-        path_indices.into_iter().map(|i| files[i].clone()).collect()
+        path_indices.into_iter().map(|i| idx.file_path(i).to_path_buf()).collect()
     }
 }
 
@@ -386,51 +465,43 @@ pub enum CentralityMetric {
 pub struct HubsQuery {
     pub metric: CentralityMetric,
     pub top: usize,
+    /// Sum each relationship's `Relationship::weight` (confidence times sites) instead of
+    /// counting it once.
+    pub weighted: bool,
 }
 
 impl HubsQuery {
     /// Create a hubs query for the given metric and number of results.
     #[must_use]
     pub fn new(metric: CentralityMetric, top: usize) -> Self {
-        Self { metric, top }
+        Self { metric, top, weighted: false }
     }
-}
-
-impl Query<Vec<(PathBuf, usize, usize)>> for HubsQuery {
-    fn run(&self, graph: &KnowledgeGraph) -> Vec<(PathBuf, usize, usize)> {
-        // Map files to indices
-        let mut files: Vec<PathBuf> = graph.files.keys().cloned().collect();
-        files.sort();
-        let idx: HashMap<PathBuf, usize> =
-            files.iter().cloned().enumerate().map(|(i, p)| (p, i)).collect();
 
-        // Build item->file index and degree counters
-        let mut item_to_file: HashMap<ItemId, usize> = HashMap::new();
-        for (path, file) in &graph.files {
-            if let Some(&i) = idx.get(path) {
-                for it in &file.items {
-                    item_to_file.insert(it.id.clone(), i);
-                }
-            }
-        }
-        let n = files.len();
-        let mut indeg = vec![0usize; n];
-        let mut outdeg = vec![0usize; n];
-
-        // Count edges at file level; ignore self-loops
-        for rel in &graph.relationships {
-            if let (Some(&u), Some(&v)) =
-                (item_to_file.get(&rel.from_item), item_to_file.get(&rel.to_item))
-            {
-                if u != v {
-                    outdeg[u] += 1;
-                    indeg[v] += 1;
-                }
-            }
-        }
+    /// Rank by weighted degree, so heavily and confidently coupled files stand out from ones
+    /// mentioned once or linked by guessed calls.
+    #[must_use]
+    pub fn weighted(mut self, yes: bool) -> Self {
+        self.weighted = yes;
+        self
+    }
+}
 
-        let mut rows: Vec<(PathBuf, usize, usize)> =
-            (0..n).map(|i| (files[i].clone(), indeg[i], outdeg[i])).collect();
+impl Query<Vec<(PathBuf, f64, f64)>> for HubsQuery {
+    #[allow(clippy::cast_precision_loss)]
+    fn run(&self, graph: &KnowledgeGraph) -> Vec<(PathBuf, f64, f64)> {
+        // Degrees count edges between files; self-loops are ignored by the index
+        let idx = graph.index();
+        let mut rows: Vec<(PathBuf, f64, f64)> = (0..idx.file_count())
+            .map(|f| {
+                let (indeg, outdeg) = if self.weighted {
+                    idx.file_weighted_degree(f)
+                } else {
+                    let (i, o) = idx.file_degree(f);
+                    (i as f64, o as f64)
+                };
+                (idx.file_path(f).to_path_buf(), indeg, outdeg)
+            })
+            .collect();
 
         // Sort by chosen metric desc, then by path asc for stability
         rows.sort_by(|a, b| {
@@ -446,7 +517,7 @@ impl Query<Vec<(PathBuf, usize, usize)>> for HubsQuery {
                 CentralityMetric::Out => bo,
                 CentralityMetric::Total => bi + bo,
             };
-            bk.cmp(&ak).then_with(|| a.0.cmp(&b.0))
+            bk.total_cmp(&ak).then_with(|| a.0.cmp(&b.0))
         });
 
         rows.truncate(self.top);
@@ -473,16 +544,14 @@ impl UnreferencedItemsQuery {
 
 impl Query<Vec<(PathBuf, String, String, String, String)>> for UnreferencedItemsQuery {
     fn run(&self, graph: &KnowledgeGraph) -> Vec<(PathBuf, String, String, String, String)> {
-        use crate::graph::{ItemType, RelationshipType, Visibility};
-        let mut used: HashSet<ItemId> = HashSet::new();
-        for rel in &graph.relationships {
-            match rel.relationship_type {
-                RelationshipType::Uses { .. } | RelationshipType::Calls { .. } => {
-                    used.insert(rel.to_item.clone());
-                }
-                _ => {}
-            }
-        }
+        use crate::graph::{ItemType, Visibility};
+        let idx = graph.index();
+        let used = |id: &ItemId| {
+            idx.node(id).is_some_and(|n| {
+                !idx.incoming(n, RelKind::Uses).is_empty()
+                    || !idx.incoming(n, RelKind::Calls).is_empty()
+            })
+        };
 
         let mut out: Vec<(PathBuf, String, String, String, String)> = Vec::new();
         for (path, file) in &graph.files {
@@ -500,7 +569,7 @@ impl Query<Vec<(PathBuf, String, String, String, String)>> for UnreferencedItems
                         continue;
                     }
                 }
-                if used.contains(&item.id) {
+                if used(&item.id) {
                     continue;
                 }
 
@@ -544,6 +613,9 @@ pub struct ItemInfoRelationEntry {
     pub path: String,
     pub relation: String,
     pub context: String,
+    /// Number of sites producing the relationship.
+    pub count: usize,
+    pub evidence: Vec<Evidence>,
 }
 
 #[derive(Debug, Serialize)]
@@ -553,6 +625,10 @@ pub struct ItemInfoResult {
     pub kind: String,
     pub visibility: String,
     pub path: String,
+    /// Definition path, e.g. `my_crate::module::Type::method` (absent for impl blocks).
+    pub canonical_path: Option<String>,
+    /// Paths usable from other crates, including `pub use` re-exports.
+    pub public_paths: Vec<String>,
     pub line_start: usize,
     pub line_end: usize,
     pub code: Option<String>,
@@ -575,15 +651,10 @@ impl ItemInfoQuery {
 impl Query<Option<ItemInfoResult>> for ItemInfoQuery {
     fn run(&self, graph: &KnowledgeGraph) -> Option<ItemInfoResult> {
         use crate::graph::{ItemType, Visibility};
-        // Build an index of ItemId -> (path, &Item)
-        let mut idx: HashMap<&crate::graph::ItemId, (&PathBuf, &crate::graph::Item)> =
-            HashMap::new();
-        for (p, f) in &graph.files {
-            for it in &f.items {
-                idx.insert(&it.id, (p, it));
-            }
-        }
-        let (path, item) = idx.get(&self.item_id)?.to_owned();
+        let idx = graph.index();
+        let node = idx.node(&self.item_id)?;
+        let item = idx.item(graph, node)?;
+        let path = idx.file_path(idx.file_of(node));
 
         let kind = match &item.item_type {
             ItemType::Module { .. } => "Module",
@@ -609,47 +680,26 @@ impl Query<Option<ItemInfoResult>> for ItemInfoQuery {
         // Gather relations
         let mut inbound: Vec<ItemInfoRelationEntry> = Vec::new();
         let mut outbound: Vec<ItemInfoRelationEntry> = Vec::new();
-        let rel_to_string = |r: &crate::graph::RelationshipType| -> String {
-            match r {
-                crate::graph::RelationshipType::Uses { import_type } => {
-                    format!("Uses:{import_type}")
-                }
-                crate::graph::RelationshipType::Implements { trait_name } => {
-                    format!("Implements:{trait_name}")
-                }
-                crate::graph::RelationshipType::Contains { containment_type } => {
-                    format!("Contains:{containment_type}")
-                }
-                crate::graph::RelationshipType::Extends { extension_type } => {
-                    format!("Extends:{extension_type}")
-                }
-                crate::graph::RelationshipType::Calls { call_type } => format!("Calls:{call_type}"),
-            }
+        let entry = |e: &crate::graph::index::Edge| {
+            let r = &graph.relationships[e.rel];
+            let other = idx.item(graph, e.node)?;
+            Some(ItemInfoRelationEntry {
+                id: other.id.0.clone(),
+                name: other.name.to_string(),
+                path: idx.file_path(idx.file_of(e.node)).display().to_string(),
+                relation: relation_label(&r.relationship_type),
+                context: r.context.clone(),
+                count: r.count,
+                evidence: r.evidence.clone(),
+            })
         };
-        for r in &graph.relationships {
-            if r.to_item == item.id {
-                if let Some((pp, it)) = idx.get(&r.from_item) {
-                    inbound.push(ItemInfoRelationEntry {
-                        id: r.from_item.0.clone(),
-                        name: it.name.to_string(),
-                        path: pp.display().to_string(),
-                        relation: rel_to_string(&r.relationship_type),
-                        context: r.context.clone(),
-                    });
-                }
-            }
-            if r.from_item == item.id {
-                if let Some((pp, it)) = idx.get(&r.to_item) {
-                    outbound.push(ItemInfoRelationEntry {
-                        id: r.to_item.0.clone(),
-                        name: it.name.to_string(),
-                        path: pp.display().to_string(),
-                        relation: rel_to_string(&r.relationship_type),
-                        context: r.context.clone(),
-                    });
-                }
-            }
-        }
+        // Keep relationship order across kinds
+        let mut ins: Vec<&crate::graph::index::Edge> = idx.incoming_all(node).collect();
+        let mut outs: Vec<&crate::graph::index::Edge> = idx.outgoing_all(node).collect();
+        ins.sort_by_key(|e| e.rel);
+        outs.sort_by_key(|e| e.rel);
+        inbound.extend(ins.into_iter().filter_map(entry));
+        outbound.extend(outs.into_iter().filter_map(entry));
 
         // Optional code snippet
         let mut code: Option<String> = None;
@@ -664,12 +714,15 @@ impl Query<Option<ItemInfoResult>> for ItemInfoQuery {
             }
         }
 
+        let paths = crate::graph::paths::item_paths(graph).remove(&item.id);
         Some(ItemInfoResult {
             id: item.id.0.clone(),
             name: item.name.to_string(),
             kind,
             visibility,
             path: path.display().to_string(),
+            canonical_path: paths.as_ref().map(|p| p.canonical.clone()),
+            public_paths: paths.map(|p| p.public).unwrap_or_default(),
             line_start: item.location.line_start,
             line_end: item.location.line_end,
             code,
@@ -679,22 +732,80 @@ impl Query<Option<ItemInfoResult>> for ItemInfoQuery {
     }
 }
 
+/// One entry of the crate's public surface.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct PublicApiEntry {
+    /// Path usable from other crates, e.g. `my_crate::query::Query`.
+    pub path: String,
+    /// Item keyword: `fn`, `struct`, `trait`, `mod`, ...
+    pub kind: String,
+    pub id: String,
+}
+
+/// List every public path of every item, sorted by path, as a baseline for API diffs.
+pub struct PublicApiQuery;
+
+impl PublicApiQuery {
+    /// Construct a public API query.
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Default for PublicApiQuery {
+    fn default() -> Self {
+        Self
+    }
+}
+
+impl Query<Vec<PublicApiEntry>> for PublicApiQuery {
+    fn run(&self, graph: &KnowledgeGraph) -> Vec<PublicApiEntry> {
+        let paths = crate::graph::paths::item_paths(graph);
+        let mut out: Vec<PublicApiEntry> = Vec::new();
+        for file in graph.files.values() {
+            for it in &file.items {
+                let Some(p) = paths.get(&it.id) else { continue };
+                let kind = crate::graph::paths::item_keyword(&it.item_type);
+                out.extend(p.public.iter().map(|path| PublicApiEntry {
+                    path: path.clone(),
+                    kind: kind.to_string(),
+                    id: it.id.0.clone(),
+                }));
+            }
+        }
+        out.sort_by(|a, b| {
+            a.path.cmp(&b.path).then_with(|| a.kind.cmp(&b.kind)).then_with(|| a.id.cmp(&b.id))
+        });
+        out
+    }
+}
+
 /// Compute top-N modules (by directory) by degree centrality.
 pub struct ModuleCentralityQuery {
     pub metric: CentralityMetric,
     pub top: usize,
+    /// Sum each relationship's `Relationship::weight` instead of counting it once.
+    pub weighted: bool,
 }
 
 impl ModuleCentralityQuery {
     /// Create a module centrality query for the given metric and number of results.
     #[must_use]
     pub fn new(metric: CentralityMetric, top: usize) -> Self {
-        Self { metric, top }
+        Self { metric, top, weighted: false }
+    }
+
+    /// Rank by weighted degree; see [`HubsQuery::weighted`].
+    #[must_use]
+    pub fn weighted(mut self, yes: bool) -> Self {
+        self.weighted = yes;
+        self
     }
 }
 
-impl Query<Vec<(PathBuf, usize, usize)>> for ModuleCentralityQuery {
-    fn run(&self, graph: &KnowledgeGraph) -> Vec<(PathBuf, usize, usize)> {
+impl Query<Vec<(PathBuf, f64, f64)>> for ModuleCentralityQuery {
+    fn run(&self, graph: &KnowledgeGraph) -> Vec<(PathBuf, f64, f64)> {
         // Build list of modules identified by parent directory of file
         let mut modules: HashSet<PathBuf> = HashSet::new();
         let mut file_to_module: HashMap<PathBuf, PathBuf> = HashMap::new();
@@ -709,34 +820,29 @@ impl Query<Vec<(PathBuf, usize, usize)>> for ModuleCentralityQuery {
         let midx: HashMap<PathBuf, usize> =
             mods.iter().cloned().enumerate().map(|(i, p)| (p, i)).collect();
 
-        // Map items to module index
-        let mut item_to_mod: HashMap<ItemId, usize> = HashMap::new();
-        for (path, file) in &graph.files {
-            let Some(module_path) = file_to_module.get(path) else { continue };
-            if let Some(&mi) = midx.get(module_path) {
-                for it in &file.items {
-                    item_to_mod.insert(it.id.clone(), mi);
-                }
-            }
-        }
+        // Module index per file id
+        let idx = graph.index();
+        let file_mod: Vec<usize> =
+            (0..idx.file_count()).map(|f| midx[&file_to_module[idx.file_path(f)]]).collect();
 
         let n = mods.len();
-        let mut indeg = vec![0usize; n];
-        let mut outdeg = vec![0usize; n];
+        let mut indeg = vec![0.0; n];
+        let mut outdeg = vec![0.0; n];
 
         // Count inter-module edges
-        for rel in &graph.relationships {
-            if let (Some(&u), Some(&v)) =
-                (item_to_mod.get(&rel.from_item), item_to_mod.get(&rel.to_item))
-            {
-                if u != v {
-                    outdeg[u] += 1;
-                    indeg[v] += 1;
+        for u in 0..idx.node_count() {
+            let mu = file_mod[idx.file_of(u)];
+            for e in idx.outgoing_all(u) {
+                let mv = file_mod[idx.file_of(e.node)];
+                if mu != mv {
+                    let w = if self.weighted { graph.relationships[e.rel].weight() } else { 1.0 };
+                    outdeg[mu] += w;
+                    indeg[mv] += w;
                 }
             }
         }
 
-        let mut rows: Vec<(PathBuf, usize, usize)> =
+        let mut rows: Vec<(PathBuf, f64, f64)> =
             (0..n).map(|i| (mods[i].clone(), indeg[i], outdeg[i])).collect();
 
         rows.sort_by(|a, b| {
@@ -752,7 +858,7 @@ impl Query<Vec<(PathBuf, usize, usize)>> for ModuleCentralityQuery {
                 CentralityMetric::Out => bo,
                 CentralityMetric::Total => bi + bo,
             };
-            bk.cmp(&ak).then_with(|| a.0.cmp(&b.0))
+            bk.total_cmp(&ak).then_with(|| a.0.cmp(&b.0))
         });
 
         rows.truncate(self.top);
@@ -829,6 +935,10 @@ mod tests {
             relationship_type: RelationshipType::Calls { call_type: "test".to_string() },
             strength: 1.0,
             context: String::new(),
+            confidence: Default::default(),
+            line: None,
+            count: 1,
+            evidence: Vec::new(),
         });
         g.relationships.push(Relationship {
             from_item: b_item.id.clone(),
@@ -836,6 +946,10 @@ mod tests {
             relationship_type: RelationshipType::Calls { call_type: "test".to_string() },
             strength: 1.0,
             context: String::new(),
+            confidence: Default::default(),
+            line: None,
+            count: 1,
+            evidence: Vec::new(),
         });
         if with_cycle {
             g.relationships.push(Relationship {
@@ -844,6 +958,10 @@ mod tests {
                 relationship_type: RelationshipType::Calls { call_type: "test".to_string() },
                 strength: 1.0,
                 context: String::new(),
+                confidence: Default::default(),
+                line: None,
+                count: 1,
+                evidence: Vec::new(),
             });
         }
         g
@@ -866,12 +984,12 @@ mod tests {
         // callees of fa should include file of fb
         let q_callees = FunctionUsageQuery::callees("fa");
         let callees = q_callees.run(&g);
-        assert!(callees.contains(&PathBuf::from("src/b.rs")));
+        assert!(callees.iter().any(|e| e.file == Path::new("src/b.rs") && e.name == "fb"));
 
         // callers of fb should include file of fa
         let q_callers = FunctionUsageQuery::callers("fb");
         let callers = q_callers.run(&g);
-        assert!(callers.contains(&PathBuf::from("src/a.rs")));
+        assert!(callers.iter().any(|e| e.file == Path::new("src/a.rs") && e.name == "fa"));
     }
 
     #[test]
@@ -0,0 +1,386 @@
+//! Where a build reads its Rust sources from.
+//!
+//! A build with [`GraphBuilder::provider`] asks a [`SourceProvider`] which files a project has
+//! and what they contain, and reads nothing else; every file is read at most once. Three
+//! providers come with the crate:
+//!
+//! - [`FsSource`]: the filesystem, the default of [`GraphBuilder`].
+//! - [`MemorySource`]: a map of paths to text, e.g. test fixtures or generated code.
+//! - [`OverlaySource`]: another provider with some files replaced or added, e.g. a project on
+//!   disk with the unsaved buffers of an editor.
+//!
+//! Paths are used as given: a graph built from memory has the same ids and edges as one
+//! built from disk when the map holds the same files under the same paths.
+//! Only a provider that is the disk ([`SourceProvider::on_disk`]) has its parses saved in the
+//! build cache.
+//!
+//! ```
+//! use rust_relations_explorer::graph::builder::GraphBuilder;
+//! use rust_relations_explorer::source::MemorySource;
+//! use rust_relations_explorer::utils::cache::{CacheLocation, CacheMode};
+//!
+//! let sources: MemorySource = [
+//!     ("/demo/src/lib.rs", "mod m;\npub fn a() {\n    m::b();\n}\n"),
+//!     ("/demo/src/m.rs", "pub fn b() {}\n"),
+//! ]
+//! .into_iter()
+//! .collect();
+//! let graph = GraphBuilder::new("/demo")
+//!     .provider(&sources)
+//!     .cache_mode(CacheMode::Ignore)
+//!     .cache_location(CacheLocation::Disabled)
+//!     .build()
+//!     .unwrap();
+//! assert!(graph.files.values().flat_map(|f| &f.items).any(|i| i.id.0 == "fn:crate::m::b"));
+//! ```
+//!
+//! [`GraphBuilder`]: crate::graph::builder::GraphBuilder
+//! [`GraphBuilder::provider`]: crate::graph::builder::GraphBuilder::provider
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::errors::KnowledgeGraphError;
+use crate::utils::cache::CacheEntryMeta;
+use crate::utils::file_walker::{
+    self, select_listed, Discovery, DiscoveryOptions, Globs, IgnoreRules,
+};
+
+/// Lists and reads the Rust files of a project.
+pub trait SourceProvider: Sync {
+    /// The Rust files under `root` that `opts` selects, and what was skipped, as
+    /// [`file_walker::discover`] does on disk.
+    ///
+    /// # Errors
+    /// Returns `KnowledgeGraphError::InvalidGlob` for a malformed include or exclude glob.
+    fn discover(
+        &self,
+        root: &Path,
+        opts: &DiscoveryOptions,
+    ) -> Result<Discovery, KnowledgeGraphError>;
+
+    /// The text of `path`.
+    ///
+    /// # Errors
+    /// Returns `KnowledgeGraphError::Read` when the file is missing or not UTF-8.
+    fn read(&self, path: &Path) -> Result<String, KnowledgeGraphError>;
+
+    /// Modification time and length of `path`, letting the build cache reuse a parse without
+    /// reading the file. `None`, the default, has every file read and hashed instead.
+    fn stat(&self, _path: &Path) -> Option<CacheEntryMeta> {
+        None
+    }
+
+    /// Whether every source is the file on disk, so the build may save its parses in the
+    /// cache. `false`, the default, has builds reuse cached parses whose hash matches but
+    /// never write the cache.
+    fn on_disk(&self) -> bool {
+        false
+    }
+}
+
+/// The filesystem.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FsSource;
+
+impl SourceProvider for FsSource {
+    fn discover(
+        &self,
+        root: &Path,
+        opts: &DiscoveryOptions,
+    ) -> Result<Discovery, KnowledgeGraphError> {
+        file_walker::discover(root, opts)
+    }
+
+    fn read(&self, path: &Path) -> Result<String, KnowledgeGraphError> {
+        std::fs::read_to_string(path)
+            .map_err(|source| KnowledgeGraphError::Read { file: path.to_path_buf(), source })
+    }
+
+    fn stat(&self, path: &Path) -> Option<CacheEntryMeta> {
+        Some(CacheEntryMeta::stat(path))
+    }
+
+    fn on_disk(&self) -> bool {
+        true
+    }
+}
+
+/// Files held in memory, by path.
+///
+/// Discovery applies the same rules as on disk: hidden paths, `.gitignore` and `.ignore`
+/// entries of the map, include and exclude globs, and target, vendored and generated files
+/// (a `target` or `vendor` directory counts as such next to a `Cargo.toml` in the map).
+/// Files hidden by ignore rules are listed as skipped.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MemorySource {
+    files: BTreeMap<PathBuf, String>,
+}
+
+impl MemorySource {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add or replace the file at `path`, returning its previous text.
+    pub fn insert(&mut self, path: impl Into<PathBuf>, text: impl Into<String>) -> Option<String> {
+        self.files.insert(path.into(), text.into())
+    }
+
+    /// Drop the file at `path`, returning its text.
+    pub fn remove(&mut self, path: &Path) -> Option<String> {
+        self.files.remove(path)
+    }
+
+    #[must_use]
+    pub fn get(&self, path: &Path) -> Option<&str> {
+        self.files.get(path).map(String::as_str)
+    }
+
+    #[must_use]
+    pub fn contains(&self, path: &Path) -> bool {
+        self.files.contains_key(path)
+    }
+
+    /// All paths, sorted.
+    pub fn paths(&self) -> impl Iterator<Item = &Path> {
+        self.files.keys().map(PathBuf::as_path)
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+}
+
+impl<P: Into<PathBuf>, T: Into<String>> FromIterator<(P, T)> for MemorySource {
+    fn from_iter<I: IntoIterator<Item = (P, T)>>(iter: I) -> Self {
+        Self { files: iter.into_iter().map(|(p, t)| (p.into(), t.into())).collect() }
+    }
+}
+
+impl<P: Into<PathBuf>, T: Into<String>> Extend<(P, T)> for MemorySource {
+    fn extend<I: IntoIterator<Item = (P, T)>>(&mut self, iter: I) {
+        self.files.extend(iter.into_iter().map(|(p, t)| (p.into(), t.into())));
+    }
+}
+
+impl SourceProvider for MemorySource {
+    fn discover(
+        &self,
+        root: &Path,
+        opts: &DiscoveryOptions,
+    ) -> Result<Discovery, KnowledgeGraphError> {
+        let under_root = self.paths().filter(|p| p.starts_with(root));
+        select(root, under_root, opts, |p| self.contains(p), |p| self.get(p).map(str::to_string))
+    }
+
+    fn read(&self, path: &Path) -> Result<String, KnowledgeGraphError> {
+        self.get(path).map(str::to_string).ok_or_else(|| KnowledgeGraphError::Read {
+            file: path.to_path_buf(),
+            source: std::io::Error::new(std::io::ErrorKind::NotFound, "not in the memory source"),
+        })
+    }
+}
+
+/// Another provider with some files replaced or added in memory, e.g. the project on disk
+/// with the unsaved buffers of an editor.
+///
+/// Buffered files are read from memory and always re-hashed, never taken from the cache by
+/// their stat. Buffers of files the base does not have join discovery when the rules select
+/// them; the ignore files they are checked against are read through the overlay.
+#[derive(Debug, Clone, Default)]
+pub struct OverlaySource<P = FsSource> {
+    base: P,
+    buffers: MemorySource,
+}
+
+impl<P: SourceProvider> OverlaySource<P> {
+    #[must_use]
+    pub fn new(base: P) -> Self {
+        Self { base, buffers: MemorySource::default() }
+    }
+
+    /// Replace the content of `path`, or add the file.
+    pub fn set(&mut self, path: impl Into<PathBuf>, text: impl Into<String>) {
+        self.buffers.insert(path, text);
+    }
+
+    /// Drop the buffer of `path`, so the base content shows again.
+    pub fn revert(&mut self, path: &Path) -> Option<String> {
+        self.buffers.remove(path)
+    }
+
+    #[must_use]
+    pub fn base(&self) -> &P {
+        &self.base
+    }
+
+    #[must_use]
+    pub fn buffers(&self) -> &MemorySource {
+        &self.buffers
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.buffers.contains(path) || self.base.read(path).is_ok()
+    }
+}
+
+impl<P: SourceProvider> SourceProvider for OverlaySource<P> {
+    fn discover(
+        &self,
+        root: &Path,
+        opts: &DiscoveryOptions,
+    ) -> Result<Discovery, KnowledgeGraphError> {
+        let mut found = self.base.discover(root, opts)?;
+        let known: HashSet<&Path> = found.files.iter().map(PathBuf::as_path).collect();
+        let skipped: Vec<&Path> = found.skipped.iter().map(|(p, _)| p.as_path()).collect();
+        // Buffers of files the base neither found nor skipped, itself or by a directory
+        let added: Vec<&Path> = self
+            .buffers
+            .paths()
+            .filter(|p| p.starts_with(root) && !known.contains(p))
+            .filter(|p| !skipped.iter().any(|s| p.starts_with(s)))
+            .collect();
+        if added.is_empty() {
+            return Ok(found);
+        }
+        let extra = select(root, added, opts, |p| self.exists(p), |p| self.read(p).ok())?;
+        found.files.extend(extra.files);
+        found.files.sort();
+        found.skipped.extend(extra.skipped);
+        found.skipped.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(found)
+    }
+
+    fn read(&self, path: &Path) -> Result<String, KnowledgeGraphError> {
+        match self.buffers.get(path) {
+            Some(text) => Ok(text.to_string()),
+            None => self.base.read(path),
+        }
+    }
+
+    fn stat(&self, path: &Path) -> Option<CacheEntryMeta> {
+        if self.buffers.contains(path) {
+            return None;
+        }
+        self.base.stat(path)
+    }
+
+    fn on_disk(&self) -> bool {
+        self.buffers.is_empty() && self.base.on_disk()
+    }
+}
+
+// Discovery over a listed set of files under `root`: `exists` tells whether a file exists
+// and `read` gives the text of ignore files.
+fn select<'a>(
+    root: &Path,
+    paths: impl IntoIterator<Item = &'a Path>,
+    opts: &DiscoveryOptions,
+    exists: impl Fn(&Path) -> bool,
+    read: impl Fn(&Path) -> Option<String>,
+) -> Result<Discovery, KnowledgeGraphError> {
+    let globs = Globs::new(opts)?;
+    let paths: Vec<&Path> = paths.into_iter().collect();
+    let rules = if opts.no_ignore {
+        IgnoreRules::new(Vec::new())
+    } else {
+        // Ignore files of every directory holding a file, and of the directories above it
+        let dirs: BTreeSet<&Path> = paths.iter().flat_map(|p| p.ancestors().skip(1)).collect();
+        let mut files = Vec::new();
+        for dir in dirs {
+            for (name, is_ignore) in [(".gitignore", false), (".ignore", true)] {
+                if let Some(text) = read(&dir.join(name)) {
+                    files.push((dir.to_path_buf(), is_ignore, text));
+                }
+            }
+        }
+        IgnoreRules::new(files)
+    };
+    let (candidates, mut skipped) = select_listed(
+        root,
+        root,
+        paths.iter().copied(),
+        |rel| exists(&root.join(rel)),
+        &rules,
+        &globs,
+        opts,
+    );
+    let mut files: Vec<PathBuf> = candidates.into_iter().map(Path::to_path_buf).collect();
+    files.sort();
+    skipped.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(Discovery { files, skipped })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::file_walker::Exclusion;
+
+    #[test]
+    fn memory_discovery_applies_the_disk_rules() {
+        let sources: MemorySource = [
+            ("/p/Cargo.toml", ""),
+            ("/p/.gitignore", "scratch/\n"),
+            ("/p/src/lib.rs", "pub mod m;\n"),
+            ("/p/src/m.rs", "pub fn b() {}\n"),
+            ("/p/src/gen.rs", "// @generated\n"),
+            ("/p/.hidden/h.rs", ""),
+            ("/p/scratch/t.rs", ""),
+            ("/p/target/x.rs", ""),
+            ("/p/vendor/dep/src/lib.rs", ""),
+            ("/other/src/lib.rs", ""),
+        ]
+        .into_iter()
+        .collect();
+        let root = Path::new("/p");
+        let found = sources.discover(root, &DiscoveryOptions::default()).unwrap();
+        // `@generated` files are only left out once a build reads them
+        assert_eq!(
+            found.files,
+            [root.join("src/gen.rs"), root.join("src/lib.rs"), root.join("src/m.rs")]
+        );
+        assert_eq!(
+            found.skipped,
+            [
+                (root.join("scratch/t.rs"), Exclusion::Ignored),
+                (root.join("target"), Exclusion::Target),
+                (root.join("vendor"), Exclusion::Vendored),
+            ]
+        );
+
+        let all = DiscoveryOptions {
+            no_ignore: true,
+            skip_target: false,
+            skip_generated: false,
+            skip_vendored: false,
+            ..DiscoveryOptions::default()
+        };
+        assert_eq!(sources.discover(root, &all).unwrap().files.len(), 6);
+        assert!(matches!(
+            sources.read(Path::new("/p/src/none.rs")),
+            Err(KnowledgeGraphError::Read { .. })
+        ));
+    }
+
+    #[test]
+    fn overlay_reads_buffers_first_and_never_stats_them() {
+        let base: MemorySource = [("/p/src/lib.rs", "pub fn a() {}\n")].into_iter().collect();
+        let mut overlay = OverlaySource::new(base);
+        overlay.set("/p/src/lib.rs", "pub fn b() {}\n");
+        overlay.set("/p/src/new.rs", "pub fn c() {}\n");
+        assert_eq!(overlay.read(Path::new("/p/src/lib.rs")).unwrap(), "pub fn b() {}\n");
+        assert!(overlay.stat(Path::new("/p/src/lib.rs")).is_none());
+        let found = overlay.discover(Path::new("/p"), &DiscoveryOptions::default()).unwrap();
+        assert_eq!(found.files.len(), 2);
+
+        overlay.revert(Path::new("/p/src/lib.rs"));
+        assert_eq!(overlay.read(Path::new("/p/src/lib.rs")).unwrap(), "pub fn a() {}\n");
+    }
+}
@@ -70,6 +70,7 @@ pub mod config {
         pub rankdir: Option<String>, // "LR" | "TB"
         pub splines: Option<String>, // "curved" | "ortho" | "polyline"
         pub rounded: Option<bool>,
+        pub weighted: Option<bool>,
     }
 
     #[derive(Debug, Clone, Deserialize, Default)]
@@ -82,12 +83,63 @@ pub mod config {
         pub default_format: Option<String>, // "text" | "json"
     }
 
+    /// `[diff]`: limits that make `diff` exit non-zero; flags take precedence.
+    #[derive(Debug, Clone, Deserialize, Default)]
+    pub struct DiffConfig {
+        pub max_new_cycles: Option<usize>,
+        pub max_removed_items: Option<usize>,
+        pub max_added_relationships: Option<usize>,
+        pub max_removed_relationships: Option<usize>,
+        pub max_centrality_delta: Option<usize>,
+    }
+
+    /// `[filter]`: subgraph selection used when no filter flags are given.
+    #[derive(Debug, Clone, Deserialize, Default)]
+    pub struct FilterConfig {
+        #[serde(default)]
+        pub paths: Vec<String>, // globs, e.g. "src/graph/**"
+        #[serde(default)]
+        pub modules: Vec<String>, // e.g. "crate::graph"
+        #[serde(default)]
+        pub kinds: Vec<String>, // "function" | "struct" | ..
+        pub visibility: Option<String>, // "public" | "crate"
+        #[serde(default)]
+        pub relationships: Vec<String>, // "uses" | "calls" | ..
+        #[serde(default)]
+        pub focus: Vec<String>, // item ids
+        pub hops: Option<usize>,
+    }
+
+    /// `[build]`: which files builds scan; flags take precedence.
+    #[derive(Debug, Clone, Deserialize, Default)]
+    pub struct BuildConfig {
+        #[serde(default)]
+        pub include: Vec<String>, // globs, e.g. "src/**"
+        #[serde(default)]
+        pub exclude: Vec<String>, // globs, e.g. "benches/**"
+        pub skip_target: Option<bool>,
+        pub skip_generated: Option<bool>,
+        pub skip_vendored: Option<bool>,
+    }
+
+    /// `[cache]`: where builds keep their cache; flags and `RRE_CACHE_DIR` take precedence.
+    #[derive(Debug, Clone, Deserialize, Default)]
+    pub struct CacheConfig {
+        pub dir: Option<String>,
+        /// Keep the cache in `.knowledge_cache/` in the project root.
+        pub project: Option<bool>,
+    }
+
     #[derive(Debug, Clone, Deserialize, Default)]
     pub struct Config {
         pub root: Option<String>,
         pub dot: Option<DotConfig>,
         pub svg: Option<SvgConfig>,
         pub query: Option<QueryConfig>,
+        pub filter: Option<FilterConfig>,
+        pub diff: Option<DiffConfig>,
+        pub cache: Option<CacheConfig>,
+        pub build: Option<BuildConfig>,
     }
 
     fn default_config_path(root: &Path) -> PathBuf {
@@ -121,23 +173,133 @@ pub mod cache {
     use std::collections::HashMap;
     use std::path::{Path, PathBuf};
 
+    use crate::graph::incremental::FileAnalysis;
     use crate::graph::FileNode;
 
+    /// What a cache entry knows about its file.
+    ///
+    /// The content `hash` decides whether an entry is current. Modification time and length
+    /// are only a pre-check that lets unchanged files skip reading: they are trusted when
+    /// they match and the file was already older than `checked_at` by more than the
+    /// timestamp granularity, since a later edit must then change `mtime`. Anything else
+    /// (a touch, a same-length edit within the same second) is settled by hashing.
     #[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
     pub struct CacheEntryMeta {
+        /// Modification time: whole seconds since the epoch, plus nanoseconds.
         pub mtime: u64,
+        #[serde(default)]
+        pub mtime_nanos: u32,
         pub len: u64,
+        /// [`content_hash`] of the file; 0 in caches written before hashes were recorded.
+        #[serde(default)]
+        pub hash: u64,
+        /// Seconds since the epoch when `hash` was computed.
+        #[serde(default)]
+        pub checked_at: u64,
+    }
+
+    impl CacheEntryMeta {
+        /// Modification time and length of `path`, without a hash.
+        #[must_use]
+        pub fn stat(path: &Path) -> Self {
+            let meta = std::fs::metadata(path).ok();
+            let len = meta.as_ref().map_or(0, std::fs::Metadata::len);
+            let since = meta
+                .and_then(|m| m.modified().ok())
+                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                .unwrap_or_default();
+            Self {
+                mtime: since.as_secs(),
+                mtime_nanos: since.subsec_nanos(),
+                len,
+                ..Self::default()
+            }
+        }
+
+        /// The same stat with the hash of `content`, checked now.
+        #[must_use]
+        pub fn with_hash(mut self, content: &[u8]) -> Self {
+            self.hash = content_hash(content);
+            self.checked_at = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs());
+            self
+        }
+
+        /// True when the file behind `current` (from [`Self::stat`]) is known unchanged
+        /// without reading it.
+        #[must_use]
+        pub fn unchanged(&self, current: &Self) -> bool {
+            // One spare second covers filesystems with 2-second timestamps
+            self.hash != 0
+                && self.mtime == current.mtime
+                && self.mtime_nanos == current.mtime_nanos
+                && self.len == current.len
+                && self.mtime + 1 < self.checked_at
+        }
+    }
+
+    /// 64-bit FNV-1a hash of file content.
+    #[must_use]
+    pub fn content_hash(bytes: &[u8]) -> u64 {
+        const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+        const PRIME: u64 = 0x0100_0000_01b3;
+        // 0 marks "no hash recorded"
+        bytes.iter().fold(OFFSET, |h, &b| (h ^ u64::from(b)).wrapping_mul(PRIME)).max(1)
     }
 
     #[derive(Debug, Clone, Serialize, Deserialize, Default)]
     pub struct CacheEntry {
         pub meta: CacheEntryMeta,
         pub node: FileNode,
+        /// Relationship analysis of the file from the last single-root build; it may be
+        /// outdated (see `FileAnalysis::hash`) and is then only used to find what changed.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub analysis: Option<FileAnalysis>,
     }
 
+    /// Layout version of the cache file, bumped whenever what an entry stores changes.
+    pub const CACHE_FORMAT: u32 = 1;
+
+    /// Parsed files and their analyses, keyed by file path.
+    ///
+    /// A cache is only reused when it was written with this [`CACHE_FORMAT`] by this version of
+    /// the tool, since another parser may produce different `FileNode`s for the same text.
     #[derive(Debug, Clone, Serialize, Deserialize, Default)]
     pub struct Cache {
+        /// Set on save; caches from before versioning read as 0.
+        #[serde(default)]
+        pub format: u32,
+        /// Set on save to the crate version.
+        #[serde(default)]
+        pub tool_version: String,
         pub entries: HashMap<PathBuf, CacheEntry>,
+        /// Parses of files read from git revisions (`build --rev`), under [`revision_key`],
+        /// so every revision holding the same blob at the same path shares one entry.
+        #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+        pub revisions: HashMap<String, CacheEntry>,
+        /// Analysis features the cached analyses were made with; other features analyze
+        /// every file again.
+        #[serde(default)]
+        pub features: Vec<String>,
+    }
+
+    /// Revision entries unused for this long are dropped when the cache is saved after a
+    /// revision build.
+    pub const REVISION_TTL_SECS: u64 = 30 * 24 * 60 * 60;
+
+    /// Key of a revision entry: git blob id and path relative to the project root.
+    #[must_use]
+    pub fn revision_key(blob: &str, rel: &Path) -> String {
+        format!("{blob} {}", rel.to_string_lossy().replace('\\', "/"))
+    }
+
+    impl Cache {
+        /// Whether this build of the tool can reuse the cache.
+        #[must_use]
+        pub fn is_current(&self) -> bool {
+            self.format == CACHE_FORMAT && self.tool_version == env!("CARGO_PKG_VERSION")
+        }
     }
 
     #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -147,85 +309,1297 @@ pub mod cache {
         Rebuild,
     }
 
-    fn cache_path(root: &Path) -> PathBuf {
-        root.join(".knowledge_cache.json")
+    /// Where a project's cache file is kept.
+    ///
+    /// The lock and temporary files of a save sit next to the cache file, so each location
+    /// keeps all of them in its cache directory.
+    ///
+    /// The library never consults the environment for it; the CLI resolves the per-user
+    /// cache directory and passes it as [`Self::Dir`].
+    #[derive(Debug, Clone, Default, PartialEq, Eq)]
+    pub enum CacheLocation {
+        /// `.knowledge_cache/cache.json` in the project root.
+        #[default]
+        Project,
+        /// One file per project in this directory, named after the project path, so scanned
+        /// projects are left untouched.
+        Dir(PathBuf),
+        /// No cache file: nothing is loaded or saved, e.g. for sources that only live in
+        /// memory.
+        Disabled,
+    }
+
+    impl CacheLocation {
+        /// Cache file for the project at `root`; empty for [`Self::Disabled`].
+        #[must_use]
+        pub fn file(&self, root: &Path) -> PathBuf {
+            match self {
+                Self::Project => root.join(".knowledge_cache").join("cache.json"),
+                Self::Dir(dir) => dir.join(project_key(root)),
+                Self::Disabled => PathBuf::new(),
+            }
+        }
+    }
+
+    // `<dir name>-<hash of the absolute path>.json`, so checkouts with the same name differ.
+    fn project_key(root: &Path) -> String {
+        let abs = std::fs::canonicalize(root).unwrap_or_else(|_| root.to_path_buf());
+        let name: String = abs
+            .file_name()
+            .and_then(|s| s.to_str())
+            .unwrap_or("project")
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+            .collect();
+        let hash = content_hash(abs.to_string_lossy().as_bytes());
+        format!("{name}-{hash:016x}.json")
+    }
+
+    /// What reading a cache file found.
+    #[derive(Debug)]
+    pub enum CacheRead {
+        Missing,
+        /// The file exists but cannot be read or decoded.
+        Invalid(String),
+        /// Written with another format or tool version; never reused.
+        Outdated {
+            format: u32,
+            tool_version: String,
+        },
+        Current(Cache),
+    }
+
+    /// The cache file of one project, with an advisory lock file (`<cache>.lock`) next to it.
+    ///
+    /// Saves write a temporary file and rename it over the cache, so readers never see a
+    /// partial file, and hold the lock exclusively so concurrent runs do not interleave.
+    /// Saving or clearing also removes the `.knowledge_cache.json` that earlier versions kept
+    /// in the project root; its format is never reused.
+    #[derive(Debug, Clone)]
+    pub struct CacheStore {
+        file: PathBuf,
+        // Cache file of earlier versions; `None` for `CacheLocation::Disabled`
+        legacy: Option<PathBuf>,
+    }
+
+    impl CacheStore {
+        #[must_use]
+        pub fn new(root: &Path, location: &CacheLocation) -> Self {
+            let legacy =
+                (*location != CacheLocation::Disabled).then(|| root.join(".knowledge_cache.json"));
+            Self { file: location.file(root), legacy }
+        }
+
+        // Best-effort; returns whether a legacy cache file was removed
+        fn remove_legacy(&self) -> bool {
+            self.legacy.as_ref().is_some_and(|l| std::fs::remove_file(l).is_ok())
+        }
+
+        /// Path of the cache file.
+        #[must_use]
+        pub fn file(&self) -> &Path {
+            &self.file
+        }
+
+        fn lock_file(&self) -> PathBuf {
+            let mut name = self.file.as_os_str().to_owned();
+            name.push(".lock");
+            PathBuf::from(name)
+        }
+
+        fn lock(&self, exclusive: bool) -> std::io::Result<std::fs::File> {
+            let lock = std::fs::OpenOptions::new()
+                .create(true)
+                .truncate(false)
+                .write(true)
+                .open(self.lock_file())?;
+            if exclusive {
+                fs2::FileExt::lock_exclusive(&lock)?;
+            } else {
+                fs2::FileExt::lock_shared(&lock)?;
+            }
+            Ok(lock)
+        }
+
+        /// Read the cache file and check its version.
+        #[must_use]
+        pub fn read(&self) -> CacheRead {
+            if !self.file.exists() {
+                return CacheRead::Missing;
+            }
+            // Without a lock (read-only location) the rename still keeps reads whole
+            let _lock = self.lock(false).ok();
+            let cache = match std::fs::read(&self.file) {
+                Ok(data) => serde_json::from_slice::<Cache>(&data),
+                Err(e) => return CacheRead::Invalid(e.to_string()),
+            };
+            match cache {
+                Ok(c) if c.is_current() => CacheRead::Current(c),
+                Ok(c) => CacheRead::Outdated { format: c.format, tool_version: c.tool_version },
+                Err(e) => CacheRead::Invalid(e.to_string()),
+            }
+        }
+
+        /// The cache if it exists and this version can reuse it.
+        #[must_use]
+        pub fn load(&self) -> Option<Cache> {
+            match self.read() {
+                CacheRead::Current(c) => Some(c),
+                _ => None,
+            }
+        }
+
+        /// Replace the cache file atomically, stamping the current format and tool version.
+        /// Does nothing for [`CacheLocation::Disabled`].
+        ///
+        /// # Errors
+        /// Returns the I/O error when the directory, temporary file or lock cannot be written.
+        pub fn save(&self, cache: &Cache) -> std::io::Result<()> {
+            if self.file.as_os_str().is_empty() {
+                return Ok(());
+            }
+            if let Some(dir) = self.file.parent().filter(|d| !d.as_os_str().is_empty()) {
+                std::fs::create_dir_all(dir)?;
+            }
+            let _lock = self.lock(true)?;
+            let data = serde_json::to_vec(&Stamped {
+                format: CACHE_FORMAT,
+                tool_version: env!("CARGO_PKG_VERSION"),
+                entries: &cache.entries,
+                revisions: &cache.revisions,
+                features: &cache.features,
+            })
+            .map_err(std::io::Error::other)?;
+            let mut tmp = self.file.as_os_str().to_owned();
+            tmp.push(format!(".tmp{}", std::process::id()));
+            let tmp = PathBuf::from(tmp);
+            let written = (|| {
+                use std::io::Write;
+                let mut f = std::fs::File::create(&tmp)?;
+                f.write_all(&data)?;
+                f.sync_all()?;
+                std::fs::rename(&tmp, &self.file)
+            })();
+            if written.is_err() {
+                let _ = std::fs::remove_file(&tmp);
+            } else {
+                self.remove_legacy();
+            }
+            written
+        }
+
+        /// Remove the cache file, its lock file and a legacy cache file; returns whether there
+        /// was a cache file.
+        ///
+        /// # Errors
+        /// Returns the I/O error when the cache file exists but cannot be removed.
+        pub fn clear(&self) -> std::io::Result<bool> {
+            let existed = self.file.exists();
+            if existed {
+                let _lock = self.lock(true)?;
+                std::fs::remove_file(&self.file)?;
+            }
+            // Removed after unlocking, which Windows needs; a stale lock file is harmless
+            if !self.file.as_os_str().is_empty() {
+                let _ = std::fs::remove_file(self.lock_file());
+            }
+            Ok(self.remove_legacy() || existed)
+        }
+    }
+
+    /// Summary of a cache file, as shown by `cache stats`.
+    #[derive(Debug, Clone, Default, Serialize)]
+    pub struct CacheStats {
+        pub file: PathBuf,
+        /// `missing`, `invalid`, `outdated` or `current`.
+        pub state: String,
+        pub bytes: u64,
+        pub format: u32,
+        pub tool_version: String,
+        pub entries: usize,
+        /// Parses of files read from git revisions.
+        pub revisions: usize,
+        /// Entries that also hold a relationship analysis.
+        pub analyzed: usize,
+        pub items: usize,
+        /// Cached `Uses` and `Calls` edges.
+        pub edges: usize,
+    }
+
+    /// Result of checking a cache file against the files it describes (`cache verify`).
+    #[derive(Debug, Clone, Default, Serialize)]
+    pub struct CacheVerification {
+        pub file: PathBuf,
+        pub state: String,
+        /// Defects that make the cache unusable or inconsistent.
+        pub problems: Vec<String>,
+        /// Files whose content changed since they were cached; the next build refreshes them.
+        pub stale: Vec<PathBuf>,
+        /// Cached files that no longer exist; the next build drops them.
+        pub missing: Vec<PathBuf>,
     }
 
+    impl CacheVerification {
+        #[must_use]
+        pub fn is_ok(&self) -> bool {
+            self.problems.is_empty()
+        }
+    }
+
+    impl CacheStore {
+        /// Size, version and contents of the cache file.
+        #[must_use]
+        pub fn stats(&self) -> CacheStats {
+            let mut stats = CacheStats {
+                file: self.file.clone(),
+                bytes: std::fs::metadata(&self.file).map_or(0, |m| m.len()),
+                ..CacheStats::default()
+            };
+            let cache = match self.read() {
+                CacheRead::Missing => {
+                    stats.state = "missing".to_string();
+                    return stats;
+                }
+                CacheRead::Invalid(_) => {
+                    stats.state = "invalid".to_string();
+                    return stats;
+                }
+                CacheRead::Outdated { format, tool_version } => {
+                    stats.state = "outdated".to_string();
+                    stats.format = format;
+                    stats.tool_version = tool_version;
+                    return stats;
+                }
+                CacheRead::Current(c) => c,
+            };
+            stats.state = "current".to_string();
+            stats.format = cache.format;
+            stats.tool_version = cache.tool_version;
+            stats.entries = cache.entries.len();
+            stats.revisions = cache.revisions.len();
+            for entry in cache.entries.values() {
+                stats.items += entry.node.items.len();
+                if let Some(a) = &entry.analysis {
+                    stats.analyzed += 1;
+                    stats.edges += a.uses.len() + a.calls.len();
+                }
+            }
+            stats
+        }
+
+        /// Check that the cache decodes, is current, and which entries are outdated.
+        #[must_use]
+        pub fn verify(&self) -> CacheVerification {
+            let mut out = CacheVerification { file: self.file.clone(), ..Default::default() };
+            let cache = match self.read() {
+                CacheRead::Missing => {
+                    out.state = "missing".to_string();
+                    return out;
+                }
+                CacheRead::Invalid(e) => {
+                    out.state = "invalid".to_string();
+                    out.problems.push(format!("cannot decode cache: {e}"));
+                    return out;
+                }
+                CacheRead::Outdated { format, tool_version } => {
+                    out.state = "outdated".to_string();
+                    out.problems.push(format!(
+                        "written with format {format} by version {tool_version:?}; this is format {CACHE_FORMAT}, version {}",
+                        env!("CARGO_PKG_VERSION")
+                    ));
+                    return out;
+                }
+                CacheRead::Current(c) => c,
+            };
+            out.state = "current".to_string();
+            let mut paths: Vec<&PathBuf> = cache.entries.keys().collect();
+            paths.sort();
+            for path in paths {
+                let entry = &cache.entries[path];
+                if entry.node.path != *path {
+                    out.problems.push(format!(
+                        "entry {} holds the parse of {}",
+                        path.display(),
+                        entry.node.path.display()
+                    ));
+                }
+                match std::fs::read(path) {
+                    Ok(content) if content_hash(&content) == entry.meta.hash => {}
+                    Ok(_) => out.stale.push(path.clone()),
+                    Err(_) => out.missing.push(path.clone()),
+                }
+            }
+            out
+        }
+    }
+
+    // `Cache` as saved: current stamp, borrowed entries.
+    #[derive(Serialize)]
+    struct Stamped<'a> {
+        format: u32,
+        tool_version: &'a str,
+        entries: &'a HashMap<PathBuf, CacheEntry>,
+        #[serde(skip_serializing_if = "HashMap::is_empty")]
+        revisions: &'a HashMap<String, CacheEntry>,
+        features: &'a [String],
+    }
+
+    /// The cache of `root` at the default location, if this version can reuse it.
     #[must_use]
     pub fn load_cache(root: &Path) -> Option<Cache> {
-        let path = cache_path(root);
-        let data = std::fs::read_to_string(path).ok()?;
-        serde_json::from_str::<Cache>(&data).ok()
+        CacheStore::new(root, &CacheLocation::default()).load()
     }
 
+    /// Save the cache of `root` at the default location, best-effort.
     pub fn save_cache(root: &Path, cache: &Cache) {
-        let path = cache_path(root);
-        if let Ok(data) = serde_json::to_string_pretty(cache) {
-            let _ = std::fs::write(path, data);
-        }
+        let _ = CacheStore::new(root, &CacheLocation::default()).save(cache);
     }
 
+    /// Remove the cache of `root` at the default location, best-effort.
     pub fn clear_cache(root: &Path) {
-        let path = cache_path(root);
-        let _ = std::fs::remove_file(path);
+        let _ = CacheStore::new(root, &CacheLocation::default()).clear();
     }
 }
 
 pub mod file_walker {
-    /// Discover Rust source files under `root`, with an option to bypass ignore rules.
-    #[must_use]
-    pub fn rust_files_with_options(root: &str, no_ignore: bool) -> Vec<String> {
-        let mut out = Vec::new();
+    use crate::errors::KnowledgeGraphError;
+    use globset::{Glob, GlobSet, GlobSetBuilder};
+    use ignore::gitignore::{Gitignore, GitignoreBuilder};
+    use std::collections::{BTreeMap, HashMap, HashSet};
+    use std::fmt;
+    use std::io::Read;
+    use std::path::{Path, PathBuf};
+    use std::sync::{Arc, Mutex};
+
+    /// Which Rust files a build scans, in addition to `.gitignore`/`.ignore` rules.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct DiscoveryOptions {
+        /// Bypass `.gitignore`/`.ignore` rules.
+        pub no_ignore: bool,
+        /// Globs relative to the root; when any are given, only matching files are scanned.
+        pub include: Vec<String>,
+        /// Globs relative to the root; matching files are skipped, even when included.
+        pub exclude: Vec<String>,
+        /// Skip Cargo build directories: `target/` next to a `Cargo.toml`, and any directory
+        /// tagged with `CACHEDIR.TAG`.
+        pub skip_target: bool,
+        /// Skip build-script output (`OUT_DIR`) and files whose leading comments carry an
+        /// `@generated` marker. Discovery only prunes `OUT_DIR`; the marker is checked when a
+        /// build reads the file, so discovery never opens files.
+        pub skip_generated: bool,
+        /// Skip vendored crates: directories with a `.cargo-checksum.json`, and `vendor/` next
+        /// to a `Cargo.toml`.
+        pub skip_vendored: bool,
+    }
+
+    impl Default for DiscoveryOptions {
+        fn default() -> Self {
+            Self {
+                no_ignore: false,
+                include: Vec::new(),
+                exclude: Vec::new(),
+                skip_target: true,
+                skip_generated: true,
+                skip_vendored: true,
+            }
+        }
+    }
+
+    impl DiscoveryOptions {
+        /// The default rules, with ignore files bypassed or not.
+        #[must_use]
+        pub fn with_no_ignore(no_ignore: bool) -> Self {
+            Self { no_ignore, ..Self::default() }
+        }
+    }
+
+    /// Why discovery left a file, or a whole directory, out.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum Exclusion {
+        /// Matched by `.gitignore`/`.ignore`.
+        Ignored,
+        /// Matched by this exclude glob.
+        Excluded(String),
+        /// Include globs are set and none matches.
+        NotIncluded,
+        /// A Cargo build directory.
+        Target,
+        /// Build-script output under `target/<profile>/build/<pkg>-<hash>/out`.
+        OutDir,
+        /// The file declares itself `@generated`.
+        Generated,
+        /// A vendored crate.
+        Vendored,
+    }
+
+    impl fmt::Display for Exclusion {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                Self::Ignored => f.write_str("ignored by .gitignore/.ignore"),
+                Self::Excluded(glob) => write!(f, "excluded by `{glob}`"),
+                Self::NotIncluded => f.write_str("matches no include pattern"),
+                Self::Target => f.write_str("build output (target directory)"),
+                Self::OutDir => f.write_str("generated by a build script (OUT_DIR)"),
+                Self::Generated => f.write_str("generated (`@generated` header)"),
+                Self::Vendored => f.write_str("vendored crate"),
+            }
+        }
+    }
+
+    /// Outcome of a file walk: the files to scan and what was left out.
+    #[derive(Debug, Clone, Default)]
+    pub struct Discovery {
+        /// Rust files to scan, sorted.
+        pub files: Vec<PathBuf>,
+        /// Skipped Rust files and pruned directories, sorted by path. Files hidden by ignore
+        /// rules and `@generated` files are only listed by [`explain`].
+        pub skipped: Vec<(PathBuf, Exclusion)>,
+    }
+
+    pub(crate) struct Globs {
+        include: Option<GlobSet>,
+        exclude: GlobSet,
+        exclude_patterns: Vec<String>,
+    }
+
+    fn glob_set(patterns: &[String]) -> Result<GlobSet, KnowledgeGraphError> {
+        let mut builder = GlobSetBuilder::new();
+        for p in patterns {
+            let glob = Glob::new(p).map_err(|e| KnowledgeGraphError::InvalidGlob {
+                pattern: p.clone(),
+                message: e.kind().to_string(),
+            })?;
+            builder.add(glob);
+        }
+        builder.build().map_err(|e| KnowledgeGraphError::InvalidGlob {
+            pattern: patterns.join(", "),
+            message: e.to_string(),
+        })
+    }
+
+    impl Globs {
+        pub(crate) fn new(opts: &DiscoveryOptions) -> Result<Self, KnowledgeGraphError> {
+            Ok(Self {
+                include: if opts.include.is_empty() {
+                    None
+                } else {
+                    Some(glob_set(&opts.include)?)
+                },
+                exclude: glob_set(&opts.exclude)?,
+                exclude_patterns: opts.exclude.clone(),
+            })
+        }
+
+        pub(crate) fn check(&self, rel: &Path) -> Option<Exclusion> {
+            if let Some(i) = self.exclude.matches(rel).first() {
+                return Some(Exclusion::Excluded(self.exclude_patterns[*i].clone()));
+            }
+            match &self.include {
+                Some(set) if !set.is_match(rel) => Some(Exclusion::NotIncluded),
+                _ => None,
+            }
+        }
+    }
+
+    // Why the directory `dir` is skipped as a whole; `is_file` tells whether a path names a
+    // file, on disk or in a git tree.
+    pub(crate) fn dir_exclusion(
+        dir: &Path,
+        opts: &DiscoveryOptions,
+        is_file: impl Fn(&Path) -> bool,
+    ) -> Option<Exclusion> {
+        let named = |name: &str| dir.file_name() == Some(name.as_ref());
+        let next_to_manifest = || dir.parent().is_some_and(|p| is_file(&p.join("Cargo.toml")));
+        if opts.skip_target
+            && (is_file(&dir.join("CACHEDIR.TAG")) || (named("target") && next_to_manifest()))
+        {
+            Some(Exclusion::Target)
+        } else if opts.skip_vendored
+            && (is_file(&dir.join(".cargo-checksum.json"))
+                || (named("vendor") && next_to_manifest()))
+        {
+            Some(Exclusion::Vendored)
+        } else if opts.skip_generated && is_out_dir(dir) {
+            Some(Exclusion::OutDir)
+        } else {
+            None
+        }
+    }
+
+    // `build/<pkg>-<hash>/out`, where build scripts write their output
+    fn is_out_dir(dir: &Path) -> bool {
+        let mut names = dir.iter().rev().map(|c| c.to_string_lossy());
+        let (Some(out), Some(pkg), Some(build)) = (names.next(), names.next(), names.next()) else {
+            return false;
+        };
+        out == "out"
+            && build == "build"
+            && pkg.rsplit_once('-').is_some_and(|(_, hash)| {
+                hash.len() == 16 && hash.bytes().all(|b| b.is_ascii_hexdigit())
+            })
+    }
+
+    // True when a plain comment at the top of `path` contains `@generated`, the marker code
+    // generators put in their output
+    fn has_generated_header(path: &Path) -> bool {
+        let mut head = Vec::with_capacity(1024);
+        let Ok(file) = std::fs::File::open(path) else {
+            return false;
+        };
+        if file.take(1024).read_to_end(&mut head).is_err() {
+            return false;
+        }
+        generated_marker(&head)
+    }
+
+    // `@generated` in the plain comments that open `text`
+    pub(crate) fn generated_marker(text: &[u8]) -> bool {
+        let head = &text[..text.len().min(1024)];
+        for line in String::from_utf8_lossy(head).lines().map(str::trim) {
+            if line.is_empty() {
+                continue;
+            }
+            let comment = ["//", "/*", "*", "#!["].iter().any(|p| line.starts_with(p));
+            if !comment {
+                return false;
+            }
+            // Doc comments describe the module; generators mark files with plain comments
+            let doc = ["//!", "///", "/*!", "/**"].iter().any(|p| line.starts_with(p));
+            if !doc && line.contains("@generated") {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// `.gitignore`/`.ignore` rules of a tree that is not walked on disk, most specific
+    /// first: deeper directories before their parents and, within one directory, `.ignore`
+    /// before `.gitignore`.
+    pub(crate) struct IgnoreRules(Vec<(PathBuf, Gitignore)>);
+
+    impl IgnoreRules {
+        /// Rules from `(directory, is .ignore, content)` of each ignore file.
+        pub(crate) fn new(mut files: Vec<(PathBuf, bool, String)>) -> Self {
+            files.sort_by(|a, b| {
+                b.0.components().count().cmp(&a.0.components().count()).then(b.1.cmp(&a.1))
+            });
+            let mut rules = Vec::with_capacity(files.len());
+            for (dir, _, content) in files {
+                let mut builder = GitignoreBuilder::new(&dir);
+                for line in content.lines() {
+                    let _ = builder.add_line(None, line);
+                }
+                if let Ok(matcher) = builder.build() {
+                    rules.push((dir, matcher));
+                }
+            }
+            Self(rules)
+        }
+
+        /// The ignore file name and whether it is `.ignore`, for `path`'s file name.
+        pub(crate) fn kind(path: &Path) -> Option<bool> {
+            match path.file_name()?.to_str()? {
+                ".ignore" => Some(true),
+                ".gitignore" => Some(false),
+                _ => None,
+            }
+        }
+
+        pub(crate) fn ignored(&self, path: &Path) -> bool {
+            for (dir, rules) in &self.0 {
+                if !path.starts_with(dir) {
+                    continue;
+                }
+                let m = rules.matched_path_or_any_parents(path, false);
+                if m.is_ignore() {
+                    return true;
+                }
+                if m.is_whitelist() {
+                    return false;
+                }
+            }
+            false
+        }
+    }
+
+    /// Apply the discovery rules, but for `@generated` headers, to a listed tree.
+    ///
+    /// `tree` holds paths relative to the tree's top and the project sits at `base` in it;
+    /// `is_file` tells whether a project-relative path is a file. Returns the candidate paths
+    /// (from the top) and the skipped files and directories, under `root`.
+    pub(crate) fn select_listed<'a>(
+        root: &Path,
+        base: &Path,
+        tree: impl IntoIterator<Item = &'a Path>,
+        is_file: impl Fn(&Path) -> bool,
+        rules: &IgnoreRules,
+        globs: &Globs,
+        opts: &DiscoveryOptions,
+    ) -> (Vec<&'a Path>, Vec<(PathBuf, Exclusion)>) {
+        let mut candidates = Vec::new();
+        let mut skipped = Vec::new();
+        let mut pruned: BTreeMap<PathBuf, Exclusion> = BTreeMap::new();
+        let mut dirs: HashMap<PathBuf, Option<Exclusion>> = HashMap::new();
+        'files: for full in tree {
+            let Ok(rel) = full.strip_prefix(base) else { continue };
+            if rel.extension() != Some("rs".as_ref()) {
+                continue;
+            }
+            // Hidden files and directories are not walked, as on disk
+            if rel.iter().any(|c| c.to_string_lossy().starts_with('.')) {
+                continue;
+            }
+            let mut ancestors: Vec<&Path> =
+                rel.ancestors().skip(1).filter(|a| !a.as_os_str().is_empty()).collect();
+            ancestors.reverse();
+            for dir in ancestors {
+                let reason = dirs
+                    .entry(dir.to_path_buf())
+                    .or_insert_with(|| dir_exclusion(dir, opts, &is_file));
+                if let Some(reason) = reason {
+                    pruned.entry(root.join(dir)).or_insert_with(|| reason.clone());
+                    continue 'files;
+                }
+            }
+            let reason = if !opts.no_ignore && rules.ignored(full) {
+                Some(Exclusion::Ignored)
+            } else {
+                globs.check(rel)
+            };
+            match reason {
+                Some(r) => skipped.push((root.join(rel), r)),
+                None => candidates.push(full),
+            }
+        }
+        skipped.extend(pruned);
+        (candidates, skipped)
+    }
+
+    // One walk of `root`; ignore files are honored unless `opts.no_ignore` or `!rules`.
+    fn walk(root: &Path, opts: &DiscoveryOptions, globs: &Globs, rules: bool) -> Discovery {
+        let use_rules = rules && !opts.no_ignore;
         let mut walker = ignore::WalkBuilder::new(root);
         // Explicitly enable .gitignore/.ignore support and parent traversal (unless bypassed)
         walker
             .follow_links(false)
-            .git_ignore(!no_ignore)
+            .git_ignore(use_rules)
             .git_global(false)
             .git_exclude(false)
-            .ignore(!no_ignore)
+            .ignore(use_rules)
             .parents(true);
+        // Prune build, vendored and build-script directories without descending into them
+        let pruned = Arc::new(Mutex::new(Vec::new()));
+        {
+            let pruned = Arc::clone(&pruned);
+            let opts = opts.clone();
+            walker.filter_entry(move |entry| {
+                if entry.depth() == 0 || !entry.file_type().is_some_and(|t| t.is_dir()) {
+                    return true;
+                }
+                let dir = entry.path();
+                let Some(reason) = dir_exclusion(dir, &opts, Path::is_file) else {
+                    return true;
+                };
+                pruned
+                    .lock()
+                    .unwrap_or_else(std::sync::PoisonError::into_inner)
+                    .push((dir.to_path_buf(), reason));
+                false
+            });
+        }
         // Build a Gitignore matcher from root-level ignore files for explicit checks (unless bypassed)
-        let root_path = std::path::Path::new(root);
-        let matcher = if no_ignore {
-            None
-        } else {
-            let mut gi_builder = ignore::gitignore::GitignoreBuilder::new(root_path);
-            let gi = root_path.join(".gitignore");
+        let matcher = if use_rules {
+            let mut gi_builder = ignore::gitignore::GitignoreBuilder::new(root);
+            let gi = root.join(".gitignore");
             if gi.exists() {
                 let _ = gi_builder.add(gi);
             }
-            let ign = root_path.join(".ignore");
+            let ign = root.join(".ignore");
             if ign.exists() {
                 let _ = gi_builder.add(ign);
             }
             gi_builder.build().ok()
+        } else {
+            None
         };
+        let mut out = Discovery::default();
         for entry in walker.build().flatten() {
-            if entry.file_type().is_some_and(|t| t.is_file()) {
-                // Explicit filter using matcher (in addition to WalkBuilder's own filtering)
-                if let Some(m) = &matcher {
-                    if m.matched(entry.path(), false).is_ignore() {
+            if !entry.file_type().is_some_and(|t| t.is_file())
+                || entry.path().extension() != Some(std::ffi::OsStr::new("rs"))
+            {
+                continue;
+            }
+            // Explicit filter using matcher (in addition to WalkBuilder's own filtering)
+            if let Some(m) = &matcher {
+                if m.matched(entry.path(), false).is_ignore() {
+                    continue;
+                }
+            }
+            let path = entry.path();
+            let rel = path.strip_prefix(root).unwrap_or(path);
+            match globs.check(rel) {
+                Some(r) => out.skipped.push((path.to_path_buf(), r)),
+                None => out.files.push(path.to_path_buf()),
+            }
+        }
+        drop(walker);
+        out.skipped
+            .extend(pruned.lock().unwrap_or_else(std::sync::PoisonError::into_inner).drain(..));
+        out.files.sort();
+        out.skipped.sort_by(|a, b| a.0.cmp(&b.0));
+        out
+    }
+
+    /// Discover the Rust files under `root` that a build scans.
+    ///
+    /// # Errors
+    /// Returns `KnowledgeGraphError::InvalidGlob` for a malformed include or exclude glob.
+    pub fn discover(
+        root: &Path,
+        opts: &DiscoveryOptions,
+    ) -> Result<Discovery, KnowledgeGraphError> {
+        Ok(walk(root, opts, &Globs::new(opts)?, true))
+    }
+
+    /// As [`discover`], also listing the files hidden by ignore rules (a second walk) and,
+    /// with `skip_generated`, the files a build would leave out for their `@generated`
+    /// header. Unlike [`discover`] this reads the head of every selected file.
+    ///
+    /// # Errors
+    /// As for [`discover`].
+    pub fn explain(root: &Path, opts: &DiscoveryOptions) -> Result<Discovery, KnowledgeGraphError> {
+        let globs = Globs::new(opts)?;
+        let mut found = walk(root, opts, &globs, true);
+        if opts.skip_generated {
+            let (generated, files) =
+                found.files.into_iter().partition(|p: &PathBuf| has_generated_header(p));
+            found.files = files;
+            found.skipped.extend(generated.into_iter().map(|p| (p, Exclusion::Generated)));
+            found.skipped.sort_by(|a, b| a.0.cmp(&b.0));
+        }
+        if opts.no_ignore {
+            return Ok(found);
+        }
+        let all = walk(root, opts, &globs, false);
+        let seen: HashSet<&PathBuf> =
+            found.files.iter().chain(found.skipped.iter().map(|(p, _)| p)).collect();
+        let mut skipped = found.skipped.clone();
+        skipped.extend(
+            all.files.iter().filter(|p| !seen.contains(p)).map(|p| (p.clone(), Exclusion::Ignored)),
+        );
+        skipped.extend(all.skipped.iter().filter(|(p, _)| !seen.contains(p)).cloned());
+        skipped.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(Discovery { files: found.files, skipped })
+    }
+
+    /// Discover Rust source files under `root` with the default rules, with an option to
+    /// bypass ignore rules.
+    #[must_use]
+    pub fn rust_files_with_options(root: &str, no_ignore: bool) -> Vec<String> {
+        discover(Path::new(root), &DiscoveryOptions::with_no_ignore(no_ignore))
+            .map(|d| d.files.iter().filter_map(|p| p.to_str().map(String::from)).collect())
+            .unwrap_or_default()
+    }
+
+    /// Discover Rust source files under `root` with the default rules, respecting ignore
+    /// files.
+    #[must_use]
+    pub fn rust_files(root: &str) -> Vec<String> {
+        rust_files_with_options(root, false)
+    }
+}
+
+pub mod git {
+    use super::file_walker::{
+        generated_marker, select_listed, Discovery, DiscoveryOptions, Exclusion, Globs, IgnoreRules,
+    };
+    use crate::errors::KnowledgeGraphError;
+    use std::collections::{BTreeMap, HashSet};
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::path::{Path, PathBuf};
+    use std::process::{Command, Stdio};
+
+    /// A Rust file of a revision.
+    #[derive(Debug, Clone)]
+    pub struct RevisionFile {
+        /// Where the file is in a checkout of the revision at the project root.
+        pub path: PathBuf,
+        /// Git blob id of the content.
+        pub blob: String,
+        pub text: String,
+    }
+
+    /// The Rust files of a project at one git revision, read from the object database
+    /// without touching the working tree.
+    ///
+    /// Files are selected as by [`super::file_walker::discover`], with the `.gitignore` and
+    /// `.ignore` files of that revision. The project's `Cargo.toml` files are read from the
+    /// revision too, for crate names.
+    #[derive(Debug, Clone)]
+    pub struct Revision {
+        root: PathBuf,
+        commit: String,
+        files: Vec<RevisionFile>,
+        manifests: Vec<RevisionFile>,
+        skipped: Vec<(PathBuf, Exclusion)>,
+    }
+
+    fn git(dir: &Path, args: &[&str]) -> Result<String, KnowledgeGraphError> {
+        let out = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(args)
+            .output()
+            .map_err(|e| KnowledgeGraphError::Git(format!("cannot run git: {e}")))?;
+        if !out.status.success() {
+            let msg = String::from_utf8_lossy(&out.stderr).trim().to_string();
+            return Err(KnowledgeGraphError::Git(msg));
+        }
+        String::from_utf8(out.stdout).map_err(|e| KnowledgeGraphError::Git(e.to_string()))
+    }
+
+    // Content of `blobs`, in order, through one `git cat-file --batch`
+    fn read_blobs(dir: &Path, blobs: &[&str]) -> Result<Vec<Vec<u8>>, KnowledgeGraphError> {
+        if blobs.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut child = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["cat-file", "--batch"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| KnowledgeGraphError::Git(format!("cannot run git: {e}")))?;
+        let (Some(mut stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+            return Err(KnowledgeGraphError::Git("cat-file without pipes".to_string()));
+        };
+        let request: String = blobs.iter().map(|b| format!("{b}\n")).collect();
+        // Write from another thread so a full output pipe cannot block the request
+        let writer = std::thread::spawn(move || stdin.write_all(request.as_bytes()));
+        let mut out = BufReader::new(stdout);
+        let mut contents = Vec::with_capacity(blobs.len());
+        let mut header = String::new();
+        for blob in blobs {
+            header.clear();
+            out.read_line(&mut header)?;
+            let mut fields = header.split_whitespace().skip(1);
+            let size = match (fields.next(), fields.next().map(str::parse::<usize>)) {
+                (Some("blob"), Some(Ok(size))) => size,
+                _ => {
+                    let _ = child.kill();
+                    return Err(KnowledgeGraphError::Git(format!(
+                        "cannot read blob {blob}: {}",
+                        header.trim()
+                    )));
+                }
+            };
+            // Content, then a newline
+            let mut data = vec![0; size + 1];
+            out.read_exact(&mut data)?;
+            data.pop();
+            contents.push(data);
+        }
+        let _ = writer.join();
+        let _ = child.wait();
+        Ok(contents)
+    }
+
+    impl Revision {
+        /// List and read the Rust files of the project at `root` as of `rev` (a commit, branch,
+        /// tag or any other revision git understands).
+        ///
+        /// # Errors
+        /// Returns `KnowledgeGraphError::Git` when `root` is not in a git repository, `rev`
+        /// does not name a commit, or git cannot be run; `InvalidGlob` for malformed globs;
+        /// and `Read` for a selected file that is not UTF-8.
+        pub fn open(
+            root: &Path,
+            rev: &str,
+            opts: &DiscoveryOptions,
+        ) -> Result<Self, KnowledgeGraphError> {
+            if rev.is_empty() || rev.starts_with('-') {
+                return Err(KnowledgeGraphError::Git(format!("invalid revision `{rev}`")));
+            }
+            let globs = Globs::new(opts)?;
+            // Where the project sits in the repository, e.g. `crates/core/`
+            let prefix = git(root, &["rev-parse", "--show-prefix"])?.trim_end().to_string();
+            let commit =
+                git(root, &["rev-parse", "--verify", "--quiet", &format!("{rev}^{{commit}}")])
+                    .map_err(|_| KnowledgeGraphError::Git(format!("unknown revision `{rev}`")))?
+                    .trim()
+                    .to_string();
+            let listing = git(root, &["ls-tree", "-r", "-z", "--full-tree", &commit])?;
+
+            // Regular files and their blob ids, by path from the repository top
+            let mut tree: BTreeMap<PathBuf, &str> = BTreeMap::new();
+            for record in listing.split('\0').filter(|r| !r.is_empty()) {
+                let Some((info, path)) = record.split_once('\t') else { continue };
+                let mut fields = info.split(' ');
+                if let (Some(mode), Some("blob"), Some(blob)) =
+                    (fields.next(), fields.next(), fields.next())
+                {
+                    if mode != "120000" {
+                        tree.insert(PathBuf::from(path), blob);
+                    }
+                }
+            }
+            let base = PathBuf::from(&prefix);
+            let in_project: HashSet<PathBuf> = tree
+                .keys()
+                .filter_map(|p| p.strip_prefix(&base).ok())
+                .map(Path::to_path_buf)
+                .collect();
+
+            let rules = if opts.no_ignore {
+                IgnoreRules::new(Vec::new())
+            } else {
+                Self::ignore_rules(root, &tree, &base)?
+            };
+            let (selected, mut skipped) = select_listed(
+                root,
+                &base,
+                tree.keys().map(PathBuf::as_path),
+                |p| in_project.contains(p),
+                &rules,
+                &globs,
+                opts,
+            );
+            let candidates: Vec<(&Path, &str)> = selected
+                .into_iter()
+                .filter_map(|full| Some((full.strip_prefix(&base).ok()?, *tree.get(full)?)))
+                .collect();
+
+            let ids: Vec<&str> = candidates.iter().map(|(_, b)| *b).collect();
+            let mut files = Vec::with_capacity(candidates.len());
+            for ((rel, blob), content) in candidates.iter().zip(read_blobs(root, &ids)?) {
+                let path = root.join(rel);
+                if opts.skip_generated && generated_marker(&content) {
+                    skipped.push((path, Exclusion::Generated));
+                    continue;
+                }
+                let text = String::from_utf8(content).map_err(|e| KnowledgeGraphError::Read {
+                    file: path.clone(),
+                    source: std::io::Error::new(std::io::ErrorKind::InvalidData, e),
+                })?;
+                files.push(RevisionFile { path, blob: (*blob).to_string(), text });
+            }
+            skipped.sort_by(|a, b| a.0.cmp(&b.0));
+
+            let manifests: Vec<(&Path, &str)> = tree
+                .iter()
+                .filter(|(p, _)| p.file_name().is_some_and(|n| n == "Cargo.toml"))
+                .filter_map(|(p, blob)| Some((p.strip_prefix(&base).ok()?, *blob)))
+                .collect();
+            let ids: Vec<&str> = manifests.iter().map(|(_, b)| *b).collect();
+            let manifests = manifests
+                .iter()
+                .zip(read_blobs(root, &ids)?)
+                .map(|((rel, blob), content)| RevisionFile {
+                    path: root.join(rel),
+                    blob: (*blob).to_string(),
+                    text: String::from_utf8_lossy(&content).into_owned(),
+                })
+                .collect();
+            Ok(Self { root: root.to_path_buf(), commit, files, manifests, skipped })
+        }
+
+        // Ignore files of the project and of the directories above it in the repository
+        fn ignore_rules(
+            root: &Path,
+            tree: &BTreeMap<PathBuf, &str>,
+            base: &Path,
+        ) -> Result<IgnoreRules, KnowledgeGraphError> {
+            let found: Vec<(&Path, bool, &str)> = tree
+                .iter()
+                .filter_map(|(path, blob)| {
+                    let is_ignore = IgnoreRules::kind(path)?;
+                    let dir = path.parent()?;
+                    let relevant = dir.starts_with(base) || base.starts_with(dir);
+                    relevant.then_some((dir, is_ignore, *blob))
+                })
+                .collect();
+            let blobs: Vec<&str> = found.iter().map(|(_, _, b)| *b).collect();
+            let files = found
+                .iter()
+                .zip(read_blobs(root, &blobs)?)
+                .map(|((dir, is_ignore, _), content)| {
+                    (dir.to_path_buf(), *is_ignore, String::from_utf8_lossy(&content).into_owned())
+                })
+                .collect();
+            Ok(IgnoreRules::new(files))
+        }
+
+        /// The project root the revision was read for.
+        #[must_use]
+        pub fn root(&self) -> &Path {
+            &self.root
+        }
+
+        /// Full id of the commit `rev` resolved to.
+        #[must_use]
+        pub fn commit(&self) -> &str {
+            &self.commit
+        }
+
+        /// The selected files, sorted by path.
+        #[must_use]
+        pub fn files(&self) -> &[RevisionFile] {
+            &self.files
+        }
+
+        /// The project's `Cargo.toml` files, sorted by path.
+        #[must_use]
+        pub fn manifests(&self) -> &[RevisionFile] {
+            &self.manifests
+        }
+
+        /// Take the selected files.
+        #[must_use]
+        pub fn into_files(self) -> Vec<RevisionFile> {
+            self.files
+        }
+
+        /// What was selected and skipped, as for the working tree. Files hidden by ignore
+        /// rules are always listed.
+        #[must_use]
+        pub fn discovery(&self) -> Discovery {
+            Discovery {
+                files: self.files.iter().map(|f| f.path.clone()).collect(),
+                skipped: self.skipped.clone(),
+            }
+        }
+    }
+}
+
+pub mod watch {
+    use super::cache::CacheEntryMeta;
+    use super::file_walker::{discover, DiscoveryOptions};
+    use std::collections::{BTreeSet, HashMap};
+    use std::path::{Path, PathBuf};
+    use std::sync::mpsc;
+    use std::time::{Duration, Instant};
+
+    /// How a [`SourceWatcher`] notices edits.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum WatchBackend {
+        /// Filesystem notifications (inotify, FSEvents, ReadDirectoryChangesW).
+        Native,
+        /// Re-scan the roots every interval; for network and container filesystems
+        /// that deliver no notifications.
+        Poll(Duration),
+    }
+
+    /// Settings of a [`SourceWatcher`].
+    #[derive(Debug, Clone)]
+    pub struct WatchOptions {
+        pub backend: WatchBackend,
+        /// Quiet period after the last change before a batch is reported.
+        pub debounce: Duration,
+        /// Discovery rules, as for the build.
+        pub discovery: DiscoveryOptions,
+    }
+
+    impl Default for WatchOptions {
+        fn default() -> Self {
+            Self {
+                backend: WatchBackend::Native,
+                debounce: Duration::from_millis(200),
+                discovery: DiscoveryOptions::default(),
+            }
+        }
+    }
+
+    // (mtime, nanos, len) of every discovered source file
+    type Snapshot = HashMap<PathBuf, (u64, u32, u64)>;
+
+    /// Watches the Rust files a build of `roots` would discover and reports batches of
+    /// changed files.
+    ///
+    /// Notifications only wake the watcher: each batch is settled by re-running file
+    /// discovery, so ignore rules apply exactly as in the build, and events for ignored,
+    /// non-Rust or merely read files never trigger a rebuild.
+    pub struct SourceWatcher {
+        roots: Vec<PathBuf>,
+        options: WatchOptions,
+        snapshot: Snapshot,
+        events: Option<(notify::RecommendedWatcher, mpsc::Receiver<notify::Result<notify::Event>>)>,
+    }
+
+    impl SourceWatcher {
+        /// Start watching; falls back to polling every second when notifications cannot
+        /// be set up (see [`SourceWatcher::is_polling`]).
+        #[must_use]
+        pub fn new(roots: &[PathBuf], options: WatchOptions) -> Self {
+            let mut watcher =
+                Self { roots: roots.to_vec(), options, snapshot: Snapshot::new(), events: None };
+            if watcher.options.backend == WatchBackend::Native {
+                match watcher.subscribe() {
+                    Ok(events) => watcher.events = Some(events),
+                    Err(_) => watcher.options.backend = WatchBackend::Poll(Duration::from_secs(1)),
+                }
+            }
+            watcher.snapshot = watcher.scan();
+            watcher
+        }
+
+        fn subscribe(
+            &self,
+        ) -> notify::Result<(
+            notify::RecommendedWatcher,
+            mpsc::Receiver<notify::Result<notify::Event>>,
+        )> {
+            use notify::Watcher;
+            let (tx, rx) = mpsc::channel();
+            let mut watcher = notify::recommended_watcher(tx)?;
+            for root in &self.roots {
+                watcher.watch(root, notify::RecursiveMode::Recursive)?;
+            }
+            Ok((watcher, rx))
+        }
+
+        /// True when changes are found by polling rather than notifications.
+        #[must_use]
+        pub fn is_polling(&self) -> bool {
+            matches!(self.options.backend, WatchBackend::Poll(_))
+        }
+
+        fn scan(&self) -> Snapshot {
+            let mut out = Snapshot::new();
+            for root in &self.roots {
+                // Invalid globs fail the build itself; the watcher then sees no files
+                let files = discover(root, &self.options.discovery).map(|d| d.files);
+                for path in files.unwrap_or_default() {
+                    let m = CacheEntryMeta::stat(&path);
+                    out.insert(path, (m.mtime, m.mtime_nanos, m.len));
+                }
+            }
+            out
+        }
+
+        // Files added, removed or re-stamped since the last reported batch
+        fn changed(&self, current: &Snapshot) -> BTreeSet<PathBuf> {
+            let mut out: BTreeSet<PathBuf> = current
+                .iter()
+                .filter(|(p, stamp)| self.snapshot.get(*p) != Some(stamp))
+                .map(|(p, _)| p.clone())
+                .collect();
+            out.extend(self.snapshot.keys().filter(|p| !current.contains_key(*p)).cloned());
+            out
+        }
+
+        /// Block until discovered files change and return them (sorted), once no further
+        /// change arrived for the debounce period.
+        pub fn wait(&mut self) -> Vec<PathBuf> {
+            loop {
+                if let Some(batch) = self.wait_timeout(Duration::from_secs(3600)) {
+                    return batch;
+                }
+            }
+        }
+
+        /// As [`SourceWatcher::wait`], giving up with `None` after `timeout` without changes.
+        pub fn wait_timeout(&mut self, timeout: Duration) -> Option<Vec<PathBuf>> {
+            let deadline = Instant::now() + timeout;
+            loop {
+                let touched = self.next_trigger(deadline)?;
+                let current = self.scan();
+                let mut changed = self.changed(&current);
+                // A write that keeps size and timestamp is still reported by notifications
+                changed.extend(touched.into_iter().filter(|p| current.contains_key(p)));
+                self.snapshot = current;
+                if !changed.is_empty() {
+                    return Some(changed.into_iter().collect());
+                }
+            }
+        }
+
+        // Wait for a possibly relevant change and let the burst settle; returns the paths
+        // named by notifications (empty when polling), or `None` at the deadline.
+        fn next_trigger(&mut self, deadline: Instant) -> Option<BTreeSet<PathBuf>> {
+            let debounce = self.options.debounce;
+            let Some((_, rx)) = &self.events else {
+                let WatchBackend::Poll(interval) = self.options.backend else {
+                    unreachable!("native backend without a subscription")
+                };
+                loop {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return None;
+                    }
+                    std::thread::sleep(interval.min(deadline - now));
+                    let mut current = self.scan();
+                    if self.changed(&current).is_empty() {
                         continue;
                     }
+                    // Settle: wait until two scans a debounce apart agree
+                    loop {
+                        std::thread::sleep(debounce);
+                        let next = self.scan();
+                        if next == current {
+                            return Some(BTreeSet::new());
+                        }
+                        current = next;
+                    }
+                }
+            };
+            let mut touched = BTreeSet::new();
+            loop {
+                let now = Instant::now();
+                if now >= deadline {
+                    return None;
+                }
+                match rx.recv_timeout(deadline - now) {
+                    Ok(Ok(event)) => {
+                        if relevant(&event, &mut touched) {
+                            break;
+                        }
+                    }
+                    // Overflowed queues and similar: rescan to be safe
+                    Ok(Err(_)) => break,
+                    Err(mpsc::RecvTimeoutError::Timeout) => return None,
+                    Err(mpsc::RecvTimeoutError::Disconnected) => {
+                        self.events = None;
+                        self.options.backend = WatchBackend::Poll(Duration::from_secs(1));
+                        return self.next_trigger(deadline);
+                    }
                 }
-                if entry.path().extension() == Some(std::ffi::OsStr::new("rs")) {
-                    if let Some(s) = entry.path().to_str() {
-                        out.push(s.to_string());
+            }
+            // Debounce: keep collecting until the channel stays quiet
+            loop {
+                match rx.recv_timeout(debounce) {
+                    Ok(Ok(event)) => {
+                        relevant(&event, &mut touched);
                     }
+                    Ok(Err(_)) => {}
+                    Err(_) => return Some(touched),
                 }
             }
         }
-        out
     }
 
-    /// Backward-compatible helper that reads env var and delegates to `rust_files_with_options`.
-    #[must_use]
-    pub fn rust_files(root: &str) -> Vec<String> {
-        let no_ignore = std::env::var("KNOWLEDGE_RS_NO_IGNORE")
-            .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
-            .unwrap_or(false);
-        rust_files_with_options(root, no_ignore)
+    // Records the Rust files an event names; ignore-file edits count too, since they
+    // change which files are discovered. Reads never count: the build itself reads.
+    fn relevant(event: &notify::Event, touched: &mut BTreeSet<PathBuf>) -> bool {
+        if matches!(event.kind, notify::EventKind::Access(_)) {
+            return false;
+        }
+        let mut any = false;
+        for p in &event.paths {
+            if p.extension().is_some_and(|e| e == "rs") {
+                touched.insert(p.clone());
+                any = true;
+            } else if is_ignore_file(p) || p.extension().is_none() {
+                // Directories (renamed or removed wholesale) and ignore files
+                any = true;
+            }
+        }
+        any
+    }
+
+    fn is_ignore_file(p: &Path) -> bool {
+        p.file_name().is_some_and(|n| n == ".gitignore" || n == ".ignore")
     }
 }
 
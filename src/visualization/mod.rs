@@ -30,6 +30,9 @@ pub struct DotOptions {
     pub rankdir: RankDir,
     pub splines: EdgeStyle,
     pub rounded: bool,
+    /// Scale edge width by `Relationship::weight` (confidence times site count) instead of
+    /// confidence alone.
+    pub weighted: bool,
 }
 
 impl Default for DotOptions {
@@ -41,6 +44,7 @@ impl Default for DotOptions {
             rankdir: RankDir::LR,
             splines: EdgeStyle::Polyline,
             rounded: true,
+            weighted: false,
         }
     }
 }
@@ -242,7 +246,12 @@ impl DotGenerator {
                     (format!("calls:{call_type}"), "#d62728", "solid")
                 }
             };
-            let penwidth = 0.8_f64.max(rel.strength).min(3.0);
+            let penwidth = if opts.weighted {
+                // Logarithmic, so a 200-site edge is thick without swamping the drawing
+                (0.8 * (1.0 + rel.weight().max(1.0).ln())).min(6.0)
+            } else {
+                0.8_f64.max(rel.strength).min(3.0)
+            };
             let _ = writeln!(
                 s,
                 "  \"{from}\" -> \"{to}\" [label=\"{}\", color=\"{color}\", style=\"{style}\", penwidth={penwidth}];",
@@ -1,7 +1,7 @@
 use rust_relations_explorer::app::run_cli;
 use rust_relations_explorer::cli::{
-    CentralityMetricArg, Cli, Commands, Direction, DotRankDirArg, DotSplinesArg, DotThemeArg,
-    OnOffArg, OutputFormat, QueryCommands,
+    BuildArgs, CacheLocationArgs, CentralityMetricArg, Cli, Commands, Direction, DiscoveryArgs,
+    DotRankDirArg, DotSplinesArg, DotThemeArg, FilterArgs, OnOffArg, OutputFormat, QueryCommands,
 };
 use std::fs;
 use std::io::Write;
@@ -16,6 +16,11 @@ fn write_file(path: &PathBuf, content: &str) {
     f.write_all(content.as_bytes()).unwrap();
 }
 
+// Builds neither read nor write a cache, so tests leave the user cache directory alone
+fn uncached() -> BuildArgs {
+    BuildArgs { no_cache: true, ..BuildArgs::default() }
+}
+
 #[test]
 fn app_build_generates_dot_and_json() {
     let dir = tempdir().unwrap();
@@ -32,11 +37,18 @@ fn app_build_generates_dot_and_json() {
         verbose: 0,
         quiet: false,
         command: Commands::Build {
-            path: Some(root.to_path_buf()),
+            path: vec![root.to_path_buf()],
             config: None,
             no_ignore: false,
-            no_cache: false,
-            rebuild: false,
+            build: uncached(),
+            stats: false,
+            watch: false,
+            poll: false,
+            poll_interval: 1000,
+            debounce: 200,
+            discovery: DiscoveryArgs::default(),
+            list_files: false,
+            rev: None,
             json: Some(json_out.display().to_string()),
             dot: Some(dot_out.display().to_string()),
             svg: None,
@@ -46,8 +58,11 @@ fn app_build_generates_dot_and_json() {
             dot_rankdir: DotRankDirArg::LR,
             dot_splines: DotSplinesArg::Curved,
             dot_rounded: OnOffArg::On,
+            dot_weighted: OnOffArg::Off,
             svg_interactive: OnOffArg::On,
             save: None,
+            graph_format: None,
+            filter: FilterArgs::default(),
         },
     };
 
@@ -81,11 +96,18 @@ fn app_query_connected_files_json_branch() {
                 no_ignore: false,
                 file_pos: None,
                 file: Some(src.join("a.rs").display().to_string()),
+                explain: false,
                 graph: None,
                 format: OutputFormat::Json,
                 offset: 0,
                 limit: None,
             },
+            min_confidence: 0.0,
+            graph_format: None,
+            root: None,
+            filter: FilterArgs::default(),
+            build: uncached(),
+            discovery: DiscoveryArgs::default(),
         },
     };
 
@@ -115,11 +137,18 @@ fn app_query_function_usage_callers_and_callees() {
                 no_ignore: false,
                 function: "foo".into(),
                 direction: Direction::Callers,
+                through_traits: false,
                 graph: None,
                 format: OutputFormat::Text,
                 offset: 0,
                 limit: None,
             },
+            min_confidence: 0.0,
+            graph_format: None,
+            root: None,
+            filter: FilterArgs::default(),
+            build: uncached(),
+            discovery: DiscoveryArgs::default(),
         },
     };
     assert_eq!(run_cli(cli_callers), 0);
@@ -135,11 +164,18 @@ fn app_query_function_usage_callers_and_callees() {
                 no_ignore: false,
                 function: "bar".into(),
                 direction: Direction::Callees,
+                through_traits: false,
                 graph: None,
                 format: OutputFormat::Json,
                 offset: 0,
                 limit: None,
             },
+            min_confidence: 0.0,
+            graph_format: None,
+            root: None,
+            filter: FilterArgs::default(),
+            build: uncached(),
+            discovery: DiscoveryArgs::default(),
         },
     };
     assert_eq!(run_cli(cli_callees), 0);
@@ -168,6 +204,12 @@ fn app_query_cycles_json_branch() {
                 offset: 0,
                 limit: None,
             },
+            min_confidence: 0.0,
+            graph_format: None,
+            root: None,
+            filter: FilterArgs::default(),
+            build: uncached(),
+            discovery: DiscoveryArgs::default(),
         },
     };
     assert_eq!(run_cli(cli), 0);
@@ -200,6 +242,12 @@ fn app_query_path_json_path_and_no_path() {
                 offset: 0,
                 limit: None,
             },
+            min_confidence: 0.0,
+            graph_format: None,
+            root: None,
+            filter: FilterArgs::default(),
+            build: uncached(),
+            discovery: DiscoveryArgs::default(),
         },
     };
     assert_eq!(run_cli(cli_json), 0);
@@ -220,6 +268,12 @@ fn app_query_path_json_path_and_no_path() {
                 offset: 0,
                 limit: None,
             },
+            min_confidence: 0.0,
+            graph_format: None,
+            root: None,
+            filter: FilterArgs::default(),
+            build: uncached(),
+            discovery: DiscoveryArgs::default(),
         },
     };
     assert_eq!(run_cli(cli_no_path), 0);
@@ -246,10 +300,17 @@ fn app_query_module_centrality_json() {
                 graph: None,
                 metric: CentralityMetricArg::Total,
                 top: 3,
+                weighted: false,
                 format: OutputFormat::Json,
                 offset: 0,
                 limit: None,
             },
+            min_confidence: 0.0,
+            graph_format: None,
+            root: None,
+            filter: FilterArgs::default(),
+            build: uncached(),
+            discovery: DiscoveryArgs::default(),
         },
     };
     assert_eq!(run_cli(cli), 0);
@@ -281,6 +342,12 @@ fn app_query_trait_impls_text_and_json() {
                 offset: 0,
                 limit: None,
             },
+            min_confidence: 0.0,
+            graph_format: None,
+            root: None,
+            filter: FilterArgs::default(),
+            build: uncached(),
+            discovery: DiscoveryArgs::default(),
         },
     };
     assert_eq!(run_cli(cli_text), 0);
@@ -299,6 +366,12 @@ fn app_query_trait_impls_text_and_json() {
                 offset: 0,
                 limit: None,
             },
+            min_confidence: 0.0,
+            graph_format: None,
+            root: None,
+            filter: FilterArgs::default(),
+            build: uncached(),
+            discovery: DiscoveryArgs::default(),
         },
     };
     assert_eq!(run_cli(cli_json), 0);
@@ -317,11 +390,18 @@ fn app_build_with_cache_flags_and_no_ignore() {
         verbose: 0,
         quiet: false,
         command: Commands::Build {
-            path: Some(root.to_path_buf()),
+            path: vec![root.to_path_buf()],
             config: None,
             no_ignore: true,
-            no_cache: true,
-            rebuild: false,
+            build: BuildArgs { no_cache: true, ..BuildArgs::default() },
+            stats: false,
+            watch: false,
+            poll: false,
+            poll_interval: 1000,
+            debounce: 200,
+            discovery: DiscoveryArgs::default(),
+            list_files: false,
+            rev: None,
             json: None,
             dot: None,
             svg: None,
@@ -331,8 +411,11 @@ fn app_build_with_cache_flags_and_no_ignore() {
             dot_rankdir: DotRankDirArg::LR,
             dot_splines: DotSplinesArg::Curved,
             dot_rounded: OnOffArg::Off,
+            dot_weighted: OnOffArg::Off,
             svg_interactive: OnOffArg::Off,
             save: None,
+            graph_format: None,
+            filter: FilterArgs::default(),
         },
     };
     assert_eq!(run_cli(cli_no_cache), 0);
@@ -342,11 +425,22 @@ fn app_build_with_cache_flags_and_no_ignore() {
         verbose: 0,
         quiet: false,
         command: Commands::Build {
-            path: Some(root.to_path_buf()),
+            path: vec![root.to_path_buf()],
             config: None,
             no_ignore: false,
-            no_cache: false,
-            rebuild: true,
+            build: BuildArgs {
+                rebuild: true,
+                cache: CacheLocationArgs { project_cache: true, ..CacheLocationArgs::default() },
+                ..BuildArgs::default()
+            },
+            stats: false,
+            watch: false,
+            poll: false,
+            poll_interval: 1000,
+            debounce: 200,
+            discovery: DiscoveryArgs::default(),
+            list_files: false,
+            rev: None,
             json: None,
             dot: None,
             svg: None,
@@ -356,8 +450,11 @@ fn app_build_with_cache_flags_and_no_ignore() {
             dot_rankdir: DotRankDirArg::LR,
             dot_splines: DotSplinesArg::Curved,
             dot_rounded: OnOffArg::Off,
+            dot_weighted: OnOffArg::Off,
             svg_interactive: OnOffArg::Off,
             save: None,
+            graph_format: None,
+            filter: FilterArgs::default(),
         },
     };
     assert_eq!(run_cli(cli_rebuild), 0);
@@ -385,13 +482,57 @@ fn app_query_hubs_text_branch() {
                 graph: None,
                 metric: CentralityMetricArg::In,
                 top: 5,
+                weighted: false,
                 format: OutputFormat::Text,
                 offset: 0,
                 limit: None,
             },
+            min_confidence: 0.0,
+            graph_format: None,
+            root: None,
+            filter: FilterArgs::default(),
+            build: uncached(),
+            discovery: DiscoveryArgs::default(),
         },
     };
 
     let code = run_cli(cli);
     assert_eq!(code, 0);
 }
+
+#[test]
+fn app_query_public_api_text_and_json() {
+    let dir = tempdir().unwrap();
+    let root = dir.path();
+    let src = root.join("src");
+    fs::create_dir_all(&src).unwrap();
+
+    write_file(&src.join("lib.rs"), "pub mod a;\nmod b;\npub use b::Hidden;\n");
+    write_file(&src.join("a.rs"), "pub fn foo() {}\nfn bar() {}\n");
+    write_file(&src.join("b.rs"), "pub struct Hidden;\n");
+
+    for format in [OutputFormat::Text, OutputFormat::Json] {
+        let cli = Cli {
+            verbose: 0,
+            quiet: false,
+            command: Commands::Query {
+                query: QueryCommands::PublicApi {
+                    path: Some(root.to_path_buf()),
+                    config: None,
+                    no_ignore: false,
+                    graph: None,
+                    format,
+                    offset: 0,
+                    limit: None,
+                },
+                min_confidence: 0.0,
+                graph_format: None,
+                root: None,
+                filter: FilterArgs::default(),
+                build: uncached(),
+                discovery: DiscoveryArgs::default(),
+            },
+        };
+        assert_eq!(run_cli(cli), 0);
+    }
+}
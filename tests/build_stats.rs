@@ -0,0 +1,66 @@
+use assert_cmd::prelude::*;
+use predicates::str::contains;
+use rust_relations_explorer::graph::builder::GraphBuilder;
+use rust_relations_explorer::graph::format::GraphFormat;
+use rust_relations_explorer::graph::KnowledgeGraph;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use tempfile::tempdir;
+
+fn make_proj(root: &Path) {
+    fs::create_dir_all(root.join("src")).unwrap();
+    fs::write(
+        root.join("src/lib.rs"),
+        "mod m;\nuse crate::m::b;\nuse crate::m::gone;\npub struct S;\npub fn a() {\n    b();\n}\n",
+    )
+    .unwrap();
+    fs::write(root.join("src/m.rs"), "pub fn b() {}\n").unwrap();
+}
+
+#[test]
+fn stats_count_reuse_items_and_relationships() {
+    let dir = tempdir().unwrap();
+    let root = dir.path();
+    make_proj(root);
+
+    let first = GraphBuilder::new(root).build().unwrap();
+    let stats = first.metadata.stats.clone().expect("stats recorded");
+    assert_eq!((stats.files_discovered, stats.files_reused, stats.files_parsed), (2, 0, 2));
+    assert_eq!(stats.items_by_kind.get("fn"), Some(&2));
+    assert_eq!(stats.items_by_kind.get("struct"), Some(&1));
+    assert_eq!(stats.relationships_by_type.get("calls"), Some(&1));
+    assert_eq!(stats.relationships_by_type.get("uses"), Some(&1));
+    assert_eq!(stats.unresolved_imports, 1);
+    assert!(stats.timings.total_ms >= stats.timings.parsing_ms);
+
+    let second = GraphBuilder::new(root).build().unwrap();
+    let stats = second.metadata.stats.clone().unwrap();
+    assert_eq!((stats.files_reused, stats.files_parsed), (2, 0));
+
+    // Stats survive both save formats
+    for (name, format) in [("g.json", GraphFormat::Json), ("g.bin", GraphFormat::Binary)] {
+        let path = dir.path().join(name);
+        second.save(&path, format).unwrap();
+        let loaded = KnowledgeGraph::load(&path, format).unwrap();
+        assert_eq!(loaded.metadata.stats, second.metadata.stats, "{name}");
+    }
+}
+
+#[test]
+fn cli_build_stats_prints_report() {
+    let dir = tempdir().unwrap();
+    let root = dir.path();
+    make_proj(root);
+    Command::cargo_bin("rust-relations-explorer")
+        .unwrap()
+        .env("RRE_CACHE_DIR", dir.path().join("cache"))
+        .args(["build", "--stats", "--path"])
+        .arg(root)
+        .assert()
+        .success()
+        .stdout(contains("Files: 2 discovered, 0 reused from cache, 2 parsed"))
+        .stdout(contains("Unresolved crate imports: 1"))
+        .stdout(contains("call analysis"))
+        .stdout(contains("total"));
+}
@@ -1,4 +1,4 @@
-use rust_relations_explorer::graph::KnowledgeGraph;
+use rust_relations_explorer::graph::builder::GraphBuilder;
 use rust_relations_explorer::utils::cache::{self, CacheMode};
 use std::fs::{self, File};
 use std::io::Write;
@@ -40,7 +40,7 @@ fn cache_mode_use_detects_changes_and_prunes_removed() {
     let root = make_proj(tmp.path());
 
     // First build: creates cache with 2 files
-    let g1 = KnowledgeGraph::build_from_directory_with_cache(&root, CacheMode::Use).unwrap();
+    let g1 = GraphBuilder::new(&root).build().unwrap();
     assert!(g1.files.len() >= 2);
     assert_eq!(count_cache_entries(&root), 2);
 
@@ -52,14 +52,14 @@ fn cache_mode_use_detects_changes_and_prunes_removed() {
     write_file(&root.join("src/m.rs"), "pub fn b() {}\npub fn c() {}\n");
 
     // Second build with Use should reuse lib.rs and reparse m.rs
-    let _g2 = KnowledgeGraph::build_from_directory_with_cache(&root, CacheMode::Use).unwrap();
+    let _g2 = GraphBuilder::new(&root).build().unwrap();
     let m2 = read_cache_meta_len(&root, "src/m.rs").unwrap();
     assert_ne!(m1, m2, "cache entry for modified file should update");
     assert_eq!(count_cache_entries(&root), 2);
 
     // Remove m.rs; build again should prune
     fs::remove_file(root.join("src/m.rs")).unwrap();
-    let _g3 = KnowledgeGraph::build_from_directory_with_cache(&root, CacheMode::Use).unwrap();
+    let _g3 = GraphBuilder::new(&root).build().unwrap();
     assert_eq!(count_cache_entries(&root), 1);
 }
 
@@ -69,21 +69,33 @@ fn cache_mode_ignore_rebuilds_without_reuse() {
     let root = make_proj(tmp.path());
 
     // Seed cache by a normal build
-    let _ = KnowledgeGraph::build_from_directory_with_cache(&root, CacheMode::Use).unwrap();
-    let before = read_cache_meta_len(&root, "src/m.rs").unwrap();
+    let _ = GraphBuilder::new(&root).build().unwrap();
+    let cache_file =
+        cache::CacheStore::new(&root, &cache::CacheLocation::Project).file().to_path_buf();
+    let seeded = fs::read(&cache_file).unwrap();
+    let seeded_mtime = fs::metadata(&cache_file).unwrap().modified().unwrap();
 
     thread::sleep(Duration::from_millis(1100));
-    // Build with Ignore even if file unchanged should still parse and rewrite cache (mtime may or may not change depending on FS; ensure len change)
-    write_file(&root.join("src/lib.rs"), "pub fn a() {}\npub mod m;\npub fn z() {}\n");
-    let _ = KnowledgeGraph::build_from_directory_with_cache(&root, CacheMode::Ignore).unwrap();
-    let after = read_cache_meta_len(&root, "src/m.rs").unwrap();
-    // m.rs unchanged so meta can be equal; ensure entries count preserved and lib.rs updated len
+    let lib = "pub fn a() {}\npub mod m;\npub fn z() {}\n";
+    write_file(&root.join("src/lib.rs"), lib);
+    let has_z = |g: &rust_relations_explorer::graph::KnowledgeGraph| {
+        g.files.values().flat_map(|f| &f.items).any(|i| i.id.0 == "fn:crate::z")
+    };
+
+    // Without writing, Ignore parses every file and leaves the cache file untouched
+    let g =
+        GraphBuilder::new(&root).cache_mode(CacheMode::Ignore).write_cache(false).build().unwrap();
+    assert!(has_z(&g));
+    assert_eq!(g.metadata.stats.unwrap().files_parsed, 2);
+    assert_eq!(fs::read(&cache_file).unwrap(), seeded);
+    assert_eq!(fs::metadata(&cache_file).unwrap().modified().unwrap(), seeded_mtime);
+
+    // By default Ignore still parses every file, then rewrites the cache
+    let g = GraphBuilder::new(&root).cache_mode(CacheMode::Ignore).build().unwrap();
+    assert!(has_z(&g));
+    assert_eq!(g.metadata.stats.unwrap().files_parsed, 2);
     assert_eq!(count_cache_entries(&root), 2);
-    let lib = read_cache_meta_len(&root, "src/lib.rs").unwrap();
-    assert!(lib.1 > 0);
-    assert!(before.1 > 0);
-    // just sanity check access
-    let _ = after;
+    assert_eq!(read_cache_meta_len(&root, "src/lib.rs").unwrap().1, lib.len() as u64);
 }
 
 #[test]
@@ -91,12 +103,33 @@ fn cache_mode_rebuild_clears_cache_file() {
     let tmp = tempfile::tempdir().unwrap();
     let root = make_proj(tmp.path());
 
-    let _ = KnowledgeGraph::build_from_directory_with_cache(&root, CacheMode::Use).unwrap();
+    let _ = GraphBuilder::new(&root).build().unwrap();
     assert_eq!(count_cache_entries(&root), 2);
 
     // Rebuild should remove old cache then recreate
     cache::clear_cache(&root);
     assert_eq!(count_cache_entries(&root), 0);
-    let _ = KnowledgeGraph::build_from_directory_with_cache(&root, CacheMode::Rebuild).unwrap();
+    let _ = GraphBuilder::new(&root).cache_mode(CacheMode::Rebuild).build().unwrap();
     assert_eq!(count_cache_entries(&root), 2);
 }
+
+#[test]
+fn cache_mode_use_detects_same_length_edit_within_the_same_second() {
+    let tmp = tempfile::tempdir().unwrap();
+    let root = make_proj(tmp.path());
+    let m = root.join("src/m.rs");
+    let mtime = fs::metadata(&m).unwrap().modified().unwrap();
+
+    let _ = GraphBuilder::new(&root).build().unwrap();
+
+    // Same length, and the timestamp put back as if the edit landed in the same instant
+    write_file(&m, "pub fn c() {}\n");
+    File::options().write(true).open(&m).unwrap().set_modified(mtime).unwrap();
+    assert_eq!(read_cache_meta_len(&root, "src/m.rs").unwrap().1, fs::metadata(&m).unwrap().len());
+
+    let g = GraphBuilder::new(&root).build().unwrap();
+    let ids: Vec<&str> =
+        g.files.values().flat_map(|n| n.items.iter().map(|it| it.id.0.as_str())).collect();
+    assert!(ids.contains(&"fn:crate::m::c"), "stale cache entry served: {ids:?}");
+    assert!(!ids.contains(&"fn:crate::m::b"));
+}
@@ -0,0 +1,242 @@
+use assert_cmd::prelude::*;
+use rust_relations_explorer::graph::builder::GraphBuilder;
+use rust_relations_explorer::utils::cache::{
+    self, CacheLocation, CacheMode, CacheRead, CacheStore,
+};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use std::thread;
+use tempfile::tempdir;
+
+fn make_proj(root: &Path) {
+    fs::create_dir_all(root.join("src")).unwrap();
+    fs::write(root.join("src/lib.rs"), "pub mod m;\npub fn a() {\n    m::b();\n}\n").unwrap();
+    fs::write(root.join("src/m.rs"), "pub fn b() {}\n").unwrap();
+}
+
+#[test]
+fn cache_from_another_version_is_ignored_and_rewritten() {
+    let dir = tempdir().unwrap();
+    let root = dir.path();
+    make_proj(root);
+    let build = || GraphBuilder::new(root).cache_location(CacheLocation::Project).build();
+    build().unwrap();
+
+    // Pretend an older release wrote the file
+    let file = root.join(".knowledge_cache/cache.json");
+    let mut value: serde_json::Value = serde_json::from_slice(&fs::read(&file).unwrap()).unwrap();
+    value["tool_version"] = serde_json::json!("0.0.1");
+    fs::write(&file, serde_json::to_vec(&value).unwrap()).unwrap();
+    let store = CacheStore::new(root, &CacheLocation::Project);
+    assert!(matches!(store.read(), CacheRead::Outdated { .. }));
+    assert!(store.load().is_none());
+
+    // A pre-versioning cache has neither stamp
+    let legacy = serde_json::json!({ "entries": value["entries"].clone() });
+    fs::write(&file, serde_json::to_vec(&legacy).unwrap()).unwrap();
+    assert!(matches!(store.read(), CacheRead::Outdated { format: 0, .. }));
+
+    let g = build().unwrap();
+    assert_eq!(g.files.len(), 2);
+    let cache = store.load().expect("rewritten with the current stamp");
+    assert_eq!(cache.format, cache::CACHE_FORMAT);
+    assert_eq!(cache.entries.len(), 2);
+}
+
+#[test]
+fn legacy_cache_file_is_removed_by_saves_and_clears() {
+    let dir = tempdir().unwrap();
+    let root = dir.path().join("proj");
+    make_proj(&root);
+    let legacy = root.join(".knowledge_cache.json");
+    let location = CacheLocation::Dir(dir.path().join("caches"));
+
+    // The first save removes the file an earlier version left in the project
+    fs::write(&legacy, r#"{"entries":{}}"#).unwrap();
+    GraphBuilder::new(&root).cache_location(location.clone()).build().unwrap();
+    assert!(!legacy.exists());
+
+    // Clearing removes it too, even without a current cache
+    fs::write(&legacy, r#"{"entries":{}}"#).unwrap();
+    let store = CacheStore::new(&root, &location);
+    assert!(store.clear().unwrap());
+    assert!(!legacy.exists());
+    assert!(!store.clear().unwrap());
+
+    // A disabled cache touches nothing
+    fs::write(&legacy, r#"{"entries":{}}"#).unwrap();
+    GraphBuilder::new(&root).cache_location(CacheLocation::Disabled).build().unwrap();
+    assert!(!CacheStore::new(&root, &CacheLocation::Disabled).clear().unwrap());
+    assert!(legacy.exists());
+}
+
+#[test]
+fn cache_dir_location_keeps_the_project_clean() {
+    let dir = tempdir().unwrap();
+    let root = dir.path().join("proj");
+    make_proj(&root);
+    let caches = dir.path().join("caches");
+    let location = CacheLocation::Dir(caches.clone());
+
+    let g = GraphBuilder::new(&root).cache_location(location.clone()).build().unwrap();
+    assert_eq!(g.files.len(), 2);
+    assert_eq!(fs::read_dir(&root).unwrap().count(), 1, "only src/ in the project");
+
+    let store = CacheStore::new(&root, &location);
+    assert!(store.file().starts_with(&caches));
+    let name = store.file().file_name().unwrap().to_string_lossy().into_owned();
+    assert!(name.starts_with("proj-"), "{name}");
+    assert_eq!(store.load().map(|c| c.entries.len()), Some(2));
+
+    // Another project in the same directory gets its own file
+    let other = dir.path().join("other").join("proj");
+    make_proj(&other);
+    assert_ne!(CacheStore::new(&other, &location).file(), store.file());
+}
+
+#[test]
+fn concurrent_saves_leave_a_whole_cache() {
+    let dir = tempdir().unwrap();
+    let root = dir.path();
+    make_proj(root);
+    GraphBuilder::new(root).cache_location(CacheLocation::Project).build().unwrap();
+    let store = CacheStore::new(root, &CacheLocation::Project);
+    let cache = store.load().unwrap();
+
+    thread::scope(|s| {
+        for _ in 0..8 {
+            s.spawn(|| {
+                for _ in 0..10 {
+                    store.save(&cache).unwrap();
+                    assert!(store.load().is_some());
+                }
+            });
+        }
+    });
+
+    assert_eq!(store.load().map(|c| c.entries.len()), Some(2));
+    // The lock sits with the cache; no temporary file is left behind
+    let mut names: Vec<String> = fs::read_dir(root.join(".knowledge_cache"))
+        .unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    names.sort();
+    assert_eq!(names, ["cache.json", "cache.json.lock"]);
+    let mut top: Vec<String> = fs::read_dir(root)
+        .unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    top.sort();
+    assert_eq!(top, [".knowledge_cache", "src"]);
+}
+
+#[test]
+fn library_default_is_the_project_and_builds_may_skip_writing() {
+    let dir = tempdir().unwrap();
+    let root = dir.path().join("proj");
+    make_proj(&root);
+    assert_eq!(CacheLocation::default(), CacheLocation::Project);
+
+    GraphBuilder::new(&root).cache_mode(CacheMode::Ignore).write_cache(false).build().unwrap();
+    assert_eq!(fs::read_dir(&root).unwrap().count(), 1, "only src/ in the project");
+
+    GraphBuilder::new(&root).build().unwrap();
+    let store = CacheStore::new(&root, &CacheLocation::default());
+    assert_eq!(store.file(), root.join(".knowledge_cache/cache.json"));
+    assert!(store.file().exists());
+
+    // Clearing removes the lock file along with the cache
+    assert!(store.clear().unwrap());
+    assert_eq!(fs::read_dir(root.join(".knowledge_cache")).unwrap().count(), 0);
+    assert!(!store.clear().unwrap());
+}
+
+#[test]
+fn cli_default_is_the_user_cache_directory() {
+    let dir = tempdir().unwrap();
+    let root = dir.path().join("proj");
+    make_proj(&root);
+    let home = dir.path().join("home");
+    let mut cmd = Command::cargo_bin("rust-relations-explorer").unwrap();
+    cmd.env_remove("RRE_CACHE_DIR")
+        .env("HOME", &home)
+        .env("XDG_CACHE_HOME", home.join(".cache"))
+        .env("LOCALAPPDATA", &home)
+        .arg("build")
+        .arg("--path")
+        .arg(&root);
+    cmd.assert().success();
+    assert_eq!(fs::read_dir(&root).unwrap().count(), 1, "only src/ in the project");
+
+    let caches = [
+        home.join(".cache/rust-relations-explorer"),
+        home.join("Library/Caches/rust-relations-explorer"),
+        home.join("rust-relations-explorer"),
+    ];
+    let dir = caches.iter().find(|d| d.is_dir()).expect("a per-user cache directory");
+    let file = CacheStore::new(&root, &CacheLocation::Dir(dir.clone())).file().to_path_buf();
+    assert!(file.exists());
+}
+
+#[test]
+fn cli_project_cache_is_opt_in() {
+    let dir = tempdir().unwrap();
+    let root = dir.path().join("proj");
+    make_proj(&root);
+    let cache_file = root.join(".knowledge_cache/cache.json");
+    let build = |args: &[&str]| {
+        let mut cmd = Command::cargo_bin("rust-relations-explorer").unwrap();
+        cmd.env_remove("RRE_CACHE_DIR").arg("build").args(args).arg("--path").arg(&root);
+        cmd.assert().success();
+    };
+
+    build(&["--project-cache"]);
+    assert!(cache_file.exists());
+    fs::remove_dir_all(root.join(".knowledge_cache")).unwrap();
+
+    let cfg = dir.path().join("rre.toml");
+    fs::write(&cfg, "[cache]\nproject = true\n").unwrap();
+    build(&["--config", cfg.to_str().unwrap()]);
+    assert!(cache_file.exists());
+}
+
+#[test]
+fn cli_cache_subcommand_reports_verifies_and_clears() {
+    let dir = tempdir().unwrap();
+    let root = dir.path().join("proj");
+    make_proj(&root);
+    let caches = dir.path().join("caches");
+    let run = |args: &[&str]| {
+        let mut cmd = Command::cargo_bin("rust-relations-explorer").unwrap();
+        cmd.args(args).arg("--path").arg(&root).arg("--cache-dir").arg(&caches);
+        cmd.assert()
+    };
+
+    run(&["build"]).success();
+    assert_eq!(fs::read_dir(&root).unwrap().count(), 1, "only src/ in the project");
+
+    let out = run(&["cache", "stats", "--format", "json"]).success().get_output().stdout.clone();
+    let stats: serde_json::Value = serde_json::from_slice(&out).unwrap();
+    assert_eq!(stats["state"], "current");
+    assert_eq!(stats["entries"], 2);
+    assert_eq!(stats["analyzed"], 2);
+
+    run(&["cache", "verify"]).success().stdout(predicates::str::contains("OK"));
+
+    // A source edit is reported as stale, but the cache is still usable
+    fs::write(root.join("src/m.rs"), "pub fn b() {}\npub fn c() {}\n").unwrap();
+    let out = run(&["cache", "verify", "--format", "json"]).get_output().stdout.clone();
+    let report: serde_json::Value = serde_json::from_slice(&out).unwrap();
+    assert_eq!(report["stale"].as_array().map(Vec::len), Some(1));
+
+    // A corrupt file fails verification
+    let file = CacheStore::new(&root, &CacheLocation::Dir(caches.clone())).file().to_path_buf();
+    fs::write(&file, "{ not json").unwrap();
+    run(&["cache", "verify"]).code(1);
+    run(&["cache", "stats"]).success().stdout(predicates::str::contains("invalid"));
+
+    run(&["cache", "clear"]).success().stdout(predicates::str::contains("Removed"));
+    assert!(!file.exists());
+    run(&["cache", "clear"]).success().stdout(predicates::str::contains("No cache"));
+}
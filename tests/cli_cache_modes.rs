@@ -24,18 +24,21 @@ fn cli_build_cache_modes_succeed() {
 
     // Default (Use cache)
     let mut use_cache = Command::cargo_bin("rust-relations-explorer").unwrap();
+    use_cache.env("RRE_CACHE_DIR", dir.path().join("cache"));
     use_cache.arg("build").arg("--path").arg(root).arg("--json").arg(&json);
     use_cache.assert().success();
     assert!(json.exists());
 
     // Rebuild
     let mut rebuild = Command::cargo_bin("rust-relations-explorer").unwrap();
+    rebuild.env("RRE_CACHE_DIR", dir.path().join("cache"));
     rebuild.arg("build").arg("--path").arg(root).arg("--json").arg(&json).arg("--rebuild");
     rebuild.assert().success();
     assert!(json.exists());
 
     // No-cache
     let mut no_cache = Command::cargo_bin("rust-relations-explorer").unwrap();
+    no_cache.env("RRE_CACHE_DIR", dir.path().join("cache"));
     no_cache.arg("build").arg("--path").arg(root).arg("--json").arg(&json).arg("--no-cache");
     no_cache.assert().success();
     assert!(json.exists());
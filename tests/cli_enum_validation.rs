@@ -24,6 +24,7 @@ fn invalid_enum_value_for_format_yields_error() {
     write_file(&src.join("lib.rs"), "pub fn top() {}\n");
 
     let mut cmd = Command::cargo_bin("rust-relations-explorer").unwrap();
+    cmd.env("RRE_CACHE_DIR", dir.path().join("cache"));
     cmd.arg("query").arg("cycles").arg("--path").arg(root).arg("--format").arg("nope");
 
     cmd.assert()
@@ -43,6 +44,7 @@ fn valid_enum_values_are_accepted() {
 
     for fmt in ["text", "json"] {
         let mut cmd = Command::cargo_bin("rust-relations-explorer").unwrap();
+        cmd.env("RRE_CACHE_DIR", dir.path().join("cache"));
         cmd.arg("query").arg("cycles").arg("--path").arg(root).arg("--format").arg(fmt);
         cmd.assert().success();
     }
@@ -1,5 +1,7 @@
 use rust_relations_explorer::app::run_cli;
-use rust_relations_explorer::cli::{Cli, Commands, ItemKindArg, OutputFormat, QueryCommands};
+use rust_relations_explorer::cli::{
+    BuildArgs, Cli, Commands, DiscoveryArgs, FilterArgs, ItemKindArg, OutputFormat, QueryCommands,
+};
 use std::fs;
 use std::io::Write;
 use std::path::PathBuf;
@@ -38,6 +40,12 @@ fn iteminfo_by_name_success() {
                 show_code: false,
                 format: OutputFormat::Text,
             },
+            min_confidence: 0.0,
+            graph_format: None,
+            root: None,
+            filter: FilterArgs::default(),
+            build: BuildArgs { no_cache: true, ..BuildArgs::default() },
+            discovery: DiscoveryArgs::default(),
         },
     };
 
@@ -71,6 +79,12 @@ fn iteminfo_by_name_ambiguous() {
                 show_code: false,
                 format: OutputFormat::Text,
             },
+            min_confidence: 0.0,
+            graph_format: None,
+            root: None,
+            filter: FilterArgs::default(),
+            build: BuildArgs { no_cache: true, ..BuildArgs::default() },
+            discovery: DiscoveryArgs::default(),
         },
     };
 
@@ -102,6 +116,12 @@ fn iteminfo_by_name_not_found() {
                 show_code: false,
                 format: OutputFormat::Text,
             },
+            min_confidence: 0.0,
+            graph_format: None,
+            root: None,
+            filter: FilterArgs::default(),
+            build: BuildArgs { no_cache: true, ..BuildArgs::default() },
+            discovery: DiscoveryArgs::default(),
         },
     };
 
@@ -136,6 +156,12 @@ fn iteminfo_by_name_with_kind_success() {
                 show_code: false,
                 format: OutputFormat::Text,
             },
+            min_confidence: 0.0,
+            graph_format: None,
+            root: None,
+            filter: FilterArgs::default(),
+            build: BuildArgs { no_cache: true, ..BuildArgs::default() },
+            discovery: DiscoveryArgs::default(),
         },
     };
 
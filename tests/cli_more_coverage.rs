@@ -51,6 +51,7 @@ default_format = "json"
 
     // run build with config and outputs and save
     let mut cmd = Command::cargo_bin("rust-relations-explorer").unwrap();
+    cmd.env("RRE_CACHE_DIR", dir.path().join("cache"));
     cmd.arg("build")
         .arg("--path")
         .arg(root)
@@ -94,6 +95,7 @@ fn queries_without_graph_build_from_path_and_text_formats() {
 
     // connected-files text output via table (no --graph)
     let mut cf = Command::cargo_bin("rust-relations-explorer").unwrap();
+    cf.env("RRE_CACHE_DIR", dir.path().join("cache"));
     cf.arg("query")
         .arg("connected-files")
         .arg("--path")
@@ -108,6 +110,7 @@ fn queries_without_graph_build_from_path_and_text_formats() {
 
     // function-usage callees branch (no --graph)
     let mut fu = Command::cargo_bin("rust-relations-explorer").unwrap();
+    fu.env("RRE_CACHE_DIR", dir.path().join("cache"));
     fu.arg("query")
         .arg("function-usage")
         .arg("--path")
@@ -122,6 +125,7 @@ fn queries_without_graph_build_from_path_and_text_formats() {
 
     // hubs with metric in and text output
     let mut hubs = Command::cargo_bin("rust-relations-explorer").unwrap();
+    hubs.env("RRE_CACHE_DIR", dir.path().join("cache"));
     hubs.arg("-v")
         .arg("query")
         .arg("hubs")
@@ -139,6 +143,7 @@ fn queries_without_graph_build_from_path_and_text_formats() {
 
     // module-centrality with metric out and text output
     let mut mc = Command::cargo_bin("rust-relations-explorer").unwrap();
+    mc.env("RRE_CACHE_DIR", dir.path().join("cache"));
     mc.arg("query")
         .arg("module-centrality")
         .arg("--path")
@@ -155,6 +160,7 @@ fn queries_without_graph_build_from_path_and_text_formats() {
 
     // path query no path branch should print <no path>
     let mut pathq = Command::cargo_bin("rust-relations-explorer").unwrap();
+    pathq.env("RRE_CACHE_DIR", dir.path().join("cache"));
     pathq
         .arg("query")
         .arg("path")
@@ -182,6 +188,7 @@ fn cycles_text_output_branch() {
     write_file(&src.join("b.rs"), "pub fn fb() { }\n");
 
     let mut cy = Command::cargo_bin("rust-relations-explorer").unwrap();
+    cy.env("RRE_CACHE_DIR", dir.path().join("cache"));
     cy.arg("query").arg("cycles").arg("--path").arg(root).arg("--format").arg("text");
     cy.assert().success();
 }
@@ -31,6 +31,7 @@ fn cli_build_produces_dot_and_json_and_hubs_query_works() {
 
     // Act: run build with dot and json outputs
     let mut cmd = Command::cargo_bin("rust-relations-explorer").unwrap();
+    cmd.env("RRE_CACHE_DIR", dir.path().join("cache"));
     cmd.arg("build")
         .arg("--path")
         .arg(root)
@@ -50,6 +51,7 @@ fn cli_build_produces_dot_and_json_and_hubs_query_works() {
 
     // Act: run hubs query on saved graph
     let mut q = Command::cargo_bin("rust-relations-explorer").unwrap();
+    q.env("RRE_CACHE_DIR", dir.path().join("cache"));
     q.arg("query")
         .arg("hubs")
         .arg("--graph")
@@ -63,6 +65,30 @@ fn cli_build_produces_dot_and_json_and_hubs_query_works() {
     q.assert().success().stdout(predicate::str::contains("["));
 }
 
+#[test]
+fn cli_build_json_is_root_relative_and_write_failures_exit_nonzero() {
+    let dir = tempdir().unwrap();
+    let root = dir.path().join("proj");
+    fs::create_dir_all(root.join("src")).unwrap();
+    write_file(&root.join("src/lib.rs"), "pub fn top() {}\n");
+    let build = |json: PathBuf| {
+        let mut cmd = Command::cargo_bin("rust-relations-explorer").unwrap();
+        cmd.env("RRE_CACHE_DIR", dir.path().join("cache"));
+        cmd.arg("build").arg("--path").arg(&root).arg("--json").arg(json);
+        cmd.assert()
+    };
+
+    let json = dir.path().join("graph.json");
+    build(json.clone()).success();
+    let value: serde_json::Value = serde_json::from_slice(&fs::read(&json).unwrap()).unwrap();
+    let files: Vec<&String> = value["files"].as_object().unwrap().keys().collect();
+    assert_eq!(files, ["src/lib.rs"]);
+
+    build(dir.path().join("missing/graph.json"))
+        .code(1)
+        .stderr(predicate::str::contains("Failed to write JSON output"));
+}
+
 fn write_file(path: &PathBuf, content: &str) {
     let mut f = fs::File::create(path).unwrap();
     f.write_all(content.as_bytes()).unwrap();
@@ -26,6 +26,7 @@ fn connected_files_accepts_positional_file_argument() {
 
     // Run: rust-relations-explorer query connected-files <file> --path <root> --format json
     let mut cmd = Command::cargo_bin("rust-relations-explorer").unwrap();
+    cmd.env("RRE_CACHE_DIR", dir.path().join("cache"));
     cmd.arg("query")
         .arg("connected-files")
         .arg(src.join("a.rs"))
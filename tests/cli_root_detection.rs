@@ -25,6 +25,7 @@ fn root_detection_without_explicit_path() {
 
     // Run from the temp dir as CWD without --path; command should detect src/ and succeed
     let mut cmd = Command::cargo_bin("rust-relations-explorer").unwrap();
+    cmd.env("RRE_CACHE_DIR", dir.path().join("cache"));
     cmd.current_dir(root).arg("build");
     cmd.assert().success();
 }
@@ -39,6 +40,7 @@ fn verbose_prints_project_root_hint() {
     write_file(&src.join("lib.rs"), "pub fn top() {}\n");
 
     let mut cmd = Command::cargo_bin("rust-relations-explorer").unwrap();
+    cmd.env("RRE_CACHE_DIR", dir.path().join("cache"));
     cmd.current_dir(root).arg("-v").arg("build");
     cmd.assert().success().stderr(predicate::str::contains("Using project root:"));
 }
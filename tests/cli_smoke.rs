@@ -33,6 +33,7 @@ fn cli_build_and_connected_files_smoke() {
 
     // Act: run build command
     let mut cmd = Command::cargo_bin("rust-relations-explorer").unwrap();
+    cmd.env("RRE_CACHE_DIR", dir.path().join("cache"));
     cmd.arg("build").arg("--path").arg(root).arg("--json").arg(root.join("graph.json"));
     cmd.assert().success();
 
@@ -44,6 +45,7 @@ fn cli_build_and_connected_files_smoke() {
 
     // Act: run query connected-files
     let mut cmd2 = Command::cargo_bin("rust-relations-explorer").unwrap();
+    cmd2.env("RRE_CACHE_DIR", dir.path().join("cache"));
     cmd2.arg("query")
         .arg("connected-files")
         .arg("--path")
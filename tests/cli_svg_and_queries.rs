@@ -28,6 +28,7 @@ fn cli_build_svg_when_graphviz_available() {
     );
 
     let mut cmd = Command::cargo_bin("rust-relations-explorer").unwrap();
+    cmd.env("RRE_CACHE_DIR", dir.path().join("cache"));
     cmd.arg("build").arg("--path").arg(root).arg("--svg").arg(root.join("graph.svg"));
     cmd.assert().success();
 
@@ -70,12 +71,14 @@ fn cli_query_module_centrality_trait_impls_and_cycles() {
 
     // Build graph JSON
     let mut build = Command::cargo_bin("rust-relations-explorer").unwrap();
+    build.env("RRE_CACHE_DIR", dir.path().join("cache"));
     build.arg("build").arg("--path").arg(root).arg("--json").arg(root.join("graph.json"));
     build.assert().success();
     let graph_path = root.join("graph.json");
 
     // module-centrality: expect some output rows
     let mut mc = Command::cargo_bin("rust-relations-explorer").unwrap();
+    mc.env("RRE_CACHE_DIR", dir.path().join("cache"));
     mc.arg("query")
         .arg("module-centrality")
         .arg("--graph")
@@ -90,6 +93,7 @@ fn cli_query_module_centrality_trait_impls_and_cycles() {
 
     // trait-impls: parser may or may not extract impls in this minimal setup; accept empty but valid JSON array
     let mut ti = Command::cargo_bin("rust-relations-explorer").unwrap();
+    ti.env("RRE_CACHE_DIR", dir.path().join("cache"));
     ti.arg("query")
         .arg("trait-impls")
         .arg("--graph")
@@ -102,6 +106,7 @@ fn cli_query_module_centrality_trait_impls_and_cycles() {
 
     // cycles: command should succeed; output may be empty which is acceptable
     let mut cy = Command::cargo_bin("rust-relations-explorer").unwrap();
+    cy.env("RRE_CACHE_DIR", dir.path().join("cache"));
     cy.arg("query").arg("cycles").arg("--graph").arg(&graph_path).arg("--format").arg("json");
     cy.assert().success().stdout(predicate::str::contains("["));
 }
@@ -135,12 +140,14 @@ fn cli_query_path_and_function_usage() {
 
     // Build graph JSON to speed queries
     let mut build = Command::cargo_bin("rust-relations-explorer").unwrap();
+    build.env("RRE_CACHE_DIR", dir.path().join("cache"));
     build.arg("build").arg("--path").arg(root).arg("--json").arg(root.join("graph.json"));
     build.assert().success();
     let graph_path = root.join("graph.json");
 
     // path query: expect a path from b.rs to a.rs
     let mut pathq = Command::cargo_bin("rust-relations-explorer").unwrap();
+    pathq.env("RRE_CACHE_DIR", dir.path().join("cache"));
     pathq
         .arg("query")
         .arg("path")
@@ -156,6 +163,7 @@ fn cli_query_path_and_function_usage() {
 
     // function-usage: callers of foo should include b.rs
     let mut funcq = Command::cargo_bin("rust-relations-explorer").unwrap();
+    funcq.env("RRE_CACHE_DIR", dir.path().join("cache"));
     funcq
         .arg("query")
         .arg("function-usage")
@@ -0,0 +1,186 @@
+use assert_cmd::prelude::*;
+use predicates::str::contains;
+use rust_relations_explorer::errors::KnowledgeGraphError;
+use rust_relations_explorer::graph::builder::GraphBuilder;
+use rust_relations_explorer::utils::cache::CacheLocation;
+use rust_relations_explorer::utils::file_walker::{
+    discover, explain, Discovery, DiscoveryOptions, Exclusion,
+};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tempfile::tempdir;
+
+fn write(root: &Path, rel: &str, content: &str) {
+    let path = root.join(rel);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, content).unwrap();
+}
+
+fn make_proj(root: &Path) {
+    write(root, "Cargo.toml", "[package]\nname = \"p\"\nversion = \"0.1.0\"\n");
+    write(root, "src/lib.rs", "pub mod m;\npub mod proto;\n");
+    write(root, "src/m.rs", "//! Mentions @generated below the first item.\npub fn b() {}\n");
+    write(root, "src/proto.rs", "// @generated by protoc. Do not edit.\npub struct Msg;\n");
+    write(root, "benches/b.rs", "fn main() {}\n");
+    write(root, "target/debug/build/p-0123456789abcdef/out/gen.rs", "pub fn g() {}\n");
+    write(root, "target/debug/deps/x.rs", "pub fn x() {}\n");
+    write(root, "vendor/dep/src/lib.rs", "pub fn v() {}\n");
+    write(root, "vendor/dep/.cargo-checksum.json", "{}");
+}
+
+fn rel(root: &Path, paths: &[PathBuf]) -> Vec<String> {
+    paths.iter().map(|p| p.strip_prefix(root).unwrap().display().to_string()).collect()
+}
+
+#[test]
+fn default_rules_skip_target_generated_and_vendored() {
+    let dir = tempdir().unwrap();
+    let root = dir.path();
+    make_proj(root);
+
+    // Discovery applies path rules only; `@generated` headers are read by `explain` and the
+    // build
+    let found = discover(root, &DiscoveryOptions::default()).unwrap();
+    assert_eq!(rel(root, &found.files), ["benches/b.rs", "src/lib.rs", "src/m.rs", "src/proto.rs"]);
+    let skipped = |found: &Discovery| -> Vec<(String, Exclusion)> {
+        found
+            .skipped
+            .iter()
+            .map(|(p, why)| (p.strip_prefix(root).unwrap().display().to_string(), why.clone()))
+            .collect()
+    };
+    assert_eq!(
+        skipped(&found),
+        [("target".to_string(), Exclusion::Target), ("vendor".to_string(), Exclusion::Vendored),]
+    );
+    let explained = explain(root, &DiscoveryOptions::default()).unwrap();
+    assert_eq!(rel(root, &explained.files), ["benches/b.rs", "src/lib.rs", "src/m.rs"]);
+    assert_eq!(
+        skipped(&explained),
+        [
+            ("src/proto.rs".to_string(), Exclusion::Generated),
+            ("target".to_string(), Exclusion::Target),
+            ("vendor".to_string(), Exclusion::Vendored),
+        ]
+    );
+    let g = GraphBuilder::new(root).cache_location(CacheLocation::Disabled).build().unwrap();
+    assert_eq!(g.files.len(), 3);
+    assert!(!g.files.contains_key(&root.join("src/proto.rs")));
+
+    // Build-script output is generated code even where the target directory is scanned
+    let opts = DiscoveryOptions { skip_target: false, ..DiscoveryOptions::default() };
+    let found = discover(root, &opts).unwrap();
+    assert!(rel(root, &found.files).contains(&"target/debug/deps/x.rs".to_string()));
+    assert!(found.skipped.iter().any(|(p, why)| p.ends_with("out") && *why == Exclusion::OutDir));
+
+    let everything = DiscoveryOptions {
+        skip_target: false,
+        skip_generated: false,
+        skip_vendored: false,
+        ..DiscoveryOptions::default()
+    };
+    assert_eq!(discover(root, &everything).unwrap().files.len(), 7);
+}
+
+#[test]
+fn cached_file_that_becomes_generated_leaves_the_graph() {
+    let dir = tempdir().unwrap();
+    let root = dir.path().join("p");
+    make_proj(&root);
+    let build = || {
+        GraphBuilder::new(&root)
+            .cache_location(CacheLocation::Dir(dir.path().join("caches")))
+            .build()
+            .unwrap()
+    };
+    assert!(build().files.contains_key(&root.join("src/m.rs")));
+    write(&root, "src/m.rs", "// @generated\npub fn b() {}\n");
+    let g = build();
+    assert!(!g.files.contains_key(&root.join("src/m.rs")));
+    assert!(!g.relationships.iter().any(|r| r.to_item.0.contains("m::b")));
+}
+
+#[test]
+fn include_and_exclude_globs_are_relative_to_the_root() {
+    let dir = tempdir().unwrap();
+    let root = dir.path();
+    make_proj(root);
+
+    let opts = DiscoveryOptions {
+        include: vec!["src/**".into()],
+        exclude: vec!["**/m.rs".into()],
+        ..DiscoveryOptions::default()
+    };
+    let found = discover(root, &opts).unwrap();
+    assert_eq!(rel(root, &found.files), ["src/lib.rs", "src/proto.rs"]);
+    let reason = |name: &str| {
+        found.skipped.iter().find(|(p, _)| p.ends_with(name)).map(|(_, why)| why.clone())
+    };
+    assert_eq!(reason("m.rs"), Some(Exclusion::Excluded("**/m.rs".into())));
+    assert_eq!(reason("b.rs"), Some(Exclusion::NotIncluded));
+
+    let bad = DiscoveryOptions { exclude: vec!["src/[".into()], ..DiscoveryOptions::default() };
+    assert!(matches!(discover(root, &bad), Err(KnowledgeGraphError::InvalidGlob { .. })));
+}
+
+#[test]
+fn explain_lists_files_hidden_by_ignore_rules() {
+    let dir = tempdir().unwrap();
+    let root = dir.path();
+    make_proj(root);
+    write(root, ".ignore", "benches/\n");
+
+    let found = discover(root, &DiscoveryOptions::default()).unwrap();
+    assert!(!found.skipped.iter().any(|(p, _)| p.ends_with("benches/b.rs")));
+    let explained = explain(root, &DiscoveryOptions::default()).unwrap();
+    assert_eq!(rel(root, &explained.files), ["src/lib.rs", "src/m.rs"]);
+    assert_eq!(rel(root, &found.files), ["src/lib.rs", "src/m.rs", "src/proto.rs"]);
+    assert!(explained
+        .skipped
+        .iter()
+        .any(|(p, why)| p.ends_with("benches/b.rs") && *why == Exclusion::Ignored));
+}
+
+#[test]
+fn cli_list_files_combines_config_and_flags() {
+    let dir = tempdir().unwrap();
+    let root = dir.path();
+    make_proj(root);
+    let cfg = root.join("rre.toml");
+    fs::write(&cfg, "[build]\nexclude = [\"benches/**\"]\nskip_vendored = false\n").unwrap();
+
+    Command::cargo_bin("rust-relations-explorer")
+        .unwrap()
+        .args(["build", "--list-files", "--project-cache", "--exclude", "src/m.rs", "--config"])
+        .arg(&cfg)
+        .arg("--path")
+        .arg(root)
+        .assert()
+        .success()
+        .stdout(contains("scan  src/lib.rs"))
+        .stdout(contains("scan  vendor/dep/src/lib.rs"))
+        .stdout(contains("skip  src/m.rs  (excluded by `src/m.rs`)"))
+        // The flag replaces the config list
+        .stdout(contains("scan  benches/b.rs"))
+        .stdout(contains("skip  src/proto.rs  (generated (`@generated` header))"))
+        .stdout(contains("skip  target/  (build output (target directory))"))
+        .stdout(contains("3 files to scan, 3 skipped"));
+    assert!(!root.join(".knowledge_cache").exists(), "dry run builds nothing");
+
+    // The build itself scans the same files
+    let json = root.join("graph.json");
+    Command::cargo_bin("rust-relations-explorer")
+        .unwrap()
+        .env("RRE_CACHE_DIR", dir.path().join("cache"))
+        .args(["build", "--exclude", "src/m.rs", "--path"])
+        .arg(root)
+        .arg("--json")
+        .arg(&json)
+        .assert()
+        .success();
+    let graph = fs::read_to_string(&json).unwrap();
+    assert!(graph.contains("src/lib.rs"));
+    assert!(!graph.contains("src/m.rs"));
+    assert!(!graph.contains("src/proto.rs"));
+}
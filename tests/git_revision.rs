@@ -0,0 +1,223 @@
+use assert_cmd::prelude::*;
+use predicates::str::contains;
+use rust_relations_explorer::errors::KnowledgeGraphError;
+use rust_relations_explorer::graph::builder::GraphBuilder;
+use rust_relations_explorer::graph::{KnowledgeGraph, RelationshipType};
+use rust_relations_explorer::utils::cache::{CacheLocation, CacheStore};
+use rust_relations_explorer::utils::file_walker::{DiscoveryOptions, Exclusion};
+use rust_relations_explorer::utils::git::Revision;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use tempfile::tempdir;
+
+fn git(dir: &Path, args: &[&str]) -> String {
+    let out = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["-c", "user.name=t", "-c", "user.email=t@example.com"])
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(out.status.success(), "git {args:?}: {}", String::from_utf8_lossy(&out.stderr));
+    String::from_utf8(out.stdout).unwrap().trim().to_string()
+}
+
+fn write(root: &Path, rel: &str, content: &str) {
+    let path = root.join(rel);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, content).unwrap();
+}
+
+fn commit(repo: &Path, msg: &str) -> String {
+    git(repo, &["add", "-A"]);
+    git(repo, &["commit", "-q", "-m", msg]);
+    git(repo, &["rev-parse", "HEAD"])
+}
+
+// A repository whose project lives in `crates/p`, with two commits
+fn make_repo(repo: &Path) -> (String, String) {
+    git(repo, &["init", "-q"]);
+    write(repo, ".gitignore", "scratch/\n");
+    let root = repo.join("crates/p");
+    write(&root, "Cargo.toml", "[package]\nname = \"p\"\nversion = \"0.1.0\"\n");
+    write(&root, "src/lib.rs", "pub mod m;\npub mod n;\npub fn a() {\n    m::b();\n}\n");
+    write(&root, "src/m.rs", "pub fn b() {}\n");
+    write(&root, "src/n.rs", "pub fn c() {}\n");
+    write(&root, "scratch/tmp.rs", "pub fn t() {}\n");
+    git(repo, &["add", "-A"]);
+    // Tracked despite the ignore rule: still skipped, as on disk
+    git(repo, &["add", "-f", "crates/p/scratch/tmp.rs"]);
+    let first = commit(repo, "one");
+    write(&root, "src/m.rs", "pub fn b() {}\npub fn d() {}\n");
+    let second = commit(repo, "two");
+    (first, second)
+}
+
+fn at_revision(root: &Path, rev: &str, caches: &CacheLocation) -> KnowledgeGraph {
+    GraphBuilder::new(root).revision(rev).cache_location(caches.clone()).build().unwrap()
+}
+
+fn ids(g: &KnowledgeGraph) -> Vec<String> {
+    let mut out: Vec<String> =
+        g.files.values().flat_map(|f| f.items.iter().map(|i| i.id.0.clone())).collect();
+    out.sort();
+    out
+}
+
+fn edges(g: &KnowledgeGraph) -> Vec<String> {
+    let mut out: Vec<String> = g
+        .relationships
+        .iter()
+        .map(|r| format!("{} {:?} {}", r.from_item.0, r.relationship_type, r.to_item.0))
+        .collect();
+    out.sort();
+    out
+}
+
+#[test]
+fn revision_build_matches_a_checkout_and_leaves_the_tree_alone() {
+    let dir = tempdir().unwrap();
+    let repo = dir.path();
+    let (first, _) = make_repo(repo);
+    let root = repo.join("crates/p");
+    let cache_dir = tempdir().unwrap();
+    let caches = CacheLocation::Dir(cache_dir.path().to_path_buf());
+
+    let worktree = GraphBuilder::new(&root).cache_location(caches.clone()).build().unwrap();
+    let head = at_revision(&root, "HEAD", &caches);
+    assert_eq!(ids(&head), ids(&worktree));
+    assert_eq!(edges(&head), edges(&worktree));
+    assert!(!ids(&head).iter().any(|id| id.contains("scratch")));
+
+    // An uncommitted edit does not reach revision builds
+    write(&root, "src/n.rs", "pub fn c() {}\npub fn uncommitted() {}\n");
+    let old = at_revision(&root, &first, &caches);
+    assert!(ids(&old).contains(&"fn:crate::m::b".to_string()));
+    assert!(!ids(&old).contains(&"fn:crate::m::d".to_string()));
+    assert!(!ids(&old).contains(&"fn:crate::n::uncommitted".to_string()));
+    assert_eq!(
+        fs::read_to_string(root.join("src/m.rs")).unwrap(),
+        "pub fn b() {}\npub fn d() {}\n"
+    );
+    assert_eq!(git(repo, &["status", "--porcelain"]), "M crates/p/src/n.rs");
+}
+
+#[test]
+fn unchanged_blobs_are_shared_across_revisions() {
+    let dir = tempdir().unwrap();
+    let repo = dir.path();
+    let (first, second) = make_repo(repo);
+    let root = repo.join("crates/p");
+    let caches = CacheLocation::Dir(dir.path().join("caches"));
+
+    let g = at_revision(&root, &first, &caches);
+    let stats = g.metadata.stats.clone().unwrap();
+    assert_eq!((stats.files_discovered, stats.files_reused, stats.files_parsed), (3, 0, 3));
+
+    // Only src/m.rs differs between the commits
+    let g = at_revision(&root, &second, &caches);
+    let stats = g.metadata.stats.clone().unwrap();
+    assert_eq!((stats.files_reused, stats.files_parsed), (2, 1));
+    assert!(ids(&g).contains(&"fn:crate::m::d".to_string()));
+
+    // Both versions of src/m.rs stay cached
+    let cache = CacheStore::new(&root, &caches).load().unwrap();
+    assert_eq!(cache.revisions.len(), 4);
+    let g = at_revision(&root, &first, &caches);
+    assert_eq!(g.metadata.stats.unwrap().files_parsed, 0);
+}
+
+#[test]
+fn revision_discovery_reports_skipped_files() {
+    let dir = tempdir().unwrap();
+    let repo = dir.path();
+    make_repo(repo);
+    let root = repo.join("crates/p");
+    write(&root, "vendor/dep/src/lib.rs", "pub fn v() {}\n");
+    write(&root, "src/gen.rs", "// @generated\npub fn g() {}\n");
+    commit(repo, "three");
+
+    let rev = Revision::open(&root, "HEAD", &DiscoveryOptions::default()).unwrap();
+    assert_eq!(rev.commit(), git(repo, &["rev-parse", "HEAD"]));
+    let found = rev.discovery();
+    let rel = |p: &Path| p.strip_prefix(&root).unwrap().display().to_string();
+    assert_eq!(
+        found.files.iter().map(|p| rel(p)).collect::<Vec<_>>(),
+        ["src/lib.rs", "src/m.rs", "src/n.rs"]
+    );
+    let skipped: Vec<(String, Exclusion)> =
+        found.skipped.iter().map(|(p, why)| (rel(p), why.clone())).collect();
+    assert_eq!(
+        skipped,
+        [
+            ("scratch/tmp.rs".to_string(), Exclusion::Ignored),
+            ("src/gen.rs".to_string(), Exclusion::Generated),
+            ("vendor".to_string(), Exclusion::Vendored),
+        ]
+    );
+
+    let all = DiscoveryOptions { no_ignore: true, ..DiscoveryOptions::default() };
+    assert_eq!(Revision::open(&root, "HEAD", &all).unwrap().files().len(), 4);
+    assert!(matches!(
+        Revision::open(&root, "no-such-branch", &all),
+        Err(KnowledgeGraphError::Git(_))
+    ));
+}
+
+#[test]
+fn cli_build_rev_writes_the_old_graph() {
+    let dir = tempdir().unwrap();
+    let repo = dir.path();
+    let (first, _) = make_repo(repo);
+    let root = repo.join("crates/p");
+    let json = dir.path().join("old.json");
+    let run = |args: &[&str]| {
+        let mut cmd = Command::cargo_bin("rust-relations-explorer").unwrap();
+        cmd.args(args).arg("--path").arg(&root).arg("--cache-dir").arg(dir.path().join("c"));
+        cmd.assert()
+    };
+
+    run(&["build", "--rev", &first, "--json", json.to_str().unwrap()])
+        .success()
+        .stdout(contains(format!("at {first}")));
+    let graph = fs::read_to_string(&json).unwrap();
+    assert!(graph.contains("fn:crate::m::b"));
+    assert!(!graph.contains("fn:crate::m::d"));
+
+    run(&["build", "--rev", "HEAD~1", "--list-files"])
+        .success()
+        .stdout(contains("scan  src/m.rs"))
+        .stdout(contains("skip  scratch/tmp.rs  (ignored by .gitignore/.ignore)"));
+    run(&["build", "--rev", "no-such-branch"])
+        .code(1)
+        .stderr(contains("unknown revision `no-such-branch`"));
+}
+
+#[test]
+fn crate_names_are_read_from_the_revision() {
+    let dir = tempdir().unwrap();
+    let repo = dir.path().join("ws");
+    fs::create_dir_all(&repo).unwrap();
+    git(&repo, &["init", "-q"]);
+    write(&repo, "app/src/lib.rs", "use shapes_core::area;\npub fn run() {\n    area();\n}\n");
+    write(&repo, "core/Cargo.toml", "[package]\nname = \"shapes-core\"\nversion = \"0.1.0\"\n");
+    write(&repo, "core/src/lib.rs", "pub fn area() {}\n");
+    commit(&repo, "one");
+    // Renamed in the working tree only
+    write(&repo, "core/Cargo.toml", "[package]\nname = \"renamed\"\nversion = \"0.1.0\"\n");
+    let caches = CacheLocation::Disabled;
+
+    let revision = Revision::open(&repo, "HEAD", &DiscoveryOptions::default()).unwrap();
+    let manifests: Vec<_> = revision.manifests().iter().map(|m| m.path.clone()).collect();
+    assert_eq!(manifests, [repo.join("core/Cargo.toml")]);
+
+    let head = at_revision(&repo, "HEAD", &caches);
+    assert!(ids(&head).contains(&"fn:shapes_core::area".to_string()), "{:?}", ids(&head));
+    assert!(edges(&head).contains(&format!(
+        "fn:app::run {:?} fn:shapes_core::area",
+        RelationshipType::Calls { call_type: "heuristic".into() }
+    )));
+    let worktree = GraphBuilder::new(&repo).cache_location(caches).build().unwrap();
+    assert!(ids(&worktree).contains(&"fn:renamed::area".to_string()));
+}
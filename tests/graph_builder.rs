@@ -0,0 +1,186 @@
+use assert_cmd::prelude::*;
+use predicates::prelude::PredicateBooleanExt;
+use predicates::str::contains;
+use rust_relations_explorer::graph::builder::{BuildOptions, GraphBuilder};
+use rust_relations_explorer::graph::{schema, BuildFingerprint, KnowledgeGraph, RelationshipType};
+use rust_relations_explorer::utils::cache::{CacheLocation, CacheMode};
+use rust_relations_explorer::utils::file_walker::DiscoveryOptions;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use tempfile::tempdir;
+
+fn write(root: &Path, rel: &str, content: &str) {
+    let path = root.join(rel);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, content).unwrap();
+}
+
+fn make_proj(root: &Path) {
+    write(root, "Cargo.toml", "[package]\nname = \"p\"\nversion = \"0.1.0\"\n");
+    write(root, ".ignore", "src/scratch.rs\n");
+    write(root, "src/lib.rs", "pub mod m;\npub mod scratch;\npub fn a() {\n    m::b();\n}\n");
+    write(root, "src/m.rs", "pub fn b() {}\n");
+    write(root, "src/scratch.rs", "pub fn s() {}\n");
+}
+
+fn cache_files(dir: &Path) -> usize {
+    fs::read_dir(dir)
+        .unwrap()
+        .filter(|e| e.as_ref().unwrap().path().extension().is_some_and(|x| x == "json"))
+        .count()
+}
+
+fn has(g: &KnowledgeGraph, id: &str) -> bool {
+    g.files.values().flat_map(|f| &f.items).any(|i| i.id.0 == id)
+}
+
+#[test]
+fn options_select_files_cache_and_threads() {
+    let dir = tempdir().unwrap();
+    let root = dir.path().join("p");
+    make_proj(&root);
+    let caches = dir.path().join("caches");
+
+    let options = BuildOptions {
+        roots: vec![root.clone()],
+        cache_location: CacheLocation::Dir(caches.clone()),
+        discovery: DiscoveryOptions { no_ignore: true, ..DiscoveryOptions::default() },
+        threads: Some(1),
+        ..BuildOptions::default()
+    };
+    let g = GraphBuilder::from_options(options.clone()).build().unwrap();
+    assert!(has(&g, "fn:crate::scratch::s"));
+    assert!(g.metadata.options.no_ignore);
+    assert_eq!(g.metadata.options.parser, "regex");
+    assert!(!root.join(".knowledge_cache").exists());
+    assert_eq!(cache_files(&caches), 1);
+
+    // The second build reuses the cache; a rebuild starts over
+    let again = GraphBuilder::from_options(options.clone()).build().unwrap();
+    assert_eq!(again.metadata.stats.unwrap().files_parsed, 0);
+    let rebuilt = GraphBuilder::from_options(options).cache_mode(CacheMode::Rebuild).build();
+    assert_eq!(rebuilt.unwrap().metadata.stats.unwrap().files_parsed, 3);
+
+    let g = GraphBuilder::new(&root)
+        .cache_location(CacheLocation::Disabled)
+        .exclude("src/m.rs")
+        .build()
+        .unwrap();
+    assert!(has(&g, "fn:crate::a"));
+    assert!(!has(&g, "fn:crate::m::b"));
+    assert!(!has(&g, "fn:crate::scratch::s"));
+}
+
+#[test]
+fn cli_queries_build_with_the_build_settings() {
+    let dir = tempdir().unwrap();
+    let root = dir.path().join("p");
+    make_proj(&root);
+    let caches = dir.path().join("caches");
+    let query = |args: &[&str]| {
+        let mut cmd = Command::cargo_bin("rust-relations-explorer").unwrap();
+        cmd.env_remove("KNOWLEDGE_RS_NO_IGNORE").args(["query", "public-api", "--path"]).arg(&root);
+        cmd.arg("--cache-dir").arg(&caches).args(args);
+        cmd
+    };
+
+    query(&["--no-cache", "--threads", "2"])
+        .assert()
+        .success()
+        .stdout(contains("p::m::b"))
+        .stdout(contains("p::scratch::s").not());
+    assert!(!root.join(".knowledge_cache").exists());
+    assert!(!caches.exists(), "--no-cache writes no cache");
+
+    // The environment variable is read by the CLI only
+    query(&[])
+        .env("KNOWLEDGE_RS_NO_IGNORE", "1")
+        .assert()
+        .success()
+        .stdout(contains("p::scratch::s"));
+    assert_eq!(cache_files(&caches), 1);
+    query(&[]).env("KNOWLEDGE_RS_NO_IGNORE", "maybe").assert().failure();
+
+    // `[build]` in the query's config selects files as for `build`
+    let cfg = dir.path().join("rre.toml");
+    fs::write(&cfg, "[build]\nexclude = [\"src/m.rs\"]\n").unwrap();
+    query(&["--config", cfg.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(contains("p::a"))
+        .stdout(contains("p::m::b").not());
+}
+
+#[test]
+fn cli_verbose_build_reports_progress() {
+    let dir = tempdir().unwrap();
+    let root = dir.path();
+    make_proj(root);
+
+    Command::cargo_bin("rust-relations-explorer")
+        .unwrap()
+        .args(["-vv", "build", "--no-cache", "--threads", "2", "--path"])
+        .arg(root)
+        .assert()
+        .success()
+        .stderr(contains("Discovered 2 files under"))
+        .stderr(contains("[2/2] "))
+        .stderr(contains("Analyzing relationships across 2 files"));
+}
+
+#[test]
+fn features_select_analyses_and_invalidate_cached_ones() {
+    let dir = tempdir().unwrap();
+    let root = dir.path().join("p");
+    write(
+        &root,
+        "src/lib.rs",
+        "pub trait Shape {\n    fn area(&self) -> u32;\n}\npub struct Sq;\nimpl Shape for Sq {\n    fn area(&self) -> u32 { 1 }\n}\npub fn total(s: Sq) -> u32 {\n    s.area()\n}\n",
+    );
+    let caches = dir.path().join("caches");
+    let build = |features: &[&str]| {
+        GraphBuilder::new(&root)
+            .cache_location(CacheLocation::Dir(caches.clone()))
+            .features(features.iter().copied())
+            .build()
+            .unwrap()
+    };
+    let kinds = |g: &KnowledgeGraph| {
+        let method = g.relationships.iter().any(|r| {
+            matches!(&r.relationship_type, RelationshipType::Calls { call_type } if call_type == "method")
+        });
+        let dispatch = g.relationships.iter().any(|r| {
+            matches!(r.relationship_type, RelationshipType::Implements { .. })
+                && r.context == "Shape::area"
+        });
+        (method, dispatch)
+    };
+
+    let all = build(&[schema::TRAIT_DISPATCH, schema::METHOD_CALLS]);
+    assert_eq!(all.metadata.options.features, schema::BUILD_FEATURES);
+    assert_eq!(kinds(&all), (true, true));
+
+    // Cached analyses made with other features are not reused
+    let dispatch_only = build(&[schema::TRAIT_DISPATCH]);
+    assert_eq!(dispatch_only.metadata.options.features, [schema::TRAIT_DISPATCH]);
+    assert_eq!(dispatch_only.metadata.stats.as_ref().unwrap().files_parsed, 0);
+    assert_eq!(kinds(&dispatch_only), (false, true));
+    assert_eq!(kinds(&build(&[])), (false, false));
+    assert_eq!(kinds(&build(&[schema::METHOD_CALLS])), (true, false));
+
+    // A saved graph built with fewer features does not match this build's fingerprint
+    let current = BuildFingerprint::current(false);
+    assert!(schema::check_fingerprint(&dispatch_only.metadata.options, &current).is_err());
+    assert!(schema::check_fingerprint(&all.metadata.options, &current).is_ok());
+
+    Command::cargo_bin("rust-relations-explorer")
+        .unwrap()
+        .args(["build", "--features", "method-calls,nope", "--cache-dir"])
+        .arg(&caches)
+        .arg("--path")
+        .arg(&root)
+        .assert()
+        .failure()
+        .stderr(contains("`nope`"));
+}
@@ -0,0 +1,127 @@
+use assert_cmd::prelude::*;
+use rust_relations_explorer::graph::builder::GraphBuilder;
+use rust_relations_explorer::graph::diff::DiffRelationship;
+use rust_relations_explorer::graph::KnowledgeGraph;
+use rust_relations_explorer::utils::cache::CacheMode;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tempfile::tempdir;
+
+fn write(root: &Path, files: &[(&str, &str)]) {
+    fs::create_dir_all(root.join("src")).unwrap();
+    for (path, content) in files {
+        fs::write(root.join("src").join(path), content).unwrap();
+    }
+}
+
+fn build(root: &Path) -> KnowledgeGraph {
+    GraphBuilder::new(root).cache_mode(CacheMode::Ignore).build().unwrap()
+}
+
+const BEFORE: &[(&str, &str)] = &[
+    ("lib.rs", "mod a;\nmod b;\n"),
+    ("a.rs", "pub fn f() {\n    crate::b::g();\n}\nfn old() {}\nfn helper() {}\n"),
+    ("b.rs", "pub fn g() {}\n"),
+];
+
+// `old` removed, `fresh` added, `helper` moved to b.rs, `g` made private, b -> a call added
+const AFTER: &[(&str, &str)] = &[
+    ("lib.rs", "mod a;\nmod b;\n"),
+    ("a.rs", "pub fn f() {\n    crate::b::g();\n}\npub fn fresh() {}\n"),
+    ("b.rs", "fn g() {\n    crate::a::f();\n}\nfn helper() {}\n"),
+];
+
+fn snapshot(dir: &Path, name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let root = dir.join("project");
+    write(&root, files);
+    let saved = dir.join(name);
+    build(&root).save_json(&saved).unwrap();
+    saved
+}
+
+#[test]
+fn diff_reports_items_relationships_cycles_and_centrality() {
+    let dir = tempdir().unwrap();
+    let old = snapshot(dir.path(), "old.json", BEFORE);
+    let new = snapshot(dir.path(), "new.json", AFTER);
+    let diff = KnowledgeGraph::diff(
+        &KnowledgeGraph::load_json(&old).unwrap(),
+        &KnowledgeGraph::load_json(&new).unwrap(),
+    );
+
+    let ids = |items: &[rust_relations_explorer::graph::diff::DiffItem]| {
+        items.iter().map(|i| i.id.clone()).collect::<Vec<_>>()
+    };
+    assert_eq!(ids(&diff.added_items), vec!["fn:crate::a::fresh"]);
+    assert_eq!(ids(&diff.removed_items), vec!["fn:crate::a::old"]);
+    assert_eq!(diff.moved_items.len(), 1);
+    assert_eq!(diff.moved_items[0].from.id, "fn:crate::a::helper");
+    assert_eq!(diff.moved_items[0].to.id, "fn:crate::b::helper");
+    assert_eq!(diff.moved_items[0].to.file, PathBuf::from("src/b.rs"));
+    assert_eq!(diff.visibility_changes.len(), 1);
+    assert_eq!(diff.visibility_changes[0].id, "fn:crate::b::g");
+    assert_eq!(
+        (diff.visibility_changes[0].before.as_str(), diff.visibility_changes[0].after.as_str()),
+        ("public", "private")
+    );
+    assert!(diff.added_relationships.contains(&DiffRelationship {
+        from: "fn:crate::b::g".into(),
+        to: "fn:crate::a::f".into(),
+        kind: "calls".into(),
+    }));
+    assert!(diff.removed_relationships.is_empty());
+    assert_eq!(diff.new_cycles, vec![vec![PathBuf::from("src/a.rs"), PathBuf::from("src/b.rs")]]);
+    assert!(diff.centrality.iter().any(|c| c.file == Path::new("src/b.rs") && c.delta > 0));
+}
+
+#[test]
+fn line_shifts_alone_produce_an_empty_diff() {
+    let dir = tempdir().unwrap();
+    let old = snapshot(dir.path(), "old.json", BEFORE);
+    let shifted: Vec<(&str, String)> =
+        BEFORE.iter().map(|(p, c)| (*p, format!("// header\n\n{c}"))).collect();
+    let shifted: Vec<(&str, &str)> = shifted.iter().map(|(p, c)| (*p, c.as_str())).collect();
+    let new = snapshot(dir.path(), "new.json", &shifted);
+    let diff = KnowledgeGraph::diff(
+        &KnowledgeGraph::load_json(&old).unwrap(),
+        &KnowledgeGraph::load_json(&new).unwrap(),
+    );
+    assert!(diff.is_empty(), "{diff:?}");
+}
+
+#[test]
+fn cli_diff_prints_changes_and_enforces_limits() {
+    let dir = tempdir().unwrap();
+    let old = snapshot(dir.path(), "old.json", BEFORE);
+    let new = snapshot(dir.path(), "new.json", AFTER);
+
+    let mut cmd = Command::cargo_bin("rust-relations-explorer").unwrap();
+    cmd.arg("diff").arg(&old).arg(&new);
+    let out = cmd.assert().success().get_output().stdout.clone();
+    let text = String::from_utf8(out).unwrap();
+    assert!(text.contains("+ fn:crate::a::fresh  src/a.rs:4"), "{text}");
+    assert!(text.contains("fn:crate::b::g: public -> private"), "{text}");
+    assert!(text.contains("src/a.rs -> src/b.rs -> src/a.rs"), "{text}");
+
+    let mut cmd = Command::cargo_bin("rust-relations-explorer").unwrap();
+    cmd.arg("diff")
+        .arg(&old)
+        .arg(&new)
+        .arg("--format")
+        .arg("json")
+        .arg("--max-new-cycles")
+        .arg("0");
+    let assert = cmd.assert().code(3);
+    let out = assert.get_output();
+    let json: serde_json::Value = serde_json::from_slice(&out.stdout).unwrap();
+    assert_eq!(json["new_cycles"].as_array().unwrap().len(), 1);
+    assert!(String::from_utf8_lossy(&out.stderr).contains("new cycles: 1 exceeds limit 0"));
+
+    // Limits from the config file
+    let cfg = dir.path().join("rre.toml");
+    fs::write(&cfg, "[diff]\nmax_removed_items = 0\n").unwrap();
+    let mut cmd = Command::cargo_bin("rust-relations-explorer").unwrap();
+    cmd.arg("diff").arg(&old).arg(&new).arg("--config").arg(&cfg);
+    cmd.assert().code(3);
+}
@@ -0,0 +1,183 @@
+use assert_cmd::prelude::*;
+use rust_relations_explorer::graph::builder::GraphBuilder;
+use rust_relations_explorer::graph::filter::{GraphFilter, VisibilityFilter};
+use rust_relations_explorer::graph::index::RelKind;
+use rust_relations_explorer::graph::{KnowledgeGraph, RelationshipType};
+use rust_relations_explorer::source::MemorySource;
+use rust_relations_explorer::utils::cache::{CacheLocation, CacheMode};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use tempfile::tempdir;
+
+fn write_project(root: &Path) {
+    fs::create_dir_all(root.join("src/shapes")).unwrap();
+    fs::write(root.join("Cargo.toml"), "[package]\nname = \"demo\"\n").unwrap();
+    fs::write(
+        root.join("src/lib.rs"),
+        "pub mod shapes;\nuse crate::shapes::square::Square;\npub fn run() {\n    helper();\n}\nfn helper() {}\n",
+    )
+    .unwrap();
+    fs::write(root.join("src/shapes/mod.rs"), "pub mod square;\npub trait Area {}\n").unwrap();
+    fs::write(
+        root.join("src/shapes/square.rs"),
+        "use super::Area;\npub struct Square;\nimpl Area for Square {}\nfn side() {}\n",
+    )
+    .unwrap();
+}
+
+fn build(root: &Path) -> KnowledgeGraph {
+    GraphBuilder::new(root).cache_mode(CacheMode::Ignore).build().unwrap()
+}
+
+fn ids(g: &KnowledgeGraph) -> Vec<String> {
+    let mut ids: Vec<String> =
+        g.files.values().flat_map(|f| f.items.iter().map(|it| it.id.0.clone())).collect();
+    ids.sort();
+    ids
+}
+
+// Every relationship endpoint is an item of the subgraph
+fn assert_closed(g: &KnowledgeGraph) {
+    let ids = ids(g);
+    for r in &g.relationships {
+        assert!(ids.contains(&r.from_item.0), "dangling {}", r.from_item.0);
+        assert!(ids.contains(&r.to_item.0), "dangling {}", r.to_item.0);
+    }
+}
+
+#[test]
+fn path_and_module_filters_select_files() {
+    let dir = tempdir().unwrap();
+    write_project(dir.path());
+    let g = build(dir.path());
+
+    let by_path = g.subgraph(&GraphFilter::new().paths(["src/shapes/**"]).unwrap());
+    assert_eq!(by_path.files.len(), 2);
+    assert!(by_path.files.keys().all(|p| p.to_string_lossy().contains("shapes")));
+    assert_closed(&by_path);
+
+    let by_module = g.subgraph(&GraphFilter::new().modules(["crate::shapes::square"]));
+    assert_eq!(by_module.files.len(), 1);
+    assert!(ids(&by_module).contains(&"struct:crate::shapes::square::Square".to_string()));
+    assert!(!ids(&by_module).contains(&"trait:crate::shapes::Area".to_string()));
+    assert_closed(&by_module);
+}
+
+#[test]
+fn subgraphs_of_a_workspace_keep_crate_names_and_drop_stats() {
+    // Package names differ from the directories and exist only in memory
+    let sources: MemorySource = [
+        ("/w/a/Cargo.toml", "[package]\nname = \"alpha\"\n"),
+        ("/w/a/src/lib.rs", "pub mod m;\npub fn a() {}\n"),
+        ("/w/a/src/m.rs", "pub fn x() {}\n"),
+        ("/w/b/Cargo.toml", "[package]\nname = \"beta-core\"\n"),
+        ("/w/b/src/lib.rs", "pub mod m;\n"),
+        ("/w/b/src/m.rs", "// no items\n"),
+    ]
+    .into_iter()
+    .collect();
+    let g = GraphBuilder::new("/w")
+        .provider(&sources)
+        .cache_location(CacheLocation::Disabled)
+        .build()
+        .unwrap();
+    assert!(g.metadata.stats.is_some());
+
+    let b = g.subgraph(&GraphFilter::new().paths(["b/**"]).unwrap());
+    assert!(b.metadata.stats.is_none());
+    assert_eq!(b.files.len(), 2);
+
+    // A file without items is selected by the module its crate name gives it
+    let file = "file:b/src/m.rs".to_string();
+    assert!(ids(&g.subgraph(&GraphFilter::new().modules(["beta_core::m"]))).contains(&file));
+    let m = b.subgraph(&GraphFilter::new().modules(["beta_core::m"]));
+    assert!(ids(&m).contains(&file), "{:?}", ids(&m));
+    assert!(!ids(&m).iter().any(|id| id.contains("alpha")));
+}
+
+#[test]
+fn kind_visibility_and_relationship_filters() {
+    let dir = tempdir().unwrap();
+    write_project(dir.path());
+    let g = build(dir.path());
+
+    let fns = g.subgraph(&GraphFilter::new().kinds(["function"]).unwrap());
+    assert!(ids(&fns).iter().all(|id| id.starts_with("fn:") || id.starts_with("file:")));
+    assert!(ids(&fns).contains(&"fn:crate::helper".to_string()));
+
+    let public = g.subgraph(&GraphFilter::new().visibility(VisibilityFilter::Public));
+    assert!(ids(&public).contains(&"fn:crate::run".to_string()));
+    assert!(!ids(&public).contains(&"fn:crate::helper".to_string()));
+    assert_closed(&public);
+
+    let calls = g.subgraph(&GraphFilter::new().relationships([RelKind::Calls]));
+    assert!(!calls.relationships.is_empty());
+    assert!(calls
+        .relationships
+        .iter()
+        .all(|r| matches!(r.relationship_type, RelationshipType::Calls { .. })));
+}
+
+#[test]
+fn neighbourhood_is_bounded_by_hops() {
+    let dir = tempdir().unwrap();
+    write_project(dir.path());
+    let g = build(dir.path());
+    let calls = || GraphFilter::new().relationships([RelKind::Calls]);
+
+    let seed_only = g.subgraph(&calls().around(["fn:crate::run"], 0));
+    assert!(ids(&seed_only).contains(&"fn:crate::run".to_string()));
+    assert!(!ids(&seed_only).contains(&"fn:crate::helper".to_string()));
+
+    let one_hop = g.subgraph(&calls().around(["fn:crate::run"], 1));
+    assert!(ids(&one_hop).contains(&"fn:crate::helper".to_string()));
+    assert!(!ids(&one_hop).contains(&"struct:crate::shapes::square::Square".to_string()));
+    assert_eq!(one_hop.relationships.len(), 1);
+    assert_closed(&one_hop);
+}
+
+#[test]
+fn cli_filters_queries_and_diagrams() {
+    let dir = tempdir().unwrap();
+    write_project(dir.path());
+
+    let mut cmd = Command::cargo_bin("rust-relations-explorer").unwrap();
+    cmd.env("RRE_CACHE_DIR", dir.path().join("cache"));
+    cmd.arg("query")
+        .arg("hubs")
+        .arg("--path")
+        .arg(dir.path())
+        .arg("--filter-path")
+        .arg("src/shapes/**")
+        .arg("--format")
+        .arg("json");
+    let out = cmd.assert().success().get_output().stdout.clone();
+    let text = String::from_utf8(out).unwrap();
+    assert!(text.contains("square.rs"));
+    assert!(!text.contains("lib.rs"));
+
+    // Filter taken from the config file when no filter flag is given
+    let cfg = dir.path().join("rre.toml");
+    fs::write(&cfg, "[filter]\nmodules = [\"crate::shapes\"]\n").unwrap();
+    let dot = dir.path().join("graph.dot");
+    let mut cmd = Command::cargo_bin("rust-relations-explorer").unwrap();
+    cmd.env("RRE_CACHE_DIR", dir.path().join("cache"));
+    cmd.arg("build")
+        .arg("--path")
+        .arg(dir.path())
+        .arg("--no-cache")
+        .arg("--config")
+        .arg(&cfg)
+        .arg("--dot")
+        .arg(&dot);
+    cmd.assert().success();
+    let content = fs::read_to_string(&dot).unwrap();
+    assert!(content.contains("square.rs"));
+    assert!(!content.contains("lib.rs"));
+
+    let mut cmd = Command::cargo_bin("rust-relations-explorer").unwrap();
+    cmd.env("RRE_CACHE_DIR", dir.path().join("cache"));
+    cmd.arg("query").arg("hubs").arg("--path").arg(dir.path()).arg("--filter-path").arg("src/[");
+    cmd.assert().code(2);
+}
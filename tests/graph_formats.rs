@@ -0,0 +1,421 @@
+use assert_cmd::prelude::*;
+use rust_relations_explorer::errors::KnowledgeGraphError;
+use rust_relations_explorer::graph::builder::GraphBuilder;
+use rust_relations_explorer::graph::format::GraphFormat;
+use rust_relations_explorer::graph::schema::SCHEMA_VERSION;
+use rust_relations_explorer::graph::{Confidence, ItemId, KnowledgeGraph, RelationshipType};
+use rust_relations_explorer::query::{
+    CentralityMetric, ConnectedFilesQuery, CycleDetectionQuery, FunctionUsageQuery, HubsQuery,
+    ItemInfoQuery, PublicApiQuery, Query, UnreferencedItemsQuery,
+};
+use rust_relations_explorer::utils::cache::CacheMode;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use tempfile::tempdir;
+
+fn project(root: &Path) {
+    let src = root.join("src");
+    fs::create_dir_all(&src).unwrap();
+    fs::write(
+        src.join("lib.rs"),
+        "pub mod a;\nmod b;\npub use b::Helper;\npub fn top() { a::child(); }\n",
+    )
+    .unwrap();
+    fs::write(
+        src.join("a.rs"),
+        "use crate::top;\npub trait Shape { fn area(&self) -> f64; }\npub struct Sq;\nimpl Shape for Sq {\n    fn area(&self) -> f64 { 1.0 }\n}\npub fn child() { top(); }\n",
+    )
+    .unwrap();
+    fs::write(
+        src.join("b.rs"),
+        "pub struct Helper;\nimpl Helper {\n    pub fn new() -> Self { Helper }\n}\n",
+    )
+    .unwrap();
+}
+
+// Query outputs rendered to comparable JSON
+fn answers(g: &KnowledgeGraph, root: &Path) -> Vec<String> {
+    let a = root.join("src/a.rs");
+    let info = ItemInfoQuery::new(ItemId("fn:crate::a::child".into()), false).run(g);
+    // Rows come in file map order
+    let mut unreferenced = UnreferencedItemsQuery::new(true, None).run(g);
+    unreferenced.sort();
+    vec![
+        serde_json::to_string(&ConnectedFilesQuery::new(&a).run(g)).unwrap(),
+        serde_json::to_string(&FunctionUsageQuery::callers("top").run(g)).unwrap(),
+        serde_json::to_string(&FunctionUsageQuery::callees("child").run(g)).unwrap(),
+        serde_json::to_string(&HubsQuery::new(CentralityMetric::Total, 10).run(g)).unwrap(),
+        serde_json::to_string(&CycleDetectionQuery::new().run(g)).unwrap(),
+        serde_json::to_string(&PublicApiQuery::new().run(g)).unwrap(),
+        serde_json::to_string(&unreferenced).unwrap(),
+        serde_json::to_string(&info).unwrap(),
+    ]
+}
+
+#[test]
+fn json_and_binary_round_trips_answer_queries_identically() {
+    let dir = tempdir().unwrap();
+    let root = dir.path();
+    project(root);
+    let g = GraphBuilder::new(root).cache_mode(CacheMode::Ignore).build().unwrap();
+
+    let json_path = root.join("graph.json");
+    let bin_path = root.join("graph.bin");
+    g.save(&json_path, GraphFormat::from_path(&json_path)).unwrap();
+    g.save(&bin_path, GraphFormat::from_path(&bin_path)).unwrap();
+    let from_json = KnowledgeGraph::load(&json_path, GraphFormat::Json).unwrap();
+    let from_bin = KnowledgeGraph::load(&bin_path, GraphFormat::Binary).unwrap();
+
+    // Same graph content, and the binary file is much smaller
+    assert_eq!(serde_json::to_value(&from_bin).unwrap(), serde_json::to_value(&g).unwrap());
+    assert!(fs::metadata(&bin_path).unwrap().len() * 3 < fs::metadata(&json_path).unwrap().len());
+
+    let expected = answers(&g, root);
+    assert!(expected[1].contains("fn:crate::a::child"));
+    assert_eq!(answers(&from_json, root), expected);
+    assert_eq!(answers(&from_bin, root), expected);
+}
+
+#[test]
+fn binary_load_rejects_other_content() {
+    let dir = tempdir().unwrap();
+    let p = dir.path().join("graph.bin");
+    fs::write(&p, b"{\"files\": {}}").unwrap();
+    assert!(KnowledgeGraph::load(&p, GraphFormat::Binary).is_err());
+
+    let mut data = KnowledgeGraph::default().to_binary().unwrap();
+    assert!(KnowledgeGraph::from_binary(&data).is_ok());
+    data.truncate(data.len() - 1);
+    assert!(KnowledgeGraph::from_binary(&data).is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn binary_save_rejects_non_utf8_paths() {
+    use std::os::unix::ffi::OsStrExt;
+    let dir = tempdir().unwrap();
+    let root = dir.path();
+    project(root);
+    let mut g = GraphBuilder::new(root).cache_mode(CacheMode::Ignore).build().unwrap();
+    let odd = root.join(std::ffi::OsStr::from_bytes(b"src/\xff.rs"));
+    let mut node = g.files.values().next().unwrap().clone();
+    node.path.clone_from(&odd);
+    g.files.insert(odd, node);
+
+    let err = g.to_binary().unwrap_err();
+    assert!(matches!(err, KnowledgeGraphError::Format(m) if m.contains("UTF-8")));
+    assert!(g.save(&root.join("graph.bin"), GraphFormat::Binary).is_err());
+    assert!(!root.join("graph.bin").exists());
+}
+
+#[test]
+fn binary_version_1_loads_with_defaults() {
+    let strings: &[&str] = &[
+        "1",                  // 0: generated_at
+        "0.1.3",              // 1: tool_version
+        "regex",              // 2: parser
+        "/p",                 // 3: root
+        "/p/src/lib.rs",      // 4: file path, as built
+        "file:/p/src/lib.rs", // 5: file item id
+        "lib",                // 6
+        "fn:crate::a",        // 7
+        "a",                  // 8
+        "std::fmt",           // 9: import
+        "module",             // 10
+        "",                   // 11
+    ];
+    let mut data = b"RRXG\x01".to_vec();
+    data.push(12);
+    for s in strings {
+        data.push(u8::try_from(s.len()).unwrap());
+        data.extend(s.as_bytes());
+    }
+    // Metadata: generated_at, schema 1, tool version, no_ignore, no features, parser, root
+    data.extend([0, 1, 1, 0, 0, 2, 4]);
+    // One file with its file-level module and `fn a`
+    data.extend([1, 4, 2]);
+    data.extend([5, 0, 0, 6, 2, 0, 1, 1, 0]);
+    data.extend([7, 1, 0, 0, 8, 0, 0, 2, 2, 0]);
+    // One import without position, then metrics
+    data.extend([1, 9, 0, 0, 2, 1]);
+    // One relationship without count or evidence: item 0 contains item 1
+    data.extend([1, 0, 2, 2, 10]);
+    data.extend(1.0f64.to_le_bytes());
+    data.extend([11, 2, 0]);
+    // No module parents, children or segments
+    data.extend([0, 0, 0]);
+
+    let g = KnowledgeGraph::from_binary(&data).unwrap();
+    assert_eq!(g.metadata.schema_version, SCHEMA_VERSION);
+    assert!(g.metadata.stats.is_none());
+    let file = &g.files[Path::new("/p/src/lib.rs")];
+    assert_eq!(file.items[0].id.0, "file:src/lib.rs");
+    assert_eq!((file.imports[0].path.as_ref(), file.imports[0].line), ("std::fmt", 0));
+    let rel = &g.relationships[0];
+    assert_eq!(
+        (rel.from_item.0.as_str(), rel.to_item.0.as_str()),
+        ("file:src/lib.rs", "fn:crate::a")
+    );
+    assert_eq!(rel.count, 1);
+    assert!(rel.evidence.is_empty());
+
+    // Saved again in the current version
+    let again = KnowledgeGraph::from_binary(&g.to_binary().unwrap()).unwrap();
+    assert_eq!(again.files[Path::new("/p/src/lib.rs")].items[1].id.0, "fn:crate::a");
+    assert_eq!(again.relationships[0].count, 1);
+}
+
+#[test]
+fn cli_saves_and_queries_binary_graph() {
+    let dir = tempdir().unwrap();
+    let root = dir.path();
+    project(root);
+    let saved = root.join("graph.data");
+
+    let mut build = Command::cargo_bin("rust-relations-explorer").unwrap();
+    build
+        .arg("build")
+        .arg("--path")
+        .arg(root)
+        .arg("--no-cache")
+        .arg("--save")
+        .arg(&saved)
+        .arg("--graph-format")
+        .arg("binary");
+    build.assert().success();
+    assert_eq!(&fs::read(&saved).unwrap()[..4], b"RRXG");
+
+    let mut query = Command::cargo_bin("rust-relations-explorer").unwrap();
+    query
+        .arg("query")
+        .arg("function-usage")
+        .arg("--graph")
+        .arg(&saved)
+        .arg("--graph-format")
+        .arg("binary")
+        .arg("--function")
+        .arg("top")
+        .arg("--direction")
+        .arg("callers");
+    let out = query.assert().success().get_output().stdout.clone();
+    assert!(String::from_utf8(out).unwrap().contains("fn:crate::a::child"));
+
+    // Without the flag the extension decides, and `.data` means JSON
+    let mut wrong = Command::cargo_bin("rust-relations-explorer").unwrap();
+    wrong.arg("query").arg("hubs").arg("--graph").arg(&saved);
+    wrong.assert().failure();
+}
+
+// Rewrite a current graph into the unversioned 0.1.x shape: `kind:name:line` ids, no
+// schema/build metadata, no confidence or call-site lines, no module segments.
+fn legacy_json(g: &KnowledgeGraph) -> serde_json::Value {
+    let mut v = serde_json::to_value(g).unwrap();
+    let mut renamed = std::collections::HashMap::new();
+    for file in v["files"].as_object_mut().unwrap().values_mut() {
+        for it in file["items"].as_array_mut().unwrap() {
+            let id = it["id"].as_str().unwrap().to_string();
+            let kind = id.split(':').next().unwrap();
+            let legacy = if kind == "file" {
+                format!("file:{}", it["location"]["file"].as_str().unwrap())
+            } else {
+                format!("{kind}:{}:{}", it["name"].as_str().unwrap(), it["location"]["line_start"])
+            };
+            it["id"] = legacy.clone().into();
+            renamed.insert(id, legacy);
+        }
+    }
+    for rel in v["relationships"].as_array_mut().unwrap() {
+        for end in ["from_item", "to_item"] {
+            if let Some(legacy) = renamed.get(rel[end].as_str().unwrap()) {
+                rel[end] = legacy.clone().into();
+            }
+        }
+        let rel = rel.as_object_mut().unwrap();
+        rel.remove("confidence");
+        rel.remove("line");
+    }
+    let obj = v.as_object_mut().unwrap();
+    obj.remove("module_segments");
+    obj.insert("metadata".into(), serde_json::json!({ "generated_at": "1" }));
+    v
+}
+
+#[test]
+fn unversioned_graph_is_migrated_to_path_based_ids() {
+    let dir = tempdir().unwrap();
+    let root = dir.path();
+    project(root);
+    let g = GraphBuilder::new(root).cache_mode(CacheMode::Ignore).build().unwrap();
+    assert_eq!(g.metadata.schema_version, SCHEMA_VERSION);
+    assert_eq!(g.metadata.tool_version, env!("CARGO_PKG_VERSION"));
+    assert_eq!(g.metadata.options.parser, "regex");
+    assert_eq!(g.metadata.root.as_deref(), Some(root));
+
+    let legacy = root.join("legacy.json");
+    fs::write(&legacy, legacy_json(&g).to_string()).unwrap();
+    let migrated = KnowledgeGraph::load_json(&legacy).unwrap();
+    assert_eq!(migrated.metadata.schema_version, SCHEMA_VERSION);
+
+    let ids = |g: &KnowledgeGraph| {
+        let mut ids: Vec<String> =
+            g.files.values().flat_map(|f| f.items.iter().map(|i| i.id.0.clone())).collect();
+        ids.sort();
+        ids
+    };
+    let edges = |g: &KnowledgeGraph| {
+        let mut edges: Vec<(String, String)> =
+            g.relationships.iter().map(|r| (r.from_item.0.clone(), r.to_item.0.clone())).collect();
+        edges.sort();
+        edges
+    };
+    assert_eq!(ids(&migrated), ids(&g));
+    assert_eq!(edges(&migrated), edges(&g));
+    let callers = FunctionUsageQuery::callers("top").run(&migrated);
+    assert_eq!(callers[0].id, ItemId("fn:crate::a::child".into()));
+    // Without a recorded confidence, calls load as name-only and structural edges as exact
+    for rel in &migrated.relationships {
+        let expected = match rel.relationship_type {
+            RelationshipType::Calls { .. } => Confidence::NameOnly,
+            _ => Confidence::Exact,
+        };
+        assert_eq!(rel.confidence, expected);
+    }
+}
+
+#[test]
+fn version_1_graph_is_migrated_to_relative_file_ids_and_folded_imports() {
+    let dir = tempdir().unwrap();
+    let root = dir.path();
+    project(root);
+    let g = GraphBuilder::new(root).cache_mode(CacheMode::Ignore).build().unwrap();
+
+    // Version 1: paths as built, `file:` ids with the built path, an edge per import
+    let mut v = serde_json::to_value(&g).unwrap();
+    v["metadata"]["schema_version"] = 1.into();
+    let abs = |id: &str| match id.strip_prefix("file:") {
+        Some(rel) => format!("file:{}", root.join(rel).display()),
+        None => id.to_string(),
+    };
+    for file in v["files"].as_object_mut().unwrap().values_mut() {
+        let first = &mut file["items"][0]["id"];
+        *first = abs(first.as_str().unwrap()).into();
+    }
+    let rels = v["relationships"].as_array_mut().unwrap();
+    for rel in rels.iter_mut() {
+        for end in ["from_item", "to_item"] {
+            rel[end] = abs(rel[end].as_str().unwrap()).into();
+        }
+        let rel = rel.as_object_mut().unwrap();
+        rel.remove("count");
+        rel.remove("evidence");
+    }
+    let import = rels.iter().position(|r| r["relationship_type"].get("Uses").is_some()).unwrap();
+    let repeated = rels[import].clone();
+    rels.push(repeated);
+    let legacy = root.join("v1.json");
+    fs::write(&legacy, v.to_string()).unwrap();
+
+    let migrated = KnowledgeGraph::load_json(&legacy).unwrap();
+    assert_eq!(migrated.metadata.schema_version, SCHEMA_VERSION);
+    assert_eq!(migrated.files[&root.join("src/lib.rs")].items[0].id.0, "file:src/lib.rs");
+    assert_eq!(migrated.relationships.len(), g.relationships.len());
+    let folded = &migrated.relationships[import];
+    assert_eq!(
+        (folded.from_item.clone(), folded.count),
+        (g.relationships[import].from_item.clone(), 2)
+    );
+    assert_eq!(FunctionUsageQuery::callers("top").run(&migrated).len(), 1);
+}
+
+#[test]
+fn cli_warns_about_graphs_built_with_other_settings() {
+    let dir = tempdir().unwrap();
+    let root = dir.path();
+    project(root);
+    let mut g = GraphBuilder::new(root).cache_mode(CacheMode::Ignore).build().unwrap();
+    let plain = root.join("plain.json");
+    g.save(&plain, GraphFormat::Json).unwrap();
+    g.metadata.options.features = vec!["experimental".into()];
+    let other = root.join("other.json");
+    g.save(&other, GraphFormat::Json).unwrap();
+
+    let query = |graph: &Path| {
+        let mut cmd = Command::cargo_bin("rust-relations-explorer").unwrap();
+        cmd.args(["query", "hubs", "--graph"]).arg(graph);
+        cmd.assert().success().get_output().stderr.clone()
+    };
+    assert!(!String::from_utf8(query(&plain)).unwrap().contains("Warning"));
+    let err = String::from_utf8(query(&other)).unwrap();
+    assert!(err.contains("Warning"), "{err}");
+    assert!(err.contains("features [experimental] != [method-calls, trait-dispatch]"), "{err}");
+}
+
+#[test]
+fn graphs_from_newer_schemas_are_rejected() {
+    let dir = tempdir().unwrap();
+    let mut g = KnowledgeGraph::default();
+    g.metadata.schema_version = SCHEMA_VERSION + 1;
+    for (name, format) in [("graph.json", GraphFormat::Json), ("graph.bin", GraphFormat::Binary)] {
+        let p = dir.path().join(name);
+        g.save(&p, format).unwrap();
+        let err = KnowledgeGraph::load(&p, format).unwrap_err();
+        assert!(
+            matches!(err, KnowledgeGraphError::UnsupportedSchema { found, .. } if found == SCHEMA_VERSION + 1)
+        );
+        assert!(err.to_string().contains("rebuild the graph"));
+    }
+}
+
+#[test]
+fn saved_graph_is_portable_across_checkouts() {
+    let built = tempdir().unwrap();
+    project(built.path());
+    let g = GraphBuilder::new(built.path()).cache_mode(CacheMode::Ignore).build().unwrap();
+    assert!(g.files.contains_key(&built.path().join("src/a.rs")));
+    let out = tempdir().unwrap();
+    let json = out.path().join("graph.json");
+    let bin = out.path().join("graph.bin");
+    g.save(&json, GraphFormat::Json).unwrap();
+    g.save(&bin, GraphFormat::Binary).unwrap();
+    let saved: serde_json::Value = serde_json::from_slice(&fs::read(&json).unwrap()).unwrap();
+    assert!(saved["files"].get("src/a.rs").is_some());
+    assert!(saved["files"]["src/lib.rs"]["items"][0]["id"] == "file:src/lib.rs");
+
+    // Same sources in another checkout; the original tree is gone
+    let checkout = tempdir().unwrap();
+    project(checkout.path());
+    drop(built);
+    for graph in [&json, &bin] {
+        let mut query = Command::cargo_bin("rust-relations-explorer").unwrap();
+        query
+            .arg("query")
+            .arg("connected-files")
+            .arg("--graph")
+            .arg(graph)
+            .arg("--root")
+            .arg(checkout.path())
+            .arg("--file")
+            .arg("src/a.rs");
+        let out = query.assert().success().get_output().stdout.clone();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains(&checkout.path().join("src/lib.rs").display().to_string()));
+
+        let mut info = Command::cargo_bin("rust-relations-explorer").unwrap();
+        // Code is only printed in verbose mode
+        info.arg("-v")
+            .arg("query")
+            .arg("item-info")
+            .arg("--graph")
+            .arg(graph)
+            .arg("--root")
+            .arg(checkout.path())
+            .arg("--item-id")
+            .arg("fn:crate::a::child")
+            .arg("--format")
+            .arg("json");
+        let out = info.assert().success().get_output().stdout.clone();
+        let info: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(info["code"], "pub fn child() { top(); }");
+    }
+}
@@ -0,0 +1,127 @@
+use assert_cmd::prelude::*;
+use rust_relations_explorer::errors::KnowledgeGraphError;
+use rust_relations_explorer::graph::builder::GraphBuilder;
+use rust_relations_explorer::graph::{ItemId, KnowledgeGraph, RelationshipType};
+use rust_relations_explorer::utils::cache::CacheMode;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use tempfile::tempdir;
+
+fn write_crate(root: &Path, name: &str, files: &[(&str, &str)]) {
+    fs::create_dir_all(root.join("src")).unwrap();
+    fs::write(root.join("Cargo.toml"), format!("[package]\nname = \"{name}\"\n")).unwrap();
+    for (path, content) in files {
+        fs::write(root.join("src").join(path), content).unwrap();
+    }
+}
+
+// `app` depends on the `widgets` library from a sibling checkout
+fn two_repos(dir: &Path) {
+    write_crate(
+        &dir.join("widgets"),
+        "widgets",
+        &[
+            ("lib.rs", "pub mod shapes;\npub fn make() -> shapes::Square { shapes::Square }\n"),
+            ("shapes.rs", "pub struct Square;\n"),
+        ],
+    );
+    write_crate(
+        &dir.join("app"),
+        "app",
+        &[("lib.rs", "use widgets::shapes::Square;\npub fn run() {\n    widgets::make();\n}\n")],
+    );
+}
+
+fn has_edge(g: &KnowledgeGraph, from: &str, to: &str, uses: bool) -> bool {
+    g.relationships.iter().any(|r| {
+        r.from_item.0 == from
+            && r.to_item.0 == to
+            && matches!(r.relationship_type, RelationshipType::Uses { .. }) == uses
+    })
+}
+
+#[test]
+fn multi_root_build_connects_imports_and_calls_between_roots() {
+    let dir = tempdir().unwrap();
+    two_repos(dir.path());
+    let roots = [dir.path().join("app"), dir.path().join("widgets")];
+    let g = GraphBuilder::new(&roots[0])
+        .add_root(&roots[1])
+        .cache_mode(CacheMode::Ignore)
+        .build()
+        .unwrap();
+
+    // Ids are keyed by crate name and by file path below the common root
+    assert_eq!(g.metadata.root.as_deref(), Some(dir.path()));
+    assert_eq!(g.find_item_ids("file:app/src/lib.rs").len(), 1);
+    assert_eq!(g.find_item_ids("struct:widgets::shapes::Square").len(), 1);
+    assert!(has_edge(&g, "file:app/src/lib.rs", "struct:widgets::shapes::Square", true));
+    assert!(has_edge(&g, "fn:app::run", "fn:widgets::make", false));
+}
+
+#[test]
+fn merging_separate_builds_reresolves_imports() {
+    let dir = tempdir().unwrap();
+    two_repos(dir.path());
+    let build = |name: &str| {
+        GraphBuilder::new(dir.path().join(name)).cache_mode(CacheMode::Ignore).build().unwrap()
+    };
+    let mut app = build("app");
+    assert_eq!(app.find_item_ids("fn:crate::run").len(), 1);
+    let files = app.files.len() + build("widgets").files.len();
+
+    app.merge(build("widgets")).unwrap();
+    assert_eq!(app.files.len(), files);
+    assert_eq!(app.find_item_ids("fn:app::run").len(), 1);
+    assert!(has_edge(&app, "file:app/src/lib.rs", "struct:widgets::shapes::Square", true));
+    // Contains edges follow the re-keyed ids
+    assert!(app
+        .relationships
+        .iter()
+        .any(|r| r.from_item == ItemId("file:widgets/src/lib.rs".into())
+            && r.to_item == ItemId("fn:widgets::make".into())));
+}
+
+#[test]
+fn conflicting_item_ids_are_reported() {
+    let dir = tempdir().unwrap();
+    for name in ["one", "two"] {
+        write_crate(&dir.path().join(name), "shared", &[("lib.rs", "pub fn helper() {}\n")]);
+    }
+    let build = |name: &str| {
+        GraphBuilder::new(dir.path().join(name)).cache_mode(CacheMode::Ignore).build().unwrap()
+    };
+    let mut one = build("one");
+    let root = one.metadata.root.clone();
+    match one.merge(build("two")) {
+        Err(KnowledgeGraphError::MergeConflict { ids }) => {
+            assert_eq!(ids, vec!["fn:shared::helper".to_string()]);
+        }
+        other => panic!("expected MergeConflict, got {other:?}"),
+    }
+    // The failed merge leaves the receiver's ids and root as they were
+    assert_eq!(one.find_item_ids("fn:crate::helper").len(), 1);
+    assert!(one.find_item_ids("fn:shared::helper").is_empty());
+    assert_eq!(one.metadata.root, root);
+}
+
+#[test]
+fn cli_builds_several_roots_into_one_graph() {
+    let dir = tempdir().unwrap();
+    two_repos(dir.path());
+    let saved = dir.path().join("graph.json");
+    let mut cmd = Command::cargo_bin("rust-relations-explorer").unwrap();
+    cmd.arg("build")
+        .arg("--path")
+        .arg(dir.path().join("app"))
+        .arg("--path")
+        .arg(dir.path().join("widgets"))
+        .arg("--no-cache")
+        .arg("--save")
+        .arg(&saved);
+    cmd.assert().success();
+
+    let g = KnowledgeGraph::load_json(&saved).unwrap();
+    assert!(has_edge(&g, "fn:app::run", "fn:widgets::make", false));
+}
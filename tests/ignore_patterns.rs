@@ -1,7 +1,7 @@
 use std::fs;
 use std::path::PathBuf;
 
-use rust_relations_explorer::graph::KnowledgeGraph;
+use rust_relations_explorer::graph::builder::GraphBuilder;
 
 fn make_temp_project(entries: Vec<(&str, &str)>) -> PathBuf {
     let base = std::env::temp_dir().join(format!(
@@ -31,7 +31,7 @@ fn multiple_ignore_files_with_negations() {
         ("src/a/gone.rs", "pub fn g() {}"),
     ]);
 
-    let graph = KnowledgeGraph::build_from_directory(&root.join("src")).expect("build graph");
+    let graph = GraphBuilder::new(root.join("src")).build().expect("build graph");
     // lib.rs is re-included by root-level negation
     assert!(graph.files.keys().any(|p| p.ends_with("lib.rs")));
     // gone.rs remains ignored; keep.rs may or may not be re-included depending on walker semantics
@@ -48,7 +48,7 @@ fn ignores_files_listed_in_gitignore() {
     ]);
 
     // Build graph from src; the ignored file should not be parsed or present
-    let graph = KnowledgeGraph::build_from_directory(&root.join("src")).expect("build graph");
+    let graph = GraphBuilder::new(root.join("src")).build().expect("build graph");
 
     // Ensure kept.rs exists
     assert!(graph.files.keys().any(|p| p.ends_with("kept.rs")));
@@ -66,7 +66,7 @@ fn nested_ignore_file_in_subdir() {
         ("src/lib.rs", "pub fn root() {}"),
     ]);
 
-    let graph = KnowledgeGraph::build_from_directory(&root.join("src")).expect("build graph");
+    let graph = GraphBuilder::new(root.join("src")).build().expect("build graph");
     assert!(graph.files.keys().any(|p| p.ends_with("a/keep.rs")));
     assert!(!graph.files.keys().any(|p| p.ends_with("a/skip.rs")));
 }
@@ -80,7 +80,7 @@ fn gitignore_negation_pattern() {
         ("src/lib.rs", "pub fn root() {}"),
     ]);
 
-    let graph = KnowledgeGraph::build_from_directory(&root.join("src")).expect("build graph");
+    let graph = GraphBuilder::new(root.join("src")).build().expect("build graph");
     assert!(graph.files.keys().any(|p| p.ends_with("keep.rs")));
     assert!(!graph.files.keys().any(|p| p.ends_with("gone.rs")));
 }
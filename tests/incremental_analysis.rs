@@ -0,0 +1,93 @@
+use rust_relations_explorer::graph::builder::GraphBuilder;
+use rust_relations_explorer::graph::KnowledgeGraph;
+use rust_relations_explorer::utils::cache::CacheMode;
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+fn write(root: &Path, rel: &str, content: &str) {
+    let path = root.join(rel);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, content).unwrap();
+}
+
+fn project(root: &Path) {
+    write(root, "src/lib.rs", "mod car;\nmod drive;\nmod util;\nmod user;\n");
+    write(
+        root,
+        "src/car.rs",
+        "pub struct Engine;\nimpl Engine {\n    pub fn start(&self) {}\n}\npub struct Motor;\nimpl Motor {\n    pub fn start(&self) {}\n}\npub struct Car {\n    pub engine: Engine,\n}\n",
+    );
+    write(
+        root,
+        "src/drive.rs",
+        "use crate::car::Car;\npub fn go(c: &Car) {\n    c.engine.start();\n}\n",
+    );
+    write(root, "src/util.rs", "pub fn helper() {}\n");
+    write(root, "src/user.rs", "pub fn work() {\n    helper();\n}\n");
+}
+
+// Relationships without evidence (whose paths differ between checkouts), sorted.
+fn edges(g: &KnowledgeGraph) -> Vec<String> {
+    let mut out: Vec<String> = g
+        .relationships
+        .iter()
+        .map(|r| {
+            format!(
+                "{} -> {} {:?} {} {}",
+                r.from_item.0, r.to_item.0, r.relationship_type, r.strength, r.count
+            )
+        })
+        .collect();
+    out.sort();
+    out
+}
+
+// Build `root` incrementally and a copy of it from scratch; both must agree.
+fn assert_matches_full_build(root: &Path) {
+    let incremental = GraphBuilder::new(root).build().unwrap();
+    let copy = tempdir().unwrap();
+    for rel in ["src/lib.rs", "src/car.rs", "src/drive.rs", "src/util.rs", "src/user.rs"] {
+        if let Ok(content) = fs::read_to_string(root.join(rel)) {
+            write(copy.path(), rel, &content);
+        }
+    }
+    let full = GraphBuilder::new(copy.path()).cache_mode(CacheMode::Ignore).build().unwrap();
+    assert_eq!(edges(&incremental), edges(&full));
+}
+
+#[test]
+fn edits_elsewhere_reach_unchanged_callers() {
+    let dir = tempdir().unwrap();
+    let root = dir.path();
+    project(root);
+    assert_matches_full_build(root);
+
+    // A field's type changes: `drive.rs` is untouched but its call now lands on `Motor`
+    write(
+        root,
+        "src/car.rs",
+        "pub struct Engine;\nimpl Engine {\n    pub fn start(&self) {}\n}\npub struct Motor;\nimpl Motor {\n    pub fn start(&self) {}\n}\npub struct Car {\n    pub engine: Motor,\n}\n",
+    );
+    assert_matches_full_build(root);
+
+    // A second `helper` in the crate becomes a candidate for the call in `user.rs`
+    write(root, "src/car.rs", "pub struct Car;\npub fn helper() {}\n");
+    assert_matches_full_build(root);
+
+    // The callee disappears with its file
+    fs::remove_file(root.join("src/util.rs")).unwrap();
+    write(root, "src/lib.rs", "mod car;\nmod drive;\nmod user;\n");
+    assert_matches_full_build(root);
+}
+
+#[test]
+fn unchanged_rebuild_reuses_every_edge() {
+    let dir = tempdir().unwrap();
+    let root = dir.path();
+    project(root);
+    let first = GraphBuilder::new(root).build().unwrap();
+    let second = GraphBuilder::new(root).build().unwrap();
+    assert_eq!(edges(&first), edges(&second));
+    assert!(edges(&second).iter().any(|e| e.contains("fn:crate::car::Engine::start")));
+}
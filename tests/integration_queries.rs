@@ -1,7 +1,7 @@
 use std::fs;
 use std::path::PathBuf;
 
-use rust_relations_explorer::graph::KnowledgeGraph;
+use rust_relations_explorer::graph::builder::GraphBuilder;
 use rust_relations_explorer::query::{ConnectedFilesQuery, CycleDetectionQuery, Query};
 
 fn make_temp_project(contents: Vec<(&str, &str)>) -> PathBuf {
@@ -47,7 +47,7 @@ fn integration_connected_files() {
         ),
     ]);
 
-    let graph = KnowledgeGraph::build_from_directory(&root.join("src")).expect("build graph");
+    let graph = GraphBuilder::new(root.join("src")).build().expect("build graph");
 
     // Connected files for a.rs should include lib.rs due to import use from lib
     let connected = ConnectedFilesQuery::new(root.join("src/a.rs")).run(&graph);
@@ -79,7 +79,7 @@ fn integration_cycle_detection_simple() {
         ),
     ]);
 
-    let graph = KnowledgeGraph::build_from_directory(&root.join("src")).expect("build graph");
+    let graph = GraphBuilder::new(root.join("src")).build().expect("build graph");
     let cycles = CycleDetectionQuery::new().run(&graph);
     assert!(cycles.iter().any(|cyc| cyc.len() >= 2));
 }
@@ -1,9 +1,11 @@
+use rust_relations_explorer::graph::builder::GraphBuilder;
 use rust_relations_explorer::graph::{
     FileNode, Item, ItemId, ItemType, KnowledgeGraph, Location, Relationship, RelationshipType,
     Visibility,
 };
 use rust_relations_explorer::query::{
-    CentralityMetric, HubsQuery, ModuleCentralityQuery, Query, ShortestPathQuery,
+    CentralityMetric, FunctionUsageEntry, FunctionUsageQuery, HubsQuery, ModuleCentralityQuery,
+    Query, ShortestPathQuery,
 };
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
@@ -75,6 +77,10 @@ fn graph_chain() -> KnowledgeGraph {
         relationship_type: RelationshipType::Calls { call_type: "test".into() },
         strength: 1.0,
         context: String::new(),
+        confidence: Default::default(),
+        line: None,
+        count: 1,
+        evidence: Vec::new(),
     });
     g.relationships.push(Relationship {
         from_item: ib.id.clone(),
@@ -82,6 +88,10 @@ fn graph_chain() -> KnowledgeGraph {
         relationship_type: RelationshipType::Calls { call_type: "test".into() },
         strength: 1.0,
         context: String::new(),
+        confidence: Default::default(),
+        line: None,
+        count: 1,
+        evidence: Vec::new(),
     });
     // d has no edges, different module directory
 
@@ -106,15 +116,15 @@ fn hubs_query_metrics_and_sorting() {
     let g = graph_chain();
     // Total metric: b should have total 2 (1 in, 1 out) and be near top
     let total = HubsQuery::new(CentralityMetric::Total, 10).run(&g);
-    assert!(total.iter().any(|(p, i, o)| p.ends_with("src/b.rs") && *i == 1 && *o == 1));
+    assert!(total.iter().any(|(p, i, o)| p.ends_with("src/b.rs") && *i == 1.0 && *o == 1.0));
 
     // In metric: c has indegree 1, d has 0
     let ins = HubsQuery::new(CentralityMetric::In, 10).run(&g);
-    assert!(ins.iter().any(|(p, i, _)| p.ends_with("src/m1/c.rs") && *i == 1));
+    assert!(ins.iter().any(|(p, i, _)| p.ends_with("src/m1/c.rs") && *i == 1.0));
 
     // Out metric: a has outdegree 1
     let outs = HubsQuery::new(CentralityMetric::Out, 10).run(&g);
-    assert!(outs.iter().any(|(p, _, o)| p.ends_with("src/a.rs") && *o == 1));
+    assert!(outs.iter().any(|(p, _, o)| p.ends_with("src/a.rs") && *o == 1.0));
 }
 
 #[test]
@@ -128,3 +138,100 @@ fn module_centrality_inter_module_edges() {
     let mods: Vec<String> = rows.iter().map(|(p, _, _)| p.display().to_string()).collect();
     assert!(mods.iter().any(|m| m == "src" || m.ends_with("/src")));
 }
+
+#[test]
+fn function_usage_through_traits_reaches_implementations() {
+    // user.rs::total calls Shape::area (declared in shape.rs), implemented in sq.rs and circle.rs
+    let mut g = KnowledgeGraph::default();
+    let mut add = |path: &str, name: &str| {
+        let p = PathBuf::from(path);
+        let it = make_fn(&p, name);
+        g.files.insert(
+            p.clone(),
+            FileNode {
+                path: p,
+                items: vec![it.clone()],
+                imports: vec![],
+                metrics: Default::default(),
+            },
+        );
+        it.id
+    };
+    let decl = add("src/shape.rs", "area");
+    let sq = add("src/sq.rs", "area");
+    let circle = add("src/circle.rs", "area");
+    let total = add("src/user.rs", "total");
+    let edge = |from: &ItemId, to: &ItemId, relationship_type| Relationship {
+        from_item: from.clone(),
+        to_item: to.clone(),
+        relationship_type,
+        strength: 1.0,
+        context: String::new(),
+        confidence: Default::default(),
+        line: None,
+        count: 1,
+        evidence: Vec::new(),
+    };
+    g.relationships.push(edge(
+        &total,
+        &decl,
+        RelationshipType::Calls { call_type: "method".into() },
+    ));
+    for imp in [&sq, &circle] {
+        g.relationships.push(edge(
+            imp,
+            &decl,
+            RelationshipType::Implements { trait_name: "Shape".into() },
+        ));
+    }
+
+    let files = |entries: Vec<FunctionUsageEntry>| -> Vec<PathBuf> {
+        entries.into_iter().map(|e| e.file).collect()
+    };
+    let direct = files(FunctionUsageQuery::callees("total").run(&g));
+    assert_eq!(direct, vec![PathBuf::from("src/shape.rs")]);
+
+    let dispatched = files(FunctionUsageQuery::callees("total").through_traits(true).run(&g));
+    assert_eq!(
+        dispatched,
+        vec![
+            PathBuf::from("src/circle.rs"),
+            PathBuf::from("src/shape.rs"),
+            PathBuf::from("src/sq.rs")
+        ]
+    );
+
+    // Implements edges are not calls: implementations are not callers of the declaration
+    let callers = FunctionUsageQuery::callers("area").through_traits(true).run(&g);
+    assert_eq!(callers.len(), 1);
+    assert_eq!(callers[0].id, total);
+}
+
+#[test]
+fn function_usage_returns_calling_functions_with_lines() {
+    let td = tempfile::tempdir().unwrap();
+    let src = td.path().join("src");
+    std::fs::create_dir_all(&src).unwrap();
+    std::fs::write(src.join("lib.rs"), "mod a;\npub fn leaf() {}\n").unwrap();
+    std::fs::write(
+        src.join("a.rs"),
+        "pub fn one() {\n    crate::leaf();\n}\n\npub fn two() {\n    crate::leaf();\n    if true {\n        crate::leaf();\n    }\n}\n",
+    )
+    .unwrap();
+    let g = GraphBuilder::new(td.path())
+        .cache_mode(rust_relations_explorer::utils::cache::CacheMode::Ignore)
+        .build()
+        .unwrap();
+
+    let callers = FunctionUsageQuery::callers("leaf").run(&g);
+    let got: Vec<(&str, &[usize])> =
+        callers.iter().map(|e| (e.id.0.as_str(), e.lines.as_slice())).collect();
+    // Every call site is listed, not just the one the edge was first recorded at
+    assert_eq!(got, vec![("fn:crate::a::one", &[2][..]), ("fn:crate::a::two", &[6, 8][..])]);
+    assert!(callers.iter().all(|e| e.file == src.join("a.rs")));
+
+    let callees = FunctionUsageQuery::callees("two").run(&g);
+    assert_eq!(callees.len(), 1);
+    assert_eq!(callees[0].id, ItemId("fn:crate::leaf".into()));
+    assert_eq!(callees[0].lines, vec![6, 8]);
+}
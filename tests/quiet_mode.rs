@@ -25,11 +25,13 @@ fn build_quiet_suppresses_non_essential_output() {
 
     // Without quiet: expect the completion message
     let mut cmd_no_quiet = Command::cargo_bin("rust-relations-explorer").unwrap();
+    cmd_no_quiet.env("RRE_CACHE_DIR", dir.path().join("cache"));
     cmd_no_quiet.arg("build").arg("--path").arg(root);
     cmd_no_quiet.assert().success().stdout(predicate::str::contains("Build completed for path"));
 
     // With quiet: ensure the completion message is suppressed
     let mut cmd_quiet = Command::cargo_bin("rust-relations-explorer").unwrap();
+    cmd_quiet.env("RRE_CACHE_DIR", dir.path().join("cache"));
     cmd_quiet.arg("-q").arg("build").arg("--path").arg(root);
     cmd_quiet
         .assert()
@@ -0,0 +1,130 @@
+use assert_cmd::prelude::*;
+use rust_relations_explorer::graph::builder::GraphBuilder;
+use rust_relations_explorer::graph::{KnowledgeGraph, Relationship, RelationshipType};
+use rust_relations_explorer::query::ConnectedFilesQuery;
+use rust_relations_explorer::utils::cache::CacheMode;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use tempfile::tempdir;
+
+fn project(root: &Path) {
+    let src = root.join("src");
+    fs::create_dir_all(&src).unwrap();
+    fs::write(
+        src.join("lib.rs"),
+        "mod util;\nuse crate::util::helper;\n\npub fn run() {\n    helper(\"a\");\n    let _ = 1;\n    helper(\"b\");\n}\n",
+    )
+    .unwrap();
+    fs::write(src.join("util.rs"), "pub fn helper(_: &str) {}\n").unwrap();
+}
+
+fn build(root: &Path) -> KnowledgeGraph {
+    GraphBuilder::new(root).cache_mode(CacheMode::Ignore).build().unwrap()
+}
+
+fn edge<'g>(g: &'g KnowledgeGraph, from: &str, to: &str, calls: bool) -> &'g Relationship {
+    g.relationships
+        .iter()
+        .find(|r| {
+            r.from_item.0 == from
+                && r.to_item.0 == to
+                && matches!(r.relationship_type, RelationshipType::Calls { .. }) == calls
+        })
+        .unwrap_or_else(|| panic!("no edge {from} -> {to}"))
+}
+
+#[test]
+fn repeated_calls_aggregate_with_evidence() {
+    let dir = tempdir().unwrap();
+    project(dir.path());
+    let g = build(dir.path());
+    let lib = dir.path().join("src/lib.rs");
+
+    let call = edge(&g, "fn:crate::run", "fn:crate::util::helper", true);
+    assert_eq!(call.count, 2);
+    let sites: Vec<(usize, usize, &str)> =
+        call.evidence.iter().map(|e| (e.line, e.column, e.snippet.as_str())).collect();
+    assert_eq!(sites, vec![(5, 5, "helper(\"a\");"), (7, 5, "helper(\"b\");")]);
+    assert!(call.evidence.iter().all(|e| e.file == lib));
+
+    let import = edge(&g, "file:src/lib.rs", "fn:crate::util::helper", false);
+    assert_eq!(import.count, 1);
+    assert_eq!(import.evidence.len(), 1);
+    assert_eq!((import.evidence[0].line, import.evidence[0].column), (2, 1));
+    assert_eq!(import.evidence[0].snippet, "use crate::util::helper;");
+}
+
+#[test]
+fn evidence_survives_saving_and_loading() {
+    let dir = tempdir().unwrap();
+    project(dir.path());
+    let g = build(dir.path());
+    let bin = dir.path().join("graph.bin");
+    let json = dir.path().join("graph.json");
+    g.save(&bin, rust_relations_explorer::graph::format::GraphFormat::Binary).unwrap();
+    g.save_json(&json).unwrap();
+
+    // Evidence files are stored relative to the root, like file paths
+    let raw: serde_json::Value = serde_json::from_str(&fs::read_to_string(&json).unwrap()).unwrap();
+    let files: Vec<&str> = raw["relationships"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|r| r["evidence"].as_array())
+        .flatten()
+        .map(|e| e["file"].as_str().unwrap())
+        .collect();
+    assert!(!files.is_empty());
+    assert!(files.iter().all(|f| *f == "src/lib.rs"), "{files:?}");
+
+    for loaded in [
+        KnowledgeGraph::load(&bin, rust_relations_explorer::graph::format::GraphFormat::Binary)
+            .unwrap(),
+        KnowledgeGraph::load_json(&json).unwrap(),
+    ] {
+        let call = edge(&loaded, "fn:crate::run", "fn:crate::util::helper", true);
+        let orig = edge(&g, "fn:crate::run", "fn:crate::util::helper", true);
+        assert_eq!(call.count, orig.count);
+        assert_eq!(call.evidence, orig.evidence);
+    }
+}
+
+#[test]
+fn connected_files_explain_lists_relations_and_sites() {
+    let dir = tempdir().unwrap();
+    project(dir.path());
+    let g = build(dir.path());
+    let lib = dir.path().join("src/lib.rs");
+
+    let explained = ConnectedFilesQuery::new(&lib).explain(&g);
+    assert_eq!(explained.len(), 1);
+    assert!(explained[0].path.ends_with("src/util.rs"));
+    assert!(explained[0]
+        .relations
+        .iter()
+        .any(|r| r.relation.starts_with("Calls:") && r.count == 2));
+
+    let mut cmd = Command::cargo_bin("rust-relations-explorer").unwrap();
+    cmd.env("RRE_CACHE_DIR", dir.path().join("cache"));
+    cmd.arg("query").arg("connected-files").arg("--path").arg(dir.path()).arg("--file").arg(&lib);
+    cmd.arg("--explain");
+    let out = cmd.assert().success().get_output().stdout.clone();
+    let text = String::from_utf8(out).unwrap();
+    assert!(text.contains("fn:crate::run -> fn:crate::util::helper (x2)"), "{text}");
+    assert!(text.contains(&format!("{}:5  helper(\"a\");", lib.display())), "{text}");
+    assert!(text.contains(&format!("{}:2  use crate::util::helper;", lib.display())), "{text}");
+
+    let mut cmd = Command::cargo_bin("rust-relations-explorer").unwrap();
+    cmd.env("RRE_CACHE_DIR", dir.path().join("cache"));
+    cmd.arg("-v")
+        .arg("query")
+        .arg("item-info")
+        .arg("--path")
+        .arg(dir.path())
+        .arg("--item-id")
+        .arg("fn:crate::util::helper");
+    let out = cmd.assert().success().get_output().stdout.clone();
+    let text = String::from_utf8(out).unwrap();
+    assert!(text.contains(&format!("{}:7  helper(\"b\");", lib.display())), "{text}");
+}
@@ -0,0 +1,158 @@
+use rust_relations_explorer::graph::builder::GraphBuilder;
+use rust_relations_explorer::graph::{ItemId, KnowledgeGraph};
+use rust_relations_explorer::source::{FsSource, MemorySource, OverlaySource, SourceProvider};
+use rust_relations_explorer::utils::cache::{CacheLocation, CacheMode, CacheStore};
+use rust_relations_explorer::utils::file_walker::DiscoveryOptions;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tempfile::tempdir;
+
+const FILES: &[(&str, &str)] = &[
+    ("Cargo.toml", "[package]\nname = \"p\"\nversion = \"0.1.0\"\n"),
+    (".ignore", "scratch/\n"),
+    ("src/lib.rs", "pub mod m;\npub mod shapes;\nuse crate::m::b;\npub fn a() {\n    b();\n}\n"),
+    ("src/m.rs", "pub fn b() {}\n"),
+    (
+        "src/shapes.rs",
+        "pub trait Area {\n    fn area(&self) -> f64;\n}\npub struct Sq(pub f64);\nimpl Area for Sq {\n    fn area(&self) -> f64 {\n        self.0 * self.0\n    }\n}\npub fn total(s: &Sq) -> f64 {\n    s.area()\n}\n",
+    ),
+    ("src/proto.rs", "// @generated\npub fn g() {}\n"),
+    ("scratch/tmp.rs", "pub fn t() {}\n"),
+    ("target/debug/x.rs", "pub fn x() {}\n"),
+];
+
+// Write `FILES` under `root` and return them as a memory source with the same paths
+fn make_proj(root: &Path) -> MemorySource {
+    let mut sources = MemorySource::new();
+    for (rel, text) in FILES {
+        let path = root.join(rel);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, text).unwrap();
+        sources.insert(path, *text);
+    }
+    sources
+}
+
+fn ids(g: &KnowledgeGraph) -> Vec<String> {
+    let mut out: Vec<String> =
+        g.files.values().flat_map(|f| f.items.iter().map(|i| i.id.0.clone())).collect();
+    out.sort();
+    out
+}
+
+fn edges(g: &KnowledgeGraph) -> Vec<String> {
+    let mut out: Vec<String> = g
+        .relationships
+        .iter()
+        .map(|r| format!("{} {:?} {}", r.from_item.0, r.relationship_type, r.to_item.0))
+        .collect();
+    out.sort();
+    out
+}
+
+fn build(provider: &dyn SourceProvider, root: &Path, location: &CacheLocation) -> KnowledgeGraph {
+    GraphBuilder::new(root).provider(provider).cache_location(location.clone()).build().unwrap()
+}
+
+#[test]
+fn memory_build_matches_the_disk_build() {
+    let dir = tempdir().unwrap();
+    let root = dir.path().join("p");
+    let memory = make_proj(&root);
+    let caches = tempdir().unwrap();
+
+    let disk = build(&FsSource, &root, &CacheLocation::Dir(caches.path().to_path_buf()));
+    let opts = DiscoveryOptions::default();
+    assert_eq!(
+        memory.discover(&root, &opts).unwrap().files,
+        FsSource.discover(&root, &opts).unwrap().files
+    );
+
+    // Remove the files so nothing can come from disk
+    fs::remove_dir_all(&root).unwrap();
+    let g = build(&memory, &root, &CacheLocation::Disabled);
+    assert_eq!(ids(&g), ids(&disk));
+    assert_eq!(edges(&g), edges(&disk));
+    assert!(ids(&g).contains(&"fn:crate::shapes::total".to_string()));
+    assert!(!ids(&g).iter().any(|id| id.contains("proto") || id.contains("tmp")));
+    assert!(!root.exists(), "a disabled cache writes nothing");
+}
+
+#[test]
+fn memory_builds_write_no_cache() {
+    let dir = tempdir().unwrap();
+    let root = dir.path().join("p");
+    let memory: MemorySource = [(root.join("src/lib.rs"), "pub fn a() {}\n")].into_iter().collect();
+    fs::create_dir_all(&root).unwrap();
+
+    let g = GraphBuilder::new(&root).provider(&memory).build().unwrap();
+    assert!(ids(&g).contains(&"fn:crate::a".to_string()));
+    let again = GraphBuilder::new(&root).provider(&memory).cache_mode(CacheMode::Rebuild);
+    again.build().unwrap();
+    assert!(!CacheStore::new(&root, &CacheLocation::Project).file().exists());
+    assert_eq!(fs::read_dir(&root).unwrap().count(), 0, "nothing written to the project");
+}
+
+#[test]
+fn overlay_builds_unsaved_buffers_over_the_disk() {
+    let dir = tempdir().unwrap();
+    let root = dir.path().to_path_buf();
+    make_proj(&root);
+    let caches = CacheLocation::Dir(dir.path().join("caches"));
+    build(&FsSource, &root, &caches);
+    let cache_file = CacheStore::new(&root, &caches).file().to_path_buf();
+    let cached = fs::read(&cache_file).unwrap();
+
+    let mut overlay = OverlaySource::new(FsSource);
+    overlay.set(root.join("src/lib.rs"), "pub mod m;\npub mod n;\npub fn a() {\n    n::c();\n}\n");
+    overlay.set(root.join("src/n.rs"), "pub fn c() {}\n");
+    // A new buffer in an ignored directory stays out
+    overlay.set(root.join("scratch/new.rs"), "pub fn s() {}\n");
+    let g = build(&overlay, &root, &caches);
+
+    let calls = |g: &KnowledgeGraph, to: &str| {
+        g.relationships.iter().any(|r| {
+            r.from_item.0 == "fn:crate::a"
+                && r.to_item.0 == to
+                && format!("{:?}", r.relationship_type).starts_with("Calls")
+        })
+    };
+    assert!(calls(&g, "fn:crate::n::c"));
+    assert!(!calls(&g, "fn:crate::m::b"));
+    assert!(!ids(&g).iter().any(|id| id.ends_with("::s")));
+    let stats = g.metadata.stats.clone().unwrap();
+    // Buffers are read and hashed; unchanged files on disk come from the cache, and the
+    // `@generated` one is read and left out
+    assert_eq!((stats.files_discovered, stats.files_reused, stats.files_parsed), (5, 2, 2));
+    let on_disk: Vec<PathBuf> =
+        FsSource.discover(&root, &DiscoveryOptions::default()).unwrap().files;
+    assert!(!on_disk.contains(&root.join("src/n.rs")));
+    // Buffer parses never reach the cache
+    assert_eq!(fs::read(&cache_file).unwrap(), cached);
+
+    // Reverting the buffers gives the disk graph back
+    overlay.revert(&root.join("src/lib.rs"));
+    overlay.revert(&root.join("src/n.rs"));
+    let g = build(&overlay, &root, &caches);
+    assert!(calls(&g, "fn:crate::m::b"));
+    assert_eq!(ids(&g), ids(&build(&FsSource, &root, &caches)));
+}
+
+#[test]
+fn crate_names_come_from_manifests_in_the_provider() {
+    // Nothing of this workspace exists on disk
+    let sources: MemorySource = [
+        ("/ws/app/src/lib.rs", "use shapes_core::area;\npub fn run() {\n    area();\n}\n"),
+        ("/ws/core/Cargo.toml", "[package]\nname = \"shapes-core\"\nversion = \"0.1.0\"\n"),
+        ("/ws/core/src/lib.rs", "pub fn area() {}\n"),
+    ]
+    .into_iter()
+    .collect();
+    let g = build(&sources, Path::new("/ws"), &CacheLocation::Disabled);
+    assert!(ids(&g).contains(&"fn:shapes_core::area".to_string()), "{:?}", ids(&g));
+    assert!(edges(&g)
+        .iter()
+        .any(|e| e.starts_with("fn:app::run Calls") && e.ends_with("fn:shapes_core::area")));
+    let paths = rust_relations_explorer::graph::paths::item_paths(&g);
+    assert_eq!(paths[&ItemId("fn:shapes_core::area".into())].canonical, "shapes_core::area");
+}
@@ -0,0 +1,111 @@
+use rust_relations_explorer::utils::watch::{SourceWatcher, WatchBackend, WatchOptions};
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+use tempfile::tempdir;
+
+fn make_proj(root: &Path) {
+    fs::create_dir_all(root.join("src")).unwrap();
+    fs::create_dir_all(root.join("target")).unwrap();
+    fs::write(root.join(".ignore"), "target/\n").unwrap();
+    fs::write(root.join("src/lib.rs"), "pub mod m;\npub fn a() {\n    m::b();\n}\n").unwrap();
+    fs::write(root.join("src/m.rs"), "pub fn b() {}\n").unwrap();
+}
+
+fn options(backend: WatchBackend) -> WatchOptions {
+    WatchOptions { backend, debounce: Duration::from_millis(50), ..WatchOptions::default() }
+}
+
+// Edit `rel` shortly after the watcher starts waiting
+fn edit_later(root: &Path, rel: &str, content: &'static str) -> thread::JoinHandle<()> {
+    let path = root.join(rel);
+    thread::spawn(move || {
+        thread::sleep(Duration::from_millis(150));
+        fs::write(path, content).unwrap();
+    })
+}
+
+fn reports_edits_but_not_ignored_files(backend: WatchBackend) {
+    let dir = tempdir().unwrap();
+    let root = dir.path().to_path_buf();
+    make_proj(&root);
+    let mut watcher = SourceWatcher::new(std::slice::from_ref(&root), options(backend));
+
+    let writer = edit_later(&root, "target/gen.rs", "pub fn generated() {}\n");
+    assert_eq!(watcher.wait_timeout(Duration::from_millis(800)), None);
+    writer.join().unwrap();
+
+    let writer = edit_later(&root, "src/m.rs", "pub fn b() {}\npub fn c() {}\n");
+    let batch = watcher.wait_timeout(Duration::from_secs(10));
+    writer.join().unwrap();
+    assert_eq!(batch, Some(vec![root.join("src/m.rs")]));
+
+    // New and removed files are both reported
+    let writer = edit_later(&root, "src/n.rs", "pub fn n() {}\n");
+    assert_eq!(watcher.wait_timeout(Duration::from_secs(10)), Some(vec![root.join("src/n.rs")]));
+    writer.join().unwrap();
+    fs::remove_file(root.join("src/m.rs")).unwrap();
+    assert_eq!(watcher.wait_timeout(Duration::from_secs(10)), Some(vec![root.join("src/m.rs")]));
+}
+
+#[test]
+fn polling_watcher_reports_discovered_files_only() {
+    reports_edits_but_not_ignored_files(WatchBackend::Poll(Duration::from_millis(30)));
+}
+
+#[test]
+fn native_watcher_reports_discovered_files_only() {
+    reports_edits_but_not_ignored_files(WatchBackend::Native);
+}
+
+fn wait_for(what: &str, mut done: impl FnMut() -> bool) {
+    let deadline = Instant::now() + Duration::from_secs(30);
+    while !done() {
+        assert!(Instant::now() < deadline, "timed out waiting for {what}");
+        thread::sleep(Duration::from_millis(50));
+    }
+}
+
+#[test]
+fn cli_watch_rewrites_outputs_and_prints_a_summary() {
+    let dir = tempdir().unwrap();
+    let root = dir.path().join("proj");
+    make_proj(&root);
+    let json: PathBuf = dir.path().join("graph.json");
+    let dot = dir.path().join("graph.dot");
+
+    let mut child = Command::new(assert_cmd::cargo::cargo_bin("rust-relations-explorer"))
+        .env("RRE_CACHE_DIR", dir.path().join("cache"))
+        .arg("build")
+        .arg("--path")
+        .arg(&root)
+        .arg("--json")
+        .arg(&json)
+        .arg("--dot")
+        .arg(&dot)
+        .args(["--watch", "--poll", "--poll-interval", "50", "--debounce", "50"])
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    wait_for("first build", || {
+        fs::read_to_string(&json).is_ok_and(|s| s.contains("fn:crate::m::b"))
+    });
+    // Give the watcher time to take its first snapshot
+    thread::sleep(Duration::from_millis(300));
+    fs::write(root.join("src/m.rs"), "pub fn b() {}\npub fn fresh() {}\n").unwrap();
+    wait_for("rebuild", || {
+        fs::read_to_string(&json).is_ok_and(|s| s.contains("fn:crate::m::fresh"))
+    });
+    wait_for("dot rewrite", || fs::read_to_string(&dot).is_ok_and(|s| s.contains("fresh")));
+
+    child.kill().unwrap();
+    child.wait().unwrap();
+    let mut out = String::new();
+    child.stdout.take().unwrap().read_to_string(&mut out).unwrap();
+    assert!(out.contains("Watching for changes (polling)"), "{out}");
+    assert!(out.contains("after src/m.rs: +1 items"), "{out}");
+}
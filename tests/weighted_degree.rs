@@ -0,0 +1,101 @@
+use rust_relations_explorer::graph::builder::GraphBuilder;
+use rust_relations_explorer::graph::{KnowledgeGraph, RelationshipType};
+use rust_relations_explorer::query::{CentralityMetric, HubsQuery, ModuleCentralityQuery, Query};
+use rust_relations_explorer::utils::cache::CacheMode;
+use rust_relations_explorer::visualization::{DotGenerator, DotOptions};
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+// `hot.rs` calls one function six times; `spread.rs` calls two functions once each
+fn project(root: &Path) -> KnowledgeGraph {
+    let src = root.join("src");
+    fs::create_dir_all(src.join("engine")).unwrap();
+    fs::write(src.join("lib.rs"), "mod hot;\nmod spread;\nmod engine;\n").unwrap();
+    fs::write(src.join("engine/mod.rs"), "pub fn work() {}\npub fn job() {}\n").unwrap();
+    let calls = "    crate::engine::work();\n".repeat(6);
+    fs::write(src.join("hot.rs"), format!("pub fn run() {{\n{calls}}}\n")).unwrap();
+    fs::write(
+        src.join("spread.rs"),
+        "pub fn a() {\n    crate::engine::work();\n}\npub fn b() {\n    crate::engine::job();\n}\n",
+    )
+    .unwrap();
+    GraphBuilder::new(root).cache_mode(CacheMode::Ignore).build().unwrap()
+}
+
+fn rank(rows: &[(std::path::PathBuf, f64, f64)], file: &str) -> (usize, f64) {
+    let row = rows.iter().position(|r| r.0.ends_with(file)).unwrap();
+    (row, rows[row].2)
+}
+
+#[test]
+fn weighted_hubs_sum_confidence_times_call_sites() {
+    let dir = tempdir().unwrap();
+    let g = project(dir.path());
+    let hot = g
+        .relationships
+        .iter()
+        .find(|r| {
+            r.from_item.0 == "fn:crate::hot::run"
+                && matches!(r.relationship_type, RelationshipType::Calls { .. })
+        })
+        .unwrap();
+    assert_eq!(hot.count, 6);
+    assert!((hot.weight() - 6.0 * hot.strength).abs() < 1e-9);
+
+    let plain = HubsQuery::new(CentralityMetric::Out, 10).run(&g);
+    let weighted = HubsQuery::new(CentralityMetric::Out, 10).weighted(true).run(&g);
+    let out_weight = |from: &str| -> f64 {
+        g.relationships
+            .iter()
+            .filter(|r| r.from_item.0.starts_with(from) && !r.to_item.0.starts_with(from))
+            .map(|r| r.weight())
+            .sum()
+    };
+    assert_eq!(rank(&plain, "hot.rs").1, 1.0);
+    assert_eq!(rank(&plain, "spread.rs").1, 2.0);
+    assert!((rank(&weighted, "hot.rs").1 - out_weight("fn:crate::hot::")).abs() < 1e-9);
+    assert!((rank(&weighted, "spread.rs").1 - out_weight("fn:crate::spread::")).abs() < 1e-9);
+    assert!(rank(&weighted, "hot.rs").0 < rank(&weighted, "spread.rs").0);
+    assert!(rank(&plain, "hot.rs").0 > rank(&plain, "spread.rs").0);
+}
+
+#[test]
+fn weighted_module_centrality_sums_edge_weights() {
+    let dir = tempdir().unwrap();
+    let g = project(dir.path());
+    let engine = |rows: Vec<(std::path::PathBuf, f64, f64)>| {
+        rows.into_iter().find(|r| r.0.ends_with("engine")).map(|r| r.1).unwrap()
+    };
+    // Calls from `hot.rs` and `spread.rs` into the `engine` directory
+    let plain = engine(ModuleCentralityQuery::new(CentralityMetric::In, 10).run(&g));
+    let weighted =
+        engine(ModuleCentralityQuery::new(CentralityMetric::In, 10).weighted(true).run(&g));
+    let into_engine: Vec<&rust_relations_explorer::graph::Relationship> = g
+        .relationships
+        .iter()
+        .filter(|r| r.to_item.0.contains("engine") && !r.from_item.0.contains("engine"))
+        .collect();
+    assert_eq!(plain, into_engine.len() as f64);
+    let expected: f64 = into_engine.iter().map(|r| r.weight()).sum();
+    assert!((weighted - expected).abs() < 1e-9);
+    // Six repeated calls into `work` weigh more than one edge
+    assert!(weighted > plain);
+}
+
+#[test]
+fn weighted_dot_edges_are_wider_for_repeated_calls() {
+    let dir = tempdir().unwrap();
+    let g = project(dir.path());
+    let width = |weighted: bool, from: &str| -> f64 {
+        let opts = DotOptions { weighted, ..DotOptions::default() };
+        let dot = DotGenerator::new().generate_dot_with_options(&g, opts).unwrap();
+        let line = dot
+            .lines()
+            .find(|l| l.contains(from) && l.contains("calls:"))
+            .unwrap_or_else(|| panic!("no call edge from {from}"));
+        line.split("penwidth=").nth(1).unwrap().trim_end_matches("];").parse().unwrap()
+    };
+    assert!(width(true, "crate__hot__run") > width(false, "crate__hot__run"));
+    assert!(width(true, "crate__hot__run") > width(true, "crate__spread__a"));
+}